@@ -0,0 +1,303 @@
+use crate::core::piece::Piece;
+use crate::error::{Result, StudFinderError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// File extensions (lowercase) treated as images by a batch scan
+pub const IMAGE_EXTENSIONS: [&str; 11] = [
+    "jpg", "jpeg", "png", "bmp", "gif", "tif", "tiff", "webp", "ppm", "pnm", "tga",
+];
+
+/// Settings for `StudFinder::scan_batch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Number of images processed at the same time
+    pub workers: usize,
+    /// Scan images already in the scan ledger again, replacing their
+    /// earlier results instead of skipping them
+    pub rescan: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            workers: std::thread::available_parallelism().map_or(1, usize::from),
+            rescan: false,
+        }
+    }
+}
+
+/// What happened to one file of a batch scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum FileOutcome {
+    /// The image was processed and its pieces stored
+    Scanned {
+        /// The stored pieces, with the IDs of their inventory rows
+        pieces: Vec<Piece>,
+        /// Number of detections put in the review queue
+        #[serde(default)]
+        queued: usize,
+    },
+    /// The file was not processed
+    Skipped {
+        /// Why the file was left alone
+        reason: String,
+    },
+    /// Processing the image failed
+    Failed {
+        /// `StudFinderError::kind` of the failure
+        kind: String,
+        /// The error message
+        reason: String,
+    },
+}
+
+/// Outcome of one file of a batch scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileReport {
+    /// The file, as found under the scanned directory
+    pub path: PathBuf,
+    /// What happened to it
+    #[serde(flatten)]
+    pub outcome: FileOutcome,
+}
+
+impl FileReport {
+    /// Report a failed file
+    #[must_use]
+    pub fn failed(path: PathBuf, error: &StudFinderError) -> Self {
+        Self {
+            path,
+            outcome: FileOutcome::Failed {
+                kind: error.kind().to_string(),
+                reason: error.to_string(),
+            },
+        }
+    }
+
+    /// Report a skipped file
+    #[must_use]
+    pub fn skipped(path: PathBuf, reason: &str) -> Self {
+        Self {
+            path,
+            outcome: FileOutcome::Skipped {
+                reason: reason.to_string(),
+            },
+        }
+    }
+}
+
+/// Progress of a running batch scan, passed to its callback after each file
+#[derive(Debug, Clone, Copy)]
+pub struct BatchProgress<'a> {
+    /// The file that was just finished
+    pub file: &'a FileReport,
+    /// Number of files finished so far, including `file`
+    pub completed: usize,
+    /// Number of files in the batch
+    pub total: usize,
+}
+
+/// Outcome of a batch scan
+///
+/// Files are listed in path order, whatever order they were processed in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchReport {
+    /// The directory that was scanned
+    pub root: PathBuf,
+    /// Number of images scanned
+    pub scanned: usize,
+    /// Number of files skipped
+    pub skipped: usize,
+    /// Number of images that failed
+    pub failed: usize,
+    /// Total quantity of pieces stored
+    pub pieces: i32,
+    /// Number of detections put in the review queue
+    #[serde(default)]
+    pub queued: usize,
+    /// One entry per file found
+    pub files: Vec<FileReport>,
+}
+
+impl BatchReport {
+    /// Build a report, sorting the files and counting their outcomes
+    #[must_use]
+    pub fn new(root: PathBuf, mut files: Vec<FileReport>) -> Self {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut report = Self {
+            root,
+            scanned: 0,
+            skipped: 0,
+            failed: 0,
+            pieces: 0,
+            queued: 0,
+            files: Vec::new(),
+        };
+        for file in &files {
+            match &file.outcome {
+                FileOutcome::Scanned {
+                    pieces,
+                    queued,
+                } => {
+                    report.scanned += 1;
+                    report.pieces += pieces.iter().map(|p| p.quantity).sum::<i32>();
+                    report.queued += queued;
+                }
+                FileOutcome::Skipped {
+                    ..
+                } => report.skipped += 1,
+                FileOutcome::Failed {
+                    ..
+                } => report.failed += 1,
+            }
+        }
+        report.files = files;
+        report
+    }
+
+    /// Save the report as JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns `Io` if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| StudFinderError::ExportFailed(e.to_string()))?;
+        std::fs::write(path.as_ref(), json).map_err(StudFinderError::Io)?;
+        debug!("Saved batch report to {}", path.as_ref().display());
+        Ok(())
+    }
+}
+
+/// Whether a path has one of the `IMAGE_EXTENSIONS`
+#[must_use]
+pub fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Hex-encoded SHA-256 of a file's content, as recorded in the scan ledger
+///
+/// # Errors
+///
+/// Returns `Io` if the file cannot be read.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).map_err(StudFinderError::Io)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(StudFinderError::Io)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Find the files under a directory, recursively, in path order
+///
+/// Symbolic links to directories are not followed.
+///
+/// # Errors
+///
+/// Returns `Io` if a directory cannot be read.
+pub fn collect_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).map_err(StudFinderError::Io)? {
+            let entry = entry.map_err(StudFinderError::Io)?;
+            let file_type = entry.file_type().map_err(StudFinderError::Io)?;
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_files_recurses() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let nested = temp_dir.path().join("drawer").join("bin");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(temp_dir.path().join("b.jpg"), "").unwrap();
+        std::fs::write(nested.join("a.PNG"), "").unwrap();
+        std::fs::write(nested.join("notes.txt"), "").unwrap();
+
+        let files = collect_files(temp_dir.path()).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                temp_dir.path().join("b.jpg"),
+                nested.join("a.PNG"),
+                nested.join("notes.txt"),
+            ]
+        );
+        let images: Vec<bool> = files.iter().map(|f| is_image_path(f)).collect();
+        assert_eq!(images, vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("a.jpg");
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_report_counts_outcomes() {
+        let piece = Piece {
+            id: "a".to_string(),
+            part_number: "3001".to_string(),
+            color: "Red".to_string(),
+            category: "Bricks".to_string(),
+            quantity: 2,
+            confidence: 0.9,
+            condition: None,
+        };
+        let report = BatchReport::new(
+            PathBuf::from("pieces"),
+            vec![
+                FileReport::failed(PathBuf::from("c.jpg"), &StudFinderError::NoPiecesDetected),
+                FileReport::skipped(PathBuf::from("b.txt"), "not an image"),
+                FileReport {
+                    path: PathBuf::from("a.jpg"),
+                    outcome: FileOutcome::Scanned {
+                        pieces: vec![piece],
+                        queued: 1,
+                    },
+                },
+            ],
+        );
+
+        assert_eq!(
+            (
+                report.scanned,
+                report.skipped,
+                report.failed,
+                report.pieces,
+                report.queued
+            ),
+            (1, 1, 1, 2, 1)
+        );
+        assert_eq!(report.files[0].path, PathBuf::from("a.jpg"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["files"][1]["status"], "skipped");
+        assert_eq!(json["files"][2]["kind"], "no_pieces_detected");
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Category reported for parts that are not in the catalog
+pub const UNKNOWN_CATEGORY: &str = "Unknown";
+
+/// Footprint and height of a part, in LEGO units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PartDimensions {
+    /// Number of studs along the short side
+    pub width: u32,
+    /// Number of studs along the long side
+    pub length: u32,
+    /// Height in plates (a plate is 1, a brick is 3)
+    pub height: u32,
+}
+
+impl PartDimensions {
+    /// Parse the dimensions of a basic brick or plate from its catalog name
+    ///
+    /// Only plain names such as "Brick 2 x 4" or "Plate 1 x 2" are
+    /// recognized, so that modified parts ("Brick 1 x 2 with Grille") never
+    /// claim the footprint of the basic part.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::core::PartDimensions;
+    ///
+    /// let dims = PartDimensions::from_name("Plate 2 x 4").unwrap();
+    /// assert_eq!((dims.width, dims.length, dims.height), (2, 4, 1));
+    /// assert!(PartDimensions::from_name("Brick 1 x 2 with Grille").is_none());
+    /// ```
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let tokens: Vec<&str> = name.split_whitespace().collect();
+        let [kind, a, "x", b] = tokens.as_slice() else {
+            return None;
+        };
+        let height = match *kind {
+            "Brick" => 3,
+            "Plate" => 1,
+            _ => return None,
+        };
+        let (a, b): (u32, u32) = (a.parse().ok()?, b.parse().ok()?);
+        Some(Self {
+            width: a.min(b),
+            length: a.max(b),
+            height,
+        })
+    }
+}
+
+impl std::fmt::Display for PartDimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} x {} x {} plate(s)",
+            self.width, self.length, self.height
+        )
+    }
+}
+
+/// Catalog entry describing a single part design
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartInfo {
+    /// Canonical part number
+    pub part_number: String,
+    /// Descriptive name, e.g. "Brick 2 x 4"
+    pub name: String,
+    /// Part category, e.g. "Bricks"
+    pub category: String,
+    /// Footprint and height, if known
+    #[serde(default)]
+    pub dimensions: Option<PartDimensions>,
+    /// Alternate and mold-variant part numbers for the same design
+    #[serde(default)]
+    pub alternates: Vec<String>,
+}
+
+impl PartInfo {
+    /// Create a catalog entry, deriving the dimensions from the name
+    #[must_use]
+    pub fn new(part_number: &str, name: &str, category: &str) -> Self {
+        Self {
+            part_number: part_number.to_string(),
+            name: name.to_string(),
+            category: category.to_string(),
+            dimensions: PartDimensions::from_name(name),
+            alternates: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for PartInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ({})", self.part_number, self.name, self.category)
+    }
+}
+
+/// Parts known without importing a catalog: (part number, name, category, alternates)
+#[rustfmt::skip]
+const BUILTIN_PARTS: &[(&str, &str, &str, &[&str])] = &[
+    ("3005", "Brick 1 x 1", "Bricks", &[]),
+    ("3004", "Brick 1 x 2", "Bricks", &[]),
+    ("3622", "Brick 1 x 3", "Bricks", &[]),
+    ("3010", "Brick 1 x 4", "Bricks", &[]),
+    ("3009", "Brick 1 x 6", "Bricks", &[]),
+    ("3008", "Brick 1 x 8", "Bricks", &[]),
+    ("3003", "Brick 2 x 2", "Bricks", &[]),
+    ("3002", "Brick 2 x 3", "Bricks", &[]),
+    ("3001", "Brick 2 x 4", "Bricks", &[]),
+    ("2456", "Brick 2 x 6", "Bricks", &["44237"]),
+    ("3007", "Brick 2 x 8", "Bricks", &[]),
+    ("3006", "Brick 2 x 10", "Bricks", &[]),
+    ("3024", "Plate 1 x 1", "Plates", &[]),
+    ("3023", "Plate 1 x 2", "Plates", &[]),
+    ("3623", "Plate 1 x 3", "Plates", &[]),
+    ("3710", "Plate 1 x 4", "Plates", &[]),
+    ("3666", "Plate 1 x 6", "Plates", &[]),
+    ("3460", "Plate 1 x 8", "Plates", &[]),
+    ("3022", "Plate 2 x 2", "Plates", &[]),
+    ("3021", "Plate 2 x 3", "Plates", &[]),
+    ("3020", "Plate 2 x 4", "Plates", &[]),
+    ("3795", "Plate 2 x 6", "Plates", &[]),
+    ("3034", "Plate 2 x 8", "Plates", &[]),
+    ("3832", "Plate 2 x 10", "Plates", &[]),
+    ("3031", "Plate 4 x 4", "Plates", &[]),
+    ("3032", "Plate 4 x 6", "Plates", &[]),
+    ("3035", "Plate 4 x 8", "Plates", &[]),
+    ("3030", "Plate 4 x 10", "Plates", &[]),
+    ("3958", "Plate 6 x 6", "Plates", &[]),
+    ("3036", "Plate 6 x 8", "Plates", &[]),
+    ("3033", "Plate 6 x 10", "Plates", &[]),
+    ("3070b", "Tile 1 x 1 with Groove", "Tiles", &["3070"]),
+    ("3069b", "Tile 1 x 2 with Groove", "Tiles", &["3069"]),
+    ("3068b", "Tile 2 x 2 with Groove", "Tiles", &["3068"]),
+    ("3062b", "Brick Round 1 x 1 Open Stud", "Bricks Round and Cones", &["3062"]),
+];
+
+/// Lookup table of part designs
+///
+/// Resolves part numbers (including alternate and mold numbers) to their
+/// name, category and dimensions, and finds the basic part with a given
+/// footprint. A built-in set of common bricks, plates and tiles is always
+/// available; a full catalog can be imported from a Rebrickable dump.
+#[derive(Debug, Clone, Default)]
+pub struct PartCatalog {
+    parts: HashMap<String, PartInfo>,
+    aliases: HashMap<String, String>,
+    footprints: HashMap<PartDimensions, String>,
+}
+
+impl PartCatalog {
+    /// Create an empty catalog
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a catalog containing the built-in parts
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::core::PartCatalog;
+    ///
+    /// let catalog = PartCatalog::builtin();
+    /// assert_eq!(catalog.category("3001"), "Bricks");
+    /// assert_eq!(catalog.get("3062").unwrap().part_number, "3062b");
+    /// ```
+    #[must_use]
+    pub fn builtin() -> Self {
+        let mut catalog = Self::new();
+        for (part_number, name, category, alternates) in BUILTIN_PARTS {
+            let mut part = PartInfo::new(part_number, name, category);
+            part.alternates = alternates.iter().map(|a| (*a).to_string()).collect();
+            catalog.insert(part);
+        }
+        catalog
+    }
+
+    /// Add or replace a part
+    ///
+    /// The first part registered for a footprint keeps it, so basic parts
+    /// loaded first are preferred over later variants with the same size.
+    pub fn insert(&mut self, part: PartInfo) {
+        for alternate in &part.alternates {
+            self.aliases
+                .insert(alternate.clone(), part.part_number.clone());
+        }
+        if let Some(dimensions) = part.dimensions {
+            self.footprints
+                .entry(dimensions)
+                .or_insert_with(|| part.part_number.clone());
+        }
+        self.parts.insert(part.part_number.clone(), part);
+    }
+
+    /// Look up a part by its number or one of its alternate numbers
+    #[must_use]
+    pub fn get(&self, part_number: &str) -> Option<&PartInfo> {
+        self.parts.get(part_number).or_else(|| {
+            self.aliases
+                .get(part_number)
+                .and_then(|canonical| self.parts.get(canonical))
+        })
+    }
+
+    /// Category of a part, or "Unknown" if it is not in the catalog
+    #[must_use]
+    pub fn category(&self, part_number: &str) -> String {
+        let category = self
+            .get(part_number)
+            .map_or(UNKNOWN_CATEGORY, |part| part.category.as_str());
+        debug!("Categorized part {} as {}", part_number, category);
+        category.to_string()
+    }
+
+    /// Find the basic part with the given footprint and height
+    #[must_use]
+    pub fn find_by_dimensions(&self, dimensions: PartDimensions) -> Option<&PartInfo> {
+        self.footprints
+            .get(&dimensions)
+            .and_then(|part_number| self.parts.get(part_number))
+    }
+
+    /// Iterate over all parts, in no particular order
+    pub fn parts(&self) -> impl Iterator<Item = &PartInfo> {
+        self.parts.values()
+    }
+
+    /// Number of parts in the catalog
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Whether the catalog contains no parts
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+}
+
+impl Extend<PartInfo> for PartCatalog {
+    fn extend<T: IntoIterator<Item = PartInfo>>(&mut self, iter: T) {
+        for part in iter {
+            self.insert(part);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions_from_name() {
+        assert_eq!(
+            PartDimensions::from_name("Brick 4 x 2"),
+            Some(PartDimensions {
+                width: 2,
+                length: 4,
+                height: 3,
+            })
+        );
+        assert_eq!(PartDimensions::from_name("Tile 2 x 2"), None);
+        assert_eq!(PartDimensions::from_name("Brick Round 1 x 1"), None);
+    }
+
+    #[test]
+    fn test_builtin_lookup() {
+        let catalog = PartCatalog::builtin();
+
+        let part = catalog.get("3020").unwrap();
+        assert_eq!(part.name, "Plate 2 x 4");
+        assert_eq!(part.category, "Plates");
+
+        assert_eq!(catalog.get("3069").unwrap().part_number, "3069b");
+        assert_eq!(catalog.category("99999"), UNKNOWN_CATEGORY);
+    }
+
+    #[test]
+    fn test_find_by_dimensions_prefers_first_part() {
+        let mut catalog = PartCatalog::builtin();
+        // A later variant with the same footprint does not replace the basic part
+        catalog.insert(PartInfo::new("3001old", "Brick 2 x 4", "Bricks"));
+
+        let dims = PartDimensions {
+            width: 2,
+            length: 4,
+            height: 3,
+        };
+        assert_eq!(
+            catalog.find_by_dimensions(dims).unwrap().part_number,
+            "3001"
+        );
+        assert!(catalog.get("3001old").is_some());
+    }
+}
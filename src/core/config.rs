@@ -1,8 +1,49 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
 
 use super::piece::{ExportFormat, ProcessorType, ScanQuality};
+use crate::error::{ConfigError, Result, StudFinderError};
+
+/// Name of the config file inside the platform config directory
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Environment variable pointing at an alternative config file
+pub const CONFIG_FILE_ENV: &str = "STUDFINDER_CONFIG";
+
+/// Prefix of environment variables that override config values
+pub const ENV_PREFIX: &str = "STUDFINDER_";
+
+/// Keys accepted by `Config::get` and `Config::set`, in file order
+pub const CONFIG_KEYS: [&str; 8] = [
+    "database_path",
+    "export_format",
+    "scan_quality",
+    "processor_type",
+    "confidence_threshold",
+    "review_threshold",
+    "template_dir",
+    "calibration_profile",
+];
+
+/// Default `confidence_threshold`
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.8;
+
+/// Default `review_threshold`
+pub const DEFAULT_REVIEW_THRESHOLD: f32 = 0.5;
+
+/// SQLite's name for an in-memory database, which has no file to check
+const MEMORY_DATABASE: &str = ":memory:";
+
+/// Short environment variable names, matching the global CLI flags
+const ENV_ALIASES: [(&str, &str); 5] = [
+    ("db", "database_path"),
+    ("processor", "processor_type"),
+    ("quality", "scan_quality"),
+    ("threshold", "confidence_threshold"),
+    ("profile", "calibration_profile"),
+];
 
 /// Configuration for the StudFinder application
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,8 +56,16 @@ pub struct Config {
     pub scan_quality: ScanQuality,
     /// Type of image processor to use
     pub processor_type: ProcessorType,
-    /// Confidence threshold for detection (0.0-1.0)
+    /// Confidence at or above which detections are added to the inventory (0.0-1.0)
     pub confidence_threshold: f32,
+    /// Confidence at or above which detections below `confidence_threshold`
+    /// are queued for review; anything lower is rejected (0.0-1.0)
+    pub review_threshold: f32,
+    /// Directory containing template images for the Detector processor
+    pub template_dir: PathBuf,
+    /// Calibration profile whose white balance is applied to measured
+    /// colors, or `None` to match colors as photographed
+    pub calibration_profile: Option<String>,
 }
 
 impl Config {
@@ -37,10 +86,591 @@ impl Config {
                 export_format: ExportFormat::Json,
                 scan_quality: ScanQuality::Balanced,
                 processor_type: ProcessorType::Scanner,
-                confidence_threshold: 0.8,
+                confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+                review_threshold: DEFAULT_REVIEW_THRESHOLD,
+                template_dir: data_dir.join("templates"),
+                calibration_profile: None,
             })
         } else {
             Err(anyhow::anyhow!("Could not determine config directory"))
         }
     }
+
+    /// Load the configuration for this run
+    ///
+    /// Starts from the defaults, applies the config file (if it exists) and
+    /// then any `STUDFINDER_*` environment variables. Command-line flags are
+    /// applied on top by the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the defaults cannot be determined, or the config
+    /// file or an environment variable holds an invalid value.
+    pub fn load() -> anyhow::Result<Self> {
+        let mut config = Self::load_file_only()?;
+        config.apply_env(std::env::vars())?;
+        Ok(config)
+    }
+
+    /// Load the defaults and the config file, ignoring the environment
+    ///
+    /// This is the configuration `config set` edits, so that overrides from
+    /// the environment are never written back to the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the defaults cannot be determined or the config
+    /// file holds an invalid value.
+    pub fn load_file_only() -> anyhow::Result<Self> {
+        let mut config = Self::init_default()?;
+        let path = Self::file_path()?;
+        if path.exists() {
+            config.merge_file(&path)?;
+        }
+        Ok(config)
+    }
+
+    /// Location of the config file
+    ///
+    /// `STUDFINDER_CONFIG` wins over `config.toml` in the platform config
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the platform config directory cannot be determined.
+    pub fn file_path() -> anyhow::Result<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_FILE_ENV) {
+            return Ok(PathBuf::from(path));
+        }
+        ProjectDirs::from("com", "studfinder", "studfinder")
+            .map(|dirs| dirs.config_dir().join(CONFIG_FILE_NAME))
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))
+    }
+
+    /// Apply the values found in a TOML config file
+    ///
+    /// Keys that are absent keep their current value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config` if the file cannot be parsed, contains an unknown key
+    /// or holds an invalid value.
+    pub fn merge_file(&mut self, path: &Path) -> Result<()> {
+        let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
+        let table: toml::Table = data
+            .parse()
+            .map_err(|e| StudFinderError::Config(format!("{}: {}", path.display(), e)))?;
+
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                other => other.to_string(),
+            };
+            self.set(&key, &value)
+                .map_err(|e| Self::in_context(&path.display().to_string(), e))?;
+        }
+        debug!("Loaded config file {}", path.display());
+        Ok(())
+    }
+
+    /// Apply `STUDFINDER_*` overrides from a set of environment variables
+    ///
+    /// Each key can be set by its upper-case name (`STUDFINDER_SCAN_QUALITY`)
+    /// or by the name of the matching CLI flag (`STUDFINDER_DB`,
+    /// `STUDFINDER_PROCESSOR`, `STUDFINDER_QUALITY`, `STUDFINDER_THRESHOLD`,
+    /// `STUDFINDER_PROFILE`).
+    /// Other `STUDFINDER_*` variables are ignored.
+    ///
+    /// The confidence threshold is applied last and may lower the review
+    /// threshold, see `fit_review_threshold`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config`, naming the variable, if a value is invalid or the
+    /// confidence threshold falls below an explicit review threshold.
+    pub fn apply_env<I>(&mut self, vars: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut threshold = None;
+        for (name, value) in vars {
+            let Some(suffix) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let suffix = suffix.to_ascii_lowercase();
+            let key = ENV_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == suffix)
+                .map_or(suffix.as_str(), |(_, key)| key);
+            if !CONFIG_KEYS.contains(&key) {
+                continue;
+            }
+            if key == "confidence_threshold" {
+                threshold = Some((name, value));
+                continue;
+            }
+
+            debug!("Applying {} from the environment", name);
+            self.set(key, &value)
+                .map_err(|e| Self::in_context(&name, e))?;
+        }
+
+        if let Some((name, value)) = threshold {
+            debug!("Applying {} from the environment", name);
+            self.set("confidence_threshold", &value)
+                .map_err(|e| Self::in_context(&name, e))?;
+            self.fit_review_threshold(&name)?;
+        }
+        Ok(())
+    }
+
+    /// Keep `review_threshold` at or below a newly set `confidence_threshold`
+    ///
+    /// A review threshold left at its default is lowered to the confidence
+    /// threshold, so that `--threshold 0.3` works on its own. A review
+    /// threshold that was changed is left alone, and the conflict is
+    /// reported against `source`, the flag or variable that set the
+    /// confidence threshold.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config`, naming `source`, if the confidence threshold is
+    /// below a review threshold other than the default.
+    pub fn fit_review_threshold(&mut self, source: &str) -> Result<()> {
+        let threshold = self.confidence_threshold;
+        // Out-of-range values are left for `validate` to report
+        if !(0.0..=1.0).contains(&threshold) || self.review_threshold <= threshold {
+            return Ok(());
+        }
+        if self.review_threshold == DEFAULT_REVIEW_THRESHOLD {
+            debug!(
+                "Lowering review_threshold to {} set by {}",
+                threshold, source
+            );
+            self.review_threshold = threshold;
+            return Ok(());
+        }
+        Err(StudFinderError::Config(format!(
+            "{}: confidence threshold {} is below review_threshold {}; lower review_threshold as well",
+            source, threshold, self.review_threshold
+        )))
+    }
+
+    /// Get a config value as a string
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config` if `key` is not a config key.
+    pub fn get(&self, key: &str) -> Result<String> {
+        Ok(match key {
+            "database_path" => self.database_path.display().to_string(),
+            "export_format" => self.export_format.to_string(),
+            "scan_quality" => self.scan_quality.to_string(),
+            "processor_type" => self.processor_type.to_string(),
+            "confidence_threshold" => self.confidence_threshold.to_string(),
+            "review_threshold" => self.review_threshold.to_string(),
+            "template_dir" => self.template_dir.display().to_string(),
+            "calibration_profile" => self.calibration_profile.clone().unwrap_or_default(),
+            _ => return Err(Self::unknown_key(key)),
+        })
+    }
+
+    /// Set a config value from a string
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use studfinder::{Config, ScanQuality};
+    ///
+    /// let mut config = Config::init_default().unwrap();
+    /// config.set("scan_quality", "accurate").unwrap();
+    /// assert_eq!(config.scan_quality, ScanQuality::Accurate);
+    /// assert!(config.set("scan_quality", "perfect").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config` if `key` is not a config key or `value` cannot be
+    /// parsed for it.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let invalid = |e: String| {
+            StudFinderError::Config(format!("invalid value '{}' for {}: {}", value, key, e))
+        };
+        match key {
+            "database_path" => self.database_path = PathBuf::from(value),
+            "export_format" => self.export_format = value.parse().map_err(invalid)?,
+            "scan_quality" => self.scan_quality = value.parse().map_err(invalid)?,
+            "processor_type" => self.processor_type = value.parse().map_err(invalid)?,
+            "confidence_threshold" => {
+                self.confidence_threshold = value
+                    .trim()
+                    .parse()
+                    .map_err(|e: std::num::ParseFloatError| invalid(e.to_string()))?;
+            }
+            "review_threshold" => {
+                self.review_threshold = value
+                    .trim()
+                    .parse()
+                    .map_err(|e: std::num::ParseFloatError| invalid(e.to_string()))?;
+            }
+            "template_dir" => self.template_dir = PathBuf::from(value),
+            // An empty name selects no profile
+            "calibration_profile" => {
+                let name = value.trim();
+                self.calibration_profile = (!name.is_empty()).then(|| name.to_string());
+            }
+            _ => return Err(Self::unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    /// Render the configuration as a TOML document
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        let mut table = toml::Table::new();
+        for key in CONFIG_KEYS {
+            let value = match key {
+                // Via the shortest decimal form, so 0.8 is not written as 0.800000011920929
+                "confidence_threshold" | "review_threshold" => {
+                    let value = self.get(key).unwrap_or_default();
+                    toml::Value::Float(value.parse().unwrap_or_default())
+                }
+                _ => toml::Value::String(self.get(key).unwrap_or_default()),
+            };
+            table.insert(key.to_string(), value);
+        }
+        table.to_string()
+    }
+
+    /// Write the configuration to a TOML file, creating its directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the directory or file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(StudFinderError::Io)?;
+        }
+        std::fs::write(path, self.to_toml()).map_err(StudFinderError::Io)?;
+        debug!("Wrote config file {}", path.display());
+        Ok(())
+    }
+
+    /// Check that the configuration can be used
+    ///
+    /// Checks that:
+    /// - `confidence_threshold` is between 0.0 and 1.0 (a percentage such as
+    ///   80 is reported with a hint)
+    /// - `review_threshold` is between 0.0 and `confidence_threshold`
+    /// - `database_path` is not empty and is, or can be created as, a
+    ///   writable file
+    /// - `template_dir` exists when the detector processor is selected
+    ///
+    /// A scan quality other than the default is accepted with the detector,
+    /// which does not use it, but logs a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` with a `ConfigError` naming the first field
+    /// that fails.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_threshold()?;
+        self.validate_review_threshold()?;
+        self.validate_database_path()?;
+        self.validate_processor()?;
+        Ok(())
+    }
+
+    fn validate_threshold(&self) -> std::result::Result<(), ConfigError> {
+        let threshold = self.confidence_threshold;
+        if threshold.is_finite() && (0.0..=1.0).contains(&threshold) {
+            return Ok(());
+        }
+
+        let mut expected = "a value between 0.0 and 1.0".to_string();
+        if threshold > 1.0 && threshold <= 100.0 {
+            expected.push_str(&format!(" (did you mean {}?)", threshold / 100.0));
+        }
+        Err(ConfigError::OutOfRange {
+            field: "confidence_threshold",
+            value: threshold.to_string(),
+            expected,
+        })
+    }
+
+    fn validate_review_threshold(&self) -> std::result::Result<(), ConfigError> {
+        let threshold = self.review_threshold;
+        if threshold.is_finite() && (0.0..=self.confidence_threshold).contains(&threshold) {
+            return Ok(());
+        }
+        Err(ConfigError::OutOfRange {
+            field: "review_threshold",
+            value: threshold.to_string(),
+            expected: format!(
+                "a value between 0.0 and confidence_threshold ({})",
+                self.confidence_threshold
+            ),
+        })
+    }
+
+    fn validate_database_path(&self) -> std::result::Result<(), ConfigError> {
+        const FIELD: &str = "database_path";
+        let path = &self.database_path;
+        if path.as_os_str().is_empty() {
+            return Err(ConfigError::EmptyPath {
+                field: FIELD,
+            });
+        }
+        if path == Path::new(MEMORY_DATABASE) {
+            return Ok(());
+        }
+
+        let not_writable = |path: &Path, reason: &str| ConfigError::NotWritable {
+            field: FIELD,
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        };
+
+        if let Ok(metadata) = std::fs::metadata(path) {
+            if metadata.is_dir() {
+                return Err(not_writable(path, "it is a directory"));
+            }
+            if metadata.permissions().readonly() {
+                return Err(not_writable(path, "the file is read-only"));
+            }
+            return Ok(());
+        }
+
+        // The database will be created, so its directory must be writable
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        match std::fs::metadata(dir) {
+            Ok(metadata) if !metadata.is_dir() => Err(not_writable(dir, "it is not a directory")),
+            Ok(metadata) if metadata.permissions().readonly() => {
+                Err(not_writable(dir, "the directory is read-only"))
+            }
+            Ok(_) => Ok(()),
+            Err(_) => Err(ConfigError::MissingDirectory {
+                field: FIELD,
+                path: dir.to_path_buf(),
+            }),
+        }
+    }
+
+    fn validate_processor(&self) -> std::result::Result<(), ConfigError> {
+        match self.processor_type {
+            ProcessorType::Scanner => Ok(()),
+            ProcessorType::Detector => {
+                if self.scan_quality != ScanQuality::Balanced {
+                    warn!(
+                        "scan_quality {} has no effect with the detector processor",
+                        self.scan_quality
+                    );
+                }
+                // Without templates the detector can never match anything
+                if self.template_dir.is_dir() {
+                    Ok(())
+                } else {
+                    Err(ConfigError::MissingDirectory {
+                        field: "template_dir",
+                        path: self.template_dir.clone(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Prefix a config error with where the bad value came from
+    fn in_context(source: &str, error: StudFinderError) -> StudFinderError {
+        match error {
+            StudFinderError::Config(message) => {
+                StudFinderError::Config(format!("{}: {}", source, message))
+            }
+            other => other,
+        }
+    }
+
+    fn unknown_key(key: &str) -> StudFinderError {
+        StudFinderError::Config(format!(
+            "unknown config key '{}' (expected one of: {})",
+            key,
+            CONFIG_KEYS.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    fn create_test_config() -> Config {
+        Config {
+            database_path: PathBuf::from("studfinder.db"),
+            export_format: ExportFormat::Json,
+            scan_quality: ScanQuality::Balanced,
+            processor_type: ProcessorType::Scanner,
+            confidence_threshold: 0.8,
+            review_threshold: 0.5,
+            template_dir: PathBuf::from("templates"),
+            calibration_profile: None,
+        }
+    }
+
+    #[test]
+    fn test_get_set() {
+        let mut config = create_test_config();
+        config.set("processor_type", "Detector").unwrap();
+        config.set("confidence_threshold", "0.65").unwrap();
+        config.set("export_format", "bricklink").unwrap();
+
+        assert_eq!(config.processor_type, ProcessorType::Detector);
+        assert_eq!(config.get("confidence_threshold").unwrap(), "0.65");
+        assert_eq!(config.get("export_format").unwrap(), "bricklink");
+
+        assert!(config.set("confidence_threshold", "high").is_err());
+        assert!(config.get("colour").is_err());
+    }
+
+    #[test]
+    fn test_save_and_merge_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nested").join(CONFIG_FILE_NAME);
+
+        let mut config = create_test_config();
+        config.scan_quality = ScanQuality::Accurate;
+        config.confidence_threshold = 0.5;
+        config.save(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("confidence_threshold = 0.5\n"), "{}", text);
+        assert!(text.contains("scan_quality = \"accurate\"\n"), "{}", text);
+
+        let mut loaded = create_test_config();
+        loaded.merge_file(&path).unwrap();
+        assert_eq!(loaded.scan_quality, ScanQuality::Accurate);
+        assert_eq!(loaded.confidence_threshold, 0.5);
+
+        // Partial files only change the keys they mention
+        std::fs::write(&path, "processor_type = \"detector\"\n").unwrap();
+        let mut loaded = create_test_config();
+        loaded.merge_file(&path).unwrap();
+        assert_eq!(loaded.processor_type, ProcessorType::Detector);
+        assert_eq!(loaded.scan_quality, ScanQuality::Balanced);
+
+        std::fs::write(&path, "scan_qualty = \"fast\"\n").unwrap();
+        assert!(create_test_config().merge_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let mut config = create_test_config();
+        let vars = [
+            ("STUDFINDER_DB", "/tmp/other.db"),
+            ("STUDFINDER_SCAN_QUALITY", "fast"),
+            ("STUDFINDER_LOG", "debug"),
+            ("HOME", "/root"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        config.apply_env(vars).unwrap();
+
+        assert_eq!(config.database_path, PathBuf::from("/tmp/other.db"));
+        assert_eq!(config.scan_quality, ScanQuality::Fast);
+
+        // A default review threshold follows the accept threshold down
+        let vars = [
+            (
+                "STUDFINDER_CONFIDENCE_THRESHOLD".to_string(),
+                "0.3".to_string(),
+            ),
+        ];
+        config.apply_env(vars).unwrap();
+        assert_eq!(config.confidence_threshold, 0.3);
+        assert_eq!(config.review_threshold, 0.3);
+
+        // An explicit one is kept, and the conflict names the variable
+        let mut config = create_test_config();
+        let vars = [
+            ("STUDFINDER_THRESHOLD", "0.3"),
+            ("STUDFINDER_REVIEW_THRESHOLD", "0.4"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        match config.apply_env(vars) {
+            Err(StudFinderError::Config(message)) => {
+                assert!(message.contains("STUDFINDER_THRESHOLD"), "{}", message);
+            }
+            other => panic!("Expected Config error, got: {:?}", other),
+        }
+
+        let vars = [("STUDFINDER_THRESHOLD".to_string(), "lots".to_string())];
+        match config.apply_env(vars) {
+            Err(StudFinderError::Config(message)) => {
+                assert!(message.contains("STUDFINDER_THRESHOLD"), "{}", message);
+            }
+            other => panic!("Expected Config error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_threshold() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            database_path: temp_dir.path().join("test.db"),
+            ..create_test_config()
+        };
+        assert!(config.validate().is_ok());
+
+        config.confidence_threshold = 80.0;
+        match config.validate() {
+            Err(StudFinderError::InvalidConfig(error)) => {
+                assert_eq!(error.field(), "confidence_threshold");
+                assert!(error.to_string().contains("did you mean 0.8?"), "{}", error);
+            }
+            other => panic!("Expected InvalidConfig, got: {:?}", other),
+        }
+
+        config.confidence_threshold = f32::NAN;
+        assert!(config.validate().is_err());
+
+        // Reviews only cover confidences below the accept threshold
+        config.confidence_threshold = 0.8;
+        config.review_threshold = 0.9;
+        match config.validate() {
+            Err(StudFinderError::InvalidConfig(error)) => {
+                assert_eq!(error.field(), "review_threshold");
+            }
+            other => panic!("Expected InvalidConfig, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let field = |config: &Config| match config.validate() {
+            Err(StudFinderError::InvalidConfig(error)) => error.field(),
+            other => panic!("Expected InvalidConfig, got: {:?}", other),
+        };
+
+        let mut config = Config {
+            database_path: temp_dir.path().join("missing").join("test.db"),
+            ..create_test_config()
+        };
+        assert_eq!(field(&config), "database_path");
+
+        // A directory is not a database file
+        config.database_path = temp_dir.path().to_path_buf();
+        assert_eq!(field(&config), "database_path");
+
+        config.database_path = PathBuf::from(MEMORY_DATABASE);
+        assert!(config.validate().is_ok());
+
+        // The detector needs its templates
+        config.processor_type = ProcessorType::Detector;
+        config.template_dir = temp_dir.path().join("templates");
+        assert_eq!(field(&config), "template_dir");
+
+        std::fs::create_dir(&config.template_dir).unwrap();
+        assert!(config.validate().is_ok());
+    }
 }
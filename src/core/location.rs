@@ -0,0 +1,189 @@
+use super::piece::Piece;
+use serde::{Deserialize, Serialize};
+
+/// Where pieces are kept: a cabinet, optionally narrowed to a drawer and a bin
+///
+/// Written as the levels joined by `/`, e.g. `A/3/12` for bin 12 of drawer 3
+/// in cabinet A.
+///
+/// # Examples
+///
+/// ```
+/// use studfinder::core::StorageLocation;
+///
+/// let location: StorageLocation = " A / 3 / 12 ".parse().unwrap();
+/// assert_eq!(location.drawer.as_deref(), Some("3"));
+/// assert_eq!(location.to_string(), "A/3/12");
+/// assert!("A//12".parse::<StorageLocation>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct StorageLocation {
+    /// Cabinet, shelf or other top-level storage unit
+    pub cabinet: String,
+    /// Drawer within the cabinet
+    pub drawer: Option<String>,
+    /// Bin within the drawer
+    pub bin: Option<String>,
+}
+
+impl std::fmt::Display for StorageLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cabinet)?;
+        for level in [
+            &self.drawer,
+            &self.bin,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            write!(f, "/{}", level)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for StorageLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let levels: Vec<&str> = s.split('/').map(str::trim).collect();
+        if levels.iter().any(|level| level.is_empty()) {
+            return Err(format!(
+                "invalid location '{}' (expected cabinet[/drawer[/bin]])",
+                s.trim()
+            ));
+        }
+        match levels.as_slice() {
+            [cabinet] => Ok(Self {
+                cabinet: cabinet.to_string(),
+                drawer: None,
+                bin: None,
+            }),
+            [cabinet, drawer] => Ok(Self {
+                cabinet: cabinet.to_string(),
+                drawer: Some(drawer.to_string()),
+                bin: None,
+            }),
+            [cabinet, drawer, bin] => Ok(Self {
+                cabinet: cabinet.to_string(),
+                drawer: Some(drawer.to_string()),
+                bin: Some(bin.to_string()),
+            }),
+            _ => Err(format!(
+                "invalid location '{}' (at most cabinet/drawer/bin)",
+                s.trim()
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for StorageLocation {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<StorageLocation> for String {
+    fn from(location: StorageLocation) -> Self {
+        location.to_string()
+    }
+}
+
+/// Part of an inventory entry's quantity kept in one location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceLocation {
+    /// Where the pieces are
+    pub location: StorageLocation,
+    /// How many of the entry's pieces are there
+    pub quantity: i32,
+}
+
+/// An inventory entry with its quantity split across storage locations
+///
+/// Pieces not assigned to any location, such as freshly scanned ones, are
+/// counted as `unplaced`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocatedPiece {
+    /// The inventory entry
+    #[serde(flatten)]
+    pub piece: Piece,
+    /// Locations holding some of the entry's pieces, in location order
+    pub locations: Vec<PieceLocation>,
+    /// Pieces not in any location
+    pub unplaced: i32,
+}
+
+impl LocatedPiece {
+    /// Pair an entry with its locations
+    ///
+    /// # Arguments
+    /// * `piece` - The inventory entry
+    /// * `locations` - Where its pieces are kept
+    #[must_use]
+    pub fn new(piece: Piece, mut locations: Vec<PieceLocation>) -> Self {
+        locations.sort_by(|a, b| a.location.cmp(&b.location));
+        let placed: i32 = locations.iter().map(|l| l.quantity).sum();
+        Self {
+            unplaced: (piece.quantity - placed).max(0),
+            piece,
+            locations,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        let location: StorageLocation = "A".parse().unwrap();
+        assert_eq!(location.drawer, None);
+        assert_eq!(location.to_string(), "A");
+
+        let location: StorageLocation = "Cabinet 1/Top drawer".parse().unwrap();
+        assert_eq!(location.cabinet, "Cabinet 1");
+        assert_eq!(location.bin, None);
+
+        assert!("".parse::<StorageLocation>().is_err());
+        assert!("A/1/2/3".parse::<StorageLocation>().is_err());
+        assert!("A/".parse::<StorageLocation>().is_err());
+
+        // Serialized as the written form
+        assert_eq!(
+            serde_json::to_string(&"A/3/12".parse::<StorageLocation>().unwrap()).unwrap(),
+            "\"A/3/12\""
+        );
+    }
+
+    #[test]
+    fn test_located_piece_counts_unplaced() {
+        let piece = Piece {
+            id: "a".to_string(),
+            part_number: "3023".to_string(),
+            color: "Dark Tan".to_string(),
+            category: "Plates".to_string(),
+            quantity: 10,
+            confidence: 1.0,
+            condition: None,
+        };
+        let at = |location: &str, quantity| PieceLocation {
+            location: location.parse().unwrap(),
+            quantity,
+        };
+
+        let located = LocatedPiece::new(
+            piece,
+            vec![
+                at("B/1", 3),
+                at("A/3/12", 4),
+            ],
+        );
+        assert_eq!(located.unplaced, 3);
+        assert_eq!(located.locations[0].location.to_string(), "A/3/12");
+    }
+}
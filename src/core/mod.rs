@@ -1,5 +1,17 @@
+pub mod batch;
+pub mod catalog;
 pub mod config;
+pub mod location;
 pub mod piece;
+pub mod review;
+pub mod set;
+pub mod watch;
 
+pub use batch::*;
+pub use catalog::*;
 pub use config::*;
+pub use location::*;
 pub use piece::*;
+pub use review::*;
+pub use set::*;
+pub use watch::*;
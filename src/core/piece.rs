@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Represents a LEGO piece with its properties and metadata
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Piece {
     /// Unique identifier for the piece
     pub id: String,
@@ -15,6 +15,9 @@ pub struct Piece {
     pub quantity: i32,
     /// Confidence level of the detection (0.0-1.0)
     pub confidence: f32,
+    /// Condition of the piece, if tracked
+    #[serde(default)]
+    pub condition: Option<PieceCondition>,
 }
 
 impl std::fmt::Display for Piece {
@@ -31,8 +34,50 @@ impl std::fmt::Display for Piece {
     }
 }
 
+/// Condition of a piece, as used by marketplaces such as BrickLink
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PieceCondition {
+    /// Unused piece
+    New,
+    /// Previously used piece
+    Used,
+}
+
+impl PieceCondition {
+    /// Lowercase name used for storage and display
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PieceCondition::New => "new",
+            PieceCondition::Used => "used",
+        }
+    }
+}
+
+impl std::fmt::Display for PieceCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for PieceCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "new" | "n" => Ok(PieceCondition::New),
+            "used" | "u" => Ok(PieceCondition::Used),
+            other => Err(format!(
+                "unknown condition '{}' (expected new or used)",
+                other
+            )),
+        }
+    }
+}
+
 /// Type of image processor to use
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessorType {
     /// Scanner implementation (color-based detection)
     Scanner,
@@ -40,17 +85,145 @@ pub enum ProcessorType {
     Detector,
 }
 
+impl ProcessorType {
+    /// Lowercase name used on the command line and in the config file
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProcessorType::Scanner => "scanner",
+            ProcessorType::Detector => "detector",
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ProcessorType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "scanner" => Ok(ProcessorType::Scanner),
+            "detector" => Ok(ProcessorType::Detector),
+            other => Err(format!(
+                "unknown processor '{}' (expected scanner or detector)",
+                other
+            )),
+        }
+    }
+}
+
 /// Format for exporting inventory data
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum ExportFormat {
     /// JSON format
     Json,
     /// CSV format
     Csv,
+    /// Rebrickable parts-list CSV (Part, Color, Quantity, Is Spare)
+    Rebrickable,
+    /// BrickLink XML upload format (wanted list or store inventory)
+    BrickLinkXml,
+    /// LDraw model (`.ldr`/`.mpd`), import only
+    LDraw,
+}
+
+impl ExportFormat {
+    /// Name used on the command line
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Rebrickable => "rebrickable",
+            ExportFormat::BrickLinkXml => "bricklink",
+            ExportFormat::LDraw => "ldraw",
+        }
+    }
+
+    /// Infer a format from a file name's extension
+    ///
+    /// `.csv` files are taken to be StudFinder's own CSV; Rebrickable parts
+    /// lists have to be requested explicitly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::ExportFormat;
+    ///
+    /// assert_eq!(ExportFormat::from_path("wanted.xml"), Some(ExportFormat::BrickLinkXml));
+    /// assert_eq!(ExportFormat::from_path("pieces"), None);
+    /// ```
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "xml" => Some(ExportFormat::BrickLinkXml),
+            "ldr" | "mpd" => Some(ExportFormat::LDraw),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "rebrickable" => Ok(ExportFormat::Rebrickable),
+            "bricklink" | "bricklink-xml" | "bricklinkxml" | "xml" => {
+                Ok(ExportFormat::BrickLinkXml)
+            }
+            "ldraw" | "ldr" | "mpd" => Ok(ExportFormat::LDraw),
+            other => Err(format!(
+                "unknown format '{}' (expected json, csv, rebrickable, bricklink or ldraw)",
+                other
+            )),
+        }
+    }
+}
+
+/// Kind of BrickLink XML document to produce
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BrickLinkList {
+    /// Wanted list upload (`MINQTY`, condition may be "any")
+    #[default]
+    Wanted,
+    /// Store inventory mass upload (`QTY`, condition required)
+    Store,
+}
+
+impl std::str::FromStr for BrickLinkList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "wanted" | "wanted-list" => Ok(BrickLinkList::Wanted),
+            "store" | "inventory" => Ok(BrickLinkList::Store),
+            other => Err(format!(
+                "unknown BrickLink list '{}' (expected wanted or store)",
+                other
+            )),
+        }
+    }
 }
 
 /// Quality level for scanning
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum ScanQuality {
     /// Fast scanning (lower accuracy, higher speed)
     Fast,
@@ -59,3 +232,37 @@ pub enum ScanQuality {
     /// Accurate scanning (higher accuracy, lower speed)
     Accurate,
 }
+
+impl ScanQuality {
+    /// Lowercase name used on the command line and in the config file
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanQuality::Fast => "fast",
+            ScanQuality::Balanced => "balanced",
+            ScanQuality::Accurate => "accurate",
+        }
+    }
+}
+
+impl std::fmt::Display for ScanQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ScanQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fast" => Ok(ScanQuality::Fast),
+            "balanced" => Ok(ScanQuality::Balanced),
+            "accurate" => Ok(ScanQuality::Accurate),
+            other => Err(format!(
+                "unknown scan quality '{}' (expected fast, balanced or accurate)",
+                other
+            )),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::config::Config;
+use super::piece::Piece;
+use crate::processing::studs::UNKNOWN_PART;
+use serde::Serialize;
+
+/// What happens to a detection, decided by its confidence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewBand {
+    /// Added to the inventory straight away
+    Accept,
+    /// Held in the review queue until someone accepts, corrects or discards it
+    Review,
+    /// Dropped
+    Reject,
+}
+
+impl ReviewBand {
+    /// Lowercase name of the band
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReviewBand::Accept => "accept",
+            ReviewBand::Review => "review",
+            ReviewBand::Reject => "reject",
+        }
+    }
+}
+
+impl std::fmt::Display for ReviewBand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Three-band policy splitting detections by confidence
+///
+/// Detections at or above `accept_threshold` are accepted, those at or
+/// above `review_threshold` are queued for review and the rest are rejected.
+///
+/// # Examples
+///
+/// ```
+/// use studfinder::core::{ReviewBand, ReviewPolicy};
+///
+/// let policy = ReviewPolicy {
+///     accept_threshold: 0.8,
+///     review_threshold: 0.5,
+/// };
+/// assert_eq!(policy.band(0.9), ReviewBand::Accept);
+/// assert_eq!(policy.band(0.6), ReviewBand::Review);
+/// assert_eq!(policy.band(0.2), ReviewBand::Reject);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReviewPolicy {
+    /// Lowest confidence that is accepted without review
+    pub accept_threshold: f32,
+    /// Lowest confidence that is queued for review
+    pub review_threshold: f32,
+}
+
+impl ReviewPolicy {
+    /// The policy set by `confidence_threshold` and `review_threshold`
+    #[must_use]
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            accept_threshold: config.confidence_threshold,
+            review_threshold: config.review_threshold,
+        }
+    }
+
+    /// The band a detected piece falls in
+    ///
+    /// Like `band`, except that a piece whose part could not be identified
+    /// is never accepted: it is queued for review however sure its color
+    /// is, and rejected like any other piece below `review_threshold`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::core::{Piece, ReviewBand, ReviewPolicy};
+    ///
+    /// let policy = ReviewPolicy {
+    ///     accept_threshold: 0.8,
+    ///     review_threshold: 0.5,
+    /// };
+    /// let piece = Piece {
+    ///     id: String::new(),
+    ///     part_number: "unknown".to_string(),
+    ///     color: "Red".to_string(),
+    ///     category: "Unknown".to_string(),
+    ///     quantity: 1,
+    ///     confidence: 0.5,
+    ///     condition: None,
+    /// };
+    /// assert_eq!(policy.band_for(&piece), ReviewBand::Review);
+    ///
+    /// let sure = Piece { confidence: 0.95, ..piece.clone() };
+    /// assert_eq!(policy.band_for(&sure), ReviewBand::Review);
+    ///
+    /// let noise = Piece { confidence: 0.1, ..piece };
+    /// assert_eq!(policy.band_for(&noise), ReviewBand::Reject);
+    /// ```
+    #[must_use]
+    pub fn band_for(&self, piece: &Piece) -> ReviewBand {
+        match self.band(piece.confidence) {
+            ReviewBand::Accept if piece.part_number == UNKNOWN_PART => ReviewBand::Review,
+            band => band,
+        }
+    }
+
+    /// The band a detection with this confidence falls in
+    #[must_use]
+    pub fn band(&self, confidence: f32) -> ReviewBand {
+        if confidence >= self.accept_threshold {
+            ReviewBand::Accept
+        } else if confidence >= self.review_threshold {
+            ReviewBand::Review
+        } else {
+            ReviewBand::Reject
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use super::catalog::PartCatalog;
+use super::piece::Piece;
+use crate::processing::palette;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A LEGO set and the parts it is built from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegoSet {
+    /// Set number with its version suffix, e.g. "10696-1"
+    pub set_number: String,
+    /// Name of the set
+    pub name: String,
+    /// Year the set was released
+    pub year: Option<i32>,
+    /// The parts in the set, spares excluded
+    pub parts: Vec<SetPart>,
+}
+
+/// A part number and color needed in some quantity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetPart {
+    /// LEGO part number
+    pub part_number: String,
+    /// Color name
+    pub color: String,
+    /// Number of pieces
+    pub quantity: i32,
+}
+
+/// Add the `-1` version suffix to a bare set number
+///
+/// # Examples
+///
+/// ```
+/// use studfinder::core::normalize_set_number;
+///
+/// assert_eq!(normalize_set_number("10696"), "10696-1");
+/// assert_eq!(normalize_set_number(" 10696-2 "), "10696-2");
+/// ```
+#[must_use]
+pub fn normalize_set_number(set_number: &str) -> String {
+    let set_number = set_number.trim();
+    if set_number.contains('-') {
+        set_number.to_string()
+    } else {
+        format!("{}-1", set_number)
+    }
+}
+
+/// How much of a set the inventory covers
+///
+/// Part numbers are compared through the part catalog, so a piece stored
+/// under an alternate number counts for the set's part. Quantities are
+/// summed over conditions. Colors are compared by their BrickLink name, so
+/// a piece stored as "Bright Red" counts for a set part in "Red"; colors
+/// missing from the palette must match by name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SetCheck {
+    /// Set number with its version suffix
+    pub set_number: String,
+    /// Name of the set
+    pub name: String,
+    /// Number of pieces in the set
+    pub required: i32,
+    /// Number of the set's pieces found in the inventory
+    pub owned: i32,
+    /// Share of the set's pieces found in the inventory (0-100)
+    pub percent_complete: f32,
+    /// Pieces still needed, by part number and color
+    pub missing: Vec<SetPart>,
+    /// Pieces of the set's parts owned beyond what the set needs
+    pub surplus: Vec<SetPart>,
+}
+
+impl SetCheck {
+    /// Compare a set's parts against the inventory
+    ///
+    /// # Arguments
+    /// * `set` - The set to check
+    /// * `pieces` - Every inventory entry
+    /// * `catalog` - Used to resolve alternate part numbers
+    #[must_use]
+    pub fn new(set: &LegoSet, pieces: &[Piece], catalog: &PartCatalog) -> Self {
+        let key = |part_number: &str, color: &str| {
+            let part_number = catalog
+                .get(part_number)
+                .map_or(part_number, |part| part.part_number.as_str());
+            let color = palette::find_by_name(color).map_or(color, |c| c.bricklink_name);
+            (part_number.to_string(), color.to_string())
+        };
+
+        let mut needed: BTreeMap<(String, String), i32> = BTreeMap::new();
+        for part in &set.parts {
+            *needed
+                .entry(key(&part.part_number, &part.color))
+                .or_default() += part.quantity;
+        }
+        let mut have: BTreeMap<(String, String), i32> = BTreeMap::new();
+        for piece in pieces {
+            *have
+                .entry(key(&piece.part_number, &piece.color))
+                .or_default() += piece.quantity;
+        }
+
+        let mut check = Self {
+            set_number: set.set_number.clone(),
+            name: set.name.clone(),
+            required: 0,
+            owned: 0,
+            percent_complete: 100.0,
+            missing: Vec::new(),
+            surplus: Vec::new(),
+        };
+        for ((part_number, color), &need) in &needed {
+            let owned = have
+                .get(&(part_number.clone(), color.clone()))
+                .copied()
+                .unwrap_or(0);
+            check.required += need;
+            check.owned += owned.min(need);
+            let part = |quantity| SetPart {
+                part_number: part_number.clone(),
+                color: color.clone(),
+                quantity,
+            };
+            if owned < need {
+                check.missing.push(part(need - owned));
+            } else if owned > need {
+                check.surplus.push(part(owned - need));
+            }
+        }
+        if check.required > 0 {
+            check.percent_complete = check.owned as f32 * 100.0 / check.required as f32;
+        }
+        check
+    }
+
+    /// Whether every piece of the set is in the inventory
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    fn piece(part_number: &str, color: &str, quantity: i32) -> Piece {
+        Piece {
+            id: format!("{}-{}", part_number, color),
+            part_number: part_number.to_string(),
+            color: color.to_string(),
+            category: "Bricks".to_string(),
+            quantity,
+            confidence: 1.0,
+            condition: None,
+        }
+    }
+
+    fn part(part_number: &str, color: &str, quantity: i32) -> SetPart {
+        SetPart {
+            part_number: part_number.to_string(),
+            color: color.to_string(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn test_set_check() {
+        let set = LegoSet {
+            set_number: "10696-1".to_string(),
+            name: "Medium Creative Brick Box".to_string(),
+            year: Some(2015),
+            parts: vec![
+                part("3001", "Red", 4),
+                part("3062b", "Yellow", 2),
+                part("3020", "Blue", 2),
+                part("3003", "Black", 1),
+            ],
+        };
+        let pieces = [
+            piece("3001", "Red", 6),
+            // Stored under an alternate number
+            piece("3062", "Yellow", 2),
+            piece("3020", "Blue", 1),
+            piece("3003", "White", 5),
+        ];
+
+        let check = SetCheck::new(&set, &pieces, &PartCatalog::builtin());
+        assert_eq!((check.required, check.owned), (9, 7));
+        assert!((check.percent_complete - 700.0 / 9.0).abs() < 1e-4);
+        assert_eq!(
+            check.missing,
+            vec![
+                part("3003", "Black", 1),
+                part("3020", "Blue", 1),
+            ]
+        );
+        // Parts the set does not use are not surplus
+        assert_eq!(check.surplus, vec![part("3001", "Red", 2)]);
+        assert!(!check.is_complete());
+    }
+
+    #[test]
+    fn test_set_check_matches_color_names_across_standards() {
+        let set = LegoSet {
+            set_number: "10696-1".to_string(),
+            name: "Medium Creative Brick Box".to_string(),
+            year: Some(2015),
+            parts: vec![
+                part("3001", "Red", 3),
+                part("3004", "Light Bluish Gray", 1),
+                part("3003", "Rebrickable color 9999", 1),
+            ],
+        };
+        let pieces = [
+            // Scanned with LEGO official color names
+            piece("3001", "Bright Red", 2),
+            piece("3004", "medium stone grey", 1),
+            piece("3003", "Rebrickable color 9999", 1),
+        ];
+
+        let check = SetCheck::new(&set, &pieces, &PartCatalog::builtin());
+        assert_eq!((check.required, check.owned), (5, 4));
+        // Reported under the BrickLink name
+        assert_eq!(check.missing, vec![part("3001", "Red", 1)]);
+        assert!(check.surplus.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use crate::core::batch::is_image_path;
+use crate::error::{Result, StudFinderError};
+use notify::event::{AccessKind, AccessMode};
+use notify::{Event, EventKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Subfolder of a watched folder that scanned images are moved into
+pub const DONE_DIR: &str = "done";
+
+/// Subfolder of a watched folder that images which failed are moved into
+pub const FAILED_DIR: &str = "failed";
+
+/// Settings for `StudFinder::watch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// How long a file must go without changes before it is scanned
+    pub settle: Duration,
+    /// Move scanned images into `done/` and failed ones into `failed/`
+    pub move_processed: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            settle: Duration::from_secs(2),
+            move_processed: false,
+        }
+    }
+}
+
+/// A file waiting for its writes to settle
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    /// When the file last changed
+    changed: Instant,
+    /// Its size at that time, if it could be read
+    size: Option<u64>,
+}
+
+/// Images seen changing in a watched folder, held until they stop changing
+///
+/// A file is settled once `settle` has passed since its last event and its
+/// size is what it was then. A file that disappears meanwhile is dropped.
+#[derive(Debug)]
+pub struct SettleQueue {
+    settle: Duration,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+impl SettleQueue {
+    /// Create an empty queue
+    ///
+    /// # Arguments
+    /// * `settle` - How long a file must go without changes
+    #[must_use]
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Note a filesystem event in the watched folder `dir`
+    ///
+    /// New, modified, renamed-in and closed-after-writing images directly in
+    /// `dir` are queued; removed files are forgotten. Other files, including
+    /// those in the `done/` and `failed/` subfolders, are ignored.
+    pub fn observe(&mut self, dir: &Path, event: &Event, now: Instant) {
+        let changed = matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(_)
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        );
+        for path in &event.paths {
+            if path.parent() != Some(dir) || !is_image_path(path) {
+                continue;
+            }
+            if changed {
+                self.touch(path.clone(), now);
+            } else if matches!(event.kind, EventKind::Remove(_)) {
+                self.pending.remove(path);
+            }
+        }
+    }
+
+    /// Restart the settle wait of a file
+    pub fn touch(&mut self, path: PathBuf, now: Instant) {
+        let size = file_size(&path);
+        self.pending.insert(
+            path,
+            Pending {
+                changed: now,
+                size,
+            },
+        );
+    }
+
+    /// When the next file is due to be checked
+    #[must_use]
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending
+            .values()
+            .map(|pending| pending.changed + self.settle)
+            .min()
+    }
+
+    /// Take the files that have settled, in path order
+    ///
+    /// Due files whose size changed without an event wait another `settle`.
+    pub fn take_settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        let due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.changed + self.settle <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let mut settled = Vec::new();
+        for path in due {
+            let size = file_size(&path);
+            if size.is_none() {
+                debug!("{} is gone", path.display());
+                self.pending.remove(&path);
+            } else if self.pending.get(&path).is_some_and(|p| p.size == size) {
+                self.pending.remove(&path);
+                settled.push(path);
+            } else {
+                debug!("{} is still being written", path.display());
+                self.touch(path, now);
+            }
+        }
+        settled.sort();
+        settled
+    }
+
+    /// Whether no file is waiting
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Size of a regular file, or `None` if it is missing or not a file
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+}
+
+/// Move a processed image into the `done/` or `failed/` subfolder of `dir`
+///
+/// The subfolder is created if needed. A file already there with the same
+/// name is kept, and the image gets a numbered name such as `piece-1.jpg`.
+///
+/// # Arguments
+/// * `dir` - The watched folder
+/// * `path` - The image to move
+/// * `succeeded` - Whether the image was scanned
+///
+/// # Returns
+/// * `Result<PathBuf>` - Where the image was moved to, or an error
+///
+/// # Errors
+///
+/// Returns `Io` if the subfolder cannot be created or the file cannot be
+/// moved.
+pub fn move_processed(dir: &Path, path: &Path, succeeded: bool) -> Result<PathBuf> {
+    let target_dir = dir.join(if succeeded { DONE_DIR } else { FAILED_DIR });
+    std::fs::create_dir_all(&target_dir).map_err(StudFinderError::Io)?;
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut target = target_dir.join(format!("{}{}", stem, extension));
+    let mut copy = 0;
+    while target.exists() {
+        copy += 1;
+        target = target_dir.join(format!("{}-{}{}", stem, copy, extension));
+    }
+
+    std::fs::rename(path, &target).map_err(StudFinderError::Io)?;
+    debug!("Moved {} to {}", path.display(), target.display());
+    Ok(target)
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+
+    #[test]
+    fn test_settle_queue() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let image = dir.join("a.jpg");
+        std::fs::write(&image, "abc").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let settle = Duration::from_millis(100);
+        let mut queue = SettleQueue::new(settle);
+        let start = Instant::now();
+        let created = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(image.clone())
+            .add_path(dir.join("notes.txt"))
+            .add_path(dir.join(DONE_DIR).join("b.jpg"));
+        queue.observe(dir, &created, start);
+        assert_eq!(queue.next_due(), Some(start + settle));
+        assert!(queue.take_settled(start).is_empty());
+
+        // Grown since the event, so it waits again
+        std::fs::write(&image, "abcdef").unwrap();
+        assert!(queue.take_settled(start + settle).is_empty());
+        assert_eq!(queue.next_due(), Some(start + settle * 2));
+        assert_eq!(queue.take_settled(start + settle * 2), vec![image.clone()]);
+        assert!(queue.is_empty());
+
+        queue.observe(dir, &created, start);
+        let removed = Event::new(EventKind::Remove(RemoveKind::File)).add_path(image);
+        queue.observe(dir, &removed, start);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_move_processed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        for _ in 0..2 {
+            std::fs::write(dir.join("piece.jpg"), "").unwrap();
+            move_processed(dir, &dir.join("piece.jpg"), true).unwrap();
+        }
+        std::fs::write(dir.join("blurry.png"), "").unwrap();
+        let moved = move_processed(dir, &dir.join("blurry.png"), false).unwrap();
+
+        assert!(dir.join(DONE_DIR).join("piece.jpg").is_file());
+        assert!(dir.join(DONE_DIR).join("piece-1.jpg").is_file());
+        assert_eq!(moved, dir.join(FAILED_DIR).join("blurry.png"));
+        assert!(!dir.join("piece.jpg").exists());
+    }
+}
@@ -72,10 +72,34 @@ pub enum StudFinderError {
     #[error("Piece not found: {0}")]
     PieceNotFound(String),
 
+    /// Requested part is not in the part catalog
+    #[error("Part not found: {0}")]
+    PartNotFound(String),
+
+    /// Requested detection is not in the review queue
+    #[error("Review item not found: {0}")]
+    ReviewNotFound(String),
+
+    /// Requested set has not been imported
+    #[error("Set not found: {0}")]
+    SetNotFound(String),
+
+    /// Requested calibration profile has not been created
+    #[error("Calibration profile not found: {0}")]
+    CalibrationNotFound(String),
+
+    /// A requested change to a piece is not valid
+    #[error("Invalid piece: {0}")]
+    InvalidPiece(String),
+
     /// Configuration error occurred
     #[error("Invalid configuration: {0}")]
     Config(String),
 
+    /// A configuration value failed validation
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(#[from] ConfigError),
+
     /// No LEGO pieces were detected in the processed image
     #[error("No pieces detected in image")]
     NoPiecesDetected,
@@ -87,6 +111,243 @@ pub enum StudFinderError {
     /// Template matching failed
     #[error("Template matching failed: {0}")]
     TemplateMatchingFailed(String),
+
+    /// A reference image could not be used for calibration
+    #[error("Calibration failed: {0}")]
+    CalibrationFailed(String),
+
+    /// A part catalog dump could not be read
+    #[error("Catalog import failed: {0}")]
+    CatalogImportFailed(String),
+
+    /// A set inventory dump could not be read
+    #[error("Set import failed: {0}")]
+    SetImportFailed(String),
+
+    /// Inventory could not be written in the requested format
+    #[error("Export failed: {0}")]
+    ExportFailed(String),
+
+    /// An inventory file could not be read
+    #[error("Import failed: {0}")]
+    ImportFailed(String),
+}
+
+impl StudFinderError {
+    /// Stable snake_case name of the error variant, for structured output
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StudFinderError::Database {
+                ..
+            } => "database",
+            StudFinderError::Migration {
+                ..
+            } => "migration",
+            StudFinderError::DatabaseLockFailed {
+                ..
+            } => "database_lock_failed",
+            StudFinderError::DatabaseResetFailed {
+                ..
+            } => "database_reset_failed",
+            StudFinderError::Image(_) => "image",
+            StudFinderError::Io(_) => "io",
+            StudFinderError::InvalidDimensions {
+                ..
+            } => "invalid_dimensions",
+            StudFinderError::UnsupportedFormat(_) => "unsupported_format",
+            StudFinderError::PieceNotFound(_) => "piece_not_found",
+            StudFinderError::PartNotFound(_) => "part_not_found",
+            StudFinderError::ReviewNotFound(_) => "review_not_found",
+            StudFinderError::SetNotFound(_) => "set_not_found",
+            StudFinderError::CalibrationNotFound(_) => "calibration_not_found",
+            StudFinderError::InvalidPiece(_) => "invalid_piece",
+            StudFinderError::Config(_) => "config",
+            StudFinderError::InvalidConfig(_) => "invalid_config",
+            StudFinderError::NoPiecesDetected => "no_pieces_detected",
+            StudFinderError::ColorDetectionFailed(_) => "color_detection_failed",
+            StudFinderError::CalibrationFailed(_) => "calibration_failed",
+            StudFinderError::TemplateMatchingFailed(_) => "template_matching_failed",
+            StudFinderError::CatalogImportFailed(_) => "catalog_import_failed",
+            StudFinderError::SetImportFailed(_) => "set_import_failed",
+            StudFinderError::ExportFailed(_) => "export_failed",
+            StudFinderError::ImportFailed(_) => "import_failed",
+        }
+    }
+
+    /// Process exit code for the error
+    ///
+    /// Codes group related variants and do not change between releases:
+    ///
+    /// | Code | Meaning                                                   |
+    /// |------|-----------------------------------------------------------|
+    /// | 1    | Unexpected error (not a `StudFinderError`)                |
+    /// | 2    | Invalid command line (reported by clap)                   |
+    /// | 3    | Invalid configuration                                     |
+    /// | 4    | Piece, part, set, review item or calibration not found    |
+    /// | 5    | Invalid piece edit                                        |
+    /// | 6    | Unreadable or unsupported image                           |
+    /// | 7    | Nothing recognized in the image, or unusable reference    |
+    /// | 8    | Import or export file could not be used                   |
+    /// | 9    | I/O error                                                 |
+    /// | 10   | Database error                                            |
+    #[must_use]
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            StudFinderError::Config(_) | StudFinderError::InvalidConfig(_) => 3,
+            StudFinderError::PieceNotFound(_)
+            | StudFinderError::PartNotFound(_)
+            | StudFinderError::ReviewNotFound(_)
+            | StudFinderError::SetNotFound(_)
+            | StudFinderError::CalibrationNotFound(_) => 4,
+            StudFinderError::InvalidPiece(_) => 5,
+            StudFinderError::Image(_)
+            | StudFinderError::InvalidDimensions {
+                ..
+            }
+            | StudFinderError::UnsupportedFormat(_) => 6,
+            StudFinderError::NoPiecesDetected
+            | StudFinderError::ColorDetectionFailed(_)
+            | StudFinderError::TemplateMatchingFailed(_)
+            | StudFinderError::CalibrationFailed(_) => 7,
+            StudFinderError::CatalogImportFailed(_)
+            | StudFinderError::SetImportFailed(_)
+            | StudFinderError::ExportFailed(_)
+            | StudFinderError::ImportFailed(_) => 8,
+            StudFinderError::Io(_) => 9,
+            StudFinderError::Database {
+                ..
+            }
+            | StudFinderError::Migration {
+                ..
+            }
+            | StudFinderError::DatabaseLockFailed {
+                ..
+            }
+            | StudFinderError::DatabaseResetFailed {
+                ..
+            } => 10,
+        }
+    }
+
+    /// HTTP status code for the error, as reported by `studfinder serve`
+    ///
+    /// | Status | Meaning                                           |
+    /// |--------|---------------------------------------------------|
+    /// | 404    | Piece, part, set, review item or calibration      |
+    /// |        | profile not found                                 |
+    /// | 415    | Unsupported image format                          |
+    /// | 422    | Invalid piece edit, unreadable image, nothing     |
+    /// |        | recognized, unusable calibration reference, or a  |
+    /// |        | piece the export format cannot express            |
+    /// | 500    | Configuration, import, I/O or database error      |
+    #[must_use]
+    pub fn http_status(&self) -> u16 {
+        match self {
+            StudFinderError::PieceNotFound(_)
+            | StudFinderError::PartNotFound(_)
+            | StudFinderError::ReviewNotFound(_)
+            | StudFinderError::SetNotFound(_)
+            | StudFinderError::CalibrationNotFound(_) => 404,
+            StudFinderError::UnsupportedFormat(_) => 415,
+            StudFinderError::InvalidPiece(_)
+            | StudFinderError::Image(_)
+            | StudFinderError::InvalidDimensions {
+                ..
+            }
+            | StudFinderError::NoPiecesDetected
+            | StudFinderError::ColorDetectionFailed(_)
+            | StudFinderError::TemplateMatchingFailed(_)
+            | StudFinderError::CalibrationFailed(_)
+            | StudFinderError::ExportFailed(_) => 422,
+            StudFinderError::Config(_)
+            | StudFinderError::InvalidConfig(_)
+            | StudFinderError::CatalogImportFailed(_)
+            | StudFinderError::SetImportFailed(_)
+            | StudFinderError::ImportFailed(_)
+            | StudFinderError::Io(_)
+            | StudFinderError::Database {
+                ..
+            }
+            | StudFinderError::Migration {
+                ..
+            }
+            | StudFinderError::DatabaseLockFailed {
+                ..
+            }
+            | StudFinderError::DatabaseResetFailed {
+                ..
+            } => 500,
+        }
+    }
+}
+
+/// Reasons a configuration fails `Config::validate`
+///
+/// Every variant names the offending config field.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// A numeric value is outside its allowed range
+    #[error("{field} is {value}, expected {expected}")]
+    OutOfRange {
+        /// The config field
+        field: &'static str,
+        /// The rejected value
+        value: String,
+        /// Description of the allowed values
+        expected: String,
+    },
+
+    /// A path is empty
+    #[error("{field} is empty")]
+    EmptyPath {
+        /// The config field
+        field: &'static str,
+    },
+
+    /// A file, or the directory that should contain it, cannot be written
+    #[error("{field}: {path} is not writable: {reason}")]
+    NotWritable {
+        /// The config field
+        field: &'static str,
+        /// The offending path
+        path: std::path::PathBuf,
+        /// Why the path cannot be written
+        reason: String,
+    },
+
+    /// A directory the configuration relies on does not exist
+    #[error("{field}: directory {path} does not exist")]
+    MissingDirectory {
+        /// The config field
+        field: &'static str,
+        /// The missing directory
+        path: std::path::PathBuf,
+    },
+}
+
+impl ConfigError {
+    /// Name of the config field that failed validation
+    #[must_use]
+    pub fn field(&self) -> &'static str {
+        match self {
+            ConfigError::OutOfRange {
+                field,
+                ..
+            }
+            | ConfigError::EmptyPath {
+                field,
+            }
+            | ConfigError::NotWritable {
+                field,
+                ..
+            }
+            | ConfigError::MissingDirectory {
+                field,
+                ..
+            } => field,
+        }
+    }
 }
 
 /// A specialized Result type for StudFinder operations
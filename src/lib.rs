@@ -1,5 +1,7 @@
-use std::path::PathBuf;
-use tracing::{debug, info};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 
 // Re-export core types
 pub mod core;
@@ -16,34 +18,115 @@ pub mod storage;
 pub mod error;
 use crate::error::{Result, StudFinderError};
 
+/// Result of the blocking part of a batch scan for one image
+enum BatchJob {
+    /// The image's hash is already in the scan ledger
+    Known { path: PathBuf },
+    /// The image was hashed and run through the processor
+    Processed {
+        path: PathBuf,
+        hash: String,
+        result: Result<Vec<Detection>>,
+    },
+    /// The image could not be read
+    Unreadable {
+        path: PathBuf,
+        error: StudFinderError,
+    },
+}
+
 pub struct StudFinder {
     config: Config,
     db: storage::Database,
+    catalog: Arc<PartCatalog>,
+    white_balance: Option<WhiteBalance>,
     processor: Box<dyn processing::ImageProcessor>,
 }
 
 impl StudFinder {
+    /// Create a StudFinder from a validated configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the configuration fails
+    /// `Config::validate`, `CalibrationNotFound` if the selected calibration
+    /// profile does not exist, or an error if the database cannot be opened
+    /// or migrated.
     pub fn new(config: Config) -> Result<Self> {
+        config.validate()?;
         let db = storage::Database::new(&config.database_path)?;
-
-        // Choose processor based on configuration
-        let processor: Box<dyn processing::ImageProcessor> = match config.processor_type {
-            ProcessorType::Scanner => {
-                Box::new(processing::Scanner::new(config.scan_quality.clone()))
-            }
-            ProcessorType::Detector => {
-                Box::new(processing::Detector::new(config.confidence_threshold))
-            }
-        };
+        // Databases created by earlier versions are brought up to date here
+        db.init()?;
+        let catalog = Arc::new(Self::load_catalog(&db)?);
+        let white_balance = Self::load_white_balance(&config, &db)?;
+        let processor = Self::build_processor(&config, &catalog, white_balance);
 
         let finder = Self {
             config,
             db,
+            catalog,
+            white_balance,
             processor,
         };
         Ok(finder)
     }
 
+    /// Built-in parts extended with every part imported into the database
+    fn load_catalog(db: &storage::Database) -> Result<PartCatalog> {
+        let mut catalog = PartCatalog::builtin();
+        catalog.extend(db.list_parts()?);
+        debug!("Part catalog contains {} part(s)", catalog.len());
+        Ok(catalog)
+    }
+
+    /// White balance of the calibration profile selected in the configuration
+    fn load_white_balance(config: &Config, db: &storage::Database) -> Result<Option<WhiteBalance>> {
+        let Some(name) = &config.calibration_profile else {
+            return Ok(None);
+        };
+        let profile = db
+            .get_calibration(name)?
+            .ok_or_else(|| StudFinderError::CalibrationNotFound(name.clone()))?;
+        info!(
+            "Using calibration profile {} (measured from {})",
+            profile.name, profile.reference
+        );
+        Ok(Some(profile.white_balance))
+    }
+
+    /// Choose processor based on configuration
+    ///
+    /// Processors report everything down to `review_threshold`, so that
+    /// detections below `confidence_threshold` can be queued for review.
+    fn build_processor(
+        config: &Config,
+        catalog: &Arc<PartCatalog>,
+        white_balance: Option<WhiteBalance>,
+    ) -> Box<dyn processing::ImageProcessor> {
+        match config.processor_type {
+            ProcessorType::Scanner => {
+                let scanner =
+                    processing::Scanner::with_catalog(config.scan_quality, Arc::clone(catalog))
+                        .with_min_confidence(config.review_threshold);
+                match white_balance {
+                    Some(white_balance) => Box::new(scanner.with_white_balance(white_balance)),
+                    None => Box::new(scanner),
+                }
+            }
+            ProcessorType::Detector => {
+                let detector = processing::Detector::with_catalog(
+                    config.review_threshold,
+                    &config.template_dir,
+                    Arc::clone(catalog),
+                );
+                match white_balance {
+                    Some(white_balance) => Box::new(detector.with_white_balance(white_balance)),
+                    None => Box::new(detector),
+                }
+            }
+        }
+    }
+
     pub fn init(&self) -> Result<()> {
         debug!("Initializing StudFinder");
         self.db.init()?;
@@ -57,40 +140,495 @@ impl StudFinder {
         Ok(())
     }
 
-    pub fn ensure_initialized(&self) -> Result<()> {
-        if !self.config.database_path.exists() {
-            debug!("Database not found, initializing...");
-            self.init()?;
-        }
-        Ok(())
+    /// Scan an image and store every piece found in it
+    ///
+    /// A single photo may contain many loose pieces. Those detected with at
+    /// least `confidence_threshold` are added to the inventory and returned;
+    /// those between `review_threshold` and `confidence_threshold` are put in
+    /// the review queue instead (see `pending_reviews`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be processed, no pieces are
+    /// detected, or the pieces cannot be stored.
+    pub async fn scan_image(&self, path: PathBuf) -> Result<Vec<Piece>> {
+        let (stored, _) = self.scan(path, None).await?;
+        Ok(Detection::into_pieces(stored))
+    }
+
+    /// Scan a top-down image with a side view of the same piece and store the result
+    ///
+    /// The side view lets the processor tell plates from bricks with the
+    /// same stud layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either image cannot be processed, no pieces are
+    /// detected, or the pieces cannot be stored.
+    pub async fn scan_image_with_side_view(
+        &self,
+        path: PathBuf,
+        side_view: PathBuf,
+    ) -> Result<Vec<Piece>> {
+        let (stored, _) = self.scan(path, Some(side_view)).await?;
+        Ok(Detection::into_pieces(stored))
     }
 
-    pub async fn scan_image(&self, path: PathBuf) -> Result<Piece> {
-        self.ensure_initialized()?;
+    /// Scan an image like `scan_image`, returning the detections behind the pieces
+    ///
+    /// Every detection that was stored or queued for review is returned, with
+    /// its ranked color and part candidates; `review_policy` tells which is
+    /// which. Stored detections carry the IDs of the inventory rows their
+    /// pieces went into.
+    ///
+    /// # Arguments
+    /// * `path` - The image to scan
+    /// * `side_view` - Optional side view of the same piece
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be processed, no pieces are
+    /// detected, or the pieces cannot be stored.
+    pub async fn scan_image_detections(
+        &self,
+        path: PathBuf,
+        side_view: Option<PathBuf>,
+    ) -> Result<Vec<Detection>> {
+        let (mut stored, queued) = self.scan(path, side_view).await?;
+        stored.extend(queued);
+        Ok(stored)
+    }
+
+    /// The policy deciding which detections are stored, queued or dropped
+    pub fn review_policy(&self) -> ReviewPolicy {
+        ReviewPolicy::from_config(&self.config)
+    }
 
+    /// Scan an image and store its detections, returning those stored and those queued
+    async fn scan(
+        &self,
+        path: PathBuf,
+        side_view: Option<PathBuf>,
+    ) -> Result<(Vec<Detection>, Vec<Detection>)> {
         info!("Starting image scan for: {}", path.display());
 
         // Image processing in a blocking task
         let processor = self.processor.clone();
         let path_clone = path.clone();
-        let pieces = tokio::task::spawn_blocking(move || processor.process_image(&path_clone))
-            .await
-            .map_err(|_| StudFinderError::NoPiecesDetected)??;
+        let (hash, detections) = tokio::task::spawn_blocking(move || {
+            let detections = match side_view {
+                Some(side_view) => processor.detect_with_side_view(&path_clone, &side_view),
+                None => processor.detect(&path_clone),
+            }?;
+            Ok::<_, StudFinderError>((batch::hash_file(&path_clone)?, detections))
+        })
+        .await
+        .map_err(|_| StudFinderError::NoPiecesDetected)??;
+
+        self.store_detections(&path, &hash, detections)
+    }
+
+    /// Scan every image under a directory and store the pieces found
+    ///
+    /// The directory is searched recursively. Files without an image
+    /// extension are skipped, and up to `options.workers` images are
+    /// processed at once on blocking worker threads. A file that fails does
+    /// not stop the batch; its error is recorded in the report.
+    /// `on_progress` is called as each file is finished.
+    ///
+    /// Every scanned image is recorded in the scan ledger by content hash
+    /// together with its pieces, so rerunning an interrupted batch skips the
+    /// images already done. With `options.rescan` they are processed again
+    /// and replace their earlier results.
+    ///
+    /// # Arguments
+    /// * `dir` - The directory to scan
+    /// * `options` - Batch settings
+    /// * `on_progress` - Called after each file
+    ///
+    /// # Returns
+    /// * `Result<BatchReport>` - The outcome of every file found, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read or detected pieces
+    /// cannot be stored.
+    pub async fn scan_batch<F>(
+        &self,
+        dir: &Path,
+        options: &BatchOptions,
+        mut on_progress: F,
+    ) -> Result<BatchReport>
+    where
+        F: FnMut(BatchProgress<'_>),
+    {
+        let paths = batch::collect_files(dir)?;
+        let total = paths.len();
+        let workers = options.workers.max(1);
+        info!(
+            "Scanning {} file(s) under {} with {} worker(s)",
+            total,
+            dir.display(),
+            workers
+        );
+
+        let mut files = Vec::with_capacity(total);
+        let mut finish = |file: FileReport, files: &mut Vec<FileReport>| {
+            files.push(file);
+            on_progress(BatchProgress {
+                file: &files[files.len() - 1],
+                completed: files.len(),
+                total,
+            });
+        };
+
+        // Images already in the ledger are skipped before they are processed
+        let known = Arc::new(if options.rescan {
+            HashSet::new()
+        } else {
+            self.db.scanned_hashes()?
+        });
+
+        // A task that panics loses its result, so its path is kept here
+        let mut in_flight = HashMap::new();
+        let mut tasks = tokio::task::JoinSet::new();
+        for path in paths {
+            if !batch::is_image_path(&path) {
+                debug!("Skipping {}: not an image", path.display());
+                finish(FileReport::skipped(path, "not an image"), &mut files);
+                continue;
+            }
+
+            // Keep at most `workers` images in flight
+            while tasks.len() >= workers {
+                if let Some(joined) = tasks.join_next_with_id().await {
+                    let file = self.finish_batch_file(joined, &mut in_flight, options.rescan)?;
+                    finish(file, &mut files);
+                }
+            }
+            let processor = self.processor.clone();
+            let known = Arc::clone(&known);
+            let task_path = path.clone();
+            let task = tasks.spawn_blocking(move || {
+                let hash = match batch::hash_file(&path) {
+                    Ok(hash) => hash,
+                    Err(error) => {
+                        return BatchJob::Unreadable {
+                            path,
+                            error,
+                        }
+                    }
+                };
+                if known.contains(&hash) {
+                    return BatchJob::Known {
+                        path,
+                    };
+                }
+                let result = processor.detect(&path);
+                BatchJob::Processed {
+                    path,
+                    hash,
+                    result,
+                }
+            });
+            in_flight.insert(task.id(), task_path);
+        }
+        while let Some(joined) = tasks.join_next_with_id().await {
+            let file = self.finish_batch_file(joined, &mut in_flight, options.rescan)?;
+            finish(file, &mut files);
+        }
+
+        let report = BatchReport::new(dir.to_path_buf(), files);
+        info!(
+            "Batch complete: {} scanned, {} skipped, {} failed",
+            report.scanned, report.skipped, report.failed
+        );
+        Ok(report)
+    }
+
+    /// Store the result of one batch image in the scan ledger and describe it
+    ///
+    /// Only storage errors are returned; processing errors, and panics in
+    /// the worker, become a failed file. `in_flight` maps each running task
+    /// to the image it was given.
+    fn finish_batch_file(
+        &self,
+        joined: std::result::Result<(tokio::task::Id, BatchJob), tokio::task::JoinError>,
+        in_flight: &mut HashMap<tokio::task::Id, PathBuf>,
+        rescan: bool,
+    ) -> Result<FileReport> {
+        let job = match joined {
+            Ok((id, job)) => {
+                in_flight.remove(&id);
+                job
+            }
+            Err(error) => {
+                let path = in_flight.remove(&error.id()).unwrap_or_default();
+                warn!("Failed to process {}: {}", path.display(), error);
+                let error = StudFinderError::Io(std::io::Error::other(error));
+                return Ok(FileReport::failed(path, &error));
+            }
+        };
+        match job {
+            BatchJob::Known {
+                path,
+            } => {
+                debug!("Skipping {}: already scanned", path.display());
+                Ok(FileReport::skipped(path, "already scanned"))
+            }
+            BatchJob::Unreadable {
+                path,
+                error,
+            }
+            | BatchJob::Processed {
+                path,
+                result: Err(error),
+                ..
+            } => {
+                warn!("Failed to process {}: {}", path.display(), error);
+                Ok(FileReport::failed(path, &error))
+            }
+            BatchJob::Processed {
+                path,
+                result: Ok(pieces),
+                ..
+            } if pieces.is_empty() => {
+                Ok(FileReport::failed(path, &StudFinderError::NoPiecesDetected))
+            }
+            BatchJob::Processed {
+                path,
+                hash,
+                result: Ok(detections),
+            } => {
+                for detection in &detections {
+                    info!("Successfully detected piece: {}", detection.piece);
+                }
+                let (mut detections, pending) = self.split_detections(detections);
+                if detections.is_empty() && pending.is_empty() {
+                    return Ok(FileReport::failed(path, &StudFinderError::NoPiecesDetected));
+                }
+                let source = self.scan_source(&path, &hash);
+                let outcome = self
+                    .db
+                    .record_scan(&source, &mut detections, &pending, rescan)?;
+                let pieces = Detection::into_pieces(detections);
+                match outcome {
+                    storage::RecordOutcome::Recorded {
+                        replaced,
+                    } => {
+                        if replaced > 0 {
+                            info!(
+                                "Replaced {} earlier scan(s) of {}",
+                                replaced,
+                                path.display()
+                            );
+                        }
+                        info!(
+                            "Stored {} piece(s) from {}, {} queued for review",
+                            pieces.len(),
+                            path.display(),
+                            pending.len()
+                        );
+                        Ok(FileReport {
+                            path,
+                            outcome: FileOutcome::Scanned {
+                                pieces,
+                                queued: pending.len(),
+                            },
+                        })
+                    }
+                    storage::RecordOutcome::AlreadyScanned {
+                        path: earlier,
+                    } => {
+                        debug!("Skipping {}: same image as {}", path.display(), earlier);
+                        let reason = format!("already scanned as {}", earlier);
+                        Ok(FileReport::skipped(path, &reason))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Watch a folder and scan images as they are added to it
+    ///
+    /// Filesystem notifications tell when an image is created, written or
+    /// moved into `dir`; once it has gone `options.settle` without changes
+    /// it is scanned like `scan_image` and its pieces are stored or queued
+    /// for review. Images already in the folder when watching starts, and
+    /// files in its subfolders, are left alone. A file that fails does not
+    /// stop the watch; with `options.move_processed` it is moved into
+    /// `failed/`, and scanned images into `done/`. `on_file` is called as
+    /// each file is finished.
+    ///
+    /// Runs until `shutdown` completes, then reports every file handled.
+    ///
+    /// # Arguments
+    /// * `dir` - The folder to watch
+    /// * `options` - Watch settings
+    /// * `shutdown` - Stops the watch when it completes
+    /// * `on_file` - Called after each file
+    ///
+    /// # Returns
+    /// * `Result<BatchReport>` - The outcome of every file handled, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `Io` if the folder cannot be watched.
+    pub async fn watch<S, F>(
+        &self,
+        dir: &Path,
+        options: &WatchOptions,
+        shutdown: S,
+        mut on_file: F,
+    ) -> Result<BatchReport>
+    where
+        S: std::future::Future<Output = ()>,
+        F: FnMut(&FileReport),
+    {
+        use notify::Watcher;
+
+        let watch_error = |e: notify::Error| StudFinderError::Io(std::io::Error::other(e));
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(watch_error)?;
+        watcher
+            .watch(dir, notify::RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+        info!("Watching {} for new images", dir.display());
+
+        let mut queue = SettleQueue::new(options.settle);
+        let mut files = Vec::new();
+        tokio::pin!(shutdown);
+        loop {
+            // Without pending files the timer is disabled and never fires
+            let due = queue.next_due();
+            let deadline =
+                due.map_or_else(tokio::time::Instant::now, tokio::time::Instant::from_std);
+            tokio::select! {
+                () = &mut shutdown => break,
+                event = events.recv() => match event {
+                    Some(Ok(event)) => queue.observe(dir, &event, std::time::Instant::now()),
+                    Some(Err(e)) => warn!("Error watching {}: {}", dir.display(), e),
+                    None => break,
+                },
+                () = tokio::time::sleep_until(deadline), if due.is_some() => {
+                    for path in queue.take_settled(std::time::Instant::now()) {
+                        let file = self.scan_watched(dir, path, options).await;
+                        on_file(&file);
+                        files.push(file);
+                    }
+                }
+            }
+        }
+
+        let report = BatchReport::new(dir.to_path_buf(), files);
+        info!(
+            "Stopped watching {}: {} scanned, {} failed",
+            dir.display(),
+            report.scanned,
+            report.failed
+        );
+        Ok(report)
+    }
 
-        if pieces.is_empty() {
+    /// Scan one settled image of a watched folder and move it if asked to
+    async fn scan_watched(&self, dir: &Path, path: PathBuf, options: &WatchOptions) -> FileReport {
+        let file = match self.scan(path.clone(), None).await {
+            Ok((stored, queued)) => FileReport {
+                path,
+                outcome: FileOutcome::Scanned {
+                    pieces: Detection::into_pieces(stored),
+                    queued: queued.len(),
+                },
+            },
+            Err(error) => {
+                warn!("Failed to process {}: {}", path.display(), error);
+                FileReport::failed(path, &error)
+            }
+        };
+        if options.move_processed {
+            let succeeded = matches!(file.outcome, FileOutcome::Scanned { .. });
+            match watch::move_processed(dir, &file.path, succeeded) {
+                Ok(target) => info!("Moved {} to {}", file.path.display(), target.display()),
+                Err(e) => warn!("Could not move {}: {}", file.path.display(), e),
+            }
+        }
+        file
+    }
+
+    /// Add the pieces detected in an image to the inventory, with their observations
+    ///
+    /// Detections the review policy does not accept are queued or dropped.
+    /// Returns the stored detections and the queued ones.
+    fn store_detections(
+        &self,
+        path: &Path,
+        hash: &str,
+        detections: Vec<Detection>,
+    ) -> Result<(Vec<Detection>, Vec<Detection>)> {
+        for detection in &detections {
+            info!("Successfully detected piece: {}", detection.piece);
+        }
+
+        let (mut detections, pending) = self.split_detections(detections);
+        if detections.is_empty() && pending.is_empty() {
             return Err(StudFinderError::NoPiecesDetected);
         }
 
-        let piece = pieces
-            .into_iter()
-            .next()
-            .ok_or(StudFinderError::NoPiecesDetected)?;
-        info!("Successfully detected piece: {}", piece);
+        // Reports the inventory row each piece ended up in
+        self.db
+            .add_detections(&self.scan_source(path, hash), &mut detections, &pending)?;
+        info!(
+            "Stored {} piece(s) from {}, {} queued for review",
+            detections.len(),
+            path.display(),
+            pending.len()
+        );
+
+        Ok((detections, pending))
+    }
+
+    /// Split detections into those to store and those to queue for review
+    ///
+    /// Detections below `review_threshold` are dropped.
+    fn split_detections(&self, detections: Vec<Detection>) -> (Vec<Detection>, Vec<Detection>) {
+        let policy = self.review_policy();
+        let mut accepted = Vec::new();
+        let mut pending = Vec::new();
+        for detection in detections {
+            match policy.band_for(&detection.piece) {
+                ReviewBand::Accept => accepted.push(detection),
+                ReviewBand::Review => pending.push(detection),
+                ReviewBand::Reject => debug!(
+                    "Dropping {} ({:.2}): below review threshold",
+                    detection.piece, detection.piece.confidence
+                ),
+            }
+        }
+        (accepted, pending)
+    }
 
-        Ok(piece)
+    /// Describe an image as scanned with the configured processor
+    fn scan_source<'a>(&self, path: &'a Path, hash: &'a str) -> storage::ScanSource<'a> {
+        storage::ScanSource {
+            path,
+            hash,
+            processor: self.config.processor_type,
+            quality: self.config.scan_quality,
+        }
     }
 
-    pub fn add_piece(&self, piece: Piece) -> Result<()> {
+    /// Add a piece to the inventory
+    ///
+    /// Pieces with the same part number, color and condition share one
+    /// inventory row; adding another one increases its quantity.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the piece cannot be stored.
+    pub fn add_piece(&self, piece: Piece) -> Result<storage::AddOutcome> {
         self.db.add_piece(&piece)
     }
 
@@ -98,24 +636,544 @@ impl StudFinder {
         self.db.list_pieces()
     }
 
+    /// List the pieces matching a filter
+    ///
+    /// Color names are matched through the palette, so "red" or the LEGO
+    /// name "Bright Red" find pieces stored as "Red".
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub fn query_inventory(&self, filter: &storage::PieceFilter) -> Result<Vec<Piece>> {
+        let mut filter = filter.clone();
+        if let Some(color) = filter
+            .color
+            .as_deref()
+            .and_then(processing::palette::find_by_name)
+        {
+            filter.color = Some(color.bricklink_name.to_string());
+        }
+        self.db.query_pieces(&filter)
+    }
+
+    /// Look up a single inventory entry
+    ///
+    /// # Errors
+    ///
+    /// Returns `PieceNotFound` if no piece has this ID, or an error if the
+    /// database query fails.
+    pub fn get_piece(&self, id: &str) -> Result<Piece> {
+        self.db
+            .get_piece(id)?
+            .ok_or_else(|| StudFinderError::PieceNotFound(id.to_string()))
+    }
+
+    /// List where and how an inventory entry was seen in scanned images
+    ///
+    /// Pieces added by hand or imported have no observations.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PieceNotFound` if no piece has this ID, or an error if the
+    /// database query fails.
+    pub fn piece_observations(&self, id: &str) -> Result<Vec<storage::Observation>> {
+        self.get_piece(id)?;
+        self.db.list_observations(id)
+    }
+
+    /// Show where the pieces of an inventory entry are kept
+    ///
+    /// # Errors
+    ///
+    /// Returns `PieceNotFound` if no piece has this ID, or an error if the
+    /// database query fails.
+    pub fn piece_locations(&self, id: &str) -> Result<LocatedPiece> {
+        let piece = self.get_piece(id)?;
+        let locations = self.db.list_piece_locations(id)?;
+        Ok(LocatedPiece::new(piece, locations))
+    }
+
+    /// Move pieces of an inventory entry into a storage location
+    ///
+    /// Without `from`, the pieces come from the entry's unplaced quantity.
+    /// Without `quantity`, everything at the source is moved.
+    ///
+    /// # Returns
+    /// * `Result<LocatedPiece>` - The entry with its new locations, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPiece` if the source holds fewer pieces than asked
+    /// for, `PieceNotFound` if no piece has this ID, or an error if the
+    /// update fails.
+    pub fn move_pieces(
+        &self,
+        id: &str,
+        from: Option<&StorageLocation>,
+        to: &StorageLocation,
+        quantity: Option<i32>,
+    ) -> Result<LocatedPiece> {
+        self.db.move_pieces(id, from, to, quantity)?;
+        self.piece_locations(id)
+    }
+
+    /// Find where the pieces of a part are kept
+    ///
+    /// The part number is matched through the catalog, so entries stored
+    /// under an alternate number are found too. Colors are matched through
+    /// the palette like `query_inventory`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub fn locate(&self, part_number: &str, color: Option<&str>) -> Result<Vec<LocatedPiece>> {
+        let part_numbers = match self.catalog.get(part_number) {
+            Some(part) => std::iter::once(part.part_number.clone())
+                .chain(part.alternates.iter().cloned())
+                .collect(),
+            None => vec![part_number.to_string()],
+        };
+
+        let mut located = Vec::new();
+        for part_number in part_numbers {
+            let filter = storage::PieceFilter {
+                part_number: Some(part_number),
+                color: color.map(str::to_string),
+                ..storage::PieceFilter::default()
+            };
+            for piece in self.query_inventory(&filter)? {
+                let locations = self.db.list_piece_locations(&piece.id)?;
+                located.push(LocatedPiece::new(piece, locations));
+            }
+        }
+        debug!("Found {} entries of part {}", located.len(), part_number);
+        Ok(located)
+    }
+
+    /// Set the quantity of an inventory entry
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPiece` for a negative quantity, `PieceNotFound` if no
+    /// piece has this ID, or an error if the update fails.
+    pub fn set_quantity(&self, id: &str, quantity: i32) -> Result<()> {
+        validate_quantity(quantity)?;
+        self.db.update_quantity(id, quantity)?;
+        info!("Set quantity of piece {} to {}", id, quantity);
+        Ok(())
+    }
+
+    /// Correct the part number and/or color of an inventory entry
+    ///
+    /// The color must be in the palette and is stored under its BrickLink
+    /// name; a new part number takes its category from the catalog. If the
+    /// corrected piece matches another entry, the two are merged.
+    ///
+    /// # Returns
+    /// * `Result<Piece>` - The entry holding the corrected piece, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPiece` for an empty part number or unknown color,
+    /// `PieceNotFound` if no piece has this ID, or an error if the update
+    /// fails.
+    pub fn edit_piece(
+        &self,
+        id: &str,
+        part_number: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<Piece> {
+        self.update_piece(id, None, part_number, color)
+    }
+
+    /// Set the quantity and correct the part number and/or color of an inventory entry
+    ///
+    /// Combines `set_quantity` and `edit_piece`. Every change is validated
+    /// before any is written, so a rejected correction leaves the quantity
+    /// as it was. The quantity is set before a corrected piece is merged
+    /// into a matching entry.
+    ///
+    /// # Returns
+    /// * `Result<Piece>` - The entry holding the piece, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPiece` for a negative quantity, empty part number or
+    /// unknown color, `PieceNotFound` if no piece has this ID, or an error
+    /// if the update fails.
+    pub fn update_piece(
+        &self,
+        id: &str,
+        quantity: Option<i32>,
+        part_number: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<Piece> {
+        let mut piece = self.get_piece(id)?;
+        if let Some(quantity) = quantity {
+            validate_quantity(quantity)?;
+        }
+        self.validate_correction(&mut piece, part_number, color)?;
+
+        if let Some(quantity) = quantity {
+            self.set_quantity(id, quantity)?;
+        }
+        if part_number.is_none() && color.is_none() {
+            return self.get_piece(id);
+        }
+        let stored_id = self.db.update_piece(&piece)?;
+        if stored_id != id {
+            info!("Piece {} merged into {}", id, stored_id);
+        }
+        self.get_piece(&stored_id)
+    }
+
+    /// Apply a corrected part number and/or color to a piece
+    ///
+    /// Shared by `edit_piece` and `accept_review`. The color must be in the
+    /// palette and is stored under its BrickLink name; a new part number
+    /// takes its category from the catalog.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPiece` for an empty part number or unknown color.
+    fn validate_correction(
+        &self,
+        piece: &mut Piece,
+        part_number: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<()> {
+        if let Some(part_number) = part_number {
+            let part_number = part_number.trim();
+            if part_number.is_empty() {
+                return Err(StudFinderError::InvalidPiece(
+                    "part number must not be empty".to_string(),
+                ));
+            }
+            piece.part_number = part_number.to_string();
+            piece.category = self.catalog.category(part_number);
+        }
+        if let Some(color) = color {
+            let color = processing::palette::find_by_name(color).ok_or_else(|| {
+                StudFinderError::InvalidPiece(format!("unknown color '{}'", color))
+            })?;
+            piece.color = color.bricklink_name.to_string();
+        }
+        Ok(())
+    }
+
+    /// List the detections waiting for review, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the review queue cannot be read.
+    pub fn pending_reviews(&self) -> Result<Vec<storage::PendingReview>> {
+        self.db.list_pending_reviews()
+    }
+
+    /// Palette colors closest to a queued detection's measured color, best first
+    ///
+    /// Empty if the processor did not measure the color.
+    ///
+    /// # Arguments
+    /// * `review` - The queued detection
+    /// * `count` - Maximum number of colors to return
+    pub fn review_alternatives(
+        &self,
+        review: &storage::PendingReview,
+        count: usize,
+    ) -> Vec<ColorCandidate> {
+        review
+            .measured_rgb
+            .map_or_else(Vec::new, |rgb| ColorDetector::new().candidates(rgb, count))
+    }
+
+    /// Add a queued detection to the inventory, optionally corrected
+    ///
+    /// The piece is stored with confidence 1.0, since a person has checked
+    /// it. Its observation keeps what was detected.
+    ///
+    /// # Arguments
+    /// * `id` - ID of the queued detection
+    /// * `part_number` - Corrected part number, if the detected one is wrong
+    /// * `color` - Corrected color name, if the detected one is wrong
+    ///
+    /// # Returns
+    /// * `Result<Piece>` - The entry holding the piece, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidPiece` for an empty part number or unknown color,
+    /// `ReviewNotFound` if nothing is queued under this ID, or an error if
+    /// the piece cannot be stored.
+    pub fn accept_review(
+        &self,
+        id: &str,
+        part_number: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<Piece> {
+        let review = self
+            .db
+            .get_pending_review(id)?
+            .ok_or_else(|| StudFinderError::ReviewNotFound(id.to_string()))?;
+
+        let mut piece = Piece {
+            id: uuid::Uuid::new_v4().to_string(),
+            part_number: review.part_number,
+            color: review.color,
+            category: review.category,
+            quantity: 1,
+            confidence: 1.0,
+            condition: None,
+        };
+        self.validate_correction(&mut piece, part_number, color)?;
+
+        let stored_id = self.db.accept_review(id, &piece)?;
+        info!("Accepted review {} as {}", id, piece);
+        self.get_piece(&stored_id)
+    }
+
+    /// Drop a queued detection without adding it to the inventory
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReviewNotFound` if nothing is queued under this ID, or an
+    /// error if the delete fails.
+    pub fn discard_review(&self, id: &str) -> Result<()> {
+        self.db.discard_review(id)?;
+        info!("Discarded review {}", id);
+        Ok(())
+    }
+
+    /// Remove an entry from the inventory
+    ///
+    /// # Errors
+    ///
+    /// Returns `PieceNotFound` if no piece has this ID, or an error if the
+    /// delete fails.
+    pub fn remove_piece(&self, id: &str) -> Result<()> {
+        self.db.delete_piece(id)?;
+        info!("Removed piece {}", id);
+        Ok(())
+    }
+
+    /// The part catalog shared by the processors
+    pub fn catalog(&self) -> &PartCatalog {
+        &self.catalog
+    }
+
+    /// Look up a part by its number or one of its alternate numbers
+    pub fn part_info(&self, part_number: &str) -> Option<&PartInfo> {
+        self.catalog.get(part_number)
+    }
+
+    /// Import a part catalog dump into the database
+    ///
+    /// Accepts a directory (or `parts.csv` file) in Rebrickable's CSV format,
+    /// or a JSON array of parts. The catalog used by the processor is
+    /// refreshed immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dump cannot be read or the parts cannot be
+    /// stored.
+    pub fn import_catalog(&mut self, path: PathBuf) -> Result<usize> {
+        let parts = storage::CatalogImporter::import(&path)?;
+        let count = self.db.save_parts(&parts)?;
+
+        self.catalog = Arc::new(Self::load_catalog(&self.db)?);
+        self.processor = Self::build_processor(&self.config, &self.catalog, self.white_balance);
+        info!("Imported {} part(s) from {}", count, path.display());
+        Ok(count)
+    }
+
+    /// Import set inventories into the database
+    ///
+    /// Accepts a directory (or `sets.csv` file) in Rebrickable's CSV format.
+    /// Sets that were imported before have their part lists replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dump cannot be read or the sets cannot be
+    /// stored.
+    pub fn import_sets(&self, path: PathBuf) -> Result<usize> {
+        let sets = storage::SetImporter::import(&path)?;
+        let count = self.db.save_sets(&sets)?;
+        info!("Imported {} set(s) from {}", count, path.display());
+        Ok(count)
+    }
+
+    /// Compare an imported set against the inventory
+    ///
+    /// A set number without a version suffix refers to the first version,
+    /// so "10696" checks "10696-1".
+    ///
+    /// # Errors
+    ///
+    /// Returns `SetNotFound` if the set has not been imported, or an error
+    /// if the database cannot be read.
+    pub fn check_set(&self, set_number: &str) -> Result<SetCheck> {
+        let set_number = normalize_set_number(set_number);
+        let set = self
+            .db
+            .get_set(&set_number)?
+            .ok_or_else(|| StudFinderError::SetNotFound(set_number.clone()))?;
+        let pieces = self.db.list_pieces()?;
+
+        let check = SetCheck::new(&set, &pieces, &self.catalog);
+        debug!(
+            "Set {} is {:.1}% complete ({} missing part(s))",
+            set_number,
+            check.percent_complete,
+            check.missing.len()
+        );
+        Ok(check)
+    }
+
+    /// Create a calibration profile from a photo of a reference
+    ///
+    /// The reference, a white or grey card or a brick of a known color, is
+    /// photographed on the rig to calibrate. A profile with the same name is
+    /// replaced. The profile is used once it is selected with the
+    /// `calibration_profile` configuration key.
+    ///
+    /// # Arguments
+    /// * `name` - Name for the profile
+    /// * `image` - Photo of the reference
+    /// * `reference` - Palette color of the reference, e.g. "White"
+    ///
+    /// # Errors
+    ///
+    /// Returns `Image` if the photo cannot be read, `CalibrationFailed` if
+    /// it cannot be used as a reference, or an error if the profile cannot
+    /// be stored.
+    pub fn calibrate(
+        &self,
+        name: &str,
+        image: &Path,
+        reference: &str,
+    ) -> Result<CalibrationProfile> {
+        info!("Calibrating {} from {}", name, image.display());
+        let img = image::open(image).map_err(StudFinderError::Image)?;
+        let profile = CalibrationProfile::from_image(name, &img, reference)?;
+        self.db.save_calibration(&profile)?;
+        self.calibration_profile(&profile.name)
+    }
+
+    /// Look up a calibration profile
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationNotFound` if there is no profile by that name, or
+    /// an error if the database cannot be read.
+    pub fn calibration_profile(&self, name: &str) -> Result<CalibrationProfile> {
+        self.db
+            .get_calibration(name)?
+            .ok_or_else(|| StudFinderError::CalibrationNotFound(name.to_string()))
+    }
+
+    /// List every calibration profile, by name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be read.
+    pub fn calibration_profiles(&self) -> Result<Vec<CalibrationProfile>> {
+        self.db.list_calibrations()
+    }
+
+    /// Delete a calibration profile
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationNotFound` if there is no profile by that name, or
+    /// an error if the database cannot be written.
+    pub fn remove_calibration_profile(&self, name: &str) -> Result<()> {
+        self.db.delete_calibration(name)
+    }
+
     pub fn export_inventory(&self, path: PathBuf) -> Result<()> {
-        let pieces = self.list_inventory()?;
-        storage::export::ExportManager::export_inventory(&pieces, path, &self.config.export_format)
+        self.export_inventory_with_options(path, None, &storage::ExportOptions::default())
+    }
+
+    /// Export the inventory with format-specific options
+    ///
+    /// Without an explicit `format`, the format is inferred from the file
+    /// extension of `path`, falling back to the configured export format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inventory cannot be read or written, or a
+    /// piece cannot be expressed in the chosen format.
+    pub fn export_inventory_with_options(
+        &self,
+        path: PathBuf,
+        format: Option<ExportFormat>,
+        options: &storage::ExportOptions,
+    ) -> Result<()> {
+        let format = format
+            .or_else(|| ExportFormat::from_path(&path))
+            .unwrap_or(self.config.export_format);
+        debug!("Exporting inventory as {}", format);
+
+        let mut locations = self.db.list_locations()?;
+        let pieces: Vec<LocatedPiece> = self
+            .list_inventory()?
+            .into_iter()
+            .map(|piece| {
+                let piece_locations = locations.remove(&piece.id).unwrap_or_default();
+                LocatedPiece::new(piece, piece_locations)
+            })
+            .collect();
+        storage::export::ExportManager::export_located_inventory(&pieces, path, &format, options)
     }
 
     pub fn import_inventory(&self, path: PathBuf) -> Result<()> {
-        let pieces = storage::export::ExportManager::import_inventory(path)?;
-        for piece in pieces {
+        self.import_inventory_with_format(path, None)
+    }
+
+    /// Import an inventory file, merging it into the stored inventory
+    ///
+    /// Without an explicit `format`, the format is detected from the file
+    /// content and, failing that, its extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or the pieces
+    /// cannot be stored.
+    pub fn import_inventory_with_format(
+        &self,
+        path: PathBuf,
+        format: Option<ExportFormat>,
+    ) -> Result<()> {
+        let pieces = match format {
+            Some(format) => storage::export::ExportManager::import_inventory_as(path, &format)?,
+            None => storage::export::ExportManager::import_inventory(path)?,
+        };
+        for mut piece in pieces {
+            // Formats without a category column leave it to the catalog
+            if piece.category == UNKNOWN_CATEGORY {
+                piece.category = self.catalog.category(&piece.part_number);
+            }
             self.add_piece(piece)?;
         }
         Ok(())
     }
 }
 
+/// Reject a negative inventory quantity
+fn validate_quantity(quantity: i32) -> Result<()> {
+    if quantity < 0 {
+        return Err(StudFinderError::InvalidPiece(format!(
+            "quantity must not be negative, got {}",
+            quantity
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
+#[allow(clippy::disallowed_methods)]
 mod tests {
     use super::*;
-    use tempfile;
 
     fn create_test_config() -> Config {
         Config {
@@ -124,6 +1182,9 @@ mod tests {
             scan_quality: ScanQuality::Fast,
             processor_type: ProcessorType::Scanner,
             confidence_threshold: 0.8,
+            review_threshold: 0.5,
+            template_dir: PathBuf::from("templates"),
+            calibration_profile: None,
         }
     }
 
@@ -133,6 +1194,69 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_new_rejects_invalid_config() {
+        let result = StudFinder::new(Config {
+            confidence_threshold: 80.0,
+            ..create_test_config()
+        });
+        assert!(matches!(
+            result,
+            Err(StudFinderError::InvalidConfig(
+                error::ConfigError::OutOfRange {
+                    field: "confidence_threshold",
+                    ..
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_new_migrates_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("old.db");
+        {
+            // A version 2 database, from before pieces had a condition
+            let conn = rusqlite::Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE schema_version (
+                    version INTEGER PRIMARY KEY,
+                    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                 );
+                 INSERT INTO schema_version (version) VALUES (1), (2);
+                 CREATE TABLE pieces (
+                    id TEXT PRIMARY KEY,
+                    part_number TEXT NOT NULL,
+                    color TEXT NOT NULL,
+                    category TEXT NOT NULL,
+                    quantity INTEGER NOT NULL DEFAULT 1,
+                    confidence REAL NOT NULL DEFAULT 1.0
+                 );
+                 INSERT INTO pieces VALUES ('a', '3001', 'Red', 'Brick', 1, 0.9);
+                 INSERT INTO pieces VALUES ('b', '3001', 'Red', 'Brick', 2, 0.9);",
+            )
+            .unwrap();
+        }
+
+        let finder = StudFinder::new(Config {
+            database_path: db_path,
+            ..create_test_config()
+        })
+        .unwrap();
+        let pieces = finder.list_inventory().unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].quantity, 3);
+
+        // A path that does not exist yet gets the full schema
+        let finder = StudFinder::new(Config {
+            database_path: temp_dir.path().join("new.db"),
+            ..create_test_config()
+        })
+        .unwrap();
+        assert!(finder.list_inventory().unwrap().is_empty());
+        assert!(finder.pending_reviews().unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_scan_workflow() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -144,32 +1268,462 @@ mod tests {
             scan_quality: ScanQuality::Fast,
             processor_type: ProcessorType::Scanner,
             confidence_threshold: 0.8,
+            review_threshold: 0.5,
+            template_dir: PathBuf::from("templates"),
+            calibration_profile: None,
         };
 
         let finder = StudFinder::new(config).unwrap();
         finder.init().unwrap();
 
-        // Create a test image
-        let image_path = temp_dir.path().join("test.jpg");
-        let mut img = image::RgbImage::new(200, 200);
-        for pixel in img.pixels_mut() {
-            *pixel = image::Rgb([
-                255, 0, 0,
-            ]); // Pure red
+        // Create a test image of a red 2x2 brick, its studs catching the light
+        let image_path = temp_dir.path().join("test.png");
+        let mut img = image::RgbImage::from_pixel(
+            160,
+            160,
+            image::Rgb([
+                255, 255, 255,
+            ]),
+        );
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut img,
+            imageproc::rect::Rect::at(40, 40).of_size(80, 80),
+            image::Rgb([
+                201, 26, 9,
+            ]), // Reference red
+        );
+        for (x, y) in [
+            (60, 60),
+            (100, 60),
+            (60, 100),
+            (100, 100),
+        ] {
+            imageproc::drawing::draw_filled_circle_mut(
+                &mut img,
+                (x, y),
+                12,
+                image::Rgb([
+                    240, 90, 70,
+                ]),
+            );
         }
         img.save(&image_path).unwrap();
 
         // Test scanning
-        let piece = finder.scan_image(image_path).await.unwrap();
-        assert_eq!(piece.color, "Red");
-        assert!(piece.confidence > 0.8);
-
-        // Add the piece to the inventory
-        finder.add_piece(piece).unwrap();
+        let pieces = finder.scan_image(image_path.clone()).await.unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].color, "Red");
+        assert!(pieces[0].confidence > 0.8);
 
+        // Scanned pieces are stored automatically
         // Test inventory
         let pieces = finder.list_inventory().unwrap();
         assert_eq!(pieces.len(), 1);
         assert_eq!(pieces[0].color, "Red");
+
+        // Each stored piece links back to the image it came from
+        let observations = finder.piece_observations(&pieces[0].id).unwrap();
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].image_path, image_path.display().to_string());
+        assert_eq!(observations[0].processor, "scanner");
+        assert_eq!(observations[0].quality, "fast");
+        assert!(observations[0].measured_rgb.is_some());
+        assert!(matches!(
+            finder.piece_observations("missing"),
+            Err(StudFinderError::PieceNotFound(_))
+        ));
+
+        // The detections behind a scan carry their candidates and stored row
+        let detections = finder
+            .scan_image_detections(image_path, None)
+            .await
+            .unwrap();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].piece.id, pieces[0].id);
+        assert_eq!(detections[0].color_candidates[0].name, "Red");
+        assert_eq!(
+            finder.review_policy().band_for(&detections[0].piece),
+            ReviewBand::Accept
+        );
+
+        // A swatch without studs has a sure color but no part, so it waits for review
+        let swatch_path = temp_dir.path().join("swatch.png");
+        image::RgbImage::from_pixel(
+            200,
+            200,
+            image::Rgb([
+                201, 26, 9,
+            ]),
+        )
+        .save(&swatch_path)
+        .unwrap();
+        assert!(finder.scan_image(swatch_path).await.unwrap().is_empty());
+        let pending = finder.pending_reviews().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].part_number, UNKNOWN_PART);
+        assert_eq!(finder.list_inventory().unwrap().len(), 1);
+    }
+
+    /// Scanner that panics on images named `panic.png`
+    #[derive(Clone)]
+    struct PanickingScanner(processing::Scanner);
+
+    impl processing::ImageProcessor for PanickingScanner {
+        fn detect(&self, image_path: &Path) -> Result<Vec<Detection>> {
+            assert!(!image_path.ends_with("panic.png"), "processor panicked");
+            self.0.detect(image_path)
+        }
+
+        fn validate_image(&self, image: &image::DynamicImage) -> Result<()> {
+            processing::ImageProcessor::validate_image(&self.0, image)
+        }
+
+        fn clone_box(&self) -> Box<dyn processing::ImageProcessor> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_survives_panicking_worker() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut finder = StudFinder::new(Config {
+            database_path: temp_dir.path().join("test.db"),
+            ..create_test_config()
+        })
+        .unwrap();
+        finder.processor = Box::new(PanickingScanner(processing::Scanner::new(
+            ScanQuality::Fast,
+        )));
+
+        let images = temp_dir.path().join("images");
+        std::fs::create_dir(&images).unwrap();
+        for (name, color) in [
+            (
+                "a.png",
+                [
+                    201, 26, 9,
+                ],
+            ),
+            (
+                "panic.png",
+                [
+                    242, 205, 55,
+                ],
+            ),
+            (
+                "b.png",
+                [
+                    0, 85, 191,
+                ],
+            ),
+        ] {
+            image::RgbImage::from_pixel(200, 200, image::Rgb(color))
+                .save(images.join(name))
+                .unwrap();
+        }
+
+        let options = BatchOptions {
+            workers: 1,
+            rescan: false,
+        };
+        let report = finder.scan_batch(&images, &options, |_| {}).await.unwrap();
+        assert_eq!((report.scanned, report.failed), (2, 1));
+        let failed = report
+            .files
+            .iter()
+            .find(|file| matches!(file.outcome, FileOutcome::Failed { .. }))
+            .unwrap();
+        assert_eq!(failed.path, images.join("panic.png"));
+    }
+
+    #[test]
+    fn test_import_catalog() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = || Config {
+            database_path: temp_dir.path().join("test.db"),
+            ..create_test_config()
+        };
+
+        let mut finder = StudFinder::new(config()).unwrap();
+        assert_eq!(finder.part_info("3001").unwrap().name, "Brick 2 x 4");
+        assert!(finder.part_info("3437").is_none());
+
+        let catalog_path = temp_dir.path().join("parts.json");
+        std::fs::write(
+            &catalog_path,
+            r#"[{"part_number": "3437", "name": "Duplo Brick 2 x 2", "category": "Duplo"}]"#,
+        )
+        .unwrap();
+        assert_eq!(finder.import_catalog(catalog_path).unwrap(), 1);
+        assert_eq!(finder.part_info("3437").unwrap().category, "Duplo");
+
+        // Imported parts are persisted
+        let finder = StudFinder::new(config()).unwrap();
+        assert_eq!(finder.part_info("3437").unwrap().category, "Duplo");
+    }
+
+    #[test]
+    fn test_import_rebrickable_resolves_category() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let finder = StudFinder::new(Config {
+            database_path: temp_dir.path().join("test.db"),
+            ..create_test_config()
+        })
+        .unwrap();
+        finder.init().unwrap();
+
+        let list_path = temp_dir.path().join("parts.csv");
+        std::fs::write(&list_path, "Part,Color,Quantity,Is Spare\n3020,1,2,False\n").unwrap();
+        finder.import_inventory(list_path).unwrap();
+
+        let pieces = finder.list_inventory().unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].color, "Blue");
+        assert_eq!(pieces[0].category, "Plates");
+        assert_eq!(pieces[0].quantity, 2);
+    }
+
+    #[test]
+    fn test_check_set() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let finder = StudFinder::new(Config {
+            database_path: temp_dir.path().join("test.db"),
+            ..create_test_config()
+        })
+        .unwrap();
+        assert!(matches!(
+            finder.check_set("10696"),
+            Err(StudFinderError::SetNotFound(_))
+        ));
+
+        std::fs::write(
+            temp_dir.path().join("sets.csv"),
+            "set_num,name,year,theme_id,num_parts\n10696-1,Medium Creative Brick Box,2015,621,6\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("inventories.csv"),
+            "id,version,set_num\n1,1,10696-1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("inventory_parts.csv"),
+            "inventory_id,part_num,color_id,quantity,is_spare\n1,3001,4,4,f\n1,3020,1,2,f\n",
+        )
+        .unwrap();
+        assert_eq!(
+            finder.import_sets(temp_dir.path().to_path_buf()).unwrap(),
+            1
+        );
+
+        finder
+            .add_piece(Piece {
+                id: String::new(),
+                part_number: "3001".to_string(),
+                color: "Red".to_string(),
+                category: "Bricks".to_string(),
+                quantity: 3,
+                confidence: 1.0,
+                condition: None,
+            })
+            .unwrap();
+
+        let check = finder.check_set("10696").unwrap();
+        assert_eq!(check.set_number, "10696-1");
+        assert_eq!((check.required, check.owned), (6, 3));
+        assert_eq!(check.percent_complete, 50.0);
+        assert_eq!(check.missing.len(), 2);
+    }
+
+    #[test]
+    fn test_storage_locations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let finder = StudFinder::new(create_test_config()).unwrap();
+        finder.init().unwrap();
+        let id = finder
+            .add_piece(Piece {
+                id: String::new(),
+                part_number: "3062".to_string(),
+                color: "Yellow".to_string(),
+                category: "Bricks Round and Cones".to_string(),
+                quantity: 5,
+                confidence: 1.0,
+                condition: None,
+            })
+            .unwrap()
+            .id()
+            .to_string();
+
+        let bin: StorageLocation = "A/3/12".parse().unwrap();
+        let located = finder.move_pieces(&id, None, &bin, Some(3)).unwrap();
+        assert_eq!(located.unplaced, 2);
+
+        // Found under the catalog number of the part it is an alternate of
+        let found = finder.locate("3062b", Some("yellow")).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].locations[0].location, bin);
+        assert!(finder.locate("3062b", Some("Red")).unwrap().is_empty());
+
+        let path = temp_dir.path().join("inventory.json");
+        finder.export_inventory(path.clone()).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(json[0]["locations"][0]["quantity"], 3);
+    }
+
+    #[test]
+    fn test_edit_inventory_entries() {
+        let finder = StudFinder::new(create_test_config()).unwrap();
+        finder.init().unwrap();
+        let id = finder
+            .add_piece(Piece {
+                id: String::new(),
+                part_number: "3001".to_string(),
+                color: "Red".to_string(),
+                category: "Bricks".to_string(),
+                quantity: 2,
+                confidence: 0.9,
+                condition: None,
+            })
+            .unwrap()
+            .id()
+            .to_string();
+
+        finder.set_quantity(&id, 5).unwrap();
+        assert_eq!(finder.get_piece(&id).unwrap().quantity, 5);
+        assert!(matches!(
+            finder.set_quantity(&id, -1),
+            Err(StudFinderError::InvalidPiece(_))
+        ));
+
+        let piece = finder
+            .edit_piece(&id, Some("3020"), Some("bright blue"))
+            .unwrap();
+        assert_eq!(piece.part_number, "3020");
+        assert_eq!(piece.category, "Plates");
+        assert_eq!(piece.color, "Blue");
+        assert!(matches!(
+            finder.edit_piece(&id, None, Some("plaid")),
+            Err(StudFinderError::InvalidPiece(_))
+        ));
+
+        finder.remove_piece(&id).unwrap();
+        assert!(matches!(
+            finder.get_piece(&id),
+            Err(StudFinderError::PieceNotFound(_))
+        ));
+        assert!(matches!(
+            finder.remove_piece(&id),
+            Err(StudFinderError::PieceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_format_follows_file_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let finder = StudFinder::new(Config {
+            database_path: temp_dir.path().join("test.db"),
+            ..create_test_config()
+        })
+        .unwrap();
+        finder.init().unwrap();
+        finder
+            .add_piece(Piece {
+                id: String::new(),
+                part_number: "3001".to_string(),
+                color: "Red".to_string(),
+                category: "Bricks".to_string(),
+                quantity: 2,
+                confidence: 0.9,
+                condition: None,
+            })
+            .unwrap();
+
+        // The configured format is JSON, but the extension asks for XML
+        let xml_path = temp_dir.path().join("wanted.xml");
+        finder.export_inventory(xml_path.clone()).unwrap();
+        assert!(std::fs::read_to_string(&xml_path)
+            .unwrap()
+            .starts_with("<INVENTORY>"));
+
+        // An explicit format wins over the extension
+        let list_path = temp_dir.path().join("parts.csv");
+        finder
+            .export_inventory_with_options(
+                list_path.clone(),
+                Some(ExportFormat::Rebrickable),
+                &storage::ExportOptions::default(),
+            )
+            .unwrap();
+        assert!(std::fs::read_to_string(&list_path)
+            .unwrap()
+            .starts_with("Part,Color,Quantity,Is Spare"));
+    }
+
+    #[tokio::test]
+    async fn test_calibration_profile_corrects_colors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // A flat swatch has no part, so keep even unsure detections
+        let config = || Config {
+            database_path: temp_dir.path().join("test.db"),
+            review_threshold: 0.0,
+            ..create_test_config()
+        };
+        let warm_white = image::Rgb([
+            235, 205, 150,
+        ]);
+
+        // A white card under warm light, on a dark table
+        let card_path = temp_dir.path().join("card.png");
+        let mut card = image::RgbImage::from_pixel(
+            120,
+            120,
+            image::Rgb([
+                25, 25, 25,
+            ]),
+        );
+        for y in 20..100 {
+            for x in 20..100 {
+                card.put_pixel(x, y, warm_white);
+            }
+        }
+        card.save(&card_path).unwrap();
+        let brick_path = temp_dir.path().join("brick.png");
+        image::RgbImage::from_pixel(200, 200, warm_white)
+            .save(&brick_path)
+            .unwrap();
+
+        let finder = StudFinder::new(config()).unwrap();
+        let profile = finder.calibrate("rig", &card_path, "White").unwrap();
+        assert_eq!(profile.reference, "White");
+        assert_eq!(finder.calibration_profiles().unwrap().len(), 1);
+
+        // Without the profile the cast is mistaken for another color
+        let detections = finder
+            .scan_image_detections(brick_path.clone(), None)
+            .await
+            .unwrap();
+        assert_ne!(detections[0].piece.color, "White");
+
+        // With the profile selected, the same light reads as white
+        let calibrated = StudFinder::new(Config {
+            calibration_profile: Some("rig".to_string()),
+            ..config()
+        })
+        .unwrap();
+        let detections = calibrated
+            .scan_image_detections(brick_path, None)
+            .await
+            .unwrap();
+        assert_eq!(detections[0].piece.color, "White");
+
+        calibrated.remove_calibration_profile("rig").unwrap();
+        assert!(matches!(
+            StudFinder::new(Config {
+                calibration_profile: Some("rig".to_string()),
+                ..config()
+            }),
+            Err(StudFinderError::CalibrationNotFound(_))
+        ));
     }
 }
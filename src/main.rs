@@ -1,8 +1,24 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
-use studfinder::{Config, ExportFormat, ProcessorType, ScanQuality, StudFinder};
-use tracing::{debug, error, info};
+use clap::{ArgGroup, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use output::{
+    ConfigValues, ErrorReport, ExplainReport, ExplainedDetection, OutputFormat, PieceDetails,
+    PieceRow, ScanReport, Status,
+};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use studfinder::error::StudFinderError;
+use studfinder::storage::{ExportOptions, PieceFilter, PieceSort};
+use studfinder::{
+    BatchOptions, BatchReport, BrickLinkList, Config, ExportFormat, PieceCondition, ProcessorType,
+    ScanQuality, StorageLocation, StudFinder, WatchOptions, CONFIG_KEYS, DEFAULT_REFERENCE,
+};
+use tracing::info;
+
+mod output;
+mod review;
+mod server;
 
 #[derive(Parser)]
 #[command(name = "studfinder")]
@@ -14,6 +30,51 @@ struct Cli {
 
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    #[arg(
+        long,
+        global = true,
+        default_value = "table",
+        help = "Output format: table, json or csv"
+    )]
+    output: OutputFormat,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Database file (overrides config)"
+    )]
+    db: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Image processor: scanner or detector (overrides config)"
+    )]
+    processor: Option<ProcessorType>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Scan quality: fast, balanced or accurate (overrides config)"
+    )]
+    quality: Option<ScanQuality>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Confidence threshold, 0.0-1.0 (overrides config)"
+    )]
+    threshold: Option<f32>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Calibration profile to correct colors with; empty for none (overrides config)"
+    )]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -23,8 +84,64 @@ enum Commands {
         #[arg(help = "Path to image file")]
         path: PathBuf,
 
-        #[arg(short, long, help = "Process entire directory")]
+        #[arg(short, long, help = "Process every image under a directory")]
         batch: bool,
+
+        #[arg(
+            long,
+            requires = "batch",
+            help = "Images processed at once (default: number of CPUs)"
+        )]
+        workers: Option<usize>,
+
+        #[arg(
+            long,
+            value_name = "PATH",
+            requires = "batch",
+            help = "Save the batch report as JSON"
+        )]
+        report: Option<PathBuf>,
+
+        #[arg(
+            long,
+            requires = "batch",
+            help = "Scan images already in the scan ledger again, replacing their results"
+        )]
+        rescan: bool,
+
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with = "batch",
+            help = "Side view of the same piece, used to tell plates from bricks"
+        )]
+        side: Option<PathBuf>,
+
+        #[arg(
+            long,
+            conflicts_with = "batch",
+            help = "Show the ranked color and part candidates behind each detection"
+        )]
+        explain: bool,
+    },
+
+    #[command(about = "Watch a folder and scan images as they are added")]
+    Watch {
+        #[arg(help = "Folder to watch")]
+        dir: PathBuf,
+
+        #[arg(
+            long,
+            default_value_t = 2000,
+            help = "Milliseconds a file must go unchanged before it is scanned"
+        )]
+        settle_ms: u64,
+
+        #[arg(
+            long,
+            help = "Move scanned images into done/ and failed ones into failed/"
+        )]
+        move_processed: bool,
     },
 
     #[command(about = "Initialize database and configuration")]
@@ -36,193 +153,777 @@ enum Commands {
         force: bool,
     },
 
+    #[command(about = "Accept, correct or discard detections queued for review")]
+    Review {
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "Number of alternative colors to offer"
+        )]
+        top: usize,
+
+        #[arg(long, help = "List the queued detections instead of reviewing them")]
+        list: bool,
+    },
+
     #[command(about = "Manage piece inventory")]
     Inventory {
         #[command(subcommand)]
         action: InventoryCommands,
     },
+
+    #[command(about = "Manage the part catalog")]
+    Catalog {
+        #[command(subcommand)]
+        action: CatalogCommands,
+    },
+
+    #[command(about = "Import set inventories and check them against the inventory")]
+    Set {
+        #[command(subcommand)]
+        action: SetCommands,
+    },
+
+    #[command(about = "Manage white-balance calibration profiles")]
+    Calibration {
+        #[command(subcommand)]
+        action: CalibrationCommands,
+    },
+
+    #[command(about = "Serve the inventory and scanner as a JSON REST API")]
+    Serve {
+        #[arg(
+            long,
+            default_value = "127.0.0.1:8080",
+            help = "Address and port to listen on"
+        )]
+        bind: SocketAddr,
+    },
+
+    #[command(about = "Show or change configuration")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    #[command(about = "Show the effective configuration")]
+    Show,
+
+    #[command(about = "Print a single configuration value")]
+    Get {
+        #[arg(help = "Configuration key, e.g. scan_quality")]
+        key: String,
+    },
+
+    #[command(about = "Change a value in the config file")]
+    Set {
+        #[arg(help = "Configuration key, e.g. scan_quality")]
+        key: String,
+
+        #[arg(help = "New value")]
+        value: String,
+    },
 }
 
 #[derive(Subcommand)]
 enum InventoryCommands {
-    #[command(about = "List all pieces")]
-    List,
+    #[command(about = "List pieces, optionally filtered and sorted")]
+    List {
+        #[arg(long, help = "Only this part number")]
+        part: Option<String>,
+
+        #[arg(long, help = "Only this color")]
+        color: Option<String>,
+
+        #[arg(long, help = "Only categories starting with this text, e.g. Plate")]
+        category: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "CONFIDENCE",
+            help = "Only pieces detected with at least this confidence (0.0-1.0)"
+        )]
+        min_confidence: Option<f32>,
+
+        #[arg(long, help = "Sort by part, color, category, quantity or confidence")]
+        sort: Option<PieceSort>,
+
+        #[arg(long, help = "Sort in descending order")]
+        desc: bool,
+
+        #[arg(long, help = "Show at most this many pieces")]
+        limit: Option<usize>,
+    },
+
+    #[command(about = "Show a single inventory entry and the scans it was seen in")]
+    Show {
+        #[arg(help = "Piece ID")]
+        id: String,
+    },
+
+    #[command(about = "Set the quantity of an inventory entry")]
+    SetQty {
+        #[arg(help = "Piece ID")]
+        id: String,
+
+        #[arg(help = "New quantity")]
+        quantity: i32,
+    },
+
+    #[command(about = "Correct the part number or color of an inventory entry")]
+    #[command(group = ArgGroup::new("change").required(true).multiple(true))]
+    Edit {
+        #[arg(help = "Piece ID")]
+        id: String,
+
+        #[arg(long, group = "change", help = "New part number")]
+        part: Option<String>,
+
+        #[arg(long, group = "change", help = "New color")]
+        color: Option<String>,
+    },
+
+    #[command(about = "Move pieces of an inventory entry into a storage location")]
+    Move {
+        #[arg(help = "Piece ID")]
+        id: String,
+
+        #[arg(help = "Destination, as cabinet[/drawer[/bin]], e.g. A/3/12")]
+        to: StorageLocation,
+
+        #[arg(
+            long,
+            help = "Take the pieces from this location instead of the unplaced ones"
+        )]
+        from: Option<StorageLocation>,
+
+        #[arg(
+            long,
+            help = "Number of pieces to move; everything at the source by default"
+        )]
+        quantity: Option<i32>,
+    },
+
+    #[command(about = "Show where the pieces of a part are kept")]
+    Where {
+        #[arg(help = "Part number or alternate number")]
+        part: String,
+
+        #[arg(long, help = "Only this color")]
+        color: Option<String>,
+    },
+
+    #[command(about = "Remove an inventory entry")]
+    Remove {
+        #[arg(help = "Piece ID")]
+        id: String,
+    },
 
     #[command(about = "Export inventory to file")]
     Export {
         #[arg(help = "Path to export file")]
         path: PathBuf,
+
+        #[arg(
+            long,
+            help = "Output format (json, csv, rebrickable, bricklink); inferred from the file name by default"
+        )]
+        format: Option<ExportFormat>,
+
+        #[arg(long, help = "Report every piece in this condition (new or used)")]
+        condition: Option<PieceCondition>,
+
+        #[arg(
+            long,
+            default_value = "wanted",
+            help = "BrickLink XML list type (wanted or store)"
+        )]
+        list: BrickLinkList,
     },
 
     #[command(about = "Import inventory from file")]
     Import {
         #[arg(help = "Path to import file")]
         path: PathBuf,
+
+        #[arg(
+            long,
+            help = "Input format (json, csv, rebrickable, bricklink, ldraw); detected from the content by default"
+        )]
+        format: Option<ExportFormat>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CatalogCommands {
+    #[command(about = "Import a Rebrickable parts dump (directory, parts.csv or JSON)")]
+    Import {
+        #[arg(help = "Path to the catalog dump")]
+        path: PathBuf,
+    },
+
+    #[command(about = "Show catalog details for a part")]
+    Show {
+        #[arg(help = "Part number or alternate number")]
+        part_number: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SetCommands {
+    #[command(about = "Import a Rebrickable set dump (directory or sets.csv)")]
+    Import {
+        #[arg(help = "Path to the set dump")]
+        path: PathBuf,
+    },
+
+    #[command(about = "Show how much of a set the inventory covers")]
+    Check {
+        #[arg(help = "Set number, e.g. 10696-1 (a bare number means version 1)")]
+        set_number: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CalibrationCommands {
+    #[command(about = "Create or replace a profile from a photo of a reference card or brick")]
+    Create {
+        #[arg(help = "Profile name, e.g. the rig it is for")]
+        name: String,
+
+        #[arg(help = "Photo of the reference, taken on the rig")]
+        image: PathBuf,
+
+        #[arg(
+            long,
+            default_value = DEFAULT_REFERENCE,
+            help = "Palette color of the reference, e.g. White or Light Bluish Gray"
+        )]
+        reference: String,
+    },
+
+    #[command(about = "List calibration profiles")]
+    List,
+
+    #[command(about = "Show a calibration profile")]
+    Show {
+        #[arg(help = "Profile name")]
+        name: String,
+    },
+
+    #[command(about = "Delete a calibration profile")]
+    Remove {
+        #[arg(help = "Profile name")]
+        name: String,
     },
 }
 
-fn setup_logging(verbose: bool) -> Result<()> {
-    if verbose {
-        tracing_subscriber::fmt()
-            .with_max_level(tracing::Level::DEBUG)
-            .init();
+fn setup_logging(verbose: bool) {
+    // Logs go to stderr so stdout only carries command output
+    let level = if verbose {
+        tracing::Level::DEBUG
     } else {
-        tracing_subscriber::fmt()
-            .with_max_level(tracing::Level::INFO)
-            .init();
-    }
-    Ok(())
+        tracing::Level::INFO
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .init();
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    let format = cli.output;
 
-    setup_logging(cli.verbose)?;
+    setup_logging(cli.verbose);
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let report = ErrorReport::new(&e);
+            output::emit_error(format, &report);
+            ExitCode::from(report.exit_code)
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let format = cli.output;
+    let mut config = Config::load()?;
+    if let Some(db) = cli.db {
+        config.database_path = db;
+    }
+    if let Some(processor) = cli.processor {
+        config.processor_type = processor;
+    }
+    if let Some(quality) = cli.quality {
+        config.scan_quality = quality;
+    }
+    if let Some(threshold) = cli.threshold {
+        config.confidence_threshold = threshold;
+        config.fit_review_threshold("--threshold")?;
+    }
+    if let Some(profile) = cli.profile {
+        config.set("calibration_profile", &profile)?;
+    }
 
-    let config = get_default_config()?;
+    // Config commands work without opening the database
+    if let Commands::Config {
+        action,
+    } = cli.command
+    {
+        return run_config_command(format, &config, action);
+    }
 
-    let studfinder = StudFinder::new(config)?;
+    let config_path = Config::file_path()?;
+    let config_text = config.to_toml();
+    // Calibration commands manage the profiles themselves, so a selected
+    // profile that does not exist yet must not stop them
+    if matches!(cli.command, Commands::Calibration { .. }) {
+        config.calibration_profile = None;
+    }
+    let mut studfinder = StudFinder::new(config)?;
 
     match cli.command {
         Commands::Init => {
             info!("Initializing studfinder...");
             studfinder.init()?;
-            info!("Initialization complete");
+            if config_path.exists() {
+                info!("Using existing config file {}", config_path.display());
+            } else {
+                if let Some(dir) = config_path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&config_path, config_text)?;
+                info!("Wrote config file {}", config_path.display());
+            }
+            output::emit(format, &Status::ok("Initialization complete"))?;
         }
         Commands::Reset {
             force,
         } => {
             if !force {
-                println!("WARNING: This will delete all stored data. Are you sure? [y/N]");
+                eprintln!("WARNING: This will delete all stored data. Are you sure? [y/N]");
                 let mut input = String::new();
                 std::io::stdin().read_line(&mut input)?;
                 if !input.trim().eq_ignore_ascii_case("y") {
-                    println!("Reset cancelled");
+                    output::emit(format, &Status::ok("Reset cancelled"))?;
                     return Ok(());
                 }
             }
             info!("Resetting database...");
             studfinder.reset()?;
-            info!("Reset complete");
+            output::emit(format, &Status::ok("Reset complete"))?;
         }
         Commands::Scan {
             path,
             batch,
+            workers,
+            report,
+            rescan,
+            side,
+            explain,
         } => {
             if batch {
-                info!("Processing directory: {}", path.display());
-                process_directory(&studfinder, path).await?;
-            } else {
+                let mut options = BatchOptions {
+                    rescan,
+                    ..BatchOptions::default()
+                };
+                if let Some(workers) = workers {
+                    options.workers = workers;
+                }
+                let batch_report = scan_directory(&studfinder, &path, &options).await?;
+                if let Some(report) = report {
+                    batch_report.save(&report)?;
+                    info!("Saved batch report to {}", report.display());
+                }
+                output::emit(format, &batch_report)?;
+            } else if explain {
                 info!("Processing image: {}", path.display());
-                process_single_image(&studfinder, path).await?;
+                let policy = studfinder.review_policy();
+                let detections = studfinder
+                    .scan_image_detections(path.clone(), side)
+                    .await?
+                    .into_iter()
+                    .map(|detection| ExplainedDetection {
+                        band: policy.band_for(&detection.piece),
+                        detection,
+                    })
+                    .collect();
+                let report = ExplainReport {
+                    path,
+                    detections,
+                };
+                output::emit(format, &report)?;
+            } else {
+                let pieces = process_single_image(&studfinder, path.clone(), side).await?;
+                let report = ScanReport {
+                    path,
+                    pieces: rows(&studfinder, pieces),
+                };
+                output::emit(format, &report)?;
+            }
+        }
+        Commands::Watch {
+            dir,
+            settle_ms,
+            move_processed,
+        } => {
+            let options = WatchOptions {
+                settle: std::time::Duration::from_millis(settle_ms),
+                move_processed,
+            };
+            let report = watch_directory(&studfinder, &dir, &options).await?;
+            output::emit(format, &report)?;
+        }
+        Commands::Review {
+            top,
+            list,
+        } => {
+            if list {
+                output::emit(format, &studfinder.pending_reviews()?)?;
+            } else {
+                // Prompts go to stderr so stdout only carries the summary
+                let summary = review::run_review(
+                    &studfinder,
+                    &mut std::io::stdin().lock(),
+                    &mut std::io::stderr(),
+                    top,
+                )?;
+                output::emit(format, &Status::ok(summary.to_string()))?;
             }
         }
         Commands::Inventory {
             action,
         } => match action {
-            InventoryCommands::List => {
-                let pieces = studfinder.list_inventory()?;
-                if pieces.is_empty() {
-                    println!("No pieces in inventory");
-                } else {
-                    println!("\nInventory:");
-                    println!(
-                        "{:<36} {:<8} {:<10} {:<8} {:<10}",
-                        "ID", "PART#", "COLOR", "QTY", "CONFIDENCE"
-                    );
-                    println!("{}", "-".repeat(75));
-                    for piece in pieces {
-                        println!(
-                            "{:<36} {:<8} {:<10} {:<8} {:.1}%",
-                            piece.id,
-                            piece.part_number,
-                            piece.color,
-                            piece.quantity,
-                            piece.confidence * 100.0
-                        );
-                    }
-                    println!();
-                }
+            InventoryCommands::List {
+                part,
+                color,
+                category,
+                min_confidence,
+                sort,
+                desc,
+                limit,
+            } => {
+                let filter = PieceFilter {
+                    part_number: part,
+                    color,
+                    category,
+                    min_confidence,
+                    sort,
+                    descending: desc,
+                    limit,
+                };
+                let pieces = studfinder.query_inventory(&filter)?;
+                output::emit(format, &rows(&studfinder, pieces))?;
+            }
+            InventoryCommands::Show {
+                id,
+            } => {
+                let piece = studfinder.get_piece(&id)?;
+                let details = PieceDetails {
+                    observations: studfinder.piece_observations(&id)?,
+                    piece: PieceRow::new(&studfinder, piece),
+                };
+                output::emit(format, &details)?;
+            }
+            InventoryCommands::SetQty {
+                id,
+                quantity,
+            } => {
+                studfinder.set_quantity(&id, quantity)?;
+                output::emit(
+                    format,
+                    &Status::ok(format!("Set quantity of {} to {}", id, quantity)),
+                )?;
+            }
+            InventoryCommands::Edit {
+                id,
+                part,
+                color,
+            } => {
+                let piece = studfinder.edit_piece(&id, part.as_deref(), color.as_deref())?;
+                output::emit(format, &PieceRow::new(&studfinder, piece))?;
+            }
+            InventoryCommands::Move {
+                id,
+                to,
+                from,
+                quantity,
+            } => {
+                let located = studfinder.move_pieces(&id, from.as_ref(), &to, quantity)?;
+                output::emit(format, &located)?;
+            }
+            InventoryCommands::Where {
+                part,
+                color,
+            } => {
+                let located = studfinder.locate(&part, color.as_deref())?;
+                output::emit(format, &located)?;
+            }
+            InventoryCommands::Remove {
+                id,
+            } => {
+                studfinder.remove_piece(&id)?;
+                output::emit(format, &Status::ok(format!("Removed {}", id)))?;
             }
             InventoryCommands::Export {
                 path,
+                format: file_format,
+                condition,
+                list,
             } => {
                 info!("Exporting inventory to: {}", path.display());
-                studfinder.export_inventory(path)?;
-                info!("Export complete");
+                let options = ExportOptions {
+                    condition,
+                    bricklink_list: list,
+                };
+                studfinder.export_inventory_with_options(path, file_format, &options)?;
+                output::emit(format, &Status::ok("Export complete"))?;
             }
             InventoryCommands::Import {
                 path,
+                format: file_format,
             } => {
                 info!("Importing inventory from: {}", path.display());
-                studfinder.import_inventory(path)?;
-                info!("Import complete");
+                studfinder.import_inventory_with_format(path, file_format)?;
+                output::emit(format, &Status::ok("Import complete"))?;
+            }
+        },
+        Commands::Config {
+            ..
+        } => unreachable!("config commands are handled before opening the database"),
+        Commands::Catalog {
+            action,
+        } => match action {
+            CatalogCommands::Import {
+                path,
+            } => {
+                info!("Importing part catalog from: {}", path.display());
+                let count = studfinder.import_catalog(path)?;
+                output::emit(format, &Status::ok(format!("Imported {} part(s)", count)))?;
+            }
+            CatalogCommands::Show {
+                part_number,
+            } => {
+                let part = studfinder
+                    .part_info(&part_number)
+                    .ok_or_else(|| StudFinderError::PartNotFound(part_number.clone()))?;
+                output::emit(format, part)?;
+            }
+        },
+        Commands::Set {
+            action,
+        } => match action {
+            SetCommands::Import {
+                path,
+            } => {
+                info!("Importing sets from: {}", path.display());
+                let count = studfinder.import_sets(path)?;
+                output::emit(format, &Status::ok(format!("Imported {} set(s)", count)))?;
+            }
+            SetCommands::Check {
+                set_number,
+            } => {
+                let check = studfinder.check_set(&set_number)?;
+                output::emit(format, &check)?;
+            }
+        },
+        Commands::Calibration {
+            action,
+        } => match action {
+            CalibrationCommands::Create {
+                name,
+                image,
+                reference,
+            } => {
+                let profile = studfinder.calibrate(&name, &image, &reference)?;
+                output::emit(format, &profile)?;
+            }
+            CalibrationCommands::List => {
+                output::emit(format, &studfinder.calibration_profiles()?)?;
+            }
+            CalibrationCommands::Show {
+                name,
+            } => {
+                output::emit(format, &studfinder.calibration_profile(&name)?)?;
+            }
+            CalibrationCommands::Remove {
+                name,
+            } => {
+                studfinder.remove_calibration_profile(&name)?;
+                output::emit(
+                    format,
+                    &Status::ok(format!("Removed calibration profile {}", name)),
+                )?;
             }
         },
+        Commands::Serve {
+            bind,
+        } => {
+            server::serve(studfinder, bind).await?;
+        }
     }
 
     Ok(())
 }
 
-fn get_default_config() -> Result<Config> {
-    let dirs = directories::ProjectDirs::from("com", "studfinder", "studfinder")
-        .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
-
-    let data_dir = dirs.data_dir();
-    std::fs::create_dir_all(data_dir)?;
-
-    Ok(Config {
-        database_path: data_dir.join("studfinder.db"),
-        export_format: ExportFormat::Json,
-        scan_quality: ScanQuality::Balanced,
-        processor_type: ProcessorType::Scanner,
-        confidence_threshold: 0.8,
-    })
+/// Pair pieces with their catalog names for output
+fn rows(studfinder: &StudFinder, pieces: Vec<studfinder::Piece>) -> Vec<PieceRow> {
+    pieces
+        .into_iter()
+        .map(|piece| PieceRow::new(studfinder, piece))
+        .collect()
 }
 
-async fn process_directory(studfinder: &StudFinder, dir: PathBuf) -> Result<()> {
-    let mut successful = 0;
-    let mut failed = 0;
-
-    for entry in std::fs::read_dir(&dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            match process_single_image(studfinder, path.clone()).await {
-                Ok(()) => {
-                    successful += 1;
-                    debug!("Successfully processed: {}", path.display());
-                }
-                Err(e) => {
-                    failed += 1;
-                    error!("Failed to process {}: {}", path.display(), e);
+fn run_config_command(format: OutputFormat, config: &Config, action: ConfigCommands) -> Result<()> {
+    match action {
+        ConfigCommands::Show => {
+            let values = CONFIG_KEYS
+                .iter()
+                .map(|&key| Ok((key.to_string(), config.get(key)?)))
+                .collect::<Result<_>>()?;
+            let values = ConfigValues {
+                path: Config::file_path()?,
+                values,
+            };
+            output::emit(format, &values)?;
+        }
+        ConfigCommands::Get {
+            key,
+        } => {
+            let value = config.get(&key)?;
+            match format {
+                // A bare value is easiest to use from shell scripts
+                OutputFormat::Table => println!("{}", value),
+                OutputFormat::Json | OutputFormat::Csv => {
+                    let values = ConfigValues {
+                        path: Config::file_path()?,
+                        values: vec![(key, value)],
+                    };
+                    output::emit(format, &values)?;
                 }
             }
         }
+        ConfigCommands::Set {
+            key,
+            value,
+        } => {
+            // Edit the file's own values so environment and flag overrides
+            // are not persisted
+            let path = Config::file_path()?;
+            let mut file_config = Config::load_file_only()?;
+            file_config.set(&key, &value)?;
+            file_config.validate()?;
+            file_config.save(&path)?;
+            let message = format!(
+                "Set {} = {} in {}",
+                key,
+                file_config.get(&key)?,
+                path.display()
+            );
+            output::emit(format, &Status::ok(message))?;
+        }
     }
-
-    info!(
-        "Batch processing complete. Successful: {}, Failed: {}",
-        successful, failed
-    );
     Ok(())
 }
 
-async fn process_single_image(studfinder: &StudFinder, path: PathBuf) -> Result<()> {
-    info!("Processing image: {}", path.display());
+async fn scan_directory(
+    studfinder: &StudFinder,
+    dir: &Path,
+    options: &BatchOptions,
+) -> Result<BatchReport> {
+    info!("Processing directory: {}", dir.display());
+
+    // Drawn on stderr, and hidden when stderr is not a terminal
+    let progress = ProgressBar::new(0).with_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} {wide_msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+    let report = studfinder
+        .scan_batch(dir, options, |update| {
+            progress.set_length(update.total as u64);
+            progress.set_position(update.completed as u64);
+            progress.set_message(update.file.path.display().to_string());
+        })
+        .await?;
+    progress.finish_and_clear();
+
+    Ok(report)
+}
 
-    let piece = studfinder.scan_image(path).await?;
+/// Watch a folder until Ctrl-C, showing a running count on stderr
+async fn watch_directory(
+    studfinder: &StudFinder,
+    dir: &Path,
+    options: &WatchOptions,
+) -> Result<BatchReport> {
+    info!("Watching directory: {} (Ctrl-C to stop)", dir.display());
 
-    info!(
-        "Detected: {} {} {} (confidence: {:.1}%)",
-        piece.color,
-        piece.category,
-        piece.part_number,
-        piece.confidence * 100.0
+    // Drawn on stderr, and hidden when stderr is not a terminal
+    let progress = ProgressBar::new_spinner().with_style(
+        ProgressStyle::with_template("{spinner} {wide_msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()),
     );
+    progress.enable_steady_tick(std::time::Duration::from_millis(250));
+    progress.set_message("Waiting for images");
+    let (mut scanned, mut failed) = (0, 0);
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    let report = studfinder
+        .watch(dir, options, shutdown, |file| {
+            match file.outcome {
+                studfinder::FileOutcome::Scanned {
+                    ..
+                } => scanned += 1,
+                _ => failed += 1,
+            }
+            progress.set_message(format!(
+                "{} scanned, {} failed; last: {}",
+                scanned,
+                failed,
+                file.path.display()
+            ));
+        })
+        .await?;
+    progress.finish_and_clear();
 
-    studfinder.add_piece(piece)?;
+    Ok(report)
+}
 
-    Ok(())
+async fn process_single_image(
+    studfinder: &StudFinder,
+    path: PathBuf,
+    side: Option<PathBuf>,
+) -> Result<Vec<studfinder::Piece>> {
+    info!("Processing image: {}", path.display());
+
+    let pieces = match side {
+        Some(side) => studfinder.scan_image_with_side_view(path, side).await?,
+        None => studfinder.scan_image(path).await?,
+    };
+
+    for piece in &pieces {
+        info!(
+            "Detected: {} {} {} (confidence: {:.1}%)",
+            piece.color,
+            piece.category,
+            piece.part_number,
+            piece.confidence * 100.0
+        );
+    }
+    let queued = studfinder.pending_reviews()?.len();
+    if queued > 0 {
+        info!(
+            "{} detection(s) waiting for review; run `studfinder review`",
+            queued
+        );
+    }
+
+    Ok(pieces)
 }
@@ -0,0 +1,1341 @@
+//! Rendering of command results as tables, JSON or CSV
+//!
+//! Every command builds a value implementing [`Render`] and hands it to
+//! [`emit`], which writes it to stdout in the format chosen with `--output`.
+//! Logs go to stderr, so stdout only ever carries the result.
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::io::Write;
+use std::path::PathBuf;
+use studfinder::error::StudFinderError;
+use studfinder::storage::{Observation, PendingReview};
+use studfinder::{
+    BatchReport, CalibrationProfile, Detection, FileOutcome, LocatedPiece, PartInfo, Piece,
+    PieceCondition, ReviewBand, SetCheck, SetPart, StudFinder,
+};
+
+/// Exit code for errors that are not a `StudFinderError`
+const EXIT_UNEXPECTED: u8 = 1;
+
+/// How command results are written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned text for people
+    #[default]
+    Table,
+    /// A single JSON document
+    Json,
+    /// CSV with a header row
+    Csv,
+}
+
+impl OutputFormat {
+    /// Lowercase name of the format
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "table" | "text" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!(
+                "unknown output format '{}' (expected table, json or csv)",
+                other
+            )),
+        }
+    }
+}
+
+/// A command result that can be written in every `OutputFormat`
+///
+/// JSON output comes from the `Serialize` implementation.
+pub trait Render: Serialize {
+    /// Write the value as text for people
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()>;
+
+    /// Write the value as CSV, starting with the header row
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()>;
+}
+
+/// Write a command result to stdout
+///
+/// # Errors
+///
+/// Returns an error if stdout cannot be written.
+pub fn emit<T: Render>(format: OutputFormat, value: &T) -> anyhow::Result<()> {
+    render(format, value, std::io::stdout().lock())
+}
+
+/// Write a command result in the given format
+fn render<T: Render, W: Write>(format: OutputFormat, value: &T, mut out: W) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Table => value.write_table(&mut out)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, value)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(out);
+            value.write_csv(&mut writer)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Report a failed command
+///
+/// JSON output puts the error on stdout, where scripts read results; the
+/// other formats print it to stderr.
+pub fn emit_error(format: OutputFormat, error: &ErrorReport) {
+    match format {
+        OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct Envelope<'a> {
+                error: &'a ErrorReport,
+            }
+            let envelope = Envelope {
+                error,
+            };
+            match serde_json::to_string_pretty(&envelope) {
+                Ok(json) => println!("{}", json),
+                Err(_) => eprintln!("Error: {}", error.message),
+            }
+        }
+        OutputFormat::Table | OutputFormat::Csv => eprintln!("Error: {}", error.message),
+    }
+}
+
+/// Description of a failed command
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    /// `StudFinderError::kind`, or "unexpected"
+    pub kind: &'static str,
+    /// The full error message, including its causes
+    pub message: String,
+    /// The process exit code
+    pub exit_code: u8,
+}
+
+impl ErrorReport {
+    /// Describe an error, classifying it by the first `StudFinderError` in its chain
+    pub fn new(error: &anyhow::Error) -> Self {
+        let source = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<StudFinderError>());
+        Self {
+            kind: source.map_or("unexpected", StudFinderError::kind),
+            message: Self::message(error),
+            exit_code: source.map_or(EXIT_UNEXPECTED, StudFinderError::exit_code),
+        }
+    }
+
+    /// The error and its causes, skipping causes the message already repeats
+    fn message(error: &anyhow::Error) -> String {
+        let mut message = String::new();
+        for cause in error.chain() {
+            let text = cause.to_string();
+            if message.contains(&text) {
+                continue;
+            }
+            if !message.is_empty() {
+                message.push_str(": ");
+            }
+            message.push_str(&text);
+        }
+        message
+    }
+}
+
+/// Header of every CSV listing pieces
+const PIECE_HEADER: [&str; 8] = [
+    "id",
+    "part_number",
+    "name",
+    "color",
+    "category",
+    "quantity",
+    "confidence",
+    "condition",
+];
+
+/// A piece as shown to the user, with its catalog name
+#[derive(Debug, Serialize)]
+pub struct PieceRow {
+    pub id: String,
+    pub part_number: String,
+    pub name: Option<String>,
+    pub color: String,
+    pub category: String,
+    pub quantity: i32,
+    pub confidence: f32,
+    pub condition: Option<PieceCondition>,
+}
+
+impl PieceRow {
+    /// Combine a piece with its name from the part catalog
+    pub fn new(studfinder: &StudFinder, piece: Piece) -> Self {
+        let name = studfinder
+            .part_info(&piece.part_number)
+            .map(|part| part.name.clone());
+        Self {
+            id: piece.id,
+            part_number: piece.part_number,
+            name,
+            color: piece.color,
+            category: piece.category,
+            quantity: piece.quantity,
+            confidence: piece.confidence,
+            condition: piece.condition,
+        }
+    }
+
+    /// The row's CSV fields, in `PIECE_HEADER` order
+    fn record(&self) -> [String; 8] {
+        [
+            self.id.clone(),
+            self.part_number.clone(),
+            self.name.clone().unwrap_or_default(),
+            self.color.clone(),
+            self.category.clone(),
+            self.quantity.to_string(),
+            self.confidence.to_string(),
+            self.condition.map(|c| c.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+impl Render for PieceRow {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "ID:         {}", self.id)?;
+        writeln!(out, "Part:       {}", self.part_number)?;
+        if let Some(name) = &self.name {
+            writeln!(out, "Name:       {}", name)?;
+        }
+        writeln!(out, "Color:      {}", self.color)?;
+        writeln!(out, "Category:   {}", self.category)?;
+        writeln!(out, "Quantity:   {}", self.quantity)?;
+        writeln!(out, "Confidence: {:.1}%", self.confidence * 100.0)?;
+        if let Some(condition) = self.condition {
+            writeln!(out, "Condition:  {}", condition)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(PIECE_HEADER)?;
+        out.write_record(self.record())
+    }
+}
+
+impl Render for Vec<PieceRow> {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        if self.is_empty() {
+            return writeln!(out, "No pieces in inventory");
+        }
+        writeln!(out, "\nInventory:")?;
+        writeln!(
+            out,
+            "{:<36} {:<8} {:<24} {:<10} {:<8} {:<10}",
+            "ID", "PART#", "NAME", "COLOR", "QTY", "CONFIDENCE"
+        )?;
+        writeln!(out, "{}", "-".repeat(100))?;
+        for piece in self {
+            writeln!(
+                out,
+                "{:<36} {:<8} {:<24} {:<10} {:<8} {:.1}%",
+                piece.id,
+                piece.part_number,
+                piece.name.as_deref().unwrap_or(""),
+                piece.color,
+                piece.quantity,
+                piece.confidence * 100.0
+            )?;
+        }
+        writeln!(out)
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(PIECE_HEADER)?;
+        for piece in self {
+            out.write_record(piece.record())?;
+        }
+        Ok(())
+    }
+}
+
+/// Columns describing an observation, appended to `PIECE_HEADER` in `inventory show` CSV
+const OBSERVATION_HEADER: [&str; 13] = [
+    "observed_at",
+    "image_path",
+    "image_hash",
+    "bbox_x",
+    "bbox_y",
+    "bbox_width",
+    "bbox_height",
+    "processor",
+    "quality",
+    "measured_rgb",
+    "detected_part_number",
+    "detected_color",
+    "detected_confidence",
+];
+
+/// An inventory entry with the scans it was seen in
+#[derive(Debug, Serialize)]
+pub struct PieceDetails {
+    #[serde(flatten)]
+    pub piece: PieceRow,
+    pub observations: Vec<Observation>,
+}
+
+/// Measured color as `#rrggbb`, or empty if nothing was measured
+fn hex_rgb(rgb: Option<[u8; 3]>) -> String {
+    rgb.map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+        .unwrap_or_default()
+}
+
+impl Render for PieceDetails {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        self.piece.write_table(out)?;
+        if self.observations.is_empty() {
+            return writeln!(out, "Observations: none");
+        }
+        writeln!(out, "Observations:")?;
+        for observation in &self.observations {
+            let bounds = observation.bounds;
+            writeln!(
+                out,
+                "  {}  {} at ({}, {}) {}x{}",
+                observation.observed_at,
+                observation.image_path,
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height
+            )?;
+            let measured = hex_rgb(observation.measured_rgb);
+            writeln!(
+                out,
+                "    detected {} {} ({:.1}%), measured {}, {}/{}",
+                observation.color,
+                observation.part_number,
+                observation.confidence * 100.0,
+                if measured.is_empty() { "-" } else { &measured },
+                observation.processor,
+                observation.quality
+            )?;
+        }
+        Ok(())
+    }
+
+    /// One row per observation; an entry without observations gets a single row
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(PIECE_HEADER.iter().chain(&OBSERVATION_HEADER))?;
+        let piece = self.piece.record();
+        if self.observations.is_empty() {
+            let empty = std::iter::repeat_n(String::new(), OBSERVATION_HEADER.len());
+            return out.write_record(piece.iter().cloned().chain(empty));
+        }
+        for observation in &self.observations {
+            let bounds = observation.bounds;
+            let fields = [
+                observation.observed_at.clone(),
+                observation.image_path.clone(),
+                observation.image_hash.clone(),
+                bounds.x.to_string(),
+                bounds.y.to_string(),
+                bounds.width.to_string(),
+                bounds.height.to_string(),
+                observation.processor.clone(),
+                observation.quality.clone(),
+                hex_rgb(observation.measured_rgb),
+                observation.part_number.clone(),
+                observation.color.clone(),
+                observation.confidence.to_string(),
+            ];
+            out.write_record(piece.iter().cloned().chain(fields))?;
+        }
+        Ok(())
+    }
+}
+
+/// Columns of `review --list` CSV
+const REVIEW_HEADER: [&str; 14] = [
+    "id",
+    "part_number",
+    "color",
+    "category",
+    "confidence",
+    "queued_at",
+    "image_path",
+    "bbox_x",
+    "bbox_y",
+    "bbox_width",
+    "bbox_height",
+    "processor",
+    "quality",
+    "measured_rgb",
+];
+
+impl Render for Vec<PendingReview> {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        if self.is_empty() {
+            return writeln!(out, "No detections waiting for review");
+        }
+        writeln!(out, "\nReview queue:")?;
+        writeln!(
+            out,
+            "{:<36} {:<8} {:<10} {:<10} {:<20} IMAGE",
+            "ID", "PART#", "COLOR", "CONFIDENCE", "QUEUED"
+        )?;
+        writeln!(out, "{}", "-".repeat(100))?;
+        for review in self {
+            let confidence = format!("{:.1}%", review.confidence * 100.0);
+            writeln!(
+                out,
+                "{:<36} {:<8} {:<10} {:<10} {:<20} {}",
+                review.id,
+                review.part_number,
+                review.color,
+                confidence,
+                review.queued_at,
+                review.image_path
+            )?;
+        }
+        writeln!(out)
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(REVIEW_HEADER)?;
+        for review in self {
+            let bounds = review.bounds;
+            out.write_record([
+                review.id.clone(),
+                review.part_number.clone(),
+                review.color.clone(),
+                review.category.clone(),
+                review.confidence.to_string(),
+                review.queued_at.clone(),
+                review.image_path.clone(),
+                bounds.x.to_string(),
+                bounds.y.to_string(),
+                bounds.width.to_string(),
+                bounds.height.to_string(),
+                review.processor.clone(),
+                review.quality.clone(),
+                hex_rgb(review.measured_rgb),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+/// Pieces found in a single image
+#[derive(Debug, Serialize)]
+pub struct ScanReport {
+    pub path: PathBuf,
+    pub pieces: Vec<PieceRow>,
+}
+
+impl Render for ScanReport {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{}: {} piece(s)",
+            self.path.display(),
+            self.pieces.len()
+        )?;
+        for piece in &self.pieces {
+            writeln!(
+                out,
+                "  {} {} {} (confidence: {:.1}%)",
+                piece.color,
+                piece.category,
+                piece.part_number,
+                piece.confidence * 100.0
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        let path = self.path.display().to_string();
+        out.write_record(std::iter::once("path").chain(PIECE_HEADER))?;
+        for piece in &self.pieces {
+            out.write_record(std::iter::once(path.clone()).chain(piece.record()))?;
+        }
+        Ok(())
+    }
+}
+
+/// A detection shown by `scan --explain`, with what happened to it
+#[derive(Debug, Serialize)]
+pub struct ExplainedDetection {
+    /// Whether the piece was stored or queued for review
+    pub band: ReviewBand,
+    #[serde(flatten)]
+    pub detection: Detection,
+}
+
+/// The detections behind a single-image scan, with their candidates
+#[derive(Debug, Serialize)]
+pub struct ExplainReport {
+    pub path: PathBuf,
+    pub detections: Vec<ExplainedDetection>,
+}
+
+/// Columns of `scan --explain` CSV: one row per candidate
+const EXPLAIN_HEADER: [&str; 9] = [
+    "path",
+    "detection",
+    "band",
+    "piece_id",
+    "kind",
+    "rank",
+    "candidate",
+    "score",
+    "delta_e",
+];
+
+impl Render for ExplainReport {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "{}: {} detection(s)",
+            self.path.display(),
+            self.detections.len()
+        )?;
+        for (index, explained) in self.detections.iter().enumerate() {
+            let detection = &explained.detection;
+            let piece = &detection.piece;
+            let bounds = detection.bounds;
+            let outcome = match explained.band {
+                ReviewBand::Accept => format!("stored in {}", piece.id),
+                ReviewBand::Review | ReviewBand::Reject => "queued for review".to_string(),
+            };
+            writeln!(
+                out,
+                "  #{} {} {} {} ({:.1}%), {}",
+                index + 1,
+                piece.color,
+                piece.category,
+                piece.part_number,
+                piece.confidence * 100.0,
+                outcome
+            )?;
+            let measured = hex_rgb(detection.measured_rgb);
+            writeln!(
+                out,
+                "    at ({}, {}) {}x{}, measured {}",
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height,
+                if measured.is_empty() { "-" } else { &measured }
+            )?;
+            let colors: Vec<String> = detection
+                .color_candidates
+                .iter()
+                .map(|c| format!("{} {:.2} (delta E {:.1})", c.name, c.score, c.delta_e))
+                .collect();
+            let parts: Vec<String> = detection
+                .part_candidates
+                .iter()
+                .map(|c| format!("{} {:.2}", c.part_number, c.score))
+                .collect();
+            let list = |items: Vec<String>| {
+                if items.is_empty() {
+                    "none".to_string()
+                } else {
+                    items.join(", ")
+                }
+            };
+            writeln!(out, "    colors: {}", list(colors))?;
+            writeln!(out, "    parts:  {}", list(parts))?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        let path = self.path.display().to_string();
+        out.write_record(EXPLAIN_HEADER)?;
+        for (index, explained) in self.detections.iter().enumerate() {
+            let detection = &explained.detection;
+            let row = |kind: &str, rank: usize, candidate: &str, score: f32, delta_e: String| {
+                [
+                    path.clone(),
+                    (index + 1).to_string(),
+                    explained.band.to_string(),
+                    detection.piece.id.clone(),
+                    kind.to_string(),
+                    rank.to_string(),
+                    candidate.to_string(),
+                    score.to_string(),
+                    delta_e,
+                ]
+            };
+            for (rank, color) in detection.color_candidates.iter().enumerate() {
+                out.write_record(row(
+                    "color",
+                    rank + 1,
+                    &color.name,
+                    color.score,
+                    color.delta_e.to_string(),
+                ))?;
+            }
+            for (rank, part) in detection.part_candidates.iter().enumerate() {
+                out.write_record(row(
+                    "part",
+                    rank + 1,
+                    &part.part_number,
+                    part.score,
+                    String::new(),
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Header of a batch report CSV: the file, then the columns of its pieces
+const BATCH_HEADER: [&str; 10] = [
+    "path",
+    "status",
+    "reason",
+    "id",
+    "part_number",
+    "color",
+    "category",
+    "quantity",
+    "confidence",
+    "condition",
+];
+
+impl Render for BatchReport {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        for file in &self.files {
+            let path = file.path.display();
+            match &file.outcome {
+                FileOutcome::Scanned {
+                    pieces,
+                    queued: 0,
+                } => writeln!(out, "scanned {}: {} piece(s)", path, pieces.len())?,
+                FileOutcome::Scanned {
+                    pieces,
+                    queued,
+                } => writeln!(
+                    out,
+                    "scanned {}: {} piece(s), {} queued for review",
+                    path,
+                    pieces.len(),
+                    queued
+                )?,
+                FileOutcome::Skipped {
+                    reason,
+                } => writeln!(out, "skipped {}: {}", path, reason)?,
+                FileOutcome::Failed {
+                    reason,
+                    ..
+                } => writeln!(out, "failed  {}: {}", path, reason)?,
+            }
+        }
+        writeln!(
+            out,
+            "Batch complete. Scanned: {}, Skipped: {}, Failed: {}, Pieces: {}, Queued: {}",
+            self.scanned, self.skipped, self.failed, self.pieces, self.queued
+        )
+    }
+
+    /// One row per piece; skipped and failed files get a single row
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(BATCH_HEADER)?;
+        for file in &self.files {
+            let path = file.path.display().to_string();
+            let (status, reason, pieces) = match &file.outcome {
+                FileOutcome::Scanned {
+                    pieces,
+                    ..
+                } => ("scanned", "", pieces.as_slice()),
+                FileOutcome::Skipped {
+                    reason,
+                } => ("skipped", reason.as_str(), &[][..]),
+                FileOutcome::Failed {
+                    reason,
+                    ..
+                } => ("failed", reason.as_str(), &[][..]),
+            };
+            if pieces.is_empty() {
+                out.write_record([
+                    path.as_str(),
+                    status,
+                    reason,
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                ])?;
+            }
+            for piece in pieces {
+                out.write_record([
+                    path.clone(),
+                    status.to_string(),
+                    reason.to_string(),
+                    piece.id.clone(),
+                    piece.part_number.clone(),
+                    piece.color.clone(),
+                    piece.category.clone(),
+                    piece.quantity.to_string(),
+                    piece.confidence.to_string(),
+                    piece.condition.map(|c| c.to_string()).unwrap_or_default(),
+                ])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Confirmation of a command that has no other result
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub status: &'static str,
+    pub message: String,
+}
+
+impl Status {
+    /// A successful command
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            status: "ok",
+            message: message.into(),
+        }
+    }
+}
+
+impl Render for Status {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "{}", self.message)
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record([
+            "status", "message",
+        ])?;
+        out.write_record([
+            self.status,
+            &self.message,
+        ])
+    }
+}
+
+impl Render for PartInfo {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "Part:       {}", self.part_number)?;
+        writeln!(out, "Name:       {}", self.name)?;
+        writeln!(out, "Category:   {}", self.category)?;
+        if let Some(dimensions) = self.dimensions {
+            writeln!(out, "Dimensions: {}", dimensions)?;
+        }
+        if !self.alternates.is_empty() {
+            writeln!(out, "Alternates: {}", self.alternates.join(", "))?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record([
+            "part_number",
+            "name",
+            "category",
+            "dimensions",
+            "alternates",
+        ])?;
+        out.write_record([
+            self.part_number.clone(),
+            self.name.clone(),
+            self.category.clone(),
+            self.dimensions.map(|d| d.to_string()).unwrap_or_default(),
+            self.alternates.join(" "),
+        ])
+    }
+}
+
+/// Columns of `calibration list` and `calibration show` CSV
+const CALIBRATION_HEADER: [&str; 7] = [
+    "name",
+    "reference",
+    "measured_rgb",
+    "red_gain",
+    "green_gain",
+    "blue_gain",
+    "created_at",
+];
+
+/// One CSV row of a calibration profile
+fn calibration_record(profile: &CalibrationProfile) -> [String; 7] {
+    let [red, green, blue] = profile.white_balance.gains();
+    [
+        profile.name.clone(),
+        profile.reference.clone(),
+        hex_rgb(Some(profile.measured)),
+        red.to_string(),
+        green.to_string(),
+        blue.to_string(),
+        profile.created_at.clone().unwrap_or_default(),
+    ]
+}
+
+impl Render for CalibrationProfile {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        let [red, green, blue] = self.white_balance.gains();
+        writeln!(out, "Profile:   {}", self.name)?;
+        writeln!(out, "Reference: {}", self.reference)?;
+        writeln!(out, "Measured:  {}", hex_rgb(Some(self.measured)))?;
+        writeln!(
+            out,
+            "Gains:     R {:.3}  G {:.3}  B {:.3}",
+            red, green, blue
+        )?;
+        if let Some(created_at) = &self.created_at {
+            writeln!(out, "Created:   {}", created_at)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(CALIBRATION_HEADER)?;
+        out.write_record(calibration_record(self))
+    }
+}
+
+impl Render for Vec<CalibrationProfile> {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        if self.is_empty() {
+            return writeln!(out, "No calibration profiles");
+        }
+        writeln!(out, "\nCalibration profiles:")?;
+        writeln!(
+            out,
+            "{:<20} {:<12} {:<8} {:<23} CREATED",
+            "NAME", "REFERENCE", "MEASURED", "GAINS (R G B)"
+        )?;
+        writeln!(out, "{}", "-".repeat(85))?;
+        for profile in self {
+            let [red, green, blue] = profile.white_balance.gains();
+            let gains = format!("{:.3} {:.3} {:.3}", red, green, blue);
+            writeln!(
+                out,
+                "{:<20} {:<12} {:<8} {:<23} {}",
+                profile.name,
+                profile.reference,
+                hex_rgb(Some(profile.measured)),
+                gains,
+                profile.created_at.as_deref().unwrap_or("")
+            )?;
+        }
+        writeln!(out)
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(CALIBRATION_HEADER)?;
+        for profile in self {
+            out.write_record(calibration_record(profile))?;
+        }
+        Ok(())
+    }
+}
+
+/// Columns of `inventory where` and `inventory move` CSV: one row per location
+const LOCATION_HEADER: [&str; 6] = [
+    "id",
+    "part_number",
+    "color",
+    "condition",
+    "location",
+    "quantity",
+];
+
+/// Where an entry's pieces are, with its unplaced pieces last
+///
+/// An entry with no pieces anywhere gets a single unplaced share of zero,
+/// so it still shows up.
+fn location_shares(located: &LocatedPiece) -> Vec<(String, i32)> {
+    let mut shares: Vec<(String, i32)> = located
+        .locations
+        .iter()
+        .map(|l| (l.location.to_string(), l.quantity))
+        .collect();
+    if located.unplaced > 0 || shares.is_empty() {
+        shares.push((String::new(), located.unplaced));
+    }
+    shares
+}
+
+impl Render for LocatedPiece {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        let piece = &self.piece;
+        write!(out, "{} {} {}", piece.id, piece.color, piece.part_number)?;
+        if let Some(condition) = piece.condition {
+            write!(out, " ({})", condition)?;
+        }
+        writeln!(out, ", {} piece(s)", piece.quantity)?;
+        for (location, quantity) in location_shares(self) {
+            let location = if location.is_empty() {
+                "unplaced".to_string()
+            } else {
+                location
+            };
+            writeln!(out, "  {:<24} {:>6}", location, quantity)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        write_location_csv(out, std::slice::from_ref(self))
+    }
+}
+
+impl Render for Vec<LocatedPiece> {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        if self.is_empty() {
+            return writeln!(out, "No matching pieces in inventory");
+        }
+        for located in self {
+            located.write_table(out)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        write_location_csv(out, self)
+    }
+}
+
+/// Write entries as `LOCATION_HEADER` rows
+fn write_location_csv<W: Write>(
+    out: &mut csv::Writer<W>,
+    pieces: &[LocatedPiece],
+) -> csv::Result<()> {
+    out.write_record(LOCATION_HEADER)?;
+    for located in pieces {
+        let piece = &located.piece;
+        for (location, quantity) in location_shares(located) {
+            out.write_record([
+                piece.id.clone(),
+                piece.part_number.clone(),
+                piece.color.clone(),
+                piece.condition.map(|c| c.to_string()).unwrap_or_default(),
+                location,
+                quantity.to_string(),
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+/// Columns of `set check` CSV: one row per missing or surplus part
+const SET_CHECK_HEADER: [&str; 5] = [
+    "set_number",
+    "status",
+    "part_number",
+    "color",
+    "quantity",
+];
+
+impl Render for SetCheck {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "{} {}", self.set_number, self.name)?;
+        writeln!(
+            out,
+            "{:.1}% complete: {} of {} piece(s)",
+            self.percent_complete, self.owned, self.required
+        )?;
+        let list = |out: &mut W, title: &str, parts: &[SetPart]| {
+            if parts.is_empty() {
+                return Ok(());
+            }
+            writeln!(out, "\n{}:", title)?;
+            writeln!(out, "{:<12} {:<24} {:>8}", "PART", "COLOR", "QUANTITY")?;
+            for part in parts {
+                writeln!(
+                    out,
+                    "{:<12} {:<24} {:>8}",
+                    part.part_number, part.color, part.quantity
+                )?;
+            }
+            Ok(())
+        };
+        list(out, "Missing", &self.missing)?;
+        list(out, "Surplus", &self.surplus)
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record(SET_CHECK_HEADER)?;
+        let missing = self.missing.iter().map(|part| ("missing", part));
+        let surplus = self.surplus.iter().map(|part| ("surplus", part));
+        for (status, part) in missing.chain(surplus) {
+            out.write_record([
+                self.set_number.clone(),
+                status.to_string(),
+                part.part_number.clone(),
+                part.color.clone(),
+                part.quantity.to_string(),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+/// Configuration values, in `CONFIG_KEYS` order
+///
+/// Serializes as a flat object of keys and values.
+#[derive(Debug)]
+pub struct ConfigValues {
+    /// The config file the values are read from
+    pub path: PathBuf,
+    pub values: Vec<(String, String)>,
+}
+
+impl Serialize for ConfigValues {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for (key, value) in &self.values {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Render for ConfigValues {
+    fn write_table<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "# {}", self.path.display())?;
+        for (key, value) in &self.values {
+            writeln!(out, "{} = {}", key, value)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut csv::Writer<W>) -> csv::Result<()> {
+        out.write_record([
+            "key", "value",
+        ])?;
+        for (key, value) in &self.values {
+            out.write_record([
+                key, value,
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use studfinder::{ColorCandidate, FileReport, PartCandidate};
+
+    fn rendered<T: Render>(format: OutputFormat, value: &T) -> String {
+        let mut out = Vec::new();
+        render(format, value, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn row() -> PieceRow {
+        PieceRow {
+            id: "a".to_string(),
+            part_number: "3001".to_string(),
+            name: Some("Brick 2 x 4".to_string()),
+            color: "Red".to_string(),
+            category: "Bricks".to_string(),
+            quantity: 2,
+            confidence: 0.5,
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn test_piece_list_formats() {
+        let pieces = vec![row()];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&rendered(OutputFormat::Json, &pieces)).unwrap();
+        assert_eq!(json[0]["part_number"], "3001");
+        assert_eq!(json[0]["condition"], serde_json::Value::Null);
+
+        assert_eq!(
+            rendered(OutputFormat::Csv, &pieces),
+            "id,part_number,name,color,category,quantity,confidence,condition\n\
+             a,3001,Brick 2 x 4,Red,Bricks,2,0.5,\n"
+        );
+        assert!(rendered(OutputFormat::Table, &pieces).contains("Brick 2 x 4"));
+    }
+
+    #[test]
+    fn test_piece_details_csv_has_a_row_per_observation() {
+        let observation = Observation {
+            id: "o".to_string(),
+            piece_id: "a".to_string(),
+            image_path: "tray.jpg".to_string(),
+            image_hash: "abc".to_string(),
+            bounds: studfinder::BoundingBox {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+            },
+            processor: "scanner".to_string(),
+            quality: "balanced".to_string(),
+            measured_rgb: Some([
+                201, 26, 9,
+            ]),
+            part_number: "3001".to_string(),
+            color: "Red".to_string(),
+            confidence: 0.5,
+            observed_at: "2024-01-01 00:00:00".to_string(),
+        };
+        let mut details = PieceDetails {
+            piece: row(),
+            observations: vec![observation],
+        };
+
+        let csv = rendered(OutputFormat::Csv, &details);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(
+            lines[1].ends_with(
+                "2024-01-01 00:00:00,tray.jpg,abc,1,2,3,4,scanner,balanced,#c91a09,3001,Red,0.5"
+            ),
+            "{}",
+            lines[1]
+        );
+        assert!(rendered(OutputFormat::Table, &details).contains("measured #c91a09"));
+
+        // An entry without observations still has its row
+        details.observations.clear();
+        let csv = rendered(OutputFormat::Csv, &details);
+        assert_eq!(csv.lines().count(), 2);
+        assert!(rendered(OutputFormat::Table, &details).contains("Observations: none"));
+    }
+
+    #[test]
+    fn test_explain_lists_candidates() {
+        let piece = Piece {
+            id: "a".to_string(),
+            part_number: "3001".to_string(),
+            color: "Red".to_string(),
+            category: "Bricks".to_string(),
+            quantity: 1,
+            confidence: 0.6,
+            condition: None,
+        };
+        let report = ExplainReport {
+            path: PathBuf::from("tray.jpg"),
+            detections: vec![
+                ExplainedDetection {
+                    band: ReviewBand::Review,
+                    detection: Detection {
+                        piece,
+                        bounds: studfinder::BoundingBox {
+                            x: 1,
+                            y: 2,
+                            width: 3,
+                            height: 4,
+                        },
+                        measured_rgb: None,
+                        color_candidates: vec![
+                            ColorCandidate {
+                                name: "Red".to_string(),
+                                delta_e: 2.0,
+                                score: 0.98,
+                            },
+                            ColorCandidate {
+                                name: "Rust".to_string(),
+                                delta_e: 5.0,
+                                score: 0.95,
+                            },
+                        ],
+                        part_candidates: vec![
+                            PartCandidate {
+                                part_number: "3001".to_string(),
+                                score: 0.9,
+                            },
+                        ],
+                    },
+                },
+            ],
+        };
+
+        let csv = rendered(OutputFormat::Csv, &report);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], "tray.jpg,1,review,a,color,2,Rust,0.95,5");
+        assert_eq!(lines[3], "tray.jpg,1,review,a,part,1,3001,0.9,");
+
+        let table = rendered(OutputFormat::Table, &report);
+        assert!(table.contains("queued for review"), "{}", table);
+        assert!(table.contains("Rust 0.95 (delta E 5.0)"), "{}", table);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&rendered(OutputFormat::Json, &report)).unwrap();
+        assert_eq!(json["detections"][0]["band"], "review");
+        assert_eq!(
+            json["detections"][0]["part_candidates"][0]["part_number"],
+            "3001"
+        );
+    }
+
+    #[test]
+    fn test_set_check_lists_missing_and_surplus() {
+        let part = |part_number: &str, color: &str, quantity| SetPart {
+            part_number: part_number.to_string(),
+            color: color.to_string(),
+            quantity,
+        };
+        let check = SetCheck {
+            set_number: "10696-1".to_string(),
+            name: "Medium Creative Brick Box".to_string(),
+            required: 6,
+            owned: 4,
+            percent_complete: 66.666_67,
+            missing: vec![part("3020", "Blue", 2)],
+            surplus: vec![part("3001", "Red", 1)],
+        };
+
+        let csv = rendered(OutputFormat::Csv, &check);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "set_number,status,part_number,color,quantity",
+                "10696-1,missing,3020,Blue,2",
+                "10696-1,surplus,3001,Red,1",
+            ]
+        );
+
+        let table = rendered(OutputFormat::Table, &check);
+        assert!(table.contains("66.7% complete: 4 of 6"), "{}", table);
+        assert!(table.contains("Surplus:"), "{}", table);
+    }
+
+    #[test]
+    fn test_calibration_profiles_formats() {
+        let balance = studfinder::WhiteBalance::from_reference(
+            [
+                240, 215, 170,
+            ],
+            [
+                255, 255, 255,
+            ],
+        )
+        .unwrap();
+        let profiles = vec![
+            CalibrationProfile {
+                name: "rig1".to_string(),
+                reference: "White".to_string(),
+                measured: [
+                    240, 215, 170,
+                ],
+                white_balance: balance,
+                created_at: Some("2024-01-01 00:00:00".to_string()),
+            },
+        ];
+
+        let table = rendered(OutputFormat::Table, &profiles);
+        assert!(table.contains("rig1"));
+        assert!(table.contains("#f0d7aa"));
+
+        let csv = rendered(OutputFormat::Csv, &profiles);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CALIBRATION_HEADER.join(",").as_str()));
+        assert!(lines.next().unwrap().starts_with("rig1,White,#f0d7aa,"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&rendered(OutputFormat::Json, &profiles[0])).unwrap();
+        assert_eq!(json["measured"][2], 170);
+        assert_eq!(
+            rendered(OutputFormat::Table, &Vec::<CalibrationProfile>::new()),
+            "No calibration profiles\n"
+        );
+    }
+
+    #[test]
+    fn test_batch_report_csv_keeps_failed_files() {
+        let piece = Piece {
+            id: "a".to_string(),
+            part_number: "3001".to_string(),
+            color: "Red".to_string(),
+            category: "Bricks".to_string(),
+            quantity: 2,
+            confidence: 0.5,
+            condition: None,
+        };
+        let report = BatchReport::new(
+            PathBuf::from("."),
+            vec![
+                FileReport {
+                    path: PathBuf::from("a.jpg"),
+                    outcome: FileOutcome::Scanned {
+                        pieces: vec![piece],
+                        queued: 0,
+                    },
+                },
+                FileReport::failed(PathBuf::from("b.jpg"), &StudFinderError::NoPiecesDetected),
+            ],
+        );
+
+        let csv = rendered(OutputFormat::Csv, &report);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "a.jpg,scanned,,a,3001,Red,Bricks,2,0.5,");
+        assert_eq!(lines[2], "b.jpg,failed,No pieces detected in image,,,,,,,");
+    }
+
+    #[test]
+    fn test_error_report_uses_error_variant() {
+        let error = anyhow::Error::from(StudFinderError::PieceNotFound("x".to_string()))
+            .context("showing piece");
+        let report = ErrorReport::new(&error);
+        assert_eq!(report.kind, "piece_not_found");
+        assert_eq!(report.exit_code, 4);
+        assert_eq!(report.message, "showing piece: Piece not found: x");
+
+        // Variants that embed their source are not repeated
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        let report = ErrorReport::new(&StudFinderError::Io(io).into());
+        assert_eq!(report.message, "IO error: gone");
+        assert_eq!(report.exit_code, 9);
+
+        let report = ErrorReport::new(&anyhow::anyhow!("boom"));
+        assert_eq!(report.kind, "unexpected");
+        assert_eq!(report.exit_code, EXIT_UNEXPECTED);
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("JSON".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("text".parse::<OutputFormat>(), Ok(OutputFormat::Table));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::error::{Result, StudFinderError};
+use crate::processing::color::{linear_to_srgb, srgb_to_linear, ColorDetector};
+use crate::processing::palette;
+use crate::processing::segment::{Segmenter, SegmenterConfig};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Palette color assumed for the reference when none is named
+pub const DEFAULT_REFERENCE: &str = "White";
+
+/// Darkest channel value (sRGB) a reference may measure
+///
+/// Below it, noise dominates and the correction would amplify it.
+const MIN_REFERENCE_LEVEL: u8 = 32;
+
+/// Channel value (sRGB) from which a reference is considered clipped
+const CLIPPED_LEVEL: u8 = 254;
+
+/// A color correction for one camera and lighting setup
+///
+/// The 3x3 matrix maps linear-light RGB as measured to linear-light RGB as
+/// it would look under the palette's reference lighting. Calibration from a
+/// reference produces a diagonal matrix: one gain per channel.
+///
+/// # Examples
+///
+/// ```
+/// use studfinder::processing::WhiteBalance;
+///
+/// // A white reference measured with a warm cast
+/// let balance = WhiteBalance::from_reference([250, 220, 180], [255, 255, 255]).unwrap();
+/// assert_eq!(balance.apply([250, 220, 180]), [255, 255, 255]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WhiteBalance {
+    /// Row-major correction matrix in linear RGB
+    pub matrix: [[f32; 3]; 3],
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl WhiteBalance {
+    /// The correction that leaves colors unchanged
+    pub const IDENTITY: Self = Self {
+        matrix: [
+            [
+                1.0, 0.0, 0.0,
+            ],
+            [
+                0.0, 1.0, 0.0,
+            ],
+            [
+                0.0, 0.0, 1.0,
+            ],
+        ],
+    };
+
+    /// Compute the correction that turns a measured reference into its known color
+    ///
+    /// # Arguments
+    /// * `measured` - The reference's color in the photo (sRGB)
+    /// * `reference` - The reference's true color (sRGB)
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationFailed` if a channel of `measured` is too dark to
+    /// correct reliably.
+    pub fn from_reference(measured: [u8; 3], reference: [u8; 3]) -> Result<Self> {
+        if measured
+            .iter()
+            .any(|&channel| channel < MIN_REFERENCE_LEVEL)
+        {
+            return Err(StudFinderError::CalibrationFailed(format!(
+                "reference measured as ({}, {}, {}) is too dark; use a lighter reference or more light",
+                measured[0], measured[1], measured[2]
+            )));
+        }
+        if measured.iter().any(|&channel| channel >= CLIPPED_LEVEL) {
+            warn!(
+                "Reference measured as ({}, {}, {}) is overexposed; the correction may be too weak",
+                measured[0], measured[1], measured[2]
+            );
+        }
+
+        let measured = measured.map(|channel| srgb_to_linear(f32::from(channel)));
+        let reference = reference.map(|channel| srgb_to_linear(f32::from(channel)));
+        let mut matrix = Self::IDENTITY.matrix;
+        for (channel, row) in matrix.iter_mut().enumerate() {
+            row[channel] = reference[channel] / measured[channel];
+        }
+        debug!(
+            "White balance gains: ({:.3}, {:.3}, {:.3})",
+            matrix[0][0], matrix[1][1], matrix[2][2]
+        );
+        Ok(Self {
+            matrix,
+        })
+    }
+
+    /// Per-channel gains, the diagonal of the matrix
+    #[must_use]
+    pub fn gains(&self) -> [f32; 3] {
+        [
+            self.matrix[0][0],
+            self.matrix[1][1],
+            self.matrix[2][2],
+        ]
+    }
+
+    /// Correct a measured sRGB color
+    #[must_use]
+    pub fn apply(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let linear = rgb.map(|channel| srgb_to_linear(f32::from(channel)));
+        self.matrix.map(|row| {
+            let value: f32 = row.iter().zip(linear).map(|(m, c)| m * c).sum();
+            linear_to_srgb(value.clamp(0.0, 1.0)).round() as u8
+        })
+    }
+}
+
+/// A named white balance, measured from a photo of a reference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationProfile {
+    /// Name the profile is selected by, e.g. the rig it was measured on
+    pub name: String,
+    /// Palette color of the photographed reference
+    pub reference: String,
+    /// Color the reference was measured as (sRGB)
+    pub measured: [u8; 3],
+    /// The correction
+    pub white_balance: WhiteBalance,
+    /// When the profile was stored
+    pub created_at: Option<String>,
+}
+
+impl CalibrationProfile {
+    /// Measure a reference in a photo and compute its correction
+    ///
+    /// The reference is the largest region the segmenter finds, or the whole
+    /// image if it fills the frame, and its median color is compared with
+    /// the palette color named by `reference`: a white or grey card, or a
+    /// brick of a known color.
+    ///
+    /// # Arguments
+    /// * `name` - Name for the profile
+    /// * `img` - Photo of the reference, taken on the rig to calibrate
+    /// * `reference` - Palette color of the reference, e.g. "White"
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationFailed` if the name is empty, the reference color
+    /// is not in the palette, or the reference is too dark.
+    pub fn from_image(name: &str, img: &DynamicImage, reference: &str) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(StudFinderError::CalibrationFailed(
+                "profile name is empty".to_string(),
+            ));
+        }
+        let color = palette::find_by_name(reference).ok_or_else(|| {
+            StudFinderError::CalibrationFailed(format!(
+                "unknown reference color '{}'",
+                reference.trim()
+            ))
+        })?;
+
+        let rgb = img.to_rgb8();
+        let region = Segmenter::new(SegmenterConfig::default())
+            .segment(img)
+            .into_iter()
+            .max_by_key(|region| region.area)
+            .ok_or_else(|| StudFinderError::CalibrationFailed("image is empty".to_string()))?;
+        let measured = ColorDetector::new()
+            .detect_color_pixels(region.pixels(&rgb))
+            .measured
+            .ok_or_else(|| StudFinderError::CalibrationFailed("image is empty".to_string()))?;
+        debug!(
+            "Reference {} measured as ({}, {}, {}) over {} pixel(s)",
+            color.bricklink_name, measured[0], measured[1], measured[2], region.area
+        );
+
+        Ok(Self {
+            name: name.to_string(),
+            reference: color.bricklink_name.to_string(),
+            measured,
+            white_balance: WhiteBalance::from_reference(measured, color.rgb)?,
+            created_at: None,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_white_balance_corrects_cast() {
+        let balance = WhiteBalance::from_reference(
+            [
+                230, 200, 160,
+            ],
+            [
+                255, 255, 255,
+            ],
+        )
+        .unwrap();
+        let gains = balance.gains();
+        assert!(gains[0] < gains[1] && gains[1] < gains[2]);
+
+        // A neutral grey under the same light comes out neutral again
+        let grey = balance.apply([
+            115, 100, 80,
+        ]);
+        let spread = grey.iter().max().unwrap() - grey.iter().min().unwrap();
+        assert!(spread <= 12, "{:?}", grey);
+
+        assert_eq!(WhiteBalance::IDENTITY.apply([201, 26, 9,]), [201, 26, 9,]);
+        assert!(matches!(
+            WhiteBalance::from_reference([200, 10, 200,], [255, 255, 255,],),
+            Err(StudFinderError::CalibrationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_profile_from_image() {
+        // A warm-lit white card on a dark background
+        let mut img = RgbImage::from_pixel(
+            120,
+            120,
+            Rgb([
+                30, 30, 30,
+            ]),
+        );
+        for y in 30..90 {
+            for x in 30..90 {
+                img.put_pixel(
+                    x,
+                    y,
+                    Rgb([
+                        240, 215, 170,
+                    ]),
+                );
+            }
+        }
+        let img = DynamicImage::ImageRgb8(img);
+
+        let profile = CalibrationProfile::from_image(" rig 1 ", &img, "white").unwrap();
+        assert_eq!(profile.name, "rig 1");
+        assert_eq!(profile.reference, "White");
+        assert_eq!(profile.measured, [240, 215, 170,]);
+        assert_eq!(
+            profile.white_balance.apply(profile.measured),
+            [255, 255, 255,]
+        );
+
+        assert!(CalibrationProfile::from_image("rig", &img, "Plaid").is_err());
+        assert!(CalibrationProfile::from_image(" ", &img, "White").is_err());
+    }
+}
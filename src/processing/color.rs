@@ -1,12 +1,52 @@
-use image::DynamicImage;
-use std::collections::HashMap;
+use crate::processing::calibration::WhiteBalance;
+use crate::processing::palette::{ColorFinish, PaletteColor, PALETTE};
+use image::{DynamicImage, Rgb};
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
+/// Number of ranked color candidates kept in a `ColorInfo`
+pub const COLOR_CANDIDATES: usize = 5;
+
+/// ΔE by which the best match must beat the runner-up to be unambiguous
+///
+/// Smaller gaps scale confidence down in proportion, so that a measured
+/// color halfway between two palette colors is never accepted as either.
+const DECISIVE_GAP: f32 = 4.0;
+
 /// Represents a detected color with its confidence score
 #[derive(Debug, Clone)]
 pub struct ColorInfo {
     pub name: String,
     pub confidence: f32,
+    /// Median RGB of the measured pixels, after white balance, or `None` if
+    /// there were none
+    pub measured: Option<[u8; 3]>,
+    /// The `COLOR_CANDIDATES` palette colors closest to `measured`, nearest first
+    pub candidates: Vec<ColorCandidate>,
+}
+
+/// A palette color considered for a measured color
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorCandidate {
+    /// Color name in the detector's color standard
+    pub name: String,
+    /// CIEDE2000 distance from the measured color
+    pub delta_e: f32,
+    /// Similarity to the measured color (0.0-1.0), `1 - delta_e / 100`
+    ///
+    /// On the same scale as `ColorDetectorConfig::threshold`: a candidate
+    /// scoring below the threshold is too far away to be reported.
+    pub score: f32,
+}
+
+impl ColorCandidate {
+    fn new(name: &str, delta_e: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            delta_e,
+            score: (1.0 - delta_e / 100.0).clamp(0.0, 1.0),
+        }
+    }
 }
 
 /// Enum representing different color standards
@@ -21,10 +61,17 @@ pub enum ColorStandard {
 /// Configuration for color detection
 #[derive(Debug, Clone)]
 pub struct ColorDetectorConfig {
-    /// Threshold for color detection (0.0-1.0)
+    /// Strictness of color matching (0.0-1.0)
+    ///
+    /// The best palette match must lie within `(1 - threshold) * 100`
+    /// ΔE2000 units of the measured color, otherwise the color is reported
+    /// as "Unknown".
     pub threshold: f32,
     /// Color standard to use
     pub standard: ColorStandard,
+    /// Correction for the rig's lighting, applied to measured colors before
+    /// they are matched
+    pub white_balance: Option<WhiteBalance>,
 }
 
 impl Default for ColorDetectorConfig {
@@ -32,14 +79,161 @@ impl Default for ColorDetectorConfig {
         Self {
             threshold: 0.75,
             standard: ColorStandard::BrickLink,
+            white_balance: None,
         }
     }
 }
 
+/// Convert an sRGB channel (0-255) to linear light (0.0-1.0)
+pub(crate) fn srgb_to_linear(channel: f32) -> f32 {
+    let c = channel / 255.0;
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert linear light (0.0-1.0) to an sRGB channel (0-255)
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    let c = if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    c * 255.0
+}
+
+/// A color in the CIELAB color space (D65 white point)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+    /// Lightness (0-100)
+    pub l: f32,
+    /// Green-red axis
+    pub a: f32,
+    /// Blue-yellow axis
+    pub b: f32,
+}
+
+impl Lab {
+    /// Convert an sRGB color (0-255 per channel) to CIELAB
+    #[must_use]
+    pub fn from_rgb(rgb: [f32; 3]) -> Self {
+        fn f(t: f32) -> f32 {
+            const DELTA: f32 = 6.0 / 29.0;
+            if t > DELTA * DELTA * DELTA {
+                t.cbrt()
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        }
+
+        let [r, g, b] = rgb.map(srgb_to_linear);
+
+        // sRGB -> XYZ, normalized by the D65 reference white
+        let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+        let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+        let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        Self {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+
+    /// Perceptual color difference according to CIEDE2000
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::Lab;
+    ///
+    /// let red = Lab::from_rgb([201.0, 26.0, 9.0]);
+    /// assert!(red.delta_e_2000(&red) < f32::EPSILON);
+    /// ```
+    #[must_use]
+    pub fn delta_e_2000(&self, other: &Lab) -> f32 {
+        let (l1, a1, b1) = (f64::from(self.l), f64::from(self.a), f64::from(self.b));
+        let (l2, a2, b2) = (f64::from(other.l), f64::from(other.a), f64::from(other.b));
+
+        let pow25_7 = 25f64.powi(7);
+        let c_bar = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+        let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + pow25_7)).sqrt());
+
+        let a1p = (1.0 + g) * a1;
+        let a2p = (1.0 + g) * a2;
+        let c1p = a1p.hypot(b1);
+        let c2p = a2p.hypot(b2);
+
+        let hue = |b: f64, a: f64| {
+            if a == 0.0 && b == 0.0 {
+                0.0
+            } else {
+                b.atan2(a).to_degrees().rem_euclid(360.0)
+            }
+        };
+        let h1p = hue(b1, a1p);
+        let h2p = hue(b2, a2p);
+
+        let delta_lp = l2 - l1;
+        let delta_cp = c2p - c1p;
+        let delta_hp = if c1p * c2p == 0.0 {
+            0.0
+        } else {
+            let diff = h2p - h1p;
+            if diff.abs() <= 180.0 {
+                diff
+            } else if diff > 180.0 {
+                diff - 360.0
+            } else {
+                diff + 360.0
+            }
+        };
+        let delta_big_hp = 2.0 * (c1p * c2p).sqrt() * (delta_hp / 2.0).to_radians().sin();
+
+        let l_bar_p = (l1 + l2) / 2.0;
+        let c_bar_p = (c1p + c2p) / 2.0;
+        let h_bar_p = if c1p * c2p == 0.0 {
+            h1p + h2p
+        } else if (h1p - h2p).abs() <= 180.0 {
+            (h1p + h2p) / 2.0
+        } else if h1p + h2p < 360.0 {
+            (h1p + h2p + 360.0) / 2.0
+        } else {
+            (h1p + h2p - 360.0) / 2.0
+        };
+
+        let t = 1.0 - 0.17 * (h_bar_p - 30.0).to_radians().cos()
+            + 0.24 * (2.0 * h_bar_p).to_radians().cos()
+            + 0.32 * (3.0 * h_bar_p + 6.0).to_radians().cos()
+            - 0.20 * (4.0 * h_bar_p - 63.0).to_radians().cos();
+        let delta_theta = 30.0 * (-((h_bar_p - 275.0) / 25.0).powi(2)).exp();
+        let r_c = 2.0 * (c_bar_p.powi(7) / (c_bar_p.powi(7) + pow25_7)).sqrt();
+        let s_l =
+            1.0 + (0.015 * (l_bar_p - 50.0).powi(2)) / (20.0 + (l_bar_p - 50.0).powi(2)).sqrt();
+        let s_c = 1.0 + 0.045 * c_bar_p;
+        let s_h = 1.0 + 0.015 * c_bar_p * t;
+        let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+        let l_term = delta_lp / s_l;
+        let c_term = delta_cp / s_c;
+        let h_term = delta_big_hp / s_h;
+
+        (l_term * l_term + c_term * c_term + h_term * h_term + r_t * c_term * h_term).sqrt() as f32
+    }
+}
+
 /// Color detector for identifying colors in images
+///
+/// Measured colors are matched against the reference palette by nearest
+/// neighbour in CIELAB using the CIEDE2000 color difference. Only solid
+/// colors are considered, since transparent and metallic finishes cannot be
+/// told apart from their solid counterparts by their color alone.
 pub struct ColorDetector {
     config: ColorDetectorConfig,
-    color_profiles: HashMap<String, Vec<(u8, u8, u8)>>,
+    references: Vec<(&'static PaletteColor, Lab)>,
 }
 
 impl Default for ColorDetector {
@@ -75,55 +269,21 @@ impl ColorDetector {
     /// let config = ColorDetectorConfig {
     ///     threshold: 0.8,
     ///     standard: ColorStandard::LegoOfficial,
+    ///     white_balance: None,
     /// };
     /// let detector = ColorDetector::with_config(config);
     /// ```
     #[must_use]
     pub fn with_config(config: ColorDetectorConfig) -> Self {
-        let mut detector = Self {
-            config,
-            color_profiles: HashMap::new(),
-        };
-
-        // Initialize color profiles based on the selected standard
-        detector.initialize_color_profiles();
+        let references = PALETTE
+            .iter()
+            .filter(|color| color.finish == ColorFinish::Solid)
+            .map(|color| (color, Lab::from_rgb(color.rgb.map(f32::from))))
+            .collect();
 
-        detector
-    }
-
-    /// Initialize color profiles based on the selected standard
-    fn initialize_color_profiles(&mut self) {
-        match self.config.standard {
-            ColorStandard::BrickLink => {
-                // BrickLink color profiles (simplified for demonstration)
-                self.color_profiles
-                    .insert("Red".to_string(), vec![(255, 0, 0)]);
-                self.color_profiles
-                    .insert("Green".to_string(), vec![(0, 255, 0)]);
-                self.color_profiles
-                    .insert("Blue".to_string(), vec![(0, 0, 255)]);
-                self.color_profiles
-                    .insert("Yellow".to_string(), vec![(255, 255, 0)]);
-                self.color_profiles
-                    .insert("White".to_string(), vec![(255, 255, 255)]);
-                self.color_profiles
-                    .insert("Black".to_string(), vec![(0, 0, 0)]);
-            }
-            ColorStandard::LegoOfficial => {
-                // LEGO official color profiles (would be more accurate in a real implementation)
-                self.color_profiles
-                    .insert("Bright Red".to_string(), vec![(255, 0, 0)]);
-                self.color_profiles
-                    .insert("Dark Green".to_string(), vec![(0, 255, 0)]);
-                self.color_profiles
-                    .insert("Bright Blue".to_string(), vec![(0, 0, 255)]);
-                self.color_profiles
-                    .insert("Bright Yellow".to_string(), vec![(255, 255, 0)]);
-                self.color_profiles
-                    .insert("White".to_string(), vec![(255, 255, 255)]);
-                self.color_profiles
-                    .insert("Black".to_string(), vec![(0, 0, 0)]);
-            }
+        Self {
+            config,
+            references,
         }
     }
 
@@ -136,7 +296,7 @@ impl ColorDetector {
     ///
     /// ```
     /// use studfinder::processing::ColorDetector;
-    /// use image::DynamicImage;
+    /// use image::{DynamicImage, Rgb};
     ///
     /// let detector = ColorDetector::new();
     /// let img = DynamicImage::new_rgb8(100, 100);
@@ -147,13 +307,38 @@ impl ColorDetector {
     /// ```
     #[must_use]
     pub fn detect_color(&self, img: &DynamicImage) -> ColorInfo {
-        let mut colors = [0u32; 3];
-        let mut pixel_count = 0;
+        self.detect_color_pixels(img.to_rgb8().pixels().copied())
+    }
 
-        for pixel in img.to_rgb8().pixels() {
-            colors[0] += u32::from(pixel[0]);
-            colors[1] += u32::from(pixel[1]);
-            colors[2] += u32::from(pixel[2]);
+    /// Detect the predominant color of a set of pixels
+    ///
+    /// Used to classify a single segmented region without letting the
+    /// surrounding background skew the result. The per-channel median is
+    /// matched, so stud highlights and shadows do not shift the color.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::ColorDetector;
+    ///
+    /// let detector = ColorDetector::new();
+    /// let pixels = vec![image::Rgb([201, 26, 9]); 16];
+    /// let color_info = detector.detect_color_pixels(pixels);
+    /// assert_eq!(color_info.name, "Red");
+    /// ```
+    #[must_use]
+    pub fn detect_color_pixels<I>(&self, pixels: I) -> ColorInfo
+    where
+        I: IntoIterator<Item = Rgb<u8>>,
+    {
+        // Per-channel histograms; the median ignores stud highlights and shadows
+        let mut histograms = [[0u64; 256]; 3];
+        let mut pixel_count = 0u64;
+
+        for pixel in pixels {
+            for (histogram, value) in histograms.iter_mut().zip(pixel.0) {
+                histogram[usize::from(value)] += 1;
+            }
             pixel_count += 1;
         }
 
@@ -162,95 +347,129 @@ impl ColorDetector {
             return ColorInfo {
                 name: "Unknown".to_string(),
                 confidence: 0.0,
+                measured: None,
+                candidates: Vec::new(),
             };
         }
 
-        let avg_r = (colors[0] / pixel_count) as f32;
-        let avg_g = (colors[1] / pixel_count) as f32;
-        let avg_b = (colors[2] / pixel_count) as f32;
+        let median = histograms.map(|histogram| {
+            let mut seen = 0u64;
+            let value = histogram
+                .iter()
+                .position(|&count| {
+                    seen += count;
+                    seen * 2 > pixel_count
+                })
+                .unwrap_or(0);
+            value as u8
+        });
+        let median = match &self.config.white_balance {
+            Some(white_balance) => {
+                let corrected = white_balance.apply(median);
+                debug!(
+                    "White balance: ({}, {}, {}) -> ({}, {}, {})",
+                    median[0], median[1], median[2], corrected[0], corrected[1], corrected[2]
+                );
+                corrected
+            }
+            None => median,
+        };
+        let measured = Some(median);
+        let median = median.map(f32::from);
 
         debug!(
-            "Average RGB values: ({:.1}, {:.1}, {:.1})",
-            avg_r, avg_g, avg_b
+            "Median RGB values: ({:.0}, {:.0}, {:.0})",
+            median[0], median[1], median[2]
         );
 
-        let threshold = self.config.threshold * 255.0;
-        let low_threshold = (1.0 - self.config.threshold) * 255.0;
+        let ranked = self.rank(Lab::from_rgb(median));
+        let candidates = self.to_candidates(&ranked, COLOR_CANDIDATES);
+        let (best, best_distance) = ranked[0];
+        let runner_up_distance = ranked.get(1).map_or(f32::INFINITY, |(_, d)| *d);
+
+        let max_distance = (1.0 - self.config.threshold) * 100.0;
+        if best_distance > max_distance {
+            debug!(
+                "Could not determine color definitively: nearest is {} at ΔE {:.1}",
+                best.bricklink_name, best_distance
+            );
+            return ColorInfo {
+                name: "Unknown".to_string(),
+                confidence: 0.0,
+                measured,
+                candidates,
+            };
+        }
 
-        let (color, confidence) = match () {
-            // Red: high R, low G&B
-            () if avg_r > threshold && avg_g < low_threshold && avg_b < low_threshold => {
-                let conf = (avg_r - avg_g.max(avg_b)) / 255.0;
-                (self.get_color_name("Red"), conf)
-            }
-            // Green: high G, low R&B
-            () if avg_r < low_threshold && avg_g > threshold && avg_b < low_threshold => {
-                let conf = (avg_g - avg_r.max(avg_b)) / 255.0;
-                (self.get_color_name("Green"), conf)
-            }
-            // Blue: high B, low R&G
-            () if avg_r < low_threshold && avg_g < low_threshold && avg_b > threshold => {
-                let conf = (avg_b - avg_r.max(avg_g)) / 255.0;
-                (self.get_color_name("Blue"), conf)
-            }
-            // Yellow: high R&G, low B
-            () if avg_r > threshold && avg_g > threshold && avg_b < low_threshold => {
-                let conf = (avg_r.min(avg_g) - avg_b) / 255.0;
-                (self.get_color_name("Yellow"), conf.min(1.0))
-            }
-            // White: all high
-            () if avg_r > threshold && avg_g > threshold && avg_b > threshold => {
-                let min_val = avg_r.min(avg_g).min(avg_b);
-                let conf = min_val / 255.0;
-                (self.get_color_name("White"), conf)
-            }
-            // Black: all low
-            () if avg_r < low_threshold && avg_g < low_threshold && avg_b < low_threshold => {
-                let max_val = avg_r.max(avg_g).max(avg_b);
-                let conf = 1.0 - (max_val / low_threshold);
-                (self.get_color_name("Black"), conf)
-            }
-            () => {
-                debug!("Could not determine color definitively");
-                ("Unknown".to_string(), 0.0)
-            }
-        };
+        // Confidence is mostly the gap to the runner-up: a tie is worth
+        // nothing however close both colors are, a decisive gap is worth the
+        // best match's score
+        let clarity = ((runner_up_distance - best_distance) / DECISIVE_GAP).clamp(0.0, 1.0);
+        let confidence = candidates[0].score * clarity;
 
+        let color = best.name(self.config.standard).to_string();
         debug!(
-            "Color detection result: {} with {:.2}% confidence",
+            "Color detection result: {} (ΔE {:.1}, runner-up ΔE {:.1}) with {:.2}% confidence",
             color,
+            best_distance,
+            runner_up_distance,
             confidence * 100.0
         );
 
         ColorInfo {
             name: color,
             confidence,
+            measured,
+            candidates,
         }
     }
 
-    /// Get the color name based on the selected standard
+    /// The palette colors closest to a measured color, nearest first
     ///
-    /// Converts a base color name to the appropriate name in the selected color standard.
-    /// For example, "Red" might become "Bright Red" in the LEGO official standard.
-    fn get_color_name(&self, base_color: &str) -> String {
-        match self.config.standard {
-            ColorStandard::BrickLink => base_color.to_string(),
-            ColorStandard::LegoOfficial => match base_color {
-                "Red" => "Bright Red",
-                "Green" => "Dark Green",
-                "Blue" => "Bright Blue",
-                "Yellow" => "Bright Yellow",
-                _ => base_color,
-            }
-            .to_string(),
-        }
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::ColorDetector;
+    ///
+    /// let candidates = ColorDetector::new().candidates([201, 26, 9], 3);
+    /// assert_eq!(candidates.len(), 3);
+    /// assert_eq!(candidates[0].name, "Red");
+    /// ```
+    #[must_use]
+    pub fn candidates(&self, measured: [u8; 3], count: usize) -> Vec<ColorCandidate> {
+        self.to_candidates(&self.rank(Lab::from_rgb(measured.map(f32::from))), count)
+    }
+
+    /// The first `count` ranked colors as candidates
+    fn to_candidates(
+        &self,
+        ranked: &[(&'static PaletteColor, f32)],
+        count: usize,
+    ) -> Vec<ColorCandidate> {
+        ranked
+            .iter()
+            .take(count)
+            .map(|(color, delta_e)| ColorCandidate::new(color.name(self.config.standard), *delta_e))
+            .collect()
+    }
+
+    /// Rank the palette colors by CIEDE2000 distance to a measured color
+    fn rank(&self, measured: Lab) -> Vec<(&'static PaletteColor, f32)> {
+        let mut ranked: Vec<_> = self
+            .references
+            .iter()
+            .map(|(color, lab)| (*color, measured.delta_e_2000(lab)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::disallowed_methods)]
 mod tests {
     use super::*;
-    use image::{Rgb, RgbImage};
+    use image::RgbImage;
 
     fn create_test_image(r: u8, g: u8, b: u8) -> DynamicImage {
         let mut img = RgbImage::new(100, 100);
@@ -267,19 +486,19 @@ mod tests {
         let detector = ColorDetector::new();
 
         // Test red
-        let img = create_test_image(255, 0, 0);
+        let img = create_test_image(201, 26, 9);
         let color_info = detector.detect_color(&img);
         assert_eq!(color_info.name, "Red");
         assert!(color_info.confidence > 0.9);
 
         // Test green
-        let img = create_test_image(0, 255, 0);
+        let img = create_test_image(35, 120, 65);
         let color_info = detector.detect_color(&img);
         assert_eq!(color_info.name, "Green");
         assert!(color_info.confidence > 0.9);
 
         // Test blue
-        let img = create_test_image(0, 0, 255);
+        let img = create_test_image(0, 85, 191);
         let color_info = detector.detect_color(&img);
         assert_eq!(color_info.name, "Blue");
         assert!(color_info.confidence > 0.9);
@@ -290,23 +509,24 @@ mod tests {
         let config = ColorDetectorConfig {
             threshold: 0.75,
             standard: ColorStandard::LegoOfficial,
+            white_balance: None,
         };
         let detector = ColorDetector::with_config(config);
 
         // Test red
-        let img = create_test_image(255, 0, 0);
+        let img = create_test_image(201, 26, 9);
         let color_info = detector.detect_color(&img);
         assert_eq!(color_info.name, "Bright Red");
         assert!(color_info.confidence > 0.9);
 
         // Test green
-        let img = create_test_image(0, 255, 0);
+        let img = create_test_image(35, 120, 65);
         let color_info = detector.detect_color(&img);
         assert_eq!(color_info.name, "Dark Green");
         assert!(color_info.confidence > 0.9);
 
         // Test blue
-        let img = create_test_image(0, 0, 255);
+        let img = create_test_image(0, 85, 191);
         let color_info = detector.detect_color(&img);
         assert_eq!(color_info.name, "Bright Blue");
         assert!(color_info.confidence > 0.9);
@@ -316,12 +536,12 @@ mod tests {
     fn test_confidence_decreases_with_impurity() {
         let detector = ColorDetector::new();
 
-        // Pure red
-        let pure_img = create_test_image(255, 0, 0);
+        // Reference red
+        let pure_img = create_test_image(201, 26, 9);
         let pure_color = detector.detect_color(&pure_img);
 
         // Impure red (with some green and blue)
-        let impure_img = create_test_image(255, 50, 50);
+        let impure_img = create_test_image(210, 60, 50);
         let impure_color = detector.detect_color(&impure_img);
 
         // Both should be detected as red
@@ -331,4 +551,124 @@ mod tests {
         // But pure red should have higher confidence
         assert!(pure_color.confidence > impure_color.confidence);
     }
+
+    #[test]
+    fn test_off_reference_colors_keep_confidence() {
+        let detector = ColorDetector::new();
+
+        // Photographed reds drift from the reference; confidence follows the drift
+        let confidences: Vec<f32> = [
+            (206, 32, 15),
+            (215, 40, 25),
+        ]
+        .into_iter()
+        .map(|(r, g, b)| {
+            let color_info = detector.detect_color(&create_test_image(r, g, b));
+            assert_eq!(color_info.name, "Red");
+            assert!(color_info.confidence > 0.8);
+            color_info.confidence
+        })
+        .collect();
+        assert!(confidences[0] > confidences[1]);
+
+        // Pure red is still nearest to Red, but not by enough to accept it
+        let pure_red = detector.detect_color(&create_test_image(255, 0, 0));
+        assert_eq!(pure_red.name, "Red");
+        assert!(pure_red.confidence < 0.8);
+    }
+
+    #[test]
+    fn test_ties_are_not_confident() {
+        let detector = ColorDetector::new();
+
+        // Two grays almost equally close: both are good matches, neither is sure
+        let gray = detector.detect_color(&create_test_image(150, 150, 150));
+        assert!(gray.candidates[0].score > 0.9);
+        assert!(gray.candidates[1].score > 0.9);
+        assert!(gray.confidence < 0.5);
+    }
+
+    #[test]
+    fn test_extended_palette_colors() {
+        let detector = ColorDetector::new();
+
+        for (rgb, expected) in [
+            (
+                [
+                    108, 110, 104,
+                ],
+                "Dark Bluish Gray",
+            ),
+            (
+                [
+                    160, 165, 169,
+                ],
+                "Light Bluish Gray",
+            ),
+            (
+                [
+                    228, 205, 158,
+                ],
+                "Tan",
+            ),
+            (
+                [
+                    88, 42, 18,
+                ],
+                "Reddish Brown",
+            ),
+        ] {
+            let img = create_test_image(rgb[0], rgb[1], rgb[2]);
+            let color_info = detector.detect_color(&img);
+            assert_eq!(color_info.name, expected);
+            assert!(color_info.confidence > 0.9);
+        }
+    }
+
+    #[test]
+    fn test_unknown_beyond_threshold() {
+        let detector = ColorDetector::with_config(ColorDetectorConfig {
+            threshold: 0.99,
+            standard: ColorStandard::BrickLink,
+            white_balance: None,
+        });
+
+        let img = create_test_image(210, 60, 50);
+        let color_info = detector.detect_color(&img);
+        assert_eq!(color_info.name, "Unknown");
+        assert_eq!(color_info.confidence, 0.0);
+
+        // The nearest colors are still listed, each scoring below the threshold
+        assert_eq!(color_info.candidates.len(), COLOR_CANDIDATES);
+        assert!(color_info.candidates.iter().all(|c| c.score < 0.99));
+    }
+
+    #[test]
+    fn test_color_candidates_are_ranked() {
+        let color_info = ColorDetector::new().detect_color(&create_test_image(201, 26, 9));
+        let candidates = &color_info.candidates;
+        assert_eq!(candidates.len(), COLOR_CANDIDATES);
+        assert_eq!(candidates[0].name, color_info.name);
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].delta_e <= pair[1].delta_e));
+        assert!((candidates[1].score - (1.0 - candidates[1].delta_e / 100.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_delta_e_2000_reference_pair() {
+        // Test pair 1 from Sharma, Wu and Dalal (2005)
+        let a = Lab {
+            l: 50.0,
+            a: 2.6772,
+            b: -79.7751,
+        };
+        let b = Lab {
+            l: 50.0,
+            a: 0.0,
+            b: -82.7485,
+        };
+        assert!((a.delta_e_2000(&b) - 2.0425).abs() < 1e-3);
+        assert!((b.delta_e_2000(&a) - 2.0425).abs() < 1e-3);
+    }
 }
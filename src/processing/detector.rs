@@ -1,11 +1,15 @@
+use crate::core::catalog::PartCatalog;
 use crate::core::piece::Piece;
 use crate::error::{Result, StudFinderError};
+use crate::processing::calibration::WhiteBalance;
 use crate::processing::color::{ColorDetector, ColorDetectorConfig, ColorStandard};
-use crate::processing::processor::ImageProcessor;
-use image::{DynamicImage, GenericImageView};
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use tracing::{debug, info};
+use crate::processing::processor::{Detection, ImageProcessor, PartCandidate, PART_CANDIDATES};
+use crate::processing::segment::Segmenter;
+use crate::processing::template::TemplateLibrary;
+use image::{DynamicImage, GenericImageView, GrayImage};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 /// Detector implementation using template matching for LEGO piece identification
@@ -14,47 +18,101 @@ use uuid::Uuid;
 /// to identify specific LEGO pieces based on their visual characteristics.
 #[derive(Clone)]
 pub struct Detector {
-    #[allow(dead_code)]
-    templates: HashMap<String, PathBuf>,
+    templates: Arc<TemplateLibrary>,
+    catalog: Arc<PartCatalog>,
     confidence_threshold: f32,
+    segmenter: Segmenter,
+    white_balance: Option<WhiteBalance>,
 }
 
 impl Detector {
     /// Create a new Detector with the specified confidence threshold
     ///
+    /// Template images are loaded from `template_dir`. A missing or empty
+    /// directory is not fatal here; detection will fail with
+    /// `TemplateMatchingFailed` until templates are available.
+    ///
     /// # Arguments
     /// * `confidence_threshold` - Minimum confidence level (0.0-1.0) for piece detection
+    /// * `template_dir` - Directory containing one template image per part
     ///
     /// # Examples
     ///
     /// ```
     /// use studfinder::processing::Detector;
     ///
-    /// let detector = Detector::new(0.8);
+    /// let detector = Detector::new(0.8, "templates");
     /// ```
     #[must_use]
-    pub fn new(confidence_threshold: f32) -> Self {
+    pub fn new<P: AsRef<Path>>(confidence_threshold: f32, template_dir: P) -> Self {
+        Self::with_catalog(
+            confidence_threshold,
+            template_dir,
+            Arc::new(PartCatalog::builtin()),
+        )
+    }
+
+    /// Create a new Detector that looks up parts in the given catalog
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use studfinder::core::PartCatalog;
+    /// use studfinder::processing::Detector;
+    ///
+    /// let catalog = Arc::new(PartCatalog::builtin());
+    /// let detector = Detector::with_catalog(0.8, "templates", catalog);
+    /// ```
+    #[must_use]
+    pub fn with_catalog<P: AsRef<Path>>(
+        confidence_threshold: f32,
+        template_dir: P,
+        catalog: Arc<PartCatalog>,
+    ) -> Self {
         info!(
             "Initializing detector with confidence threshold: {}",
             confidence_threshold
         );
 
-        // In a real implementation, this would load templates from a directory
-        let mut templates = HashMap::new();
-        templates.insert("3001".to_string(), PathBuf::from("templates/3001.jpg"));
-        templates.insert("3020".to_string(), PathBuf::from("templates/3020.jpg"));
-        templates.insert("3062".to_string(), PathBuf::from("templates/3062.jpg"));
+        let templates = TemplateLibrary::load(template_dir).unwrap_or_else(|e| {
+            warn!("{}", e);
+            TemplateLibrary::default()
+        });
 
         debug!("Loaded {} template(s)", templates.len());
 
         Self {
-            templates,
+            templates: Arc::new(templates),
+            catalog,
             confidence_threshold,
+            segmenter: Segmenter::default(),
+            white_balance: None,
         }
     }
 
+    /// Correct measured colors for the rig's lighting before matching them
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::{Detector, WhiteBalance};
+    ///
+    /// let balance = WhiteBalance::from_reference([250, 220, 180], [255, 255, 255]).unwrap();
+    /// let detector = Detector::new(0.8, "templates").with_white_balance(balance);
+    /// ```
+    #[must_use]
+    pub fn with_white_balance(mut self, white_balance: WhiteBalance) -> Self {
+        debug!("Detector white balance gains: {:?}", white_balance.gains());
+        self.white_balance = Some(white_balance);
+        self
+    }
+
     /// Detect LEGO pieces in an image using template matching
     ///
+    /// The image is segmented into one region per piece and each region is
+    /// matched independently.
+    ///
     /// # Arguments
     /// * `image_path` - Path to the image file to process
     ///
@@ -66,6 +124,7 @@ impl Detector {
     /// Returns an error if:
     /// - The image file cannot be opened or read
     /// - The image validation fails (e.g., image is too small)
+    /// - No templates are loaded
     ///
     /// # Examples
     ///
@@ -74,7 +133,7 @@ impl Detector {
     /// use std::path::Path;
     ///
     /// # async fn example() -> anyhow::Result<()> {
-    /// let detector = Detector::new(0.8);
+    /// let detector = Detector::new(0.8, "templates");
     /// let pieces = detector.detect_pieces(Path::new("test_data/test.jpg"))?;
     ///
     /// for piece in pieces {
@@ -84,12 +143,15 @@ impl Detector {
     /// # }
     /// ```
     pub fn detect_pieces<P: AsRef<Path>>(&self, image_path: P) -> Result<Vec<Piece>> {
-        debug!(
-            "Starting piece detection for: {}",
-            image_path.as_ref().display()
-        );
+        self.detect_regions(image_path.as_ref())
+            .map(Detection::into_pieces)
+    }
+
+    /// Match every region of an image, keeping where and how each was measured
+    fn detect_regions(&self, image_path: &Path) -> Result<Vec<Detection>> {
+        debug!("Starting piece detection for: {}", image_path.display());
 
-        let img = image::open(&image_path).map_err(StudFinderError::Image)?;
+        let img = image::open(image_path).map_err(StudFinderError::Image)?;
         debug!(
             "Image loaded successfully: {}x{}",
             img.width(),
@@ -98,63 +160,100 @@ impl Detector {
 
         self.validate_image(&img)?;
 
-        // In a real implementation, this would use OpenCV for template matching
-        // For now, we'll simulate detection with a simple implementation
+        let regions = self.segmenter.segment(&img);
+        let rgb = img.to_rgb8();
 
         // Use the ColorDetector to analyze the color
         let color_detector = ColorDetector::with_config(ColorDetectorConfig {
             threshold: 0.75,
             standard: ColorStandard::BrickLink,
+            white_balance: self.white_balance,
         });
-        let color_info = color_detector.detect_color(&img);
 
-        // Find best matching template
-        let (part_number, match_confidence) = self.find_best_template(&img);
+        let mut detections = Vec::with_capacity(regions.len());
+        for region in &regions {
+            let color_info = color_detector.detect_color_pixels(region.pixels(&rgb));
 
-        // Calculate overall confidence
-        let confidence = (color_info.confidence + match_confidence) / 2.0;
+            // Rank the templates; the best match decides the part
+            let part_candidates = self.rank_templates(&region.mask)?;
+            let Some(best) = part_candidates.first() else {
+                continue;
+            };
+            let (part_number, match_confidence) = (best.part_number.clone(), best.score);
 
-        if confidence < self.confidence_threshold {
-            debug!("Detection confidence too low: {:.2}", confidence);
-            return Ok(vec![]);
-        }
+            // Calculate overall confidence
+            let confidence = (color_info.confidence + match_confidence) / 2.0;
+
+            if confidence < self.confidence_threshold {
+                debug!(
+                    "Detection confidence too low for region at ({}, {}): {:.2}",
+                    region.bounds.x, region.bounds.y, confidence
+                );
+                continue;
+            }
 
-        let category = self.categorize_part(&part_number);
+            let category = self.catalog.category(&part_number);
 
-        let pieces = vec![
-            Piece {
+            let piece = Piece {
                 id: Uuid::new_v4().to_string(),
                 part_number,
                 color: color_info.name,
                 category,
                 quantity: 1,
                 confidence,
-            },
-        ];
+                condition: None,
+            };
+            debug!("Created piece record: {:?}", piece);
+            detections.push(Detection {
+                piece,
+                bounds: region.bounds,
+                measured_rgb: color_info.measured,
+                color_candidates: color_info.candidates,
+                part_candidates,
+            });
+        }
 
-        debug!("Created piece record: {:?}", pieces[0]);
-        Ok(pieces)
+        info!(
+            "Detected {} piece(s) in {} region(s)",
+            detections.len(),
+            regions.len()
+        );
+        Ok(detections)
     }
 
-    /// Find the best matching template for the image
+    /// Rank the parts by how well their templates match a region silhouette
     ///
-    /// In a real implementation, this would use OpenCV for template matching.
-    /// Currently, it returns a simulated result.
-    fn find_best_template(&self, _img: &DynamicImage) -> (String, f32) {
-        // In a real implementation, this would use OpenCV for template matching
-        // For now, we'll simulate with a simple implementation
-
-        // Simulate finding the best match
-        let part_number = "3001".to_string();
-        let confidence = 0.85;
-
-        debug!(
-            "Template matching found part {} with {:.2}% confidence",
-            part_number,
-            confidence * 100.0
-        );
+    /// Templates named after an alternate number count for the canonical
+    /// part. At most `PART_CANDIDATES` parts are returned, best first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemplateMatchingFailed` if no templates are loaded
+    fn rank_templates(&self, mask: &GrayImage) -> Result<Vec<PartCandidate>> {
+        let mut candidates: Vec<PartCandidate> = Vec::new();
+        for (part_number, score) in self.templates.rank(mask)? {
+            let part_number = self
+                .catalog
+                .get(&part_number)
+                .map_or(part_number, |part| part.part_number.clone());
+            // Ranked best first, so a part already listed scored higher
+            if candidates.iter().all(|c| c.part_number != part_number) {
+                candidates.push(PartCandidate {
+                    part_number,
+                    score,
+                });
+            }
+        }
+        candidates.truncate(PART_CANDIDATES);
 
-        (part_number, confidence)
+        if let Some(best) = candidates.first() {
+            debug!(
+                "Template matching found part {} with {:.2}% confidence",
+                best.part_number,
+                best.score * 100.0
+            );
+        }
+        Ok(candidates)
     }
 
     /// Validate that the image meets minimum requirements
@@ -180,25 +279,11 @@ impl Detector {
         }
         Ok(())
     }
-
-    /// Categorize a part based on its part number
-    ///
-    /// Maps part numbers to their corresponding categories (e.g., Brick, Plate, Tile)
-    fn categorize_part(&self, part_number: &str) -> String {
-        let category = match part_number {
-            "3001" => "Brick",
-            "3020" => "Plate",
-            "3062" => "Tile",
-            _ => "Unknown",
-        };
-        debug!("Categorized part {} as {}", part_number, category);
-        category.to_string()
-    }
 }
 
 impl ImageProcessor for Detector {
-    fn process_image(&self, image_path: &Path) -> Result<Vec<Piece>> {
-        self.detect_pieces(image_path)
+    fn detect(&self, image_path: &Path) -> Result<Vec<Detection>> {
+        self.detect_regions(image_path)
     }
 
     fn validate_image(&self, image: &DynamicImage) -> Result<()> {
@@ -211,14 +296,36 @@ impl ImageProcessor for Detector {
 }
 
 #[cfg(test)]
+#[allow(clippy::disallowed_methods)]
 mod tests {
     use super::*;
-    use image::{ImageBuffer, Rgb};
-    use tempfile;
+    use image::{ImageBuffer, Rgb, RgbImage};
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    fn draw_brick(width: u32, height: u32, canvas: u32) -> RgbImage {
+        let mut img = RgbImage::from_pixel(
+            canvas,
+            canvas,
+            Rgb([
+                255, 255, 255,
+            ]),
+        );
+        let x = ((canvas - width) / 2) as i32;
+        let y = ((canvas - height) / 2) as i32;
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at(x, y).of_size(width, height),
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        img
+    }
 
     #[test]
     fn test_detector_validation() {
-        let detector = Detector::new(0.8);
+        let detector = Detector::new(0.8, "nonexistent-templates");
 
         // Valid image
         let img = DynamicImage::ImageRgb8(ImageBuffer::new(200, 200));
@@ -231,24 +338,50 @@ mod tests {
 
     #[test]
     fn test_detector_process() {
-        let detector = Detector::new(0.8);
-
-        // Create a test image
         let temp_dir = tempfile::tempdir().unwrap();
-        let path = temp_dir.path().join("test.jpg");
 
-        let mut img = image::RgbImage::new(200, 200);
-        for pixel in img.pixels_mut() {
-            *pixel = Rgb([
-                255, 0, 0,
-            ]); // Pure red
-        }
-        img.save(&path).unwrap();
+        // Templates for a 2x4 and a 2x2 brick
+        let template_dir = temp_dir.path().join("templates");
+        std::fs::create_dir(&template_dir).unwrap();
+        draw_brick(160, 80, 200)
+            .save(template_dir.join("3001.png"))
+            .unwrap();
+        draw_brick(80, 80, 200)
+            .save(template_dir.join("3003.png"))
+            .unwrap();
+
+        let detector = Detector::new(0.8, &template_dir);
+
+        // A 2x4 brick photographed upright at a smaller scale
+        let path = temp_dir.path().join("test.png");
+        draw_brick(60, 120, 200).save(&path).unwrap();
 
         // Test detection
         let pieces = detector.process_image(&path).unwrap();
-        assert!(!pieces.is_empty());
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].part_number, "3001");
         assert_eq!(pieces[0].color, "Red");
         assert!(pieces[0].confidence > 0.8);
+
+        // Both templates are ranked, best first
+        let detections = detector.detect(&path).unwrap();
+        let candidates = &detections[0].part_candidates;
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].part_number, "3001");
+        assert_eq!(candidates[1].part_number, "3003");
+        assert!(candidates[0].score > candidates[1].score);
+    }
+
+    #[test]
+    fn test_detector_without_templates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.png");
+        draw_brick(60, 120, 200).save(&path).unwrap();
+
+        let detector = Detector::new(0.8, temp_dir.path().join("missing"));
+        match detector.process_image(&path) {
+            Err(StudFinderError::TemplateMatchingFailed(_)) => {}
+            other => panic!("Expected TemplateMatchingFailed, got: {:?}", other),
+        }
     }
 }
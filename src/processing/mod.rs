@@ -1,9 +1,18 @@
+pub mod calibration;
 pub mod color;
 pub mod detector;
+pub mod palette;
 pub mod processor;
 pub mod scanner;
+pub mod segment;
+pub mod studs;
+pub mod template;
 
+pub use calibration::*;
 pub use color::*;
 pub use detector::*;
 pub use processor::*;
 pub use scanner::*;
+pub use segment::*;
+pub use studs::*;
+pub use template::*;
@@ -0,0 +1,356 @@
+//! Reference LEGO color palette
+//!
+//! The palette follows BrickLink's color guide: solid, transparent,
+//! glitter, pearl, metallic, chrome, speckle, glow-in-the-dark and milky
+//! colors. Only solid colors are matched against scanned pieces; the rest
+//! resolve the color ids and names found in imported files.
+//!
+//! Each entry ties a BrickLink color to its LEGO official name and its
+//! Rebrickable id (which, for solid colors, matches the LDraw color code).
+//! Reference RGB values follow Rebrickable's color table.
+
+use crate::processing::color::ColorStandard;
+
+/// Surface finish of a palette color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFinish {
+    /// Opaque, non-metallic plastic
+    Solid,
+    /// Transparent plastic, with or without glitter
+    Transparent,
+    /// Pearl, metallic, chrome, speckle, glow-in-the-dark and milky finishes
+    Metallic,
+}
+
+/// A single reference color in the palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteColor {
+    /// BrickLink color id
+    pub bricklink_id: u32,
+    /// BrickLink color name
+    pub bricklink_name: &'static str,
+    /// Rebrickable color id
+    pub rebrickable_id: u32,
+    /// LEGO official color id, if it has one
+    pub lego_id: Option<u32>,
+    /// LEGO official color name
+    pub lego_name: &'static str,
+    /// Reference sRGB value
+    pub rgb: [u8; 3],
+    /// Surface finish
+    pub finish: ColorFinish,
+}
+
+impl PaletteColor {
+    /// Name of the color in the given color standard
+    #[must_use]
+    pub fn name(&self, standard: ColorStandard) -> &'static str {
+        match standard {
+            ColorStandard::BrickLink => self.bricklink_name,
+            ColorStandard::LegoOfficial => self.lego_name,
+        }
+    }
+}
+
+const fn solid(
+    bricklink_id: u32,
+    bricklink_name: &'static str,
+    rebrickable_id: u32,
+    lego_id: Option<u32>,
+    lego_name: &'static str,
+    rgb: u32,
+) -> PaletteColor {
+    entry(
+        bricklink_id,
+        bricklink_name,
+        rebrickable_id,
+        lego_id,
+        lego_name,
+        rgb,
+        ColorFinish::Solid,
+    )
+}
+
+const fn entry(
+    bricklink_id: u32,
+    bricklink_name: &'static str,
+    rebrickable_id: u32,
+    lego_id: Option<u32>,
+    lego_name: &'static str,
+    rgb: u32,
+    finish: ColorFinish,
+) -> PaletteColor {
+    PaletteColor {
+        bricklink_id,
+        bricklink_name,
+        rebrickable_id,
+        lego_id,
+        lego_name,
+        rgb: [
+            ((rgb >> 16) & 0xFF) as u8,
+            ((rgb >> 8) & 0xFF) as u8,
+            (rgb & 0xFF) as u8,
+        ],
+        finish,
+    }
+}
+
+use ColorFinish::{Metallic, Transparent};
+
+/// The reference palette, grouped by finish and hue
+///
+/// Names are unique within each color standard.
+#[rustfmt::skip]
+pub static PALETTE: &[PaletteColor] = &[
+    // Whites, grays and black
+    solid(1,   "White",                  15,   Some(1),   "White",                  0xFFFFFF),
+    solid(49,  "Very Light Gray",        503,  None,      "Very Light Gray",        0xE6E3DA),
+    solid(99,  "Very Light Bluish Gray", 151,  Some(208), "Light Stone Grey",       0xE6E3E0),
+    solid(86,  "Light Bluish Gray",      71,   Some(194), "Medium Stone Grey",      0xA0A5A9),
+    solid(9,   "Light Gray",             7,    Some(2),   "Grey",                   0x9BA19D),
+    solid(10,  "Dark Gray",              8,    Some(27),  "Dark Grey",              0x6D6E5C),
+    solid(85,  "Dark Bluish Gray",       72,   Some(199), "Dark Stone Grey",        0x6C6E68),
+    solid(11,  "Black",                  0,    Some(26),  "Black",                  0x05131D),
+    // Reds and browns
+    solid(59,  "Dark Red",               320,  Some(154), "New Dark Red",           0x720E0F),
+    solid(5,   "Red",                    4,    Some(21),  "Bright Red",             0xC91A09),
+    solid(27,  "Rust",                   216,  Some(216), "Rust",                   0xB31004),
+    solid(25,  "Salmon",                 12,   None,      "Salmon",                 0xF2705E),
+    solid(26,  "Light Salmon",           100,  None,      "Light Salmon",           0xFEBABD),
+    solid(58,  "Sand Red",               335,  Some(153), "Sand Red",               0xD67572),
+    solid(88,  "Reddish Brown",          70,   Some(192), "Reddish Brown",          0x582A12),
+    solid(8,   "Brown",                  6,    Some(25),  "Earth Orange",           0x583927),
+    solid(120, "Dark Brown",             308,  Some(308), "Dark Brown",             0x352100),
+    solid(69,  "Dark Tan",               28,   Some(138), "Sand Yellow",            0x958A73),
+    solid(2,   "Tan",                    19,   Some(5),   "Brick Yellow",           0xE4CD9E),
+    solid(90,  "Light Nougat",           78,   Some(283), "Light Nougat",           0xF6D7B3),
+    solid(28,  "Nougat",                 92,   Some(18),  "Nougat",                 0xD09168),
+    solid(150, "Medium Nougat",          84,   Some(312), "Medium Nougat",          0xAA7D55),
+    solid(225, "Dark Nougat",            128,  Some(128), "Dark Nougat",            0xAD6140),
+    solid(240, "Medium Brown",           370,  Some(370), "Medium Brown",           0x755945),
+    solid(241, "Medium Tan",             371,  Some(371), "Medium Tan",             0xCCA373),
+    solid(106, "Fabuland Brown",         450,  None,      "Fabuland Brown",         0xB67B50),
+    // Oranges and yellows
+    solid(29,  "Earth Orange",           366,  Some(12),  "Light Orange Brown",     0xFA9C1C),
+    solid(68,  "Dark Orange",            484,  Some(38),  "Dark Orange",            0xA95500),
+    solid(4,   "Orange",                 25,   Some(106), "Bright Orange",          0xFE8A18),
+    solid(31,  "Medium Orange",          462,  Some(105), "Bright Yellowish Orange", 0xFFA70B),
+    solid(110, "Bright Light Orange",    191,  Some(191), "Flame Yellowish Orange", 0xF8BB3D),
+    solid(32,  "Light Orange",           125,  None,      "Light Orange",           0xF9BA61),
+    solid(96,  "Very Light Orange",      68,   None,      "Very Light Orange",      0xF3CF9B),
+    solid(3,   "Yellow",                 14,   Some(24),  "Bright Yellow",          0xF2CD37),
+    solid(103, "Bright Light Yellow",    226,  Some(226), "Cool Yellow",            0xFFF03A),
+    solid(33,  "Light Yellow",           18,   Some(3),   "Light Yellow",           0xFBE696),
+    // Greens
+    solid(34,  "Lime",                   27,   Some(119), "Bright Yellowish Green", 0xBBE90B),
+    solid(76,  "Medium Lime",            115,  Some(115), "Medium Yellowish Green", 0xC7D23C),
+    solid(35,  "Light Lime",             120,  None,      "Light Lime",             0xD9E4A7),
+    solid(158, "Yellowish Green",        326,  Some(326), "Spring Yellowish Green", 0xDFEEA5),
+    solid(155, "Olive Green",            330,  Some(330), "Olive Green",            0x9B9A5A),
+    solid(36,  "Bright Green",           10,   Some(37),  "Bright Green",           0x4B9F4A),
+    solid(6,   "Green",                  2,    Some(28),  "Dark Green",             0x237841),
+    solid(80,  "Dark Green",             288,  Some(141), "Earth Green",            0x184632),
+    solid(37,  "Medium Green",           74,   Some(29),  "Medium Green",           0x73DCA1),
+    solid(38,  "Light Green",            17,   Some(6),   "Light Green",            0xC2DAB8),
+    solid(48,  "Sand Green",             378,  Some(151), "Sand Green",             0xA0BCAC),
+    // Turquoises and blues
+    solid(39,  "Dark Turquoise",         3,    Some(107), "Bright Bluish Green",    0x008F9B),
+    solid(40,  "Light Turquoise",        11,   None,      "Light Turquoise",        0x55A5AF),
+    solid(41,  "Aqua",                   118,  Some(118), "Light Bluish Green",     0xB3D7D1),
+    solid(152, "Light Aqua",             323,  Some(323), "Aqua",                   0xADC3C0),
+    solid(156, "Medium Azure",           322,  Some(322), "Medium Azur",            0x36AEBF),
+    solid(153, "Dark Azure",             321,  Some(321), "Dark Azur",              0x078BC9),
+    solid(87,  "Sky Blue",               232,  Some(232), "Dove Blue",              0x7DBFDD),
+    solid(105, "Bright Light Blue",      212,  Some(212), "Light Royal Blue",       0x9FC3E9),
+    solid(62,  "Light Blue",             9,    Some(45),  "Light Blue",             0xB4D2E3),
+    solid(42,  "Medium Blue",            73,   Some(102), "Medium Blue",            0x5A93DB),
+    solid(72,  "Maersk Blue",            313,  None,      "Maersk Blue",            0x3592C3),
+    solid(7,   "Blue",                   1,    Some(23),  "Bright Blue",            0x0055BF),
+    solid(63,  "Dark Blue",              272,  Some(140), "Earth Blue",             0x0A3463),
+    solid(55,  "Sand Blue",              379,  Some(135), "Sand Blue",              0x6074A1),
+    // Violets, purples and pinks
+    solid(109, "Dark Blue-Violet",       23,   Some(196), "Dark Royal Blue",        0x2032B0),
+    solid(97,  "Blue-Violet",            89,   None,      "Blue-Violet",            0x4C61DB),
+    solid(43,  "Violet",                 110,  None,      "Violet",                 0x4354A3),
+    solid(73,  "Medium Violet",          112,  Some(112), "Medium Bluish Violet",   0x6874CA),
+    solid(44,  "Light Violet",           20,   Some(39),  "Light Bluish Violet",    0xC9CAE2),
+    solid(89,  "Dark Purple",            85,   Some(268), "Medium Lilac",           0x3F3691),
+    solid(24,  "Purple",                 22,   Some(104), "Bright Violet",          0x81007B),
+    solid(157, "Medium Lavender",        30,   Some(324), "Medium Lavender",        0xAC78BA),
+    solid(154, "Lavender",               31,   Some(325), "Lavender",               0xE1D5ED),
+    solid(54,  "Sand Purple",            373,  Some(136), "Sand Violet",            0x845E84),
+    solid(71,  "Magenta",                26,   Some(124), "Bright Reddish Violet",  0x923978),
+    solid(47,  "Dark Pink",              5,    Some(22),  "Medium Reddish Violet",  0xC870A0),
+    solid(94,  "Medium Dark Pink",       351,  Some(221), "Bright Purple",          0xF785B1),
+    solid(104, "Bright Pink",            29,   Some(222), "Light Purple",           0xE4ADC8),
+    solid(23,  "Pink",                   13,   None,      "Pink",                   0xFC97AC),
+    solid(56,  "Light Pink",             77,   None,      "Light Pink",             0xFECCCF),
+    solid(93,  "Light Purple",           69,   Some(236), "Bright Reddish Lilac",   0xCD6298),
+    solid(220, "Coral",                  1050, Some(353), "Vibrant Coral",          0xFF698F),
+    // Transparent
+    entry(12,  "Trans-Clear",        47,  Some(40),  "Transparent",                            0xFCFCFC, Transparent),
+    entry(13,  "Trans-Black",        40,  Some(111), "Transparent Brown",                      0x635F52, Transparent),
+    entry(17,  "Trans-Red",          36,  Some(41),  "Transparent Red",                        0xC91A09, Transparent),
+    entry(18,  "Trans-Neon Orange",  57,  Some(47),  "Transparent Fluorescent Reddish Orange", 0xFF800D, Transparent),
+    entry(98,  "Trans-Orange",       182, Some(182), "Transparent Bright Orange",              0xF08F1C, Transparent),
+    entry(19,  "Trans-Yellow",       46,  Some(44),  "Transparent Yellow",                     0xF5CD2F, Transparent),
+    entry(16,  "Trans-Neon Green",   42,  Some(49),  "Transparent Fluorescent Green",          0xF8F184, Transparent),
+    entry(20,  "Trans-Green",        34,  Some(48),  "Transparent Green",                      0x84B68D, Transparent),
+    entry(108, "Trans-Bright Green", 35,  Some(311), "Transparent Bright Green",               0xD9E4A7, Transparent),
+    entry(15,  "Trans-Light Blue",   43,  Some(42),  "Transparent Light Blue",                 0xAEEFEC, Transparent),
+    entry(74,  "Trans-Medium Blue",  41,  None,      "Trans-Medium Blue",                      0x559AB7, Transparent),
+    entry(14,  "Trans-Dark Blue",    33,  Some(43),  "Transparent Blue",                       0x0020A0, Transparent),
+    entry(50,  "Trans-Dark Pink",    37,  Some(113), "Transparent Medium Reddish Violet",      0xDF6695, Transparent),
+    entry(51,  "Trans-Purple",       52,  Some(126), "Transparent Bright Bluish Violet",       0xA5A5CB, Transparent),
+    entry(107, "Trans-Pink",         45,  None,      "Trans-Pink",                             0xFC97AC, Transparent),
+    entry(113, "Trans-Very Lt Blue", 39,  None,      "Trans-Very Lt Blue",                     0xC1DFF0, Transparent),
+    entry(114, "Trans-Light Purple", 44,  None,      "Trans-Light Purple",                     0x96709F, Transparent),
+    entry(121, "Trans-Neon Yellow",  54,  Some(157), "Transparent Fluorescent Yellow",         0xDAB000, Transparent),
+    entry(164, "Trans-Light Orange", 231, Some(231), "Transparent Flame Yellowish Orange",      0xFCB76D, Transparent),
+    entry(221, "Trans-Light Green",  285, None,      "Trans-Light Green",                      0x94E5AB, Transparent),
+    // Glitter
+    entry(101, "Glitter Trans-Clear",      117, None, "Glitter Trans-Clear",      0xFFFFFF, Transparent),
+    entry(100, "Glitter Trans-Dark Pink",  114, None, "Glitter Trans-Dark Pink",  0xDF6695, Transparent),
+    entry(102, "Glitter Trans-Purple",     129, None, "Glitter Trans-Purple",     0xA5A5CB, Transparent),
+    entry(162, "Glitter Trans-Light Blue", 302, None, "Glitter Trans-Light Blue", 0xAEEFEC, Transparent),
+    entry(163, "Glitter Trans-Neon Green", 339, None, "Glitter Trans-Neon Green", 0xC0F500, Transparent),
+    entry(222, "Glitter Trans-Orange",     341, None, "Glitter Trans-Orange",     0xF08F1C, Transparent),
+    // Pearl, metallic and chrome
+    entry(115, "Pearl Gold",         297, Some(297), "Warm Gold",          0xAA7F2E, Metallic),
+    entry(95,  "Flat Silver",        179, Some(315), "Silver Metallic",    0x898788, Metallic),
+    entry(77,  "Pearl Dark Gray",    148, Some(148), "Metallic Dark Grey", 0x575857, Metallic),
+    entry(66,  "Pearl Light Gray",   135, Some(131), "Silver",             0x9CA3A8, Metallic),
+    entry(22,  "Chrome Silver",      383, None,      "Chrome Silver",      0xE0E0E0, Metallic),
+    entry(21,  "Chrome Gold",        334, None,      "Chrome Gold",        0xBBA53D, Metallic),
+    entry(67,  "Metallic Silver",    80,  None,      "Metallic Silver",    0xA5A9B4, Metallic),
+    entry(65,  "Metallic Gold",      82,  None,      "Metallic Gold",      0xDBAC34, Metallic),
+    entry(70,  "Metallic Green",     81,  None,      "Metallic Green",     0x899B5F, Metallic),
+    entry(83,  "Pearl White",        183, Some(183), "Metallic White",     0xF2F3F2, Metallic),
+    entry(119, "Pearl Very Light Gray", 150, None,   "Pearl Very Light Gray", 0xABADAC, Metallic),
+    entry(61,  "Pearl Light Gold",   142, Some(127), "Gold",               0xDCBC81, Metallic),
+    entry(81,  "Flat Dark Gold",     178, Some(147), "Metallic Sand Yellow", 0xB4883E, Metallic),
+    entry(84,  "Copper",             134, Some(139), "Copper",             0xAE7A59, Metallic),
+    entry(57,  "Chrome Antique Brass", 60, None,     "Chrome Antique Brass", 0x645A4C, Metallic),
+    entry(52,  "Chrome Blue",        61,  None,      "Chrome Blue",        0x6C96BF, Metallic),
+    entry(64,  "Chrome Green",       62,  None,      "Chrome Green",       0x3CB371, Metallic),
+    entry(82,  "Chrome Pink",        63,  None,      "Chrome Pink",        0xAA4D8E, Metallic),
+    entry(122, "Chrome Black",       64,  None,      "Chrome Black",       0x1B2A34, Metallic),
+    // Speckle, glow in the dark and milky
+    entry(111, "Speckle Black-Silver",  132, None,      "Speckle Black-Silver",  0x05131D, Metallic),
+    entry(116, "Speckle Black-Copper",  75,  None,      "Speckle Black-Copper",  0x05131D, Metallic),
+    entry(151, "Speckle Black-Gold",    133, None,      "Speckle Black-Gold",    0x05131D, Metallic),
+    entry(117, "Speckle DBGray-Silver", 76,  None,      "Speckle DBGray-Silver", 0x635F61, Metallic),
+    entry(46,  "Glow In Dark Opaque",   21,  Some(50),  "Phosphorescent White",  0xD4D5C9, Metallic),
+    entry(118, "Glow In Dark Trans",    294, Some(294), "Phosphorescent Green",  0xBDC6AD, Metallic),
+    entry(159, "Glow In Dark White",    329, Some(329), "White Glow",            0xF5F3D7, Metallic),
+    entry(60,  "Milky White",           79,  Some(20),  "Nature",                0xFFFFFF, Metallic),
+];
+
+/// Find a palette color by name
+///
+/// Matches BrickLink and LEGO official names, ignoring case. A few names
+/// mean different colors in the two standards (LEGO's "Dark Green" is
+/// BrickLink's "Green"); the BrickLink color wins.
+///
+/// # Examples
+///
+/// ```
+/// use studfinder::processing::palette;
+///
+/// let color = palette::find_by_name("bright red").unwrap();
+/// assert_eq!(color.bricklink_name, "Red");
+/// ```
+#[must_use]
+pub fn find_by_name(name: &str) -> Option<&'static PaletteColor> {
+    let name = name.trim();
+    PALETTE
+        .iter()
+        .find(|c| c.bricklink_name.eq_ignore_ascii_case(name))
+        .or_else(|| {
+            PALETTE
+                .iter()
+                .find(|c| c.lego_name.eq_ignore_ascii_case(name))
+        })
+}
+
+/// Find a palette color by its BrickLink color id
+#[must_use]
+pub fn find_by_bricklink_id(id: u32) -> Option<&'static PaletteColor> {
+    PALETTE.iter().find(|c| c.bricklink_id == id)
+}
+
+/// Find a palette color by its Rebrickable color id
+#[must_use]
+pub fn find_by_rebrickable_id(id: u32) -> Option<&'static PaletteColor> {
+    PALETTE.iter().find(|c| c.rebrickable_id == id)
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_ids_are_unique() {
+        let bricklink: HashSet<u32> = PALETTE.iter().map(|c| c.bricklink_id).collect();
+        let rebrickable: HashSet<u32> = PALETTE.iter().map(|c| c.rebrickable_id).collect();
+        assert_eq!(bricklink.len(), PALETTE.len());
+        assert_eq!(rebrickable.len(), PALETTE.len());
+    }
+
+    #[test]
+    fn test_names_are_unique_per_standard() {
+        for standard in [
+            ColorStandard::BrickLink,
+            ColorStandard::LegoOfficial,
+        ] {
+            let names: HashSet<String> = PALETTE
+                .iter()
+                .map(|c| c.name(standard).to_lowercase())
+                .collect();
+            assert_eq!(names.len(), PALETTE.len(), "{:?}", standard);
+        }
+        let lego_ids: Vec<u32> = PALETTE.iter().filter_map(|c| c.lego_id).collect();
+        let unique: HashSet<u32> = lego_ids.iter().copied().collect();
+        assert_eq!(unique.len(), lego_ids.len());
+    }
+
+    #[test]
+    fn test_lookups() {
+        let color = find_by_name("Dark Bluish Gray").unwrap();
+        assert_eq!(color.bricklink_id, 85);
+        assert_eq!(color.rebrickable_id, 72);
+        assert_eq!(color.rgb, [0x6C, 0x6E, 0x68]);
+
+        assert_eq!(find_by_name("brick yellow").unwrap().bricklink_name, "Tan");
+        assert_eq!(
+            find_by_name("light orange brown").unwrap().bricklink_name,
+            "Earth Orange"
+        );
+        // A name used by both standards means the BrickLink color
+        assert_eq!(find_by_name("Dark Green").unwrap().bricklink_id, 80);
+        assert_eq!(find_by_bricklink_id(5).unwrap().lego_name, "Bright Red");
+        assert_eq!(
+            find_by_rebrickable_id(70).unwrap().bricklink_name,
+            "Reddish Brown"
+        );
+        assert!(find_by_name("Not A Color").is_none());
+    }
+
+    #[test]
+    fn test_special_finishes_are_listed() {
+        let pearl_white = find_by_bricklink_id(83).unwrap();
+        assert_eq!(pearl_white.rebrickable_id, 183);
+        assert_eq!(pearl_white.finish, ColorFinish::Metallic);
+        assert_eq!(
+            find_by_rebrickable_id(117).unwrap().bricklink_name,
+            "Glitter Trans-Clear"
+        );
+        assert_eq!(
+            find_by_name("nature").unwrap().bricklink_name,
+            "Milky White"
+        );
+        assert_eq!(find_by_rebrickable_id(128).unwrap().bricklink_id, 225);
+    }
+}
@@ -1,20 +1,64 @@
 use crate::core::Piece;
 use crate::error::Result;
+use crate::processing::color::ColorCandidate;
+use crate::processing::segment::BoundingBox;
 use image::DynamicImage;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+/// Number of ranked part candidates kept in a `Detection`
+pub const PART_CANDIDATES: usize = 5;
+
+/// A part number considered for a detected piece
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartCandidate {
+    /// Canonical part number
+    pub part_number: String,
+    /// How well the piece matches the part (0.0-1.0)
+    pub score: f32,
+}
+
+/// A piece identified in an image, with the measurements behind it
+///
+/// Besides the chosen part and color, a detection keeps the runners-up:
+/// `color_candidates` and `part_candidates` are ranked best first, and the
+/// piece's part number and color are normally the first of each. The piece
+/// is reported as part `unknown` or color `Unknown` when even the best
+/// candidate is not good enough, so the lists may be non-empty then.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Detection {
+    /// The identified piece
+    pub piece: Piece,
+    /// Where the piece was found in the image
+    pub bounds: BoundingBox,
+    /// Median RGB of the piece's pixels, before palette matching
+    pub measured_rgb: Option<[u8; 3]>,
+    /// Palette colors closest to `measured_rgb`, nearest first
+    pub color_candidates: Vec<ColorCandidate>,
+    /// Parts the piece may be, best match first
+    pub part_candidates: Vec<PartCandidate>,
+}
+
+impl Detection {
+    /// Strip the measurements from a list of detections
+    #[must_use]
+    pub fn into_pieces(detections: Vec<Detection>) -> Vec<Piece> {
+        detections.into_iter().map(|d| d.piece).collect()
+    }
+}
+
 /// Trait for image processing implementations
 ///
 /// This trait defines the interface for different image processing strategies
 /// that can be used to identify LEGO pieces in images.
 pub trait ImageProcessor: Send + Sync {
-    /// Process an image to identify LEGO pieces
+    /// Identify the LEGO pieces in an image, with where and how each was measured
     ///
     /// # Arguments
     /// * `image_path` - Path to the image file to process
     ///
     /// # Returns
-    /// * `Result<Vec<Piece>>` - A list of identified pieces or an error
+    /// * `Result<Vec<Detection>>` - One detection per identified piece, or an error
     ///
     /// # Errors
     ///
@@ -22,7 +66,53 @@ pub trait ImageProcessor: Send + Sync {
     /// - The image file cannot be opened or read
     /// - The image validation fails
     /// - The processing algorithm encounters an error
-    fn process_image(&self, image_path: &Path) -> Result<Vec<Piece>>;
+    fn detect(&self, image_path: &Path) -> Result<Vec<Detection>>;
+
+    /// Identify the pieces in a top-down image together with a side view
+    ///
+    /// Processors that cannot use a side view ignore it and fall back to
+    /// `detect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as `detect`, or if the
+    /// side view cannot be read.
+    fn detect_with_side_view(
+        &self,
+        image_path: &Path,
+        _side_view_path: &Path,
+    ) -> Result<Vec<Detection>> {
+        self.detect(image_path)
+    }
+
+    /// Process an image to identify LEGO pieces
+    ///
+    /// # Arguments
+    /// * `image_path` - Path to the image file to process
+    ///
+    /// # Returns
+    /// * `Result<Vec<Piece>>` - A list of identified pieces or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as `detect`.
+    fn process_image(&self, image_path: &Path) -> Result<Vec<Piece>> {
+        self.detect(image_path).map(Detection::into_pieces)
+    }
+
+    /// Process a top-down image together with a side view of the same piece
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as `detect_with_side_view`.
+    fn process_image_with_side_view(
+        &self,
+        image_path: &Path,
+        side_view_path: &Path,
+    ) -> Result<Vec<Piece>> {
+        self.detect_with_side_view(image_path, side_view_path)
+            .map(Detection::into_pieces)
+    }
 
     /// Validate that an image meets the requirements for processing
     ///
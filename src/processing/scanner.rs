@@ -1,19 +1,32 @@
+use crate::core::catalog::{PartCatalog, PartDimensions};
 use crate::core::piece::{Piece, ScanQuality};
 use crate::error::{Result, StudFinderError};
+use crate::processing::calibration::WhiteBalance;
 use crate::processing::color::{ColorDetector, ColorDetectorConfig, ColorStandard};
-use crate::processing::processor::ImageProcessor;
-use image::{DynamicImage, GenericImageView};
+use crate::processing::processor::{Detection, ImageProcessor, PartCandidate};
+use crate::processing::segment::{Region, Segmenter, SegmenterConfig};
+use crate::processing::studs::{HeightClass, StudDetector, StudGrid, UNKNOWN_PART};
+use image::{DynamicImage, GenericImageView, GrayImage, RgbImage};
 use std::path::Path;
+use std::sync::Arc;
 use tracing::{debug, info};
 use uuid::Uuid;
 
+/// Score weight of the other height's part when the height was assumed
+const ASSUMED_HEIGHT_RUNNER_UP_WEIGHT: f32 = 0.5;
+
+/// Score weight of the other height's part when a side view measured the height
+const MEASURED_HEIGHT_RUNNER_UP_WEIGHT: f32 = 0.2;
+
 /// Scanner implementation for LEGO piece identification
 ///
-/// This implementation focuses on color detection and basic shape analysis
-/// to identify LEGO pieces in images.
+/// This implementation combines color detection with stud counting: the
+/// studs on top of each piece give its footprint, and an optional side view
+/// gives its height, which together identify basic bricks and plates.
 #[derive(Clone)]
 pub struct Scanner {
     config: ScanConfig,
+    catalog: Arc<PartCatalog>,
 }
 
 /// Configuration for the scanner
@@ -22,6 +35,7 @@ struct ScanConfig {
     min_confidence: f32,
     min_region_size: u32,
     color_detector_config: ColorDetectorConfig,
+    segmenter_config: SegmenterConfig,
 }
 
 impl Scanner {
@@ -40,6 +54,22 @@ impl Scanner {
     /// ```
     #[must_use]
     pub fn new(quality: ScanQuality) -> Self {
+        Self::with_catalog(quality, Arc::new(PartCatalog::builtin()))
+    }
+
+    /// Create a new Scanner that looks up parts in the given catalog
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use studfinder::core::{PartCatalog, ScanQuality};
+    /// use studfinder::processing::Scanner;
+    ///
+    /// let scanner = Scanner::with_catalog(ScanQuality::Fast, Arc::new(PartCatalog::builtin()));
+    /// ```
+    #[must_use]
+    pub fn with_catalog(quality: ScanQuality, catalog: Arc<PartCatalog>) -> Self {
         info!("Initializing scanner with quality: {:?}", quality);
 
         let config = match quality {
@@ -49,6 +79,12 @@ impl Scanner {
                 color_detector_config: ColorDetectorConfig {
                     threshold: 0.6,
                     standard: ColorStandard::BrickLink,
+                    white_balance: None,
+                },
+                segmenter_config: SegmenterConfig {
+                    background_tolerance: 60.0,
+                    min_area: 100,
+                    opening_radius: 1,
                 },
             },
             ScanQuality::Balanced => ScanConfig {
@@ -57,6 +93,12 @@ impl Scanner {
                 color_detector_config: ColorDetectorConfig {
                     threshold: 0.75,
                     standard: ColorStandard::BrickLink,
+                    white_balance: None,
+                },
+                segmenter_config: SegmenterConfig {
+                    background_tolerance: 48.0,
+                    min_area: 200,
+                    opening_radius: 1,
                 },
             },
             ScanQuality::Accurate => ScanConfig {
@@ -65,6 +107,12 @@ impl Scanner {
                 color_detector_config: ColorDetectorConfig {
                     threshold: 0.85,
                     standard: ColorStandard::BrickLink,
+                    white_balance: None,
+                },
+                segmenter_config: SegmenterConfig {
+                    background_tolerance: 40.0,
+                    min_area: 400,
+                    opening_radius: 1,
                 },
             },
         };
@@ -76,11 +124,55 @@ impl Scanner {
 
         Self {
             config,
+            catalog,
         }
     }
 
+    /// Use a different minimum confidence than the quality level's
+    ///
+    /// Regions whose color confidence is below it are dropped. `StudFinder`
+    /// sets it to the review threshold, so that every detection that may be
+    /// queued for review is reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::Scanner;
+    /// use studfinder::core::piece::ScanQuality;
+    ///
+    /// let scanner = Scanner::new(ScanQuality::Balanced).with_min_confidence(0.5);
+    /// ```
+    #[must_use]
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        debug!("Scanner minimum confidence set to {}", min_confidence);
+        self.config.min_confidence = min_confidence;
+        self
+    }
+
+    /// Correct measured colors for the rig's lighting before matching them
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::{Scanner, WhiteBalance};
+    /// use studfinder::core::piece::ScanQuality;
+    ///
+    /// let balance = WhiteBalance::from_reference([250, 220, 180], [255, 255, 255]).unwrap();
+    /// let scanner = Scanner::new(ScanQuality::Balanced).with_white_balance(balance);
+    /// ```
+    #[must_use]
+    pub fn with_white_balance(mut self, white_balance: WhiteBalance) -> Self {
+        debug!("Scanner white balance gains: {:?}", white_balance.gains());
+        self.config.color_detector_config.white_balance = Some(white_balance);
+        self
+    }
+
     /// Scan an image to identify LEGO pieces
     ///
+    /// The image is segmented into one region per piece and each region is
+    /// identified independently, so a photo of a tray of loose parts yields
+    /// one `Piece` per part.
+    ///
     /// # Arguments
     /// * `path` - Path to the image file to scan
     ///
@@ -111,9 +203,35 @@ impl Scanner {
     /// # }
     /// ```
     pub fn scan_image<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Piece>> {
-        debug!("Starting image scan for: {}", path.as_ref().display());
+        self.scan(path.as_ref(), None).map(Detection::into_pieces)
+    }
+
+    /// Scan a top-down image together with a side view of the same piece
+    ///
+    /// The side view is used to tell plates from bricks with the same stud
+    /// layout (e.g. 3020 from 3001). It should show the piece from its long
+    /// side, and only applies when the top-down image contains a single piece.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the top-down image
+    /// * `side_view` - Path to the side view image
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either image cannot be opened or fails validation.
+    pub fn scan_image_with_side_view<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        path: P,
+        side_view: Q,
+    ) -> Result<Vec<Piece>> {
+        self.scan(path.as_ref(), Some(side_view.as_ref()))
+            .map(Detection::into_pieces)
+    }
+
+    fn scan(&self, path: &Path, side_view: Option<&Path>) -> Result<Vec<Detection>> {
+        debug!("Starting image scan for: {}", path.display());
 
-        let img = image::open(&path).map_err(StudFinderError::Image)?;
+        let img = image::open(path).map_err(StudFinderError::Image)?;
         debug!(
             "Image loaded successfully: {}x{}",
             img.width(),
@@ -123,40 +241,99 @@ impl Scanner {
         self.validate_image(&img)?;
         debug!("Image validation passed");
 
-        // Use the ColorDetector to analyze the color
+        let segmenter = Segmenter::new(self.config.segmenter_config.clone());
+        let regions = segmenter.segment(&img);
+        let rgb = img.to_rgb8();
         let color_detector = ColorDetector::with_config(self.config.color_detector_config.clone());
-        let color_info = color_detector.detect_color(&img);
+
+        let side_region = match side_view {
+            Some(side_path) if regions.len() == 1 => {
+                debug!("Loading side view: {}", side_path.display());
+                let side_img = image::open(side_path).map_err(StudFinderError::Image)?;
+                self.validate_image(&side_img)?;
+                segmenter
+                    .segment(&side_img)
+                    .into_iter()
+                    .max_by_key(|r| r.area)
+            }
+            Some(_) => {
+                debug!(
+                    "Ignoring side view: top view contains {} pieces",
+                    regions.len()
+                );
+                None
+            }
+            None => None,
+        };
+
+        let mut detections = Vec::with_capacity(regions.len());
+        for region in &regions {
+            if let Some(detection) =
+                self.identify_region(&color_detector, &rgb, region, side_region.as_ref())
+            {
+                debug!("Created piece record: {:?}", detection.piece);
+                detections.push(detection);
+            }
+        }
+
+        info!(
+            "Identified {} piece(s) in {} region(s)",
+            detections.len(),
+            regions.len()
+        );
+        Ok(detections)
+    }
+
+    /// Identify the piece contained in a single segmented region
+    ///
+    /// The piece's confidence averages the color confidence with the score
+    /// of the best part candidate; a region without a stud grid scores zero
+    /// for its part. Returns `None` if the color detection confidence is
+    /// below the configured minimum.
+    fn identify_region(
+        &self,
+        color_detector: &ColorDetector,
+        img: &RgbImage,
+        region: &Region,
+        side_region: Option<&Region>,
+    ) -> Option<Detection> {
+        let color_info = color_detector.detect_color_pixels(region.pixels(img));
 
         if color_info.confidence < self.config.min_confidence {
             debug!(
-                "Color detection confidence too low: {:.2}",
-                color_info.confidence
+                "Color detection confidence too low for region at ({}, {}): {:.2}",
+                region.bounds.x, region.bounds.y, color_info.confidence
             );
-            return Ok(vec![]);
+            return None;
         }
 
-        info!(
+        debug!(
             "Detected color: {} (confidence: {:.2}%)",
             color_info.name,
             color_info.confidence * 100.0
         );
 
-        let part_number = self.detect_part_type(&img);
-        let category = self.categorize_part(&part_number);
+        let (part_number, part_candidates) =
+            self.detect_part_type(&region.crop(img), &region.mask, side_region);
+        let category = self.catalog.category(&part_number);
+        let part_score = part_candidates.first().map_or(0.0, |c| c.score);
+        let confidence = (color_info.confidence + part_score) / 2.0;
 
-        let pieces = vec![
-            Piece {
+        Some(Detection {
+            piece: Piece {
                 id: Uuid::new_v4().to_string(),
                 part_number,
                 color: color_info.name,
                 category,
                 quantity: 1,
-                confidence: color_info.confidence,
+                confidence,
+                condition: None,
             },
-        ];
-
-        debug!("Created piece record: {:?}", pieces[0]);
-        Ok(pieces)
+            bounds: region.bounds,
+            measured_rgb: color_info.measured,
+            color_candidates: color_info.candidates,
+            part_candidates,
+        })
     }
 
     /// Validate that the image meets minimum requirements
@@ -183,34 +360,85 @@ impl Scanner {
         Ok(())
     }
 
-    /// Detect the part type from the image
+    /// Detect the part type from a cropped region
     ///
-    /// In a real implementation, this would use more sophisticated image analysis.
-    /// Currently, it returns a simulated result.
-    fn detect_part_type(&self, _img: &DynamicImage) -> String {
-        let part_number = "3001";
-        debug!("Part type detection returned: {}", part_number);
-        part_number.to_string()
-    }
-
-    /// Categorize a part based on its part number
+    /// Counts the studs on the piece to find its footprint and, when a side
+    /// view is available, measures its height. The part with that footprint
+    /// is looked up in the catalog. Without a side view the piece
+    /// is assumed to be a brick.
     ///
-    /// Maps part numbers to their corresponding categories (e.g., Brick, Plate, Tile)
-    fn categorize_part(&self, part_number: &str) -> String {
-        let category = match part_number {
-            "3001" => "Brick",
-            "3020" => "Plate",
-            "3062" => "Tile",
-            _ => "Unknown",
+    /// The part with the same footprint at the other height is returned as a
+    /// runner-up candidate. Candidates are scored by how well the studs were
+    /// found, lowered for the runner-up by how sure the height is. The piece
+    /// takes the part of the first candidate, so a footprint only made as a
+    /// plate is still identified when the height was assumed.
+    fn detect_part_type(
+        &self,
+        img: &RgbImage,
+        mask: &GrayImage,
+        side_region: Option<&Region>,
+    ) -> (String, Vec<PartCandidate>) {
+        let studs = StudDetector::default().detect(img, mask);
+        let Some(grid) = StudGrid::infer(&studs) else {
+            debug!("No stud grid found among {} stud(s)", studs.len());
+            return (UNKNOWN_PART.to_string(), Vec::new());
+        };
+        let grid_score = studs.iter().map(|stud| stud.score).sum::<f32>() / studs.len() as f32;
+
+        let (height, other_weight) = match side_region {
+            Some(side) => (
+                HeightClass::from_side_view(side.bounds, grid.length),
+                MEASURED_HEIGHT_RUNNER_UP_WEIGHT,
+            ),
+            None => (HeightClass::Brick, ASSUMED_HEIGHT_RUNNER_UP_WEIGHT),
         };
-        debug!("Categorized part {} as {}", part_number, category);
-        category.to_string()
+        let other = match height {
+            HeightClass::Brick => HeightClass::Plate,
+            HeightClass::Plate => HeightClass::Brick,
+        };
+
+        let find = |height: HeightClass| {
+            self.catalog.find_by_dimensions(PartDimensions {
+                width: grid.width,
+                length: grid.length,
+                height: height.plates(),
+            })
+        };
+        let candidates: Vec<PartCandidate> = [
+            (find(height), 1.0),
+            (find(other), other_weight),
+        ]
+        .into_iter()
+        .filter_map(|(part, weight)| {
+            part.map(|part| PartCandidate {
+                part_number: part.part_number.clone(),
+                score: grid_score * weight,
+            })
+        })
+        .collect();
+        let part_number = candidates
+            .first()
+            .map_or(UNKNOWN_PART, |candidate| candidate.part_number.as_str())
+            .to_string();
+        debug!(
+            "Part type detection returned: {} ({} {:?})",
+            part_number, grid, height
+        );
+        (part_number, candidates)
     }
 }
 
 impl ImageProcessor for Scanner {
-    fn process_image(&self, image_path: &Path) -> Result<Vec<Piece>> {
-        self.scan_image(image_path)
+    fn detect(&self, image_path: &Path) -> Result<Vec<Detection>> {
+        self.scan(image_path, None)
+    }
+
+    fn detect_with_side_view(
+        &self,
+        image_path: &Path,
+        side_view_path: &Path,
+    ) -> Result<Vec<Detection>> {
+        self.scan(image_path, Some(side_view_path))
     }
 
     fn validate_image(&self, image: &DynamicImage) -> Result<()> {
@@ -224,39 +452,29 @@ impl ImageProcessor for Scanner {
 }
 
 #[cfg(test)]
+#[allow(clippy::disallowed_methods)]
 mod tests {
     use super::*;
     use image::Rgb;
-    use tempfile;
 
     #[test]
     fn test_scan_qualities() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("red_test.png");
+        draw_studded_part(4, 2).save(&path).unwrap();
+
         for quality in [
             ScanQuality::Fast,
             ScanQuality::Balanced,
             ScanQuality::Accurate,
         ] {
-            let mut img = image::RgbImage::new(200, 200);
-            for pixel in img.pixels_mut() {
-                *pixel = Rgb([
-                    255, 0, 0,
-                ]); // Pure red
-            }
-
-            let temp_dir = tempfile::tempdir().unwrap();
-            let path = temp_dir.path().join("red_test.png");
-            img.save(&path).unwrap();
-
-            let scanner = Scanner::new(quality.clone());
-            let result = scanner.scan_image(path).unwrap();
+            let scanner = Scanner::new(quality);
+            let result = scanner.scan_image(&path).unwrap();
 
             assert_eq!(result.len(), 1);
             assert_eq!(result[0].color, "Red");
-            match quality {
-                ScanQuality::Fast => assert!(result[0].confidence > 0.7),
-                ScanQuality::Balanced => assert!(result[0].confidence > 0.8),
-                ScanQuality::Accurate => assert!(result[0].confidence > 0.9),
-            }
+            assert_eq!(result[0].part_number, "3001");
+            assert!(result[0].confidence > 0.8);
         }
     }
 
@@ -266,15 +484,8 @@ mod tests {
 
         // Create a test image
         let temp_dir = tempfile::tempdir().unwrap();
-        let path = temp_dir.path().join("test.jpg");
-
-        let mut img = image::RgbImage::new(200, 200);
-        for pixel in img.pixels_mut() {
-            *pixel = Rgb([
-                255, 0, 0,
-            ]); // Pure red
-        }
-        img.save(&path).unwrap();
+        let path = temp_dir.path().join("test.png");
+        draw_studded_part(4, 2).save(&path).unwrap();
 
         // Test through the ImageProcessor trait
         let processor: Box<dyn ImageProcessor> = Box::new(scanner);
@@ -284,4 +495,178 @@ mod tests {
         assert_eq!(pieces[0].color, "Red");
         assert!(pieces[0].confidence > 0.8);
     }
+
+    #[test]
+    fn test_scan_multiple_pieces() {
+        let mut img = image::RgbImage::from_pixel(
+            300,
+            200,
+            Rgb([
+                255, 255, 255,
+            ]),
+        );
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut img,
+            imageproc::rect::Rect::at(20, 20).of_size(60, 40),
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut img,
+            imageproc::rect::Rect::at(150, 100).of_size(60, 60),
+            Rgb([
+                0, 85, 191,
+            ]),
+        );
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("tray.png");
+        img.save(&path).unwrap();
+
+        let scanner = Scanner::new(ScanQuality::Balanced);
+        let pieces = scanner.scan_image(&path).unwrap();
+
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].color, "Red");
+        assert_eq!(pieces[1].color, "Blue");
+    }
+
+    /// Draw a top-down view of a red part with a `columns` x `rows` stud grid
+    fn draw_studded_part(columns: u32, rows: u32) -> image::RgbImage {
+        let pitch = 40;
+        let mut img = image::RgbImage::from_pixel(
+            columns * pitch + 80,
+            rows * pitch + 80,
+            Rgb([
+                255, 255, 255,
+            ]),
+        );
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut img,
+            imageproc::rect::Rect::at(40, 40).of_size(columns * pitch, rows * pitch),
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        // Stud tops catch the light
+        for column in 0..columns {
+            for row in 0..rows {
+                imageproc::drawing::draw_filled_circle_mut(
+                    &mut img,
+                    (
+                        (40 + column * pitch + pitch / 2) as i32,
+                        (40 + row * pitch + pitch / 2) as i32,
+                    ),
+                    12,
+                    Rgb([
+                        240, 90, 70,
+                    ]),
+                );
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_stud_count_distinguishes_parts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let scanner = Scanner::new(ScanQuality::Fast);
+
+        let brick_2x4 = temp_dir.path().join("2x4.png");
+        draw_studded_part(4, 2).save(&brick_2x4).unwrap();
+        let pieces = scanner.scan_image(&brick_2x4).unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].part_number, "3001");
+        assert_eq!(pieces[0].category, "Bricks");
+
+        // Without a side view the plate of the same footprint is the runner-up
+        let detections = scanner.detect(&brick_2x4).unwrap();
+        let parts: Vec<&str> = detections[0]
+            .part_candidates
+            .iter()
+            .map(|c| c.part_number.as_str())
+            .collect();
+        assert_eq!(parts, vec!["3001", "3020"]);
+        let scores: Vec<f32> = detections[0]
+            .part_candidates
+            .iter()
+            .map(|c| c.score)
+            .collect();
+        assert!(scores[0] > 0.0);
+        assert!((scores[1] - scores[0] * ASSUMED_HEIGHT_RUNNER_UP_WEIGHT).abs() < 1e-6);
+        assert_eq!(detections[0].color_candidates[0].name, "Red");
+
+        let brick_2x2 = temp_dir.path().join("2x2.png");
+        draw_studded_part(2, 2).save(&brick_2x2).unwrap();
+        let pieces = scanner.scan_image(&brick_2x2).unwrap();
+        assert_eq!(pieces[0].part_number, "3003");
+    }
+
+    #[test]
+    fn test_plate_only_footprint_without_side_view() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("4x4.png");
+        draw_studded_part(4, 4).save(&path).unwrap();
+
+        // No 4x4 brick is known, so the assumed height gives way to the plate
+        let detections = Scanner::new(ScanQuality::Fast).detect(&path).unwrap();
+        assert_eq!(detections[0].piece.part_number, "3031");
+        assert_eq!(detections[0].piece.category, "Plates");
+        assert_eq!(detections[0].part_candidates.len(), 1);
+        assert_eq!(detections[0].part_candidates[0].part_number, "3031");
+    }
+
+    #[test]
+    fn test_side_view_identifies_plate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let top = temp_dir.path().join("top.png");
+        draw_studded_part(4, 2).save(&top).unwrap();
+
+        // A 2x4 plate seen from its long side: 160px wide, 25px tall with studs
+        let mut side_img = image::RgbImage::from_pixel(
+            240,
+            120,
+            Rgb([
+                255, 255, 255,
+            ]),
+        );
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut side_img,
+            imageproc::rect::Rect::at(40, 50).of_size(160, 25),
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        let side = temp_dir.path().join("side.png");
+        side_img.save(&side).unwrap();
+
+        let scanner = Scanner::new(ScanQuality::Fast);
+        let pieces = scanner.scan_image_with_side_view(&top, &side).unwrap();
+        assert_eq!(pieces[0].part_number, "3020");
+        assert_eq!(pieces[0].category, "Plates");
+    }
+
+    #[test]
+    fn test_part_without_studs_is_unknown() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("tile.png");
+        image::RgbImage::from_pixel(
+            200,
+            200,
+            Rgb([
+                201, 26, 9,
+            ]),
+        )
+        .save(&path)
+        .unwrap();
+
+        let detections = Scanner::new(ScanQuality::Fast).detect(&path).unwrap();
+        assert_eq!(detections[0].piece.part_number, UNKNOWN_PART);
+        assert!(detections[0].part_candidates.is_empty());
+
+        // A sure color alone does not make a sure piece
+        assert_eq!(detections[0].color_candidates[0].name, "Red");
+        assert!(detections[0].piece.confidence <= 0.5);
+    }
 }
@@ -0,0 +1,337 @@
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Rgb, RgbImage};
+use imageproc::distance_transform::Norm;
+use imageproc::region_labelling::{connected_components, Connectivity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Axis-aligned bounding box of a region, in image pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    /// Left edge of the box
+    pub x: u32,
+    /// Top edge of the box
+    pub y: u32,
+    /// Width of the box in pixels
+    pub width: u32,
+    /// Height of the box in pixels
+    pub height: u32,
+}
+
+/// A connected foreground region believed to contain a single piece
+#[derive(Debug, Clone)]
+pub struct Region {
+    /// Bounding box of the region within the source image
+    pub bounds: BoundingBox,
+    /// Number of foreground pixels in the region
+    pub area: u32,
+    /// Foreground mask cropped to `bounds` (255 = piece, 0 = background)
+    pub mask: GrayImage,
+}
+
+impl Region {
+    /// Create a region covering the whole image
+    #[must_use]
+    pub fn whole(width: u32, height: u32) -> Self {
+        Self {
+            bounds: BoundingBox {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+            area: width * height,
+            mask: GrayImage::from_pixel(width, height, Luma([255])),
+        }
+    }
+
+    /// Crop the region's bounding box out of the source image
+    #[must_use]
+    pub fn crop(&self, img: &RgbImage) -> RgbImage {
+        img.view(
+            self.bounds.x,
+            self.bounds.y,
+            self.bounds.width,
+            self.bounds.height,
+        )
+        .to_image()
+    }
+
+    /// Iterate over the source pixels that belong to the region
+    pub fn pixels<'a>(&'a self, img: &'a RgbImage) -> impl Iterator<Item = Rgb<u8>> + 'a {
+        self.mask
+            .enumerate_pixels()
+            .filter(|(_, _, m)| m[0] > 0)
+            .map(move |(x, y, _)| *img.get_pixel(self.bounds.x + x, self.bounds.y + y))
+    }
+}
+
+/// Configuration for foreground segmentation
+#[derive(Debug, Clone)]
+pub struct SegmenterConfig {
+    /// Maximum RGB distance from the background color for a pixel to count as background
+    pub background_tolerance: f32,
+    /// Minimum number of pixels for a region to be reported
+    pub min_area: u32,
+    /// Radius of the morphological opening used to remove speckle noise
+    pub opening_radius: u8,
+}
+
+impl Default for SegmenterConfig {
+    fn default() -> Self {
+        Self {
+            background_tolerance: 48.0,
+            min_area: 200,
+            opening_radius: 1,
+        }
+    }
+}
+
+/// Splits an image of several loose pieces into one region per piece
+///
+/// The background color is estimated from the image border, pixels that
+/// differ from it are marked as foreground, and the foreground mask is
+/// split into connected components.
+#[derive(Debug, Clone, Default)]
+pub struct Segmenter {
+    config: SegmenterConfig,
+}
+
+impl Segmenter {
+    /// Create a new `Segmenter` with the given configuration
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::{Segmenter, SegmenterConfig};
+    ///
+    /// let segmenter = Segmenter::new(SegmenterConfig::default());
+    /// let img = image::DynamicImage::new_rgb8(100, 100);
+    /// let regions = segmenter.segment(&img);
+    /// assert_eq!(regions.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn new(config: SegmenterConfig) -> Self {
+        Self {
+            config,
+        }
+    }
+
+    /// Segment an image into piece regions
+    ///
+    /// Regions are returned in reading order (top to bottom, left to right).
+    /// If no distinct foreground is found, the whole image is returned as a
+    /// single region so that close-up photos of one piece still work.
+    #[must_use]
+    pub fn segment(&self, img: &DynamicImage) -> Vec<Region> {
+        let rgb = img.to_rgb8();
+        let (width, height) = rgb.dimensions();
+
+        let background = Self::estimate_background(&rgb);
+        debug!(
+            "Estimated background color: ({:.0}, {:.0}, {:.0})",
+            background[0], background[1], background[2]
+        );
+
+        let mut mask = self.foreground_mask(&rgb, background);
+        if self.config.opening_radius > 0 {
+            imageproc::morphology::open_mut(&mut mask, Norm::LInf, self.config.opening_radius);
+        }
+
+        let labels = connected_components(&mask, Connectivity::Eight, Luma([0u8]));
+
+        // label -> (min_x, min_y, max_x, max_y, area)
+        let mut stats: HashMap<u32, (u32, u32, u32, u32, u32)> = HashMap::new();
+        for (x, y, label) in labels.enumerate_pixels() {
+            if label[0] == 0 {
+                continue;
+            }
+            let entry = stats.entry(label[0]).or_insert((x, y, x, y, 0));
+            entry.0 = entry.0.min(x);
+            entry.1 = entry.1.min(y);
+            entry.2 = entry.2.max(x);
+            entry.3 = entry.3.max(y);
+            entry.4 += 1;
+        }
+
+        let mut regions: Vec<Region> = stats
+            .into_iter()
+            .filter(|(_, (.., area))| *area >= self.config.min_area)
+            .map(|(label, (min_x, min_y, max_x, max_y, area))| {
+                let bounds = BoundingBox {
+                    x: min_x,
+                    y: min_y,
+                    width: max_x - min_x + 1,
+                    height: max_y - min_y + 1,
+                };
+                let region_mask = GrayImage::from_fn(bounds.width, bounds.height, |x, y| {
+                    if labels.get_pixel(bounds.x + x, bounds.y + y)[0] == label {
+                        Luma([255])
+                    } else {
+                        Luma([0])
+                    }
+                });
+                Region {
+                    bounds,
+                    area,
+                    mask: region_mask,
+                }
+            })
+            .collect();
+
+        if regions.is_empty() {
+            debug!("No distinct foreground found, treating whole image as one region");
+            return vec![Region::whole(width, height)];
+        }
+
+        regions.sort_by_key(|r| (r.bounds.y, r.bounds.x));
+        debug!("Segmented {} region(s)", regions.len());
+        regions
+    }
+
+    /// Estimate the background color as the per-channel median of the border pixels
+    fn estimate_background(img: &RgbImage) -> [f32; 3] {
+        let (width, height) = img.dimensions();
+        let mut channels: [Vec<u8>; 3] = [
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        ];
+
+        for (x, y, pixel) in img.enumerate_pixels() {
+            if x == 0 || y == 0 || x + 1 == width || y + 1 == height {
+                for (channel, value) in channels.iter_mut().zip(pixel.0) {
+                    channel.push(value);
+                }
+            }
+        }
+
+        channels.map(|mut values| {
+            if values.is_empty() {
+                return 0.0;
+            }
+            values.sort_unstable();
+            f32::from(values[values.len() / 2])
+        })
+    }
+
+    /// Mark every pixel that differs from the background by more than the tolerance
+    fn foreground_mask(&self, img: &RgbImage, background: [f32; 3]) -> GrayImage {
+        let tolerance_sq = self.config.background_tolerance * self.config.background_tolerance;
+        GrayImage::from_fn(img.width(), img.height(), |x, y| {
+            let pixel = img.get_pixel(x, y);
+            let distance_sq: f32 = pixel
+                .0
+                .iter()
+                .zip(background)
+                .map(|(&value, bg)| (f32::from(value) - bg).powi(2))
+                .sum();
+            if distance_sq > tolerance_sq {
+                Luma([255])
+            } else {
+                Luma([0])
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    fn tray_image() -> DynamicImage {
+        let mut img = RgbImage::from_pixel(
+            300,
+            200,
+            Rgb([
+                255, 255, 255,
+            ]),
+        );
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at(20, 20).of_size(60, 40),
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at(150, 30).of_size(40, 40),
+            Rgb([
+                0, 85, 191,
+            ]),
+        );
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at(60, 120).of_size(80, 50),
+            Rgb([
+                242, 205, 55,
+            ]),
+        );
+        // A speck of dust that should be filtered out
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at(250, 150).of_size(3, 3),
+            Rgb([
+                0, 0, 0,
+            ]),
+        );
+        DynamicImage::ImageRgb8(img)
+    }
+
+    #[test]
+    fn test_segments_each_piece() {
+        let segmenter = Segmenter::default();
+        let regions = segmenter.segment(&tray_image());
+
+        assert_eq!(regions.len(), 3);
+        assert_eq!(
+            regions[0].bounds,
+            BoundingBox {
+                x: 20,
+                y: 20,
+                width: 60,
+                height: 40,
+            }
+        );
+        assert_eq!(regions[0].area, 60 * 40);
+        assert_eq!(regions[1].bounds.x, 150);
+        assert_eq!(regions[2].bounds.y, 120);
+    }
+
+    #[test]
+    fn test_region_pixels_exclude_background() {
+        let img = tray_image();
+        let rgb = img.to_rgb8();
+        let regions = Segmenter::default().segment(&img);
+
+        assert!(regions[1].pixels(&rgb).all(|p| p == Rgb([0, 85, 191])));
+        assert_eq!(regions[1].crop(&rgb).dimensions(), (40, 40));
+    }
+
+    #[test]
+    fn test_uniform_image_is_single_region() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            120,
+            80,
+            Rgb([
+                255, 0, 0,
+            ]),
+        ));
+        let regions = Segmenter::default().segment(&img);
+
+        assert_eq!(regions.len(), 1);
+        assert_eq!(
+            regions[0].bounds,
+            BoundingBox {
+                x: 0,
+                y: 0,
+                width: 120,
+                height: 80,
+            }
+        );
+    }
+}
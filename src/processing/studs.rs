@@ -0,0 +1,575 @@
+use crate::processing::segment::BoundingBox;
+use image::imageops::FilterType;
+use image::{GrayImage, RgbImage};
+use imageproc::distance_transform::Norm;
+use imageproc::edges::canny;
+use imageproc::gradients::{horizontal_sobel, vertical_sobel};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use tracing::debug;
+
+/// Part number reported when the stud layout cannot be determined
+pub const UNKNOWN_PART: &str = "unknown";
+
+/// Ratio of stud radius to stud pitch (4.8 mm diameter on an 8 mm grid)
+const STUD_RADIUS_TO_PITCH: f32 = 0.3;
+
+/// Largest footprint (in studs) along the short side considered when sizing studs
+const MAX_STUDS_ACROSS: f32 = 8.0;
+
+/// Longest side, in pixels, of the region searched for studs
+///
+/// Larger regions are scaled down first; the cost of the search grows with
+/// the number of pixels times the number of radii tried.
+const MAX_WORKING_SIDE: u32 = 160;
+
+/// Side-view height/pitch ratio separating plates (~0.6) from bricks (~1.4)
+const PLATE_BRICK_RATIO_CUTOFF: f32 = 0.9;
+
+/// A circular stud top found in an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stud {
+    /// Horizontal position of the stud center within the region
+    pub x: f32,
+    /// Vertical position of the stud center within the region
+    pub y: f32,
+    /// Stud radius in pixels
+    pub radius: f32,
+    /// Fraction of the stud outline supported by edge pixels (0.0-1.0)
+    pub score: f32,
+}
+
+/// The stud layout of a part seen from above, e.g. 2x4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StudGrid {
+    /// Number of studs along the short side
+    pub width: u32,
+    /// Number of studs along the long side
+    pub length: u32,
+}
+
+impl std::fmt::Display for StudGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.length)
+    }
+}
+
+impl StudGrid {
+    /// Infer the stud grid from detected stud centers
+    ///
+    /// The grid orientation is estimated from the directions between
+    /// neighbouring studs, so rotated parts are handled. Returns `None` if no
+    /// studs are given or the studs do not form a sufficiently complete grid.
+    #[must_use]
+    pub fn infer(studs: &[Stud]) -> Option<Self> {
+        match studs.len() {
+            0 => return None,
+            1 => {
+                return Some(Self {
+                    width: 1,
+                    length: 1,
+                })
+            }
+            _ => {}
+        }
+
+        // Nearest-neighbour vectors give both the pitch and the grid angle
+        let mut distances = Vec::with_capacity(studs.len());
+        let (mut sin_sum, mut cos_sum) = (0.0f32, 0.0f32);
+        for (i, a) in studs.iter().enumerate() {
+            let nearest = studs
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, b)| (b.x - a.x, b.y - a.y))
+                .min_by(|p, q| p.0.hypot(p.1).total_cmp(&q.0.hypot(q.1)))?;
+            distances.push(nearest.0.hypot(nearest.1));
+            // Quadruple the angle so directions 90° apart reinforce each other
+            let angle = 4.0 * nearest.1.atan2(nearest.0);
+            sin_sum += angle.sin();
+            cos_sum += angle.cos();
+        }
+        distances.sort_by(f32::total_cmp);
+        let pitch = distances[distances.len() / 2];
+        let theta = sin_sum.atan2(cos_sum) / 4.0;
+
+        let (sin, cos) = theta.sin_cos();
+        let along: Vec<f32> = studs.iter().map(|s| s.x * cos + s.y * sin).collect();
+        let across: Vec<f32> = studs.iter().map(|s| -s.x * sin + s.y * cos).collect();
+        let columns = lattice_indices(&along, pitch)?;
+        let rows = lattice_indices(&across, pitch)?;
+
+        // Every stud must occupy its own grid cell
+        let mut cells: Vec<(u32, u32)> =
+            columns.iter().copied().zip(rows.iter().copied()).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        if cells.len() != studs.len() {
+            debug!("Several studs fall into the same grid cell, ignoring");
+            return None;
+        }
+
+        let column_count = columns.iter().max()? + 1;
+        let row_count = rows.iter().max()? + 1;
+
+        // Allow a few studs to be missed, but not so many the grid is a guess
+        if (studs.len() as u32) * 4 < column_count * row_count * 3 {
+            debug!(
+                "{} studs do not fill a {}x{} grid, ignoring",
+                studs.len(),
+                column_count,
+                row_count
+            );
+            return None;
+        }
+
+        Some(Self {
+            width: column_count.min(row_count),
+            length: column_count.max(row_count),
+        })
+    }
+}
+
+/// Assign projected stud positions to grid lines spaced `pitch` apart
+///
+/// Returns `None` if a position lies too far between two grid lines.
+fn lattice_indices(positions: &[f32], pitch: f32) -> Option<Vec<u32>> {
+    let origin = positions.iter().copied().min_by(f32::total_cmp)?;
+    positions
+        .iter()
+        .map(|position| {
+            let offset = (position - origin) / pitch;
+            let index = offset.round();
+            ((offset - index).abs() <= 0.3).then_some(index as u32)
+        })
+        .collect()
+}
+
+/// Height class of a part, measured in plates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeightClass {
+    /// One plate high (3.2 mm), e.g. 3020
+    Plate,
+    /// Three plates high (9.6 mm), e.g. 3001
+    Brick,
+}
+
+impl HeightClass {
+    /// Height of the part body in plates
+    #[must_use]
+    pub fn plates(self) -> u32 {
+        match self {
+            HeightClass::Plate => 1,
+            HeightClass::Brick => 3,
+        }
+    }
+
+    /// Classify the height of a part from its outline in a side view
+    ///
+    /// # Arguments
+    /// * `profile` - Bounding box of the part in the side view, studs included
+    /// * `studs_along` - Number of studs along the visible side
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::processing::{BoundingBox, HeightClass};
+    ///
+    /// // A 2x4 plate seen from its long side: 32 mm wide, 4.9 mm tall
+    /// let profile = BoundingBox { x: 0, y: 0, width: 320, height: 49 };
+    /// assert_eq!(HeightClass::from_side_view(profile, 4), HeightClass::Plate);
+    /// ```
+    #[must_use]
+    pub fn from_side_view(profile: BoundingBox, studs_along: u32) -> Self {
+        let pitch = profile.width as f32 / studs_along.max(1) as f32;
+        let ratio = profile.height as f32 / pitch;
+        debug!("Side view height/pitch ratio: {:.2}", ratio);
+
+        if ratio < PLATE_BRICK_RATIO_CUTOFF {
+            HeightClass::Plate
+        } else {
+            HeightClass::Brick
+        }
+    }
+}
+
+/// Configuration for stud detection
+#[derive(Debug, Clone)]
+pub struct StudDetectorConfig {
+    /// Minimum fraction of a stud outline that must be supported by edges
+    pub min_score: f32,
+    /// Canny low threshold
+    pub canny_low: f32,
+    /// Canny high threshold
+    pub canny_high: f32,
+}
+
+impl Default for StudDetectorConfig {
+    fn default() -> Self {
+        Self {
+            min_score: 0.4,
+            canny_low: 20.0,
+            canny_high: 60.0,
+        }
+    }
+}
+
+/// Finds circular stud tops in a top-down view of a part
+///
+/// Edge pixels inside the part vote along their gradient direction for
+/// circle centers at each candidate radius (a gradient-directed Hough
+/// transform). Peaks are kept if enough of the outline supports them and
+/// the detected studs share a common radius.
+#[derive(Debug, Clone, Default)]
+pub struct StudDetector {
+    config: StudDetectorConfig,
+}
+
+impl StudDetector {
+    /// Create a new `StudDetector` with the given configuration
+    #[must_use]
+    pub fn new(config: StudDetectorConfig) -> Self {
+        Self {
+            config,
+        }
+    }
+
+    /// Detect studs in a cropped region
+    ///
+    /// Regions larger than `MAX_WORKING_SIDE` are scaled down for the search,
+    /// and the studs found are mapped back to the region's coordinates.
+    ///
+    /// # Arguments
+    /// * `img` - The region cropped out of the source image
+    /// * `mask` - Foreground mask of the region, same size as `img`
+    #[must_use]
+    pub fn detect(&self, img: &RgbImage, mask: &GrayImage) -> Vec<Stud> {
+        let (width, height) = img.dimensions();
+        let longest = width.max(height);
+        if longest <= MAX_WORKING_SIDE {
+            return self.search(img, mask);
+        }
+
+        let scale = MAX_WORKING_SIDE as f32 / longest as f32;
+        let working_width = ((width as f32 * scale).round() as u32).max(1);
+        let working_height = ((height as f32 * scale).round() as u32).max(1);
+        debug!(
+            "Scaling {}x{} region to {}x{} for the stud search",
+            width, height, working_width, working_height
+        );
+        let img = image::imageops::resize(img, working_width, working_height, FilterType::Triangle);
+        let mask =
+            image::imageops::resize(mask, working_width, working_height, FilterType::Nearest);
+
+        let (x_scale, y_scale) = (
+            width as f32 / working_width as f32,
+            height as f32 / working_height as f32,
+        );
+        self.search(&img, &mask)
+            .into_iter()
+            .map(|stud| Stud {
+                x: stud.x * x_scale,
+                y: stud.y * y_scale,
+                radius: stud.radius * x_scale.max(y_scale),
+                ..stud
+            })
+            .collect()
+    }
+
+    /// Run the Hough search for studs on a region at its own size
+    fn search(&self, img: &RgbImage, mask: &GrayImage) -> Vec<Stud> {
+        let (width, height) = img.dimensions();
+        let short_side = width.min(height) as f32;
+        let r_min = (short_side / MAX_STUDS_ACROSS * STUD_RADIUS_TO_PITCH).max(3.0);
+        let r_max = short_side * STUD_RADIUS_TO_PITCH;
+        if r_max < r_min {
+            return Vec::new();
+        }
+
+        let gray = image::imageops::grayscale(img);
+        let edges = canny(&gray, self.config.canny_low, self.config.canny_high);
+        let gx = horizontal_sobel(&gray);
+        let gy = vertical_sobel(&gray);
+        // Ignore the part outline itself
+        let interior = imageproc::morphology::erode(mask, Norm::LInf, 3);
+
+        let edge_points: Vec<(f32, f32, f32, f32)> = edges
+            .enumerate_pixels()
+            .filter(|(x, y, e)| e[0] > 0 && interior.get_pixel(*x, *y)[0] > 0)
+            .filter_map(|(x, y, _)| {
+                let dx = f32::from(gx.get_pixel(x, y)[0]);
+                let dy = f32::from(gy.get_pixel(x, y)[0]);
+                let magnitude = dx.hypot(dy);
+                (magnitude > 0.0).then(|| (x as f32, y as f32, dx / magnitude, dy / magnitude))
+            })
+            .collect();
+        debug!(
+            "Stud search: {} edge points, radius {:.0}-{:.0}px",
+            edge_points.len(),
+            r_min,
+            r_max
+        );
+
+        let mut candidates = Vec::new();
+        let (w, h) = (width as usize, height as usize);
+        let mut radius = r_min.floor();
+        while radius <= r_max.ceil() {
+            let mut votes = vec![0u32; w * h];
+            for &(x, y, dx, dy) in &edge_points {
+                // Studs may be lighter or darker than their surroundings
+                for sign in [
+                    -1.0, 1.0,
+                ] {
+                    let cx = (x + sign * radius * dx).round();
+                    let cy = (y + sign * radius * dy).round();
+                    if cx >= 0.0 && cy >= 0.0 && (cx as usize) < w && (cy as usize) < h {
+                        votes[cy as usize * w + cx as usize] += 1;
+                    }
+                }
+            }
+
+            // Gradient direction errors scatter the votes further at larger radii
+            let reach = ((radius / 12.0).round() as usize).max(1);
+            let smoothed = box_sum(&votes, w, h, reach);
+            let circumference = TAU * radius;
+            for cy in 1..h.saturating_sub(1) {
+                for cx in 1..w.saturating_sub(1) {
+                    let value = smoothed[cy * w + cx];
+                    let score = value as f32 / circumference;
+                    if score < self.config.min_score
+                        || interior.get_pixel(cx as u32, cy as u32)[0] == 0
+                    {
+                        continue;
+                    }
+                    let is_peak = (cy - 1..=cy + 1)
+                        .flat_map(|ny| (cx - 1..=cx + 1).map(move |nx| (nx, ny)))
+                        .all(|(nx, ny)| smoothed[ny * w + nx] <= value);
+                    if is_peak {
+                        candidates.push(Stud {
+                            x: cx as f32,
+                            y: cy as f32,
+                            radius,
+                            score: score.min(1.0),
+                        });
+                    }
+                }
+            }
+            radius += 1.0;
+        }
+
+        let studs = Self::suppress(candidates);
+        debug!("Detected {} stud(s)", studs.len());
+        studs
+    }
+
+    /// Keep the strongest non-overlapping candidates that share a common radius
+    fn suppress(mut candidates: Vec<Stud>) -> Vec<Stud> {
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut accepted: Vec<Stud> = Vec::new();
+        for candidate in candidates {
+            let overlaps = accepted.iter().any(|s| {
+                (s.x - candidate.x).hypot(s.y - candidate.y) < s.radius.max(candidate.radius)
+            });
+            if !overlaps {
+                accepted.push(candidate);
+            }
+        }
+
+        if accepted.is_empty() {
+            return accepted;
+        }
+
+        let mut radii: Vec<f32> = accepted.iter().map(|s| s.radius).collect();
+        radii.sort_by(f32::total_cmp);
+        let median = radii[radii.len() / 2];
+        accepted.retain(|s| s.radius >= median * 0.75 && s.radius <= median / 0.75);
+        accepted
+    }
+}
+
+/// Box sum of a vote accumulator over a `(2 * reach + 1)` pixel square
+fn box_sum(votes: &[u32], w: usize, h: usize, reach: usize) -> Vec<u32> {
+    // Summed-area table with a zero row and column in front
+    let mut table = vec![0u32; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0;
+        for x in 0..w {
+            row += votes[y * w + x];
+            table[(y + 1) * (w + 1) + x + 1] = table[y * (w + 1) + x + 1] + row;
+        }
+    }
+
+    let mut out = vec![0u32; w * h];
+    for y in 0..h {
+        let (top, bottom) = (y.saturating_sub(reach), (y + reach + 1).min(h));
+        for x in 0..w {
+            let (left, right) = (x.saturating_sub(reach), (x + reach + 1).min(w));
+            out[y * w + x] = table[bottom * (w + 1) + right] + table[top * (w + 1) + left]
+                - table[top * (w + 1) + right]
+                - table[bottom * (w + 1) + left];
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use image::{Luma, Rgb};
+    use imageproc::drawing::draw_filled_circle_mut;
+    use std::f32::consts::PI;
+
+    /// Draw a top-down view of a part with the given stud layout
+    fn draw_part(columns: u32, rows: u32) -> (RgbImage, GrayImage) {
+        let pitch = 40;
+        let (width, height) = (columns * pitch, rows * pitch);
+        let mut img = RgbImage::from_pixel(
+            width,
+            height,
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        for column in 0..columns {
+            for row in 0..rows {
+                draw_filled_circle_mut(
+                    &mut img,
+                    (
+                        (column * pitch + pitch / 2) as i32,
+                        (row * pitch + pitch / 2) as i32,
+                    ),
+                    12,
+                    Rgb([
+                        240, 90, 70,
+                    ]),
+                );
+            }
+        }
+        let mask = GrayImage::from_pixel(width, height, Luma([255]));
+        (img, mask)
+    }
+
+    fn stud_at(x: f32, y: f32) -> Stud {
+        Stud {
+            x,
+            y,
+            radius: 5.0,
+            score: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_detects_studs_on_2x4() {
+        let (img, mask) = draw_part(4, 2);
+        let studs = StudDetector::default().detect(&img, &mask);
+
+        assert_eq!(studs.len(), 8);
+        for stud in &studs {
+            assert!((stud.radius - 12.0).abs() <= 2.0);
+        }
+        assert_eq!(
+            StudGrid::infer(&studs),
+            Some(StudGrid {
+                width: 2,
+                length: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_large_region_is_searched_scaled_down() {
+        // The 2x4 part photographed up close: 1600x800 pixels
+        let (img, mask) = draw_part(4, 2);
+        let img = image::imageops::resize(&img, 1600, 800, FilterType::Nearest);
+        let mask = image::imageops::resize(&mask, 1600, 800, FilterType::Nearest);
+        let studs = StudDetector::default().detect(&img, &mask);
+
+        assert_eq!(studs.len(), 8);
+        for stud in &studs {
+            // Studs are reported in the region's own coordinates
+            assert!((stud.radius - 120.0).abs() <= 20.0);
+            assert!(((stud.x - 200.0) % 400.0).abs() <= 20.0);
+        }
+        assert_eq!(
+            StudGrid::infer(&studs),
+            Some(StudGrid {
+                width: 2,
+                length: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_detects_studs_on_2x2() {
+        let (img, mask) = draw_part(2, 2);
+        let studs = StudDetector::default().detect(&img, &mask);
+
+        assert_eq!(
+            StudGrid::infer(&studs),
+            Some(StudGrid {
+                width: 2,
+                length: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_studs_on_tile() {
+        let img = RgbImage::from_pixel(
+            80,
+            80,
+            Rgb([
+                201, 26, 9,
+            ]),
+        );
+        let mask = GrayImage::from_pixel(80, 80, Luma([255]));
+        assert!(StudDetector::default().detect(&img, &mask).is_empty());
+    }
+
+    #[test]
+    fn test_grid_inference_handles_rotation() {
+        // A 1x3 row of studs rotated by 30 degrees
+        let (sin, cos) = (PI / 6.0).sin_cos();
+        let studs: Vec<Stud> = (0..3)
+            .map(|i| stud_at(100.0 + i as f32 * 20.0 * cos, 100.0 + i as f32 * 20.0 * sin))
+            .collect();
+        assert_eq!(
+            StudGrid::infer(&studs),
+            Some(StudGrid {
+                width: 1,
+                length: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_grid_inference_rejects_scattered_studs() {
+        let studs = vec![
+            stud_at(0.0, 0.0),
+            stud_at(10.0, 0.0),
+            stud_at(100.0, 37.0),
+            stud_at(50.0, 80.0),
+        ];
+        assert_eq!(StudGrid::infer(&studs), None);
+    }
+
+    #[test]
+    fn test_height_class_from_side_view() {
+        // 2x4 brick from its long side: 32 mm wide, 11.3 mm tall with studs
+        let brick = BoundingBox {
+            x: 0,
+            y: 0,
+            width: 320,
+            height: 113,
+        };
+        assert_eq!(HeightClass::from_side_view(brick, 4), HeightClass::Brick);
+
+        let plate = BoundingBox {
+            height: 49,
+            ..brick
+        };
+        assert_eq!(HeightClass::from_side_view(plate, 4), HeightClass::Plate);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::error::{Result, StudFinderError};
+use crate::processing::segment::Segmenter;
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use imageproc::template_matching::{find_extremes, match_template, MatchTemplateMethod};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Length in pixels of the longest side of a normalized silhouette
+const CANONICAL_SIZE: u32 = 32;
+
+/// Background margin around the normalized region silhouette
+const REGION_MARGIN: u32 = 8;
+
+/// Background margin around each template silhouette
+///
+/// Without a margin a solid template would score perfectly anywhere inside
+/// a larger solid region.
+const TEMPLATE_MARGIN: u32 = 4;
+
+/// Template scales tried relative to the region
+const SCALES: [f32; 3] = [
+    0.9, 1.0, 1.1,
+];
+
+/// Image file extensions considered when loading templates
+const TEMPLATE_EXTENSIONS: [&str; 5] = [
+    "png", "jpg", "jpeg", "bmp", "gif",
+];
+
+/// A reference silhouette for a single part
+#[derive(Debug, Clone)]
+pub struct Template {
+    /// LEGO part number the template depicts
+    pub part_number: String,
+    /// Normalized silhouette (255 = part, 0 = background)
+    silhouette: GrayImage,
+}
+
+/// A set of reference templates loaded from a directory
+///
+/// Template files are named after the part they depict, e.g. `3001.png`.
+/// Several views of the same part may be supplied by adding a suffix after
+/// an underscore, e.g. `3001_side.png`.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateLibrary {
+    templates: Vec<Template>,
+}
+
+impl TemplateLibrary {
+    /// Load every template image from a directory
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemplateMatchingFailed` if the directory cannot be read or
+    /// contains no usable template images.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        debug!("Loading templates from: {}", dir.display());
+
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            StudFinderError::TemplateMatchingFailed(format!(
+                "cannot read template directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        TEMPLATE_EXTENSIONS
+                            .iter()
+                            .any(|known| known.eq_ignore_ascii_case(ext))
+                    })
+            })
+            .collect();
+        paths.sort();
+
+        let segmenter = Segmenter::default();
+        let mut templates = Vec::with_capacity(paths.len());
+        for path in paths {
+            let Some(part_number) = Self::part_number_from_path(&path) else {
+                continue;
+            };
+            match image::open(&path) {
+                Ok(img) => {
+                    // Use the largest foreground region as the part silhouette
+                    let Some(region) = segmenter.segment(&img).into_iter().max_by_key(|r| r.area)
+                    else {
+                        continue;
+                    };
+                    templates.push(Template {
+                        part_number,
+                        silhouette: normalize_orientation(&region.mask),
+                    });
+                }
+                Err(e) => warn!("Skipping unreadable template {}: {}", path.display(), e),
+            }
+        }
+
+        if templates.is_empty() {
+            return Err(StudFinderError::TemplateMatchingFailed(format!(
+                "no templates found in {}",
+                dir.display()
+            )));
+        }
+
+        info!(
+            "Loaded {} template(s) from {}",
+            templates.len(),
+            dir.display()
+        );
+        Ok(Self {
+            templates,
+        })
+    }
+
+    /// Number of loaded templates
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    /// Whether no templates are loaded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Find the template that best matches a region silhouette
+    ///
+    /// The region is normalized for rotation (principal axis) and scale,
+    /// then compared against every template at each quarter-turn rotation
+    /// and each candidate scale using normalized cross-correlation.
+    ///
+    /// # Returns
+    ///
+    /// The best matching part number and its score (0.0-1.0)
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemplateMatchingFailed` if no templates are loaded or the
+    /// silhouette is empty.
+    pub fn best_match(&self, mask: &GrayImage) -> Result<(String, f32)> {
+        self.rank(mask)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StudFinderError::TemplateMatchingFailed("no match".to_string()))
+    }
+
+    /// Score a region silhouette against every part, best match first
+    ///
+    /// Parts with several templates (e.g. `3001.png` and `3001_side.png`)
+    /// are listed once, with their best score.
+    ///
+    /// # Returns
+    ///
+    /// Part numbers and their scores (0.0-1.0), highest score first
+    ///
+    /// # Errors
+    ///
+    /// Returns `TemplateMatchingFailed` if no templates are loaded or the
+    /// silhouette is empty.
+    pub fn rank(&self, mask: &GrayImage) -> Result<Vec<(String, f32)>> {
+        if self.templates.is_empty() {
+            return Err(StudFinderError::TemplateMatchingFailed(
+                "no templates loaded".to_string(),
+            ));
+        }
+
+        let silhouette = normalize_orientation(mask);
+        if silhouette.width() == 0 || silhouette.height() == 0 {
+            return Err(StudFinderError::TemplateMatchingFailed(
+                "region silhouette is empty".to_string(),
+            ));
+        }
+        let canvas = pad(&scale_to(&silhouette, CANONICAL_SIZE as f32), REGION_MARGIN);
+
+        let mut ranked: Vec<(String, f32)> = Vec::new();
+        for template in &self.templates {
+            let score = Self::score(&canvas, &template.silhouette);
+            debug!("Template {} scored {:.3}", template.part_number, score);
+            match ranked
+                .iter_mut()
+                .find(|(part_number, _)| *part_number == template.part_number)
+            {
+                Some((_, best)) => *best = best.max(score),
+                None => ranked.push((template.part_number.clone(), score)),
+            }
+        }
+
+        // Stable, so equal scores keep the templates' order
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(ranked)
+    }
+
+    /// Best normalized cross-correlation of a template against a region canvas
+    fn score(canvas: &GrayImage, silhouette: &GrayImage) -> f32 {
+        let rotations = [
+            silhouette.clone(),
+            imageops::rotate90(silhouette),
+            imageops::rotate180(silhouette),
+            imageops::rotate270(silhouette),
+        ];
+
+        let mut best = 0.0f32;
+        for rotated in &rotations {
+            for scale in SCALES {
+                let candidate = pad(
+                    &scale_to(rotated, CANONICAL_SIZE as f32 * scale),
+                    TEMPLATE_MARGIN,
+                );
+                if candidate.width() > canvas.width() || candidate.height() > canvas.height() {
+                    continue;
+                }
+                let scores = match_template(
+                    canvas,
+                    &candidate,
+                    MatchTemplateMethod::CrossCorrelationNormalized,
+                );
+                best = best.max(find_extremes(&scores).max_value);
+            }
+        }
+        best.clamp(0.0, 1.0)
+    }
+
+    /// Derive the part number from a template file name
+    fn part_number_from_path(path: &Path) -> Option<String> {
+        let stem = path.file_stem()?.to_str()?;
+        let part_number = stem.split('_').next()?.trim();
+        (!part_number.is_empty()).then(|| part_number.to_string())
+    }
+}
+
+/// Rotate a silhouette so its principal axis is horizontal and crop it to its foreground
+fn normalize_orientation(mask: &GrayImage) -> GrayImage {
+    let mut count = 0.0f64;
+    let (mut sum_x, mut sum_y) = (0.0f64, 0.0f64);
+    for (x, y, p) in mask.enumerate_pixels() {
+        if p[0] > 0 {
+            count += 1.0;
+            sum_x += f64::from(x);
+            sum_y += f64::from(y);
+        }
+    }
+    if count == 0.0 {
+        return GrayImage::new(0, 0);
+    }
+
+    let (cx, cy) = (sum_x / count, sum_y / count);
+    let (mut mu20, mut mu02, mut mu11) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y, p) in mask.enumerate_pixels() {
+        if p[0] > 0 {
+            let dx = f64::from(x) - cx;
+            let dy = f64::from(y) - cy;
+            mu20 += dx * dx;
+            mu02 += dy * dy;
+            mu11 += dx * dy;
+        }
+    }
+    let theta = 0.5 * (2.0 * mu11).atan2(mu20 - mu02);
+
+    // Pad to the diagonal so the rotation cannot clip the silhouette
+    let diagonal = f64::from(mask.width())
+        .hypot(f64::from(mask.height()))
+        .ceil() as u32;
+    let mut square = GrayImage::new(diagonal, diagonal);
+    imageops::overlay(
+        &mut square,
+        mask,
+        i64::from((diagonal - mask.width()) / 2),
+        i64::from((diagonal - mask.height()) / 2),
+    );
+
+    let rotated = rotate_about_center(&square, -theta as f32, Interpolation::Nearest, Luma([0]));
+    crop_to_foreground(&rotated)
+}
+
+/// Crop a mask to the bounding box of its foreground pixels
+fn crop_to_foreground(mask: &GrayImage) -> GrayImage {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, p) in mask.enumerate_pixels() {
+        if p[0] > 0 {
+            let b = bounds.get_or_insert((x, y, x, y));
+            b.0 = b.0.min(x);
+            b.1 = b.1.min(y);
+            b.2 = b.2.max(x);
+            b.3 = b.3.max(y);
+        }
+    }
+    match bounds {
+        Some((min_x, min_y, max_x, max_y)) => {
+            imageops::crop_imm(mask, min_x, min_y, max_x - min_x + 1, max_y - min_y + 1).to_image()
+        }
+        None => GrayImage::new(0, 0),
+    }
+}
+
+/// Resize a silhouette so its longest side is `size` pixels
+fn scale_to(mask: &GrayImage, size: f32) -> GrayImage {
+    let longest = mask.width().max(mask.height()).max(1) as f32;
+    let factor = size / longest;
+    let width = ((mask.width() as f32 * factor).round() as u32).max(1);
+    let height = ((mask.height() as f32 * factor).round() as u32).max(1);
+    imageops::resize(mask, width, height, FilterType::Nearest)
+}
+
+/// Surround a silhouette with a background margin
+fn pad(mask: &GrayImage, margin: u32) -> GrayImage {
+    let mut padded = GrayImage::new(mask.width() + 2 * margin, mask.height() + 2 * margin);
+    imageops::overlay(&mut padded, mask, i64::from(margin), i64::from(margin));
+    padded
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    fn rect_mask(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_pixel(width, height, Luma([255]))
+    }
+
+    fn save_template(dir: &Path, name: &str, width: u32, height: u32) {
+        let mut img = RgbImage::from_pixel(
+            width + 40,
+            height + 40,
+            Rgb([
+                255, 255, 255,
+            ]),
+        );
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at(20, 20).of_size(width, height),
+            Rgb([
+                108, 110, 104,
+            ]),
+        );
+        img.save(dir.join(name)).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_orientation_aligns_major_axis() {
+        let normalized = normalize_orientation(&rect_mask(20, 60));
+        assert!(normalized.width() > normalized.height());
+    }
+
+    #[test]
+    fn test_best_match_prefers_matching_shape() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        save_template(temp_dir.path(), "3001.png", 160, 80);
+        save_template(temp_dir.path(), "3003.png", 80, 80);
+        save_template(temp_dir.path(), "3008_top.png", 320, 40);
+
+        let library = TemplateLibrary::load(temp_dir.path()).unwrap();
+        assert_eq!(library.len(), 3);
+
+        // A 2x4 brick lying sideways at a different scale
+        let (part, score) = library.best_match(&rect_mask(30, 62)).unwrap();
+        assert_eq!(part, "3001");
+        assert!(score > 0.9);
+
+        let (part, _) = library.best_match(&rect_mask(50, 50)).unwrap();
+        assert_eq!(part, "3003");
+
+        let (part, _) = library.best_match(&rect_mask(200, 25)).unwrap();
+        assert_eq!(part, "3008");
+
+        let ranked = library.rank(&rect_mask(30, 62)).unwrap();
+        let parts: Vec<&str> = ranked.iter().map(|(part, _)| part.as_str()).collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], "3001");
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn test_load_fails_without_templates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        match TemplateLibrary::load(temp_dir.path()) {
+            Err(StudFinderError::TemplateMatchingFailed(_)) => {}
+            other => panic!("Expected TemplateMatchingFailed, got: {:?}", other),
+        }
+
+        let empty = TemplateLibrary::default();
+        assert!(empty.best_match(&rect_mask(10, 10)).is_err());
+    }
+}
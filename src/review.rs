@@ -0,0 +1,330 @@
+//! Interactive review of the detections queued between the thresholds
+//!
+//! Each queued detection is shown with the palette colors closest to its
+//! measured color. The reviewer accepts it as detected, picks one of those
+//! colors, types a correction, discards it or leaves it for later.
+
+use anyhow::Result;
+use std::io::{BufRead, Write};
+use studfinder::storage::PendingReview;
+use studfinder::StudFinder;
+
+/// What the reviewer did with the queue
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReviewSummary {
+    /// Added to the inventory as detected
+    pub accepted: usize,
+    /// Added to the inventory with a different part number or color
+    pub corrected: usize,
+    /// Removed from the queue
+    pub discarded: usize,
+    /// Left in the queue
+    pub skipped: usize,
+}
+
+impl std::fmt::Display for ReviewSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Review finished. Accepted: {}, Corrected: {}, Discarded: {}, Skipped: {}",
+            self.accepted, self.corrected, self.discarded, self.skipped
+        )
+    }
+}
+
+/// A reviewer's answer to one prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Accept,
+    Alternative(usize),
+    Correct,
+    Discard,
+    Skip,
+    Quit,
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "a" | "accept" => Ok(Action::Accept),
+            "c" | "correct" => Ok(Action::Correct),
+            "d" | "discard" => Ok(Action::Discard),
+            "s" | "skip" | "" => Ok(Action::Skip),
+            "q" | "quit" => Ok(Action::Quit),
+            other => other
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .map(Action::Alternative)
+                .ok_or_else(|| format!("unknown answer '{}'", other)),
+        }
+    }
+}
+
+/// Walk the review queue, reading answers from `input` and prompting on `out`
+///
+/// Stops at the end of the queue, on `q`, or when `input` is exhausted.
+/// Answers that cannot be applied, such as an unknown color, are reported
+/// and asked again.
+///
+/// # Arguments
+/// * `studfinder` - The inventory holding the queue
+/// * `input` - Where answers are read from
+/// * `out` - Where detections and prompts are written
+/// * `top` - Number of alternative colors to offer
+///
+/// # Errors
+///
+/// Returns an error if the queue cannot be read or updated, or `input` or
+/// `out` fail.
+pub fn run_review<R: BufRead, W: Write>(
+    studfinder: &StudFinder,
+    input: &mut R,
+    out: &mut W,
+    top: usize,
+) -> Result<ReviewSummary> {
+    let queue = studfinder.pending_reviews()?;
+    let mut summary = ReviewSummary::default();
+
+    for (index, review) in queue.iter().enumerate() {
+        let alternatives = studfinder.review_alternatives(review, top);
+        describe(out, review, index + 1, queue.len())?;
+        for (n, candidate) in alternatives.iter().enumerate() {
+            writeln!(
+                out,
+                "    {}) {} (delta E {:.1})",
+                n + 1,
+                candidate.name,
+                candidate.delta_e
+            )?;
+        }
+
+        loop {
+            let choices = match alternatives.len() {
+                0 => String::new(),
+                1 => "[1] pick color, ".to_string(),
+                n => format!("[1-{}] pick color, ", n),
+            };
+            write!(
+                out,
+                "[a]ccept, {}[c]orrect, [d]iscard, [s]kip, [q]uit: ",
+                choices
+            )?;
+            out.flush()?;
+
+            let Some(answer) = read_answer(input)? else {
+                writeln!(out)?;
+                return Ok(summary);
+            };
+            let action = match answer.parse::<Action>() {
+                Ok(Action::Alternative(n)) if n > alternatives.len() => {
+                    writeln!(out, "No alternative {}", n)?;
+                    continue;
+                }
+                Ok(action) => action,
+                Err(message) => {
+                    writeln!(out, "{}", message)?;
+                    continue;
+                }
+            };
+
+            let result = match action {
+                Action::Accept => studfinder.accept_review(&review.id, None, None),
+                Action::Alternative(n) => {
+                    let color = &alternatives[n - 1].name;
+                    studfinder.accept_review(&review.id, None, Some(color))
+                }
+                Action::Correct => {
+                    let Some(part) = ask(input, out, "Part number", &review.part_number)? else {
+                        return Ok(summary);
+                    };
+                    let Some(color) = ask(input, out, "Color", &review.color)? else {
+                        return Ok(summary);
+                    };
+                    studfinder.accept_review(&review.id, Some(&part), Some(&color))
+                }
+                Action::Discard => {
+                    studfinder.discard_review(&review.id)?;
+                    summary.discarded += 1;
+                    break;
+                }
+                Action::Skip => {
+                    summary.skipped += 1;
+                    break;
+                }
+                Action::Quit => return Ok(summary),
+            };
+
+            match result {
+                Ok(piece) => {
+                    if piece.part_number == review.part_number && piece.color == review.color {
+                        summary.accepted += 1;
+                    } else {
+                        summary.corrected += 1;
+                    }
+                    writeln!(
+                        out,
+                        "Added {} {} to {}",
+                        piece.color, piece.part_number, piece.id
+                    )?;
+                    break;
+                }
+                Err(studfinder::error::StudFinderError::InvalidPiece(message)) => {
+                    writeln!(out, "Invalid piece: {}", message)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Print a queued detection
+fn describe<W: Write>(
+    out: &mut W,
+    review: &PendingReview,
+    position: usize,
+    total: usize,
+) -> std::io::Result<()> {
+    let bounds = review.bounds;
+    writeln!(
+        out,
+        "\n[{}/{}] {} {} ({}), {:.1}% confidence",
+        position,
+        total,
+        review.color,
+        review.part_number,
+        review.category,
+        review.confidence * 100.0
+    )?;
+    writeln!(
+        out,
+        "  {} at ({}, {}) {}x{}",
+        review.image_path, bounds.x, bounds.y, bounds.width, bounds.height
+    )?;
+    match review.measured_rgb {
+        Some([r, g, b]) => writeln!(
+            out,
+            "  measured #{:02x}{:02x}{:02x}, closest colors:",
+            r, g, b
+        ),
+        None => writeln!(out, "  color not measured"),
+    }
+}
+
+/// Prompt for a value, keeping `current` on an empty answer
+///
+/// Returns `None` when `input` is exhausted.
+fn ask<R: BufRead, W: Write>(
+    input: &mut R,
+    out: &mut W,
+    label: &str,
+    current: &str,
+) -> Result<Option<String>> {
+    write!(out, "{} [{}]: ", label, current)?;
+    out.flush()?;
+    Ok(read_answer(input)?.map(|answer| {
+        if answer.is_empty() {
+            current.to_string()
+        } else {
+            answer
+        }
+    }))
+}
+
+/// Read one trimmed line, or `None` at end of input
+fn read_answer<R: BufRead>(input: &mut R) -> std::io::Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use studfinder::{Config, ExportFormat, ProcessorType, ScanQuality};
+
+    #[test]
+    fn test_action_from_str() {
+        assert_eq!("A".parse::<Action>(), Ok(Action::Accept));
+        assert_eq!("".parse::<Action>(), Ok(Action::Skip));
+        assert_eq!("2".parse::<Action>(), Ok(Action::Alternative(2)));
+        assert!("0".parse::<Action>().is_err());
+        assert!("x".parse::<Action>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_loop() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // Everything detected lands in the review queue
+        let finder = StudFinder::new(Config {
+            database_path: temp_dir.path().join("test.db"),
+            export_format: ExportFormat::Json,
+            scan_quality: ScanQuality::Fast,
+            processor_type: ProcessorType::Scanner,
+            confidence_threshold: 1.0,
+            review_threshold: 0.0,
+            template_dir: PathBuf::from("templates"),
+            calibration_profile: None,
+        })
+        .unwrap();
+        finder.init().unwrap();
+
+        let mut image_paths = Vec::new();
+        for name in [
+            "a.png", "b.png", "c.png", "d.png",
+        ] {
+            let path = temp_dir.path().join(name);
+            image::RgbImage::from_pixel(
+                120,
+                120,
+                image::Rgb([
+                    185, 40, 30,
+                ]),
+            )
+            .save(&path)
+            .unwrap();
+            image_paths.push(path);
+        }
+        for path in &image_paths {
+            assert!(finder.scan_image(path.clone()).await.unwrap().is_empty());
+        }
+        assert_eq!(finder.pending_reviews().unwrap().len(), 4);
+
+        // Accept, pick the second alternative, retry a bad color, then discard
+        let mut input = "a\n2\nc\n3001\nPuce\nc\n3001\nBlue\nd\n".as_bytes();
+        let mut out = Vec::new();
+        let summary = run_review(&finder, &mut input, &mut out, 3).unwrap();
+
+        assert_eq!(
+            summary,
+            ReviewSummary {
+                accepted: 1,
+                corrected: 2,
+                discarded: 1,
+                skipped: 0,
+            }
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("2) Red"), "{}", out);
+        assert!(
+            out.contains("Invalid piece: unknown color 'Puce'"),
+            "{}",
+            out
+        );
+        assert!(finder.pending_reviews().unwrap().is_empty());
+
+        let inventory = finder.list_inventory().unwrap();
+        let mut colors: Vec<&str> = inventory.iter().map(|p| p.color.as_str()).collect();
+        colors.sort_unstable();
+        assert_eq!(colors, vec!["Blue", "Red", "Rust"]);
+        assert!(inventory.iter().all(|p| p.confidence == 1.0));
+    }
+}
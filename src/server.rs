@@ -0,0 +1,726 @@
+//! JSON REST API served by `studfinder serve`
+//!
+//! | Method   | Path                | Does                                             |
+//! |----------|---------------------|--------------------------------------------------|
+//! | `GET`    | `/health`           | Report that the server is up                     |
+//! | `POST`   | `/scan`             | Scan an uploaded image (multipart `image`, optional `side_view`) |
+//! | `GET`    | `/inventory`        | List pieces, filtered like `inventory list`      |
+//! | `GET`    | `/inventory/export` | Download the inventory as `?format=` json, csv, rebrickable or bricklink |
+//! | `GET`    | `/inventory/{id}`   | Show an entry and its observations               |
+//! | `PATCH`  | `/inventory/{id}`   | Change an entry's quantity, part number or color |
+//! | `DELETE` | `/inventory/{id}`   | Remove an entry                                  |
+//!
+//! Responses use the same JSON shapes as `--output json`. Failures are
+//! `{"error": {"kind": …, "message": …}}` with the status code from
+//! `StudFinderError::http_status`, or 400 for a malformed request.
+
+use crate::output::{ExplainReport, ExplainedDetection, PieceDetails, PieceRow, Status};
+use axum::extract::multipart::MultipartRejection;
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use studfinder::error::StudFinderError;
+use studfinder::storage::{ExportOptions, PieceFilter, PieceSort};
+use studfinder::{BrickLinkList, ExportFormat, PieceCondition, StudFinder};
+use tracing::{debug, info, warn};
+
+/// Largest request body accepted, so that full-size photos can be uploaded
+const MAX_UPLOAD_BYTES: usize = 32 * 1024 * 1024;
+
+/// Shared state of every handler
+type AppState = Arc<StudFinder>;
+
+/// A failed request, written as a JSON error body
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    kind: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    /// A request the server could not make sense of
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            kind: "invalid_request",
+            message: message.into(),
+        }
+    }
+
+    /// A failure outside `StudFinder`, such as a panicked worker
+    fn internal(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            kind: "unexpected",
+            message: message.into(),
+        }
+    }
+}
+
+impl From<StudFinderError> for ApiError {
+    fn from(error: StudFinderError) -> Self {
+        Self {
+            status: StatusCode::from_u16(error.http_status())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            kind: &'a str,
+            message: &'a str,
+        }
+        #[derive(Serialize)]
+        struct Envelope<'a> {
+            error: Body<'a>,
+        }
+
+        if self.status.is_server_error() {
+            warn!("Request failed: {}", self.message);
+        } else {
+            debug!("Request rejected: {}", self.message);
+        }
+        let envelope = Envelope {
+            error: Body {
+                kind: self.kind,
+                message: &self.message,
+            },
+        };
+        (self.status, Json(envelope)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// Build the API routes around a `StudFinder`
+pub fn router(studfinder: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/scan", post(scan))
+        .route("/inventory", get(list_inventory))
+        .route("/inventory/export", get(export_inventory))
+        .route(
+            "/inventory/{id}",
+            get(show_piece).patch(edit_piece).delete(remove_piece),
+        )
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES))
+        .with_state(studfinder)
+}
+
+/// Serve the API until Ctrl-C is pressed
+///
+/// # Errors
+///
+/// Returns an error if the address cannot be bound or the server fails.
+pub async fn serve(studfinder: StudFinder, bind: SocketAddr) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    info!("Listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, router(Arc::new(studfinder)))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Shutting down");
+        })
+        .await?;
+    Ok(())
+}
+
+/// Run a database call off the async workers
+async fn blocking<T, F>(studfinder: &AppState, call: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&StudFinder) -> studfinder::error::Result<T> + Send + 'static,
+{
+    let studfinder = Arc::clone(studfinder);
+    tokio::task::spawn_blocking(move || call(&studfinder))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(ApiError::from)
+}
+
+async fn health() -> Json<Status> {
+    Json(Status::ok("studfinder is running"))
+}
+
+/// An uploaded image, written to a temporary file for the processor
+///
+/// The file is removed when the upload is dropped.
+struct Upload {
+    /// Name the client gave the file, or the form field name
+    name: String,
+    path: PathBuf,
+}
+
+impl Upload {
+    /// Store image bytes under a name with the extension of their format
+    async fn save(name: String, bytes: &[u8]) -> ApiResult<Self> {
+        let format = image::guess_format(bytes).map_err(|_| {
+            ApiError::from(StudFinderError::UnsupportedFormat(format!(
+                "{} is not a recognized image",
+                name
+            )))
+        })?;
+        let extension = format.extensions_str().first().copied().unwrap_or("img");
+        let path = std::env::temp_dir().join(format!(
+            "studfinder-upload-{}.{}",
+            uuid::Uuid::new_v4(),
+            extension
+        ));
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| ApiError::from(StudFinderError::Io(e)))?;
+        Ok(Self {
+            name,
+            path,
+        })
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Could not remove upload {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// `POST /scan`: scan the `image` part of a multipart upload
+///
+/// An optional `side_view` part is used to measure the piece's height.
+/// Detected pieces are stored or queued for review exactly as by
+/// `studfinder scan`; the response lists every detection with its band and
+/// candidates.
+async fn scan(
+    State(studfinder): State<AppState>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> ApiResult<Json<ExplainReport>> {
+    let mut multipart = multipart.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let mut image = None;
+    let mut side_view = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::bad_request(e.body_text()))?
+    {
+        let field_name = field.name().unwrap_or_default().to_string();
+        let name = field.file_name().unwrap_or(&field_name).to_string();
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|e| ApiError::bad_request(e.body_text()))?;
+        match field_name.as_str() {
+            "image" => image = Some(Upload::save(name, &bytes).await?),
+            "side_view" => side_view = Some(Upload::save(name, &bytes).await?),
+            other => {
+                return Err(ApiError::bad_request(format!(
+                    "unexpected field '{}' (expected image and side_view)",
+                    other
+                )))
+            }
+        }
+    }
+    let image = image.ok_or_else(|| ApiError::bad_request("missing field 'image'"))?;
+    info!("Scanning uploaded image {}", image.name);
+
+    let policy = studfinder.review_policy();
+    let detections = studfinder
+        .scan_image_detections(
+            image.path.clone(),
+            side_view.as_ref().map(|upload| upload.path.clone()),
+        )
+        .await?
+        .into_iter()
+        .map(|detection| ExplainedDetection {
+            band: policy.band_for(&detection.piece),
+            detection,
+        })
+        .collect();
+
+    Ok(Json(ExplainReport {
+        path: PathBuf::from(&image.name),
+        detections,
+    }))
+}
+
+/// Query string of `GET /inventory`, named like the `inventory list` options
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InventoryQuery {
+    part: Option<String>,
+    color: Option<String>,
+    category: Option<String>,
+    min_confidence: Option<f32>,
+    sort: Option<String>,
+    #[serde(default)]
+    desc: bool,
+    limit: Option<usize>,
+}
+
+impl InventoryQuery {
+    /// Check the values and turn them into a filter
+    fn into_filter(self) -> ApiResult<PieceFilter> {
+        if let Some(min_confidence) = self.min_confidence {
+            if !(0.0..=1.0).contains(&min_confidence) {
+                return Err(ApiError::bad_request(format!(
+                    "min_confidence is {}, expected 0.0-1.0",
+                    min_confidence
+                )));
+            }
+        }
+        let sort = self
+            .sort
+            .as_deref()
+            .map(str::parse::<PieceSort>)
+            .transpose()
+            .map_err(ApiError::bad_request)?;
+        Ok(PieceFilter {
+            part_number: self.part,
+            color: self.color,
+            category: self.category,
+            min_confidence: self.min_confidence,
+            sort,
+            descending: self.desc,
+            limit: self.limit,
+        })
+    }
+}
+
+/// `GET /inventory`
+async fn list_inventory(
+    State(studfinder): State<AppState>,
+    query: Result<Query<InventoryQuery>, QueryRejection>,
+) -> ApiResult<Json<Vec<PieceRow>>> {
+    let Query(query) = query.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let filter = query.into_filter()?;
+    let rows = blocking(&studfinder, move |studfinder| {
+        Ok(studfinder
+            .query_inventory(&filter)?
+            .into_iter()
+            .map(|piece| PieceRow::new(studfinder, piece))
+            .collect())
+    })
+    .await?;
+    Ok(Json(rows))
+}
+
+/// `GET /inventory/{id}`
+async fn show_piece(
+    State(studfinder): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<PieceDetails>> {
+    let details = blocking(&studfinder, move |studfinder| {
+        let piece = studfinder.get_piece(&id)?;
+        Ok(PieceDetails {
+            observations: studfinder.piece_observations(&id)?,
+            piece: PieceRow::new(studfinder, piece),
+        })
+    })
+    .await?;
+    Ok(Json(details))
+}
+
+/// Body of `PATCH /inventory/{id}`; at least one field is required
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PieceEdit {
+    quantity: Option<i32>,
+    part_number: Option<String>,
+    color: Option<String>,
+}
+
+/// `PATCH /inventory/{id}`
+///
+/// A corrected piece may be merged into another entry; the response is the
+/// entry that holds it afterwards.
+async fn edit_piece(
+    State(studfinder): State<AppState>,
+    Path(id): Path<String>,
+    edit: Result<Json<PieceEdit>, JsonRejection>,
+) -> ApiResult<Json<PieceRow>> {
+    let Json(edit) = edit.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    if edit.quantity.is_none() && edit.part_number.is_none() && edit.color.is_none() {
+        return Err(ApiError::bad_request(
+            "nothing to change (expected quantity, part_number or color)",
+        ));
+    }
+
+    let row = blocking(&studfinder, move |studfinder| {
+        let piece = studfinder.update_piece(
+            &id,
+            edit.quantity,
+            edit.part_number.as_deref(),
+            edit.color.as_deref(),
+        )?;
+        Ok(PieceRow::new(studfinder, piece))
+    })
+    .await?;
+    Ok(Json(row))
+}
+
+/// `DELETE /inventory/{id}`
+async fn remove_piece(
+    State(studfinder): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    blocking(&studfinder, move |studfinder| studfinder.remove_piece(&id)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query string of `GET /inventory/export`, named like the `inventory export` options
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExportQuery {
+    format: Option<String>,
+    condition: Option<String>,
+    list: Option<String>,
+}
+
+/// `GET /inventory/export`: the inventory as a file download (JSON by default)
+async fn export_inventory(
+    State(studfinder): State<AppState>,
+    query: Result<Query<ExportQuery>, QueryRejection>,
+) -> ApiResult<Response> {
+    let Query(query) = query.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let format = match query.format.as_deref() {
+        Some(format) => format
+            .parse::<ExportFormat>()
+            .map_err(ApiError::bad_request)?,
+        None => ExportFormat::Json,
+    };
+    let options = ExportOptions {
+        condition: query
+            .condition
+            .as_deref()
+            .map(str::parse::<PieceCondition>)
+            .transpose()
+            .map_err(ApiError::bad_request)?,
+        bricklink_list: query
+            .list
+            .as_deref()
+            .map(str::parse::<BrickLinkList>)
+            .transpose()
+            .map_err(ApiError::bad_request)?
+            .unwrap_or_default(),
+    };
+    let (content_type, extension) = match format {
+        ExportFormat::Json => ("application/json", "json"),
+        ExportFormat::Csv | ExportFormat::Rebrickable => ("text/csv", "csv"),
+        ExportFormat::BrickLinkXml => ("application/xml", "xml"),
+        ExportFormat::LDraw => ("text/plain", "ldr"),
+    };
+
+    let path = std::env::temp_dir().join(format!(
+        "studfinder-export-{}.{}",
+        uuid::Uuid::new_v4(),
+        extension
+    ));
+    let export_path = path.clone();
+    let exported = blocking(&studfinder, move |studfinder| {
+        studfinder.export_inventory_with_options(export_path, Some(format), &options)
+    })
+    .await;
+    let body = match exported {
+        Ok(()) => tokio::fs::read(&path).await.map_err(StudFinderError::Io),
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+    };
+    let _ = tokio::fs::remove_file(&path).await;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"inventory.{}\"", extension),
+            ),
+        ],
+        body?,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use studfinder::{Config, ProcessorType, ScanQuality};
+    use tower::ServiceExt;
+
+    const BOUNDARY: &str = "studfinder-test-boundary";
+
+    fn test_router(dir: &std::path::Path) -> Router {
+        let finder = StudFinder::new(Config {
+            database_path: dir.join("test.db"),
+            export_format: ExportFormat::Json,
+            scan_quality: ScanQuality::Fast,
+            processor_type: ProcessorType::Scanner,
+            confidence_threshold: 0.8,
+            review_threshold: 0.5,
+            template_dir: PathBuf::from("templates"),
+            calibration_profile: None,
+        })
+        .unwrap();
+        finder.init().unwrap();
+        router(Arc::new(finder))
+    }
+
+    /// Send a request, returning the status and the body as JSON
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let json = if bytes.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&bytes).into_owned().into())
+        };
+        (status, json)
+    }
+
+    fn upload(field: &str, bytes: &[u8]) -> Request<Body> {
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"tray.png\"\r\n\
+             Content-Type: image/png\r\n\r\n",
+            BOUNDARY, field
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        Request::post("/scan")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    /// A red 2x2 brick seen from above, studs catching the light
+    fn red_png() -> Vec<u8> {
+        let mut img = image::RgbImage::from_pixel(
+            160,
+            160,
+            image::Rgb([
+                255, 255, 255,
+            ]),
+        );
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut img,
+            imageproc::rect::Rect::at(40, 40).of_size(80, 80),
+            image::Rgb([
+                201, 26, 9,
+            ]),
+        );
+        for (x, y) in [
+            (60, 60),
+            (100, 60),
+            (60, 100),
+            (100, 100),
+        ] {
+            imageproc::drawing::draw_filled_circle_mut(
+                &mut img,
+                (x, y),
+                12,
+                image::Rgb([
+                    240, 90, 70,
+                ]),
+            );
+        }
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut bytes, image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_scan_and_edit_inventory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app = test_router(temp_dir.path());
+
+        let (status, json) = send(&app, Request::get("/health").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["status"], "ok");
+
+        let (status, json) = send(&app, upload("image", &red_png())).await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        assert_eq!(json["path"], "tray.png");
+        assert_eq!(json["detections"][0]["band"], "accept");
+        let id = json["detections"][0]["piece"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let (status, json) = send(
+            &app,
+            Request::get("/inventory?color=red&sort=quantity")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json.as_array().unwrap().len(), 1);
+
+        let (status, json) = send(
+            &app,
+            Request::patch(format!("/inventory/{}", id))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"quantity": 4, "color": "Blue"}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        assert_eq!(json["quantity"], 4);
+        assert_eq!(json["color"], "Blue");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/inventory/export?format=csv")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&bytes).contains(",Blue,"));
+
+        let (status, _) = send(
+            &app,
+            Request::delete(format!("/inventory/{}", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_edit_changes_nothing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app = test_router(temp_dir.path());
+        let (_, json) = send(&app, upload("image", &red_png())).await;
+        let id = json["detections"][0]["piece"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        for body in [
+            r#"{"quantity": 7, "color": "plaid"}"#,
+            r#"{"quantity": 7, "part_number": " "}"#,
+            r#"{"quantity": -1, "color": "Blue"}"#,
+        ] {
+            let (status, json) = send(
+                &app,
+                Request::patch(format!("/inventory/{}", id))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await;
+            assert_eq!(
+                status,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "{}: {}",
+                body,
+                json
+            );
+            assert_eq!(json["error"]["kind"], "invalid_piece");
+        }
+
+        let (status, json) = send(
+            &app,
+            Request::get(format!("/inventory/{}", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        assert_eq!(json["quantity"], 1);
+        assert_eq!(json["color"], "Red");
+        assert_eq!(json["observations"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_statuses() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app = test_router(temp_dir.path());
+
+        let (status, json) = send(
+            &app,
+            Request::get("/inventory/missing")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["kind"], "piece_not_found");
+
+        let (status, json) = send(
+            &app,
+            Request::delete("/inventory/missing")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["error"]["kind"], "piece_not_found");
+
+        for uri in [
+            "/inventory?sort=size",
+            "/inventory?min_confidence=2",
+            "/inventory?colour=red",
+            "/inventory/export?format=pdf",
+        ] {
+            let (status, json) = send(&app, Request::get(uri).body(Body::empty()).unwrap()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", uri, json);
+            assert_eq!(json["error"]["kind"], "invalid_request");
+        }
+
+        let (status, _) = send(
+            &app,
+            Request::patch("/inventory/missing")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, json) = send(&app, upload("image", b"not an image")).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(json["error"]["kind"], "unsupported_format");
+
+        let (status, json) = send(&app, upload("photo", &red_png())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", json);
+
+        let (status, json) = send(
+            &app,
+            Request::get("/inventory/export?format=ldraw")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["error"]["kind"], "export_failed");
+    }
+}
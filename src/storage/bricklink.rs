@@ -0,0 +1,343 @@
+use crate::core::catalog::UNKNOWN_CATEGORY;
+use crate::core::piece::{BrickLinkList, Piece, PieceCondition};
+use crate::error::{Result, StudFinderError};
+use crate::processing::palette;
+use crate::storage::export::ExportOptions;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// BrickLink item type for parts
+const ITEM_TYPE_PART: &str = "P";
+
+/// BrickLink condition code for "any condition" in wanted lists
+const CONDITION_ANY: &str = "X";
+
+/// Root element of a BrickLink XML upload
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename = "INVENTORY")]
+struct Inventory {
+    #[serde(rename = "ITEM", default)]
+    items: Vec<Item>,
+}
+
+/// A single `<ITEM>` of a BrickLink XML upload
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct Item {
+    itemtype: String,
+    itemid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minqty: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    qty: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    condition: Option<String>,
+}
+
+/// Functions for reading and writing BrickLink XML uploads
+///
+/// Both the wanted-list and the store-inventory flavor share the
+/// `<INVENTORY><ITEM>…</ITEM></INVENTORY>` layout; they differ in whether the
+/// quantity is a `MINQTY` or a `QTY` and in whether the condition may be
+/// left open. Color names are translated to BrickLink color ids through the
+/// reference palette.
+pub struct BrickLinkXml;
+
+impl BrickLinkXml {
+    /// Write pieces as a BrickLink XML upload
+    ///
+    /// The condition in `options` overrides each piece's own condition.
+    /// Pieces that end up with the same part, color and condition share an
+    /// item.
+    ///
+    /// # Arguments
+    /// * `pieces` - The pieces to export
+    /// * `path` - The path to write to
+    /// * `options` - Condition override and kind of list to write
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExportFailed` if a piece's color has no BrickLink id, or if a
+    /// store inventory is requested for a piece with no known condition.
+    pub fn write<P: AsRef<Path>>(pieces: &[Piece], path: P, options: &ExportOptions) -> Result<()> {
+        let mut rows: BTreeMap<(String, u32, &'static str), i32> = BTreeMap::new();
+        for piece in pieces {
+            let color = palette::find_by_name(&piece.color).ok_or_else(|| {
+                StudFinderError::ExportFailed(format!(
+                    "color '{}' of part {} has no BrickLink color id",
+                    piece.color, piece.part_number
+                ))
+            })?;
+            let condition = match (
+                options.condition.or(piece.condition),
+                options.bricklink_list,
+            ) {
+                (Some(condition), _) => Self::condition_code(condition),
+                (None, BrickLinkList::Wanted) => CONDITION_ANY,
+                (None, BrickLinkList::Store) => {
+                    return Err(StudFinderError::ExportFailed(format!(
+                        "part {} in {} has no condition; a store inventory needs new or used",
+                        piece.part_number, piece.color
+                    )))
+                }
+            };
+            *rows
+                .entry((piece.part_number.clone(), color.bricklink_id, condition))
+                .or_default() += piece.quantity;
+        }
+
+        let items = rows
+            .into_iter()
+            .map(|((part_number, color, condition), quantity)| {
+                let (minqty, qty) = match options.bricklink_list {
+                    BrickLinkList::Wanted => (Some(quantity), None),
+                    BrickLinkList::Store => (None, Some(quantity)),
+                };
+                Item {
+                    itemtype: ITEM_TYPE_PART.to_string(),
+                    itemid: part_number,
+                    color: Some(color),
+                    minqty,
+                    qty,
+                    condition: Some(condition.to_string()),
+                }
+            })
+            .collect();
+
+        let xml = quick_xml::se::to_string(&Inventory {
+            items,
+        })
+        .map_err(|e| StudFinderError::ExportFailed(e.to_string()))?;
+        std::fs::write(path.as_ref(), xml).map_err(StudFinderError::Io)?;
+
+        debug!("Wrote BrickLink XML to {}", path.as_ref().display());
+        Ok(())
+    }
+
+    /// Read pieces from a BrickLink XML upload
+    ///
+    /// Either flavor is accepted: the quantity is taken from `QTY` or
+    /// `MINQTY` (1 if neither is present), and an "any" condition imports as
+    /// no condition. Items that are not parts (sets, minifigures, …) are
+    /// skipped with a warning. Imported pieces are marked "Unknown" for the
+    /// caller to resolve against the part catalog.
+    ///
+    /// # Arguments
+    /// * `path` - The path to read from
+    ///
+    /// # Returns
+    /// * `Result<Vec<Piece>>` - One piece per part item, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImportFailed` if the document cannot be parsed, or an item
+    /// has no color, an unknown color id or an unknown condition code.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<Piece>> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
+        let inventory: Inventory = quick_xml::de::from_str(&data)
+            .map_err(|e| StudFinderError::ImportFailed(format!("{}: {}", path.display(), e)))?;
+
+        let mut pieces = Vec::new();
+        for (index, item) in inventory.items.into_iter().enumerate() {
+            let to_error = |message: String| {
+                StudFinderError::ImportFailed(format!(
+                    "{} (item {}, {}): {}",
+                    path.display(),
+                    index + 1,
+                    item.itemid,
+                    message
+                ))
+            };
+
+            if !item.itemtype.trim().eq_ignore_ascii_case(ITEM_TYPE_PART) {
+                warn!(
+                    "Skipping BrickLink item {} of type {}",
+                    item.itemid, item.itemtype
+                );
+                continue;
+            }
+
+            let color_id = item
+                .color
+                .ok_or_else(|| to_error("missing COLOR".to_string()))?;
+            let color = palette::find_by_bricklink_id(color_id)
+                .ok_or_else(|| to_error(format!("unknown BrickLink color id {}", color_id)))?;
+            let condition = match item.condition.as_deref().map(str::trim) {
+                None | Some("") | Some(CONDITION_ANY) => None,
+                Some(code) => Some(code.parse::<PieceCondition>().map_err(to_error)?),
+            };
+
+            pieces.push(Piece {
+                id: Uuid::new_v4().to_string(),
+                part_number: item.itemid.trim().to_string(),
+                color: color.bricklink_name.to_string(),
+                category: UNKNOWN_CATEGORY.to_string(),
+                quantity: item.qty.or(item.minqty).unwrap_or(1),
+                confidence: 1.0,
+                condition,
+            });
+        }
+
+        debug!("Read {} part(s) from BrickLink XML", pieces.len());
+        Ok(pieces)
+    }
+
+    /// Whether a document looks like a BrickLink XML upload
+    ///
+    /// The document must open with an XML declaration or the `<INVENTORY>`
+    /// element, after any byte order mark and whitespace, so that a CSV or
+    /// LDraw file mentioning `<INVENTORY` is not mistaken for one.
+    #[must_use]
+    pub fn is_document(data: &str) -> bool {
+        let data = data.trim_start_matches('\u{feff}').trim_start();
+        data.starts_with("<INVENTORY") || (data.starts_with("<?xml") && data.contains("<INVENTORY"))
+    }
+
+    /// BrickLink's single-letter condition code
+    fn condition_code(condition: PieceCondition) -> &'static str {
+        match condition {
+            PieceCondition::New => "N",
+            PieceCondition::Used => "U",
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    fn piece(part_number: &str, color: &str, condition: Option<PieceCondition>) -> Piece {
+        Piece {
+            id: Uuid::new_v4().to_string(),
+            part_number: part_number.to_string(),
+            color: color.to_string(),
+            category: "Bricks".to_string(),
+            quantity: 2,
+            confidence: 0.9,
+            condition,
+        }
+    }
+
+    #[test]
+    fn test_write_wanted_list() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("wanted.xml");
+
+        let pieces = vec![
+            piece("3001", "Red", None),
+            piece("3020", "Black", Some(PieceCondition::Used)),
+        ];
+        BrickLinkXml::write(&pieces, &path, &ExportOptions::default()).unwrap();
+
+        let xml = std::fs::read_to_string(&path).unwrap();
+        assert!(xml.starts_with("<INVENTORY><ITEM><ITEMTYPE>P</ITEMTYPE><ITEMID>3001</ITEMID><COLOR>5</COLOR><MINQTY>2</MINQTY><CONDITION>X</CONDITION></ITEM>"), "{}", xml);
+        assert!(
+            xml.contains(
+                "<ITEMID>3020</ITEMID><COLOR>11</COLOR><MINQTY>2</MINQTY><CONDITION>U</CONDITION>"
+            ),
+            "{}",
+            xml
+        );
+        assert!(!xml.contains("<QTY>"));
+    }
+
+    #[test]
+    fn test_write_store_inventory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("store.xml");
+
+        let pieces = vec![
+            piece("3001", "Red", None),
+            piece("3001", "Red", Some(PieceCondition::Used)),
+        ];
+
+        // Without a condition a store inventory cannot be written
+        let options = ExportOptions {
+            bricklink_list: BrickLinkList::Store,
+            ..ExportOptions::default()
+        };
+        let result = BrickLinkXml::write(&pieces, &path, &options);
+        assert!(matches!(result, Err(StudFinderError::ExportFailed(_))));
+
+        // The condition option applies to every piece, merging the two
+        let options = ExportOptions {
+            condition: Some(PieceCondition::New),
+            bricklink_list: BrickLinkList::Store,
+        };
+        BrickLinkXml::write(&pieces, &path, &options).unwrap();
+        let xml = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            xml.contains("<COLOR>5</COLOR><QTY>4</QTY><CONDITION>N</CONDITION>"),
+            "{}",
+            xml
+        );
+        assert!(!xml.contains("<MINQTY>"));
+    }
+
+    #[test]
+    fn test_read_document() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("inventory.xml");
+        std::fs::write(
+            &path,
+            "<?xml version=\"1.0\"?>\n<INVENTORY>\n\
+             <ITEM><ITEMTYPE>P</ITEMTYPE><ITEMID>3001</ITEMID><COLOR>5</COLOR><QTY>3</QTY><CONDITION>U</CONDITION></ITEM>\n\
+             <ITEM><ITEMTYPE>P</ITEMTYPE><ITEMID>3020</ITEMID><COLOR>11</COLOR><MINQTY>2</MINQTY><CONDITION>X</CONDITION></ITEM>\n\
+             <ITEM><ITEMTYPE>S</ITEMTYPE><ITEMID>10696-1</ITEMID></ITEM>\n\
+             </INVENTORY>\n",
+        )
+        .unwrap();
+
+        let pieces = BrickLinkXml::read(&path).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].color, "Red");
+        assert_eq!(pieces[0].quantity, 3);
+        assert_eq!(pieces[0].condition, Some(PieceCondition::Used));
+        assert_eq!(pieces[1].color, "Black");
+        assert_eq!(pieces[1].quantity, 2);
+        assert_eq!(pieces[1].condition, None);
+    }
+
+    #[test]
+    fn test_is_document() {
+        assert!(BrickLinkXml::is_document(
+            "<?xml version=\"1.0\"?>\n<INVENTORY></INVENTORY>"
+        ));
+        assert!(BrickLinkXml::is_document(
+            "\u{feff}\n  <INVENTORY></INVENTORY>"
+        ));
+        // A CSV whose notes mention the element is still a CSV
+        assert!(!BrickLinkXml::is_document(
+            "part_number,color,quantity,notes\n3001,Red,1,from <INVENTORY> upload\n"
+        ));
+        assert!(!BrickLinkXml::is_document(
+            "<?xml version=\"1.0\"?>\n<parts/>"
+        ));
+    }
+
+    #[test]
+    fn test_read_reports_unknown_color() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("inventory.xml");
+        std::fs::write(
+            &path,
+            "<INVENTORY><ITEM><ITEMTYPE>P</ITEMTYPE><ITEMID>3001</ITEMID><COLOR>9999</COLOR></ITEM></INVENTORY>",
+        )
+        .unwrap();
+
+        match BrickLinkXml::read(&path) {
+            Err(StudFinderError::ImportFailed(message)) => {
+                assert!(message.contains("item 1"), "{}", message);
+                assert!(message.contains("9999"), "{}", message);
+            }
+            other => panic!("Expected ImportFailed, got: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::core::catalog::PartInfo;
+use crate::error::{Result, StudFinderError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// Rebrickable parts table
+const PARTS_FILE: &str = "parts.csv";
+
+/// Rebrickable part category table
+const CATEGORIES_FILE: &str = "part_categories.csv";
+
+/// Rebrickable part relationship table
+const RELATIONSHIPS_FILE: &str = "part_relationships.csv";
+
+/// Relationship types whose child is another number for the parent design
+/// (`M` = mold variant, `A` = alternate)
+const ALTERNATE_RELATIONSHIPS: [&str; 2] = [
+    "M", "A",
+];
+
+/// Row of Rebrickable's `parts.csv`
+#[derive(Debug, Deserialize)]
+struct PartRecord {
+    part_num: String,
+    name: String,
+    part_cat_id: u32,
+}
+
+/// Row of Rebrickable's `part_categories.csv`
+#[derive(Debug, Deserialize)]
+struct CategoryRecord {
+    id: u32,
+    name: String,
+}
+
+/// Row of Rebrickable's `part_relationships.csv`
+#[derive(Debug, Deserialize)]
+struct RelationshipRecord {
+    rel_type: String,
+    child_part_num: String,
+    parent_part_num: String,
+}
+
+/// Functions for loading part catalog dumps
+pub struct CatalogImporter;
+
+impl CatalogImporter {
+    /// Load parts from a Rebrickable CSV dump or a JSON file
+    ///
+    /// `path` may be a directory containing Rebrickable's `parts.csv`,
+    /// `part_categories.csv` and (optionally) `part_relationships.csv`, the
+    /// `parts.csv` file itself (the other tables are looked up next to it),
+    /// or a `.json` file holding an array of parts.
+    ///
+    /// # Arguments
+    /// * `path` - The directory or file to import from
+    ///
+    /// # Returns
+    /// * `Result<Vec<PartInfo>>` - The parts found, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `CatalogImportFailed` if a required file is missing, a row
+    /// cannot be parsed, or a part refers to an unknown category.
+    pub fn import<P: AsRef<Path>>(path: P) -> Result<Vec<PartInfo>> {
+        let path = path.as_ref();
+        debug!("Importing part catalog from: {}", path.display());
+
+        let parts = if path.is_dir() {
+            Self::import_rebrickable(path)?
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        {
+            Self::import_json(path)?
+        } else {
+            let dir = path.parent().unwrap_or_else(|| Path::new("."));
+            Self::import_rebrickable_files(
+                path,
+                &dir.join(CATEGORIES_FILE),
+                &dir.join(RELATIONSHIPS_FILE),
+            )?
+        };
+
+        info!(
+            "Read {} catalog part(s) from {}",
+            parts.len(),
+            path.display()
+        );
+        Ok(parts)
+    }
+
+    /// Load a JSON array of parts
+    fn import_json(path: &Path) -> Result<Vec<PartInfo>> {
+        let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
+        serde_json::from_str(&data)
+            .map_err(|e| StudFinderError::CatalogImportFailed(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Load a directory of Rebrickable CSV tables
+    fn import_rebrickable(dir: &Path) -> Result<Vec<PartInfo>> {
+        Self::import_rebrickable_files(
+            &dir.join(PARTS_FILE),
+            &dir.join(CATEGORIES_FILE),
+            &dir.join(RELATIONSHIPS_FILE),
+        )
+    }
+
+    fn import_rebrickable_files(
+        parts_path: &Path,
+        categories_path: &Path,
+        relationships_path: &Path,
+    ) -> Result<Vec<PartInfo>> {
+        let categories: HashMap<u32, String> = Self::read_csv::<CategoryRecord>(categories_path)?
+            .into_iter()
+            .map(|record| (record.id, record.name))
+            .collect();
+        debug!("Read {} part categories", categories.len());
+
+        let mut alternates: HashMap<String, Vec<String>> = HashMap::new();
+        if relationships_path.exists() {
+            for record in Self::read_csv::<RelationshipRecord>(relationships_path)? {
+                if ALTERNATE_RELATIONSHIPS.contains(&record.rel_type.as_str()) {
+                    alternates
+                        .entry(record.parent_part_num)
+                        .or_default()
+                        .push(record.child_part_num);
+                }
+            }
+        } else {
+            warn!(
+                "{} not found, importing parts without alternates",
+                relationships_path.display()
+            );
+        }
+
+        Self::read_csv::<PartRecord>(parts_path)?
+            .into_iter()
+            .map(|record| {
+                let category = categories.get(&record.part_cat_id).ok_or_else(|| {
+                    StudFinderError::CatalogImportFailed(format!(
+                        "part {} refers to unknown category {}",
+                        record.part_num, record.part_cat_id
+                    ))
+                })?;
+                let mut part = PartInfo::new(&record.part_num, &record.name, category);
+                part.alternates = alternates.remove(&record.part_num).unwrap_or_default();
+                Ok(part)
+            })
+            .collect()
+    }
+
+    /// Read every row of a CSV file with a header line
+    fn read_csv<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+        let to_error = |e: csv::Error| {
+            let location = e
+                .position()
+                .map(|pos| format!(" (line {})", pos.line()))
+                .unwrap_or_default();
+            StudFinderError::CatalogImportFailed(format!("{}{}: {}", path.display(), location, e))
+        };
+
+        let mut reader = csv::Reader::from_path(path).map_err(to_error)?;
+        reader
+            .deserialize()
+            .collect::<std::result::Result<Vec<T>, _>>()
+            .map_err(to_error)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    fn write_dump(dir: &Path) {
+        std::fs::write(
+            dir.join(CATEGORIES_FILE),
+            "id,name\n11,Bricks\n14,Plates\n19,Tiles\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(PARTS_FILE),
+            "part_num,name,part_cat_id,part_material\n\
+             3001,Brick 2 x 4,11,Plastic\n\
+             3020,Plate 2 x 4,14,Plastic\n\
+             3068b,\"Tile 2 x 2 with Groove, Flat\",19,Plastic\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(RELATIONSHIPS_FILE),
+            "rel_type,child_part_num,parent_part_num\n\
+             M,3068a,3068b\n\
+             P,3068bpr0001,3068b\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_import_rebrickable_dump() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_dump(temp_dir.path());
+
+        let parts = CatalogImporter::import(temp_dir.path()).unwrap();
+        assert_eq!(parts.len(), 3);
+
+        let tile = parts.iter().find(|p| p.part_number == "3068b").unwrap();
+        assert_eq!(tile.name, "Tile 2 x 2 with Groove, Flat");
+        assert_eq!(tile.category, "Tiles");
+        // Prints are separate designs, mold variants are alternates
+        assert_eq!(tile.alternates, vec!["3068a".to_string()]);
+
+        let plate = parts.iter().find(|p| p.part_number == "3020").unwrap();
+        assert_eq!(plate.dimensions.unwrap().height, 1);
+
+        // Pointing at parts.csv directly finds the sibling tables
+        let parts = CatalogImporter::import(temp_dir.path().join(PARTS_FILE)).unwrap();
+        assert_eq!(parts.len(), 3);
+    }
+
+    #[test]
+    fn test_import_json() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("parts.json");
+        let parts = vec![PartInfo::new("3001", "Brick 2 x 4", "Bricks")];
+        std::fs::write(&path, serde_json::to_string(&parts).unwrap()).unwrap();
+
+        assert_eq!(CatalogImporter::import(&path).unwrap(), parts);
+    }
+
+    #[test]
+    fn test_import_reports_bad_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_dump(temp_dir.path());
+        std::fs::write(
+            temp_dir.path().join(PARTS_FILE),
+            "part_num,name,part_cat_id,part_material\n3001,Brick 2 x 4,eleven,Plastic\n",
+        )
+        .unwrap();
+
+        match CatalogImporter::import(temp_dir.path()) {
+            Err(StudFinderError::CatalogImportFailed(message)) => {
+                assert!(message.contains("line 2"), "{}", message);
+            }
+            other => panic!("Expected CatalogImportFailed, got: {:?}", other),
+        }
+    }
+}
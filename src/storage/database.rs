@@ -1,9 +1,203 @@
-use crate::core::piece::Piece;
+use crate::core::catalog::{PartDimensions, PartInfo};
+use crate::core::location::{PieceLocation, StorageLocation};
+use crate::core::piece::{Piece, PieceCondition};
+use crate::core::set::{LegoSet, SetPart};
 use crate::error::{Result, StudFinderError};
-use rusqlite::{params, Connection, OptionalExtension};
+use crate::processing::calibration::{CalibrationProfile, WhiteBalance};
+use crate::processing::processor::Detection;
+use crate::processing::segment::BoundingBox;
+use crate::storage::filter::PieceFilter;
+use crate::storage::observation::{Observation, ScanSource};
+use crate::storage::review::PendingReview;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::Mutex;
 use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// Columns selected when reading a `Piece`, in the order `piece_from_row` expects
+const PIECE_COLUMNS: &str = "id, part_number, color, category, quantity, confidence, condition";
+
+/// Result of adding a piece to the inventory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddOutcome {
+    /// No row existed for the piece's part, color and condition; a new one was created
+    Inserted {
+        /// ID of the new row
+        id: String,
+    },
+    /// The quantity was added to an existing row
+    Merged {
+        /// ID of the existing row
+        id: String,
+        /// Quantity of the row after merging
+        quantity: i32,
+    },
+}
+
+impl AddOutcome {
+    /// ID of the row the piece was stored in
+    #[must_use]
+    pub fn id(&self) -> &str {
+        match self {
+            AddOutcome::Inserted {
+                id,
+            }
+            | AddOutcome::Merged {
+                id,
+                ..
+            } => id,
+        }
+    }
+}
+
+/// Result of recording a scanned image in the scan ledger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordOutcome {
+    /// The pieces were added to the inventory and the image recorded
+    Recorded {
+        /// Number of earlier scans of the image whose pieces were taken back out
+        replaced: usize,
+    },
+    /// An image with the same content was already recorded; nothing was added
+    AlreadyScanned {
+        /// Path the earlier scan was recorded under
+        path: String,
+    },
+}
+
+/// Columns selected when reading an `Observation`, in the order `observation_from_row` expects
+const OBSERVATION_COLUMNS: &str = "id, piece_id, image_path, image_hash, \
+     bbox_x, bbox_y, bbox_width, bbox_height, processor, quality, \
+     measured_r, measured_g, measured_b, part_number, color, confidence, observed_at";
+
+/// Columns selected when reading a `PendingReview`, in the order `pending_from_row` expects
+const PENDING_COLUMNS: &str = "id, part_number, color, category, confidence, \
+     image_path, image_hash, bbox_x, bbox_y, bbox_width, bbox_height, processor, quality, \
+     measured_r, measured_g, measured_b, queued_at";
+
+/// Columns selected when reading a `CalibrationProfile`, in the order
+/// `calibration_from_row` expects
+const CALIBRATION_COLUMNS: &str =
+    "name, reference, measured_r, measured_g, measured_b, matrix, created_at";
+
+/// Build a `Piece` from a row selected with `PIECE_COLUMNS`
+fn piece_from_row(row: &Row<'_>) -> rusqlite::Result<Piece> {
+    let condition: String = row.get(6)?;
+    Ok(Piece {
+        id: row.get(0)?,
+        part_number: row.get(1)?,
+        color: row.get(2)?,
+        category: row.get(3)?,
+        quantity: row.get(4)?,
+        confidence: row.get(5)?,
+        condition: condition.parse().ok(),
+    })
+}
+
+/// Build an `Observation` from a row selected with `OBSERVATION_COLUMNS`
+fn observation_from_row(row: &Row<'_>) -> rusqlite::Result<Observation> {
+    let measured: [Option<u8>; 3] = [
+        row.get(10)?,
+        row.get(11)?,
+        row.get(12)?,
+    ];
+    Ok(Observation {
+        id: row.get(0)?,
+        piece_id: row.get(1)?,
+        image_path: row.get(2)?,
+        image_hash: row.get(3)?,
+        bounds: BoundingBox {
+            x: row.get(4)?,
+            y: row.get(5)?,
+            width: row.get(6)?,
+            height: row.get(7)?,
+        },
+        processor: row.get(8)?,
+        quality: row.get(9)?,
+        measured_rgb: match measured {
+            [Some(r), Some(g), Some(b)] => Some([
+                r, g, b,
+            ]),
+            _ => None,
+        },
+        part_number: row.get(13)?,
+        color: row.get(14)?,
+        confidence: row.get(15)?,
+        observed_at: row.get(16)?,
+    })
+}
+
+/// Build a `PendingReview` from a row selected with `PENDING_COLUMNS`
+fn pending_from_row(row: &Row<'_>) -> rusqlite::Result<PendingReview> {
+    let measured: [Option<u8>; 3] = [
+        row.get(13)?,
+        row.get(14)?,
+        row.get(15)?,
+    ];
+    Ok(PendingReview {
+        id: row.get(0)?,
+        part_number: row.get(1)?,
+        color: row.get(2)?,
+        category: row.get(3)?,
+        confidence: row.get(4)?,
+        image_path: row.get(5)?,
+        image_hash: row.get(6)?,
+        bounds: BoundingBox {
+            x: row.get(7)?,
+            y: row.get(8)?,
+            width: row.get(9)?,
+            height: row.get(10)?,
+        },
+        processor: row.get(11)?,
+        quality: row.get(12)?,
+        measured_rgb: match measured {
+            [Some(r), Some(g), Some(b)] => Some([
+                r, g, b,
+            ]),
+            _ => None,
+        },
+        queued_at: row.get(16)?,
+    })
+}
+
+/// Build a `PieceLocation` from a location and quantity column pair
+fn location_from_row(row: &Row<'_>, index: usize) -> rusqlite::Result<PieceLocation> {
+    let location: String = row.get(index)?;
+    Ok(PieceLocation {
+        location: location.parse().map_err(|e: String| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+        })?,
+        quantity: row.get(index + 1)?,
+    })
+}
+
+/// Build a `CalibrationProfile` from a row selected with `CALIBRATION_COLUMNS`
+fn calibration_from_row(row: &Row<'_>) -> rusqlite::Result<CalibrationProfile> {
+    let matrix: String = row.get(5)?;
+    let matrix = serde_json::from_str(&matrix).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(CalibrationProfile {
+        name: row.get(0)?,
+        reference: row.get(1)?,
+        measured: [
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+        ],
+        white_balance: WhiteBalance {
+            matrix,
+        },
+        created_at: row.get(6)?,
+    })
+}
+
+/// Storage form of an optional condition; unspecified is stored as ''
+fn condition_key(condition: Option<PieceCondition>) -> &'static str {
+    condition.map_or("", PieceCondition::as_str)
+}
 
 /// Database management for the StudFinder application
 ///
@@ -161,6 +355,392 @@ impl Database {
                 })?;
         }
 
+        if version < 3 {
+            debug!("Applying migration to version 3: Adding part catalog tables");
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS parts (
+                    part_number TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    category TEXT NOT NULL,
+                    width INTEGER,
+                    length INTEGER,
+                    height INTEGER
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 3,
+                operation: "create parts table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS part_alternates (
+                    alternate_number TEXT NOT NULL,
+                    part_number TEXT NOT NULL REFERENCES parts(part_number) ON DELETE CASCADE,
+                    PRIMARY KEY (part_number, alternate_number)
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 3,
+                operation: "create part_alternates table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (3)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 3,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 4 {
+            debug!(
+                "Applying migration to version 4: Aggregating pieces by part, color and condition"
+            );
+            tx.execute(
+                "ALTER TABLE pieces ADD COLUMN condition TEXT NOT NULL DEFAULT ''",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 4,
+                operation: "add condition column".to_string(),
+                source: Box::new(e),
+            })?;
+
+            // Fold duplicate rows into the oldest row of each group, weighting
+            // confidence by quantity
+            tx.execute(
+                "UPDATE pieces SET
+                    quantity = (SELECT SUM(p.quantity) FROM pieces p
+                        WHERE p.part_number = pieces.part_number
+                          AND p.color = pieces.color
+                          AND p.condition = pieces.condition),
+                    confidence = (SELECT COALESCE(
+                            SUM(p.confidence * p.quantity) / NULLIF(SUM(p.quantity), 0),
+                            MAX(p.confidence))
+                        FROM pieces p
+                        WHERE p.part_number = pieces.part_number
+                          AND p.color = pieces.color
+                          AND p.condition = pieces.condition)
+                 WHERE rowid IN (
+                    SELECT MIN(rowid) FROM pieces GROUP BY part_number, color, condition
+                 )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 4,
+                operation: "consolidate duplicate pieces".to_string(),
+                source: Box::new(e),
+            })?;
+
+            let removed = tx
+                .execute(
+                    "DELETE FROM pieces WHERE rowid NOT IN (
+                        SELECT MIN(rowid) FROM pieces GROUP BY part_number, color, condition
+                     )",
+                    [],
+                )
+                .map_err(|e| StudFinderError::Migration {
+                    version: 4,
+                    operation: "delete duplicate pieces".to_string(),
+                    source: Box::new(e),
+                })?;
+            if removed > 0 {
+                info!("Consolidated {} duplicate piece row(s)", removed);
+            }
+
+            tx.execute(
+                "CREATE UNIQUE INDEX IF NOT EXISTS idx_piece_key
+                 ON pieces(part_number, color, condition)",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 4,
+                operation: "create piece key index".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (4)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 4,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 5 {
+            debug!("Applying migration to version 5: Adding scan ledger");
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS scans (
+                    id TEXT PRIMARY KEY,
+                    path TEXT NOT NULL,
+                    hash TEXT NOT NULL UNIQUE,
+                    processor TEXT NOT NULL,
+                    scanned_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 5,
+                operation: "create scans table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE INDEX IF NOT EXISTS idx_scans_path ON scans(path)",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 5,
+                operation: "create scans path index".to_string(),
+                source: Box::new(e),
+            })?;
+
+            // Quantity each scan contributed to each piece row, so a rescan
+            // can take it back out
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS scan_pieces (
+                    scan_id TEXT NOT NULL REFERENCES scans(id) ON DELETE CASCADE,
+                    piece_id TEXT NOT NULL,
+                    quantity INTEGER NOT NULL
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 5,
+                operation: "create scan_pieces table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE INDEX IF NOT EXISTS idx_scan_pieces_scan ON scan_pieces(scan_id)",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 5,
+                operation: "create scan_pieces index".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (5)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 5,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 6 {
+            debug!("Applying migration to version 6: Adding scan observations");
+            // scan_id is NULL for single-image scans, which are not in the ledger
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS scan_observations (
+                    id TEXT PRIMARY KEY,
+                    piece_id TEXT NOT NULL,
+                    scan_id TEXT REFERENCES scans(id) ON DELETE CASCADE,
+                    image_path TEXT NOT NULL,
+                    image_hash TEXT NOT NULL,
+                    bbox_x INTEGER NOT NULL,
+                    bbox_y INTEGER NOT NULL,
+                    bbox_width INTEGER NOT NULL,
+                    bbox_height INTEGER NOT NULL,
+                    processor TEXT NOT NULL,
+                    quality TEXT NOT NULL,
+                    measured_r INTEGER,
+                    measured_g INTEGER,
+                    measured_b INTEGER,
+                    part_number TEXT NOT NULL,
+                    color TEXT NOT NULL,
+                    confidence REAL NOT NULL,
+                    observed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 6,
+                operation: "create scan_observations table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE INDEX IF NOT EXISTS idx_observations_piece ON scan_observations(piece_id)",
+                [],
+            )
+            .and_then(|_| {
+                tx.execute(
+                    "CREATE INDEX IF NOT EXISTS idx_observations_scan ON scan_observations(scan_id)",
+                    [],
+                )
+            })
+            .map_err(|e| StudFinderError::Migration {
+                version: 6,
+                operation: "create scan_observations indexes".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (6)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 6,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 7 {
+            debug!("Applying migration to version 7: Adding review queue");
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS pending_review (
+                    id TEXT PRIMARY KEY,
+                    scan_id TEXT REFERENCES scans(id) ON DELETE CASCADE,
+                    part_number TEXT NOT NULL,
+                    color TEXT NOT NULL,
+                    category TEXT NOT NULL,
+                    confidence REAL NOT NULL,
+                    image_path TEXT NOT NULL,
+                    image_hash TEXT NOT NULL,
+                    bbox_x INTEGER NOT NULL,
+                    bbox_y INTEGER NOT NULL,
+                    bbox_width INTEGER NOT NULL,
+                    bbox_height INTEGER NOT NULL,
+                    processor TEXT NOT NULL,
+                    quality TEXT NOT NULL,
+                    measured_r INTEGER,
+                    measured_g INTEGER,
+                    measured_b INTEGER,
+                    queued_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 7,
+                operation: "create pending_review table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE INDEX IF NOT EXISTS idx_pending_review_scan ON pending_review(scan_id)",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 7,
+                operation: "create pending_review index".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (7)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 7,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 8 {
+            debug!("Applying migration to version 8: Adding set inventories");
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS sets (
+                    set_number TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    year INTEGER
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 8,
+                operation: "create sets table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS set_parts (
+                    set_number TEXT NOT NULL REFERENCES sets(set_number) ON DELETE CASCADE,
+                    part_number TEXT NOT NULL,
+                    color TEXT NOT NULL,
+                    quantity INTEGER NOT NULL CHECK (quantity > 0),
+                    PRIMARY KEY (set_number, part_number, color)
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 8,
+                operation: "create set_parts table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (8)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 8,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 9 {
+            debug!("Applying migration to version 9: Adding storage locations");
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS piece_locations (
+                    piece_id TEXT NOT NULL REFERENCES pieces(id) ON DELETE CASCADE,
+                    location TEXT NOT NULL,
+                    quantity INTEGER NOT NULL CHECK (quantity > 0),
+                    PRIMARY KEY (piece_id, location)
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 9,
+                operation: "create piece_locations table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute(
+                "CREATE INDEX IF NOT EXISTS idx_piece_locations_location ON piece_locations(location)",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 9,
+                operation: "create piece_locations index".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (9)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 9,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
+        if version < 10 {
+            debug!("Applying migration to version 10: Adding calibration profiles");
+            tx.execute(
+                "CREATE TABLE IF NOT EXISTS calibration_profiles (
+                    name TEXT PRIMARY KEY,
+                    reference TEXT NOT NULL,
+                    measured_r INTEGER NOT NULL,
+                    measured_g INTEGER NOT NULL,
+                    measured_b INTEGER NOT NULL,
+                    matrix TEXT NOT NULL,
+                    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )",
+                [],
+            )
+            .map_err(|e| StudFinderError::Migration {
+                version: 10,
+                operation: "create calibration_profiles table".to_string(),
+                source: Box::new(e),
+            })?;
+
+            tx.execute("INSERT INTO schema_version (version) VALUES (10)", [])
+                .map_err(|e| StudFinderError::Migration {
+                    version: 10,
+                    operation: "update schema version".to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+
         tx.commit().map_err(|e| StudFinderError::Database {
             operation: "commit transaction".to_string(),
             source: Box::new(e),
@@ -204,12 +784,25 @@ impl Database {
                 source: Box::new(e),
             })?;
 
-            tx.execute("DROP TABLE IF EXISTS pieces", []).map_err(|e| {
-                StudFinderError::Database {
-                    operation: "drop pieces table".to_string(),
-                    source: Box::new(e),
-                }
-            })?;
+            for table in [
+                "calibration_profiles",
+                "piece_locations",
+                "set_parts",
+                "sets",
+                "pending_review",
+                "scan_observations",
+                "scan_pieces",
+                "scans",
+                "pieces",
+                "part_alternates",
+                "parts",
+            ] {
+                tx.execute(&format!("DROP TABLE IF EXISTS {}", table), [])
+                    .map_err(|e| StudFinderError::Database {
+                        operation: format!("drop {} table", table),
+                        source: Box::new(e),
+                    })?;
+            }
 
             tx.execute("DROP TABLE IF EXISTS schema_version", [])
                 .map_err(|e| StudFinderError::Database {
@@ -237,7 +830,11 @@ impl Database {
         }
     }
 
-    /// Adds a piece to the database or updates its quantity if it already exists
+    /// Adds a piece to the inventory, merging it with any existing row for the same piece
+    ///
+    /// Pieces are keyed on part number, color and condition. If a row with
+    /// the same key exists, the quantity is added to it and its confidence
+    /// becomes the quantity-weighted average; otherwise a new row is created.
     ///
     /// # Arguments
     ///
@@ -245,7 +842,7 @@ impl Database {
     ///
     /// # Returns
     ///
-    /// `Ok(())` if the piece was added or updated successfully, or an error
+    /// Whether the piece was inserted or merged, or an error
     ///
     /// # Errors
     ///
@@ -254,7 +851,7 @@ impl Database {
     /// - Failed to start a transaction
     /// - Failed to query, insert, or update the piece
     /// - Failed to commit the transaction
-    pub fn add_piece(&self, piece: &Piece) -> Result<()> {
+    pub fn add_piece(&self, piece: &Piece) -> Result<AddOutcome> {
         debug!("Adding piece to database: {}", piece);
 
         // Acquire lock and start transaction
@@ -270,74 +867,105 @@ impl Database {
             source: Box::new(e),
         })?;
 
-        let existing = {
-            let mut stmt = tx
-                .prepare(
-                    "SELECT id, part_number, color, category, quantity, confidence
-                 FROM pieces WHERE id = ?",
-                )
-                .map_err(|e| StudFinderError::Database {
-                    operation: "prepare select statement".to_string(),
-                    source: Box::new(e),
-                })?;
+        let outcome = Self::add_piece_in(&tx, piece)?;
 
-            stmt.query_row([&piece.id], |row| {
-                Ok(Piece {
-                    id: row.get(0)?,
-                    part_number: row.get(1)?,
-                    color: row.get(2)?,
-                    category: row.get(3)?,
-                    quantity: row.get(4)?,
-                    confidence: row.get(5)?,
-                })
-            })
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+        debug!(
+            "Successfully added/updated piece in database: {:?}",
+            outcome
+        );
+
+        Ok(outcome)
+    }
+
+    /// Add a piece within an open transaction (see `add_piece`)
+    fn add_piece_in(tx: &Transaction<'_>, piece: &Piece) -> Result<AddOutcome> {
+        let condition = condition_key(piece.condition);
+        let existing: Option<(String, i32, f32)> = tx
+            .query_row(
+                "SELECT id, quantity, confidence FROM pieces
+                 WHERE part_number = ?1 AND color = ?2 AND condition = ?3",
+                params![
+                    piece.part_number,
+                    piece.color,
+                    condition
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
             .optional()
             .map_err(|e| StudFinderError::Database {
                 operation: "query existing piece".to_string(),
                 source: Box::new(e),
-            })?
-        };
+            })?;
 
-        if let Some(existing_piece) = existing {
-            debug!("Found existing piece, updating quantity");
+        let outcome = if let Some((id, quantity, confidence)) = existing {
+            debug!("Found existing piece {}, merging quantity", id);
+            let total = quantity + piece.quantity;
+            let merged_confidence = if total > 0 {
+                (confidence * quantity as f32 + piece.confidence * piece.quantity as f32)
+                    / total as f32
+            } else {
+                confidence.max(piece.confidence)
+            };
             tx.execute(
-                "UPDATE pieces SET quantity = ?1 WHERE id = ?2",
+                "UPDATE pieces SET quantity = ?1, confidence = ?2 WHERE id = ?3",
                 params![
-                    piece.quantity + existing_piece.quantity,
-                    piece.id
+                    total,
+                    merged_confidence,
+                    id
                 ],
             )
             .map_err(|e| StudFinderError::Database {
                 operation: "update piece quantity".to_string(),
                 source: Box::new(e),
             })?;
+            AddOutcome::Merged {
+                id,
+                quantity: total,
+            }
         } else {
-            debug!("Inserting new piece");
+            // Keep the caller's ID unless another row already uses it
+            let id_taken = tx
+                .query_row("SELECT 1 FROM pieces WHERE id = ?", [&piece.id], |_| Ok(()))
+                .optional()
+                .map_err(|e| StudFinderError::Database {
+                    operation: "check piece id".to_string(),
+                    source: Box::new(e),
+                })?
+                .is_some();
+            let id = if id_taken || piece.id.is_empty() {
+                Uuid::new_v4().to_string()
+            } else {
+                piece.id.clone()
+            };
+
+            debug!("Inserting new piece {}", id);
             tx.execute(
-                "INSERT INTO pieces (id, part_number, color, category, quantity, confidence)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                "INSERT INTO pieces (id, part_number, color, category, quantity, confidence, condition)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 params![
-                    piece.id,
+                    id,
                     piece.part_number,
                     piece.color,
                     piece.category,
                     piece.quantity,
-                    piece.confidence
+                    piece.confidence,
+                    condition
                 ],
             )
             .map_err(|e| StudFinderError::Database {
                 operation: "insert new piece".to_string(),
                 source: Box::new(e),
             })?;
-        }
-
-        tx.commit().map_err(|e| StudFinderError::Database {
-            operation: "commit transaction".to_string(),
-            source: Box::new(e),
-        })?;
-        debug!("Successfully added/updated piece in database");
+            AddOutcome::Inserted {
+                id,
+            }
+        };
 
-        Ok(())
+        Ok(outcome)
     }
 
     /// Retrieves a piece from the database by its ID
@@ -366,26 +994,17 @@ impl Database {
             })?;
 
         let mut stmt = conn
-            .prepare(
-                "SELECT id, part_number, color, category, quantity, confidence
-             FROM pieces WHERE id = ?",
-            )
+            .prepare(&format!(
+                "SELECT {} FROM pieces WHERE id = ?",
+                PIECE_COLUMNS
+            ))
             .map_err(|e| StudFinderError::Database {
                 operation: "prepare select statement".to_string(),
                 source: Box::new(e),
             })?;
 
         let piece = stmt
-            .query_row([id], |row| {
-                Ok(Piece {
-                    id: row.get(0)?,
-                    part_number: row.get(1)?,
-                    color: row.get(2)?,
-                    category: row.get(3)?,
-                    quantity: row.get(4)?,
-                    confidence: row.get(5)?,
-                })
-            })
+            .query_row([id], piece_from_row)
             .optional()
             .map_err(|e| StudFinderError::Database {
                 operation: format!("query piece with id '{}'", id),
@@ -419,27 +1038,18 @@ impl Database {
             })?;
 
         let mut stmt = conn
-            .prepare("SELECT id, part_number, color, category, quantity, confidence FROM pieces")
+            .prepare(&format!("SELECT {} FROM pieces", PIECE_COLUMNS))
             .map_err(|e| StudFinderError::Database {
                 operation: "prepare select statement".to_string(),
                 source: Box::new(e),
             })?;
 
-        let pieces_result = stmt
-            .query_map([], |row| {
-                Ok(Piece {
-                    id: row.get(0)?,
-                    part_number: row.get(1)?,
-                    color: row.get(2)?,
-                    category: row.get(3)?,
-                    quantity: row.get(4)?,
-                    confidence: row.get(5)?,
-                })
-            })
-            .map_err(|e| StudFinderError::Database {
-                operation: "query all pieces".to_string(),
-                source: Box::new(e),
-            })?;
+        let pieces_result =
+            stmt.query_map([], piece_from_row)
+                .map_err(|e| StudFinderError::Database {
+                    operation: "query all pieces".to_string(),
+                    source: Box::new(e),
+                })?;
 
         let mut pieces = Vec::new();
         for piece_result in pieces_result {
@@ -459,6 +1069,52 @@ impl Database {
         Ok(pieces)
     }
 
+    /// Lists the pieces matching a filter
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Criteria, ordering and limit for the query
+    ///
+    /// # Returns
+    ///
+    /// The matching pieces in the requested order, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the query
+    /// - Failed to collect the results
+    pub fn query_pieces(&self, filter: &PieceFilter) -> Result<Vec<Piece>> {
+        debug!("Querying pieces with filter: {:?}", filter);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "query pieces".to_string(),
+            })?;
+
+        let (clauses, values) = filter.to_sql();
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM pieces{}", PIECE_COLUMNS, clauses))
+            .map_err(|e| StudFinderError::Database {
+                operation: "prepare filtered select statement".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let pieces = stmt
+            .query_map(params_from_iter(values), piece_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| StudFinderError::Database {
+                operation: "query filtered pieces".to_string(),
+                source: Box::new(e),
+            })?;
+
+        debug!("Found {} matching pieces", pieces.len());
+        Ok(pieces)
+    }
+
     /// Updates the quantity of a piece in the database
     ///
     /// # Arguments
@@ -475,6 +1131,7 @@ impl Database {
     /// Returns an error if:
     /// - Failed to acquire the database lock
     /// - Failed to execute the update
+    /// - The new quantity is below the number of pieces stored in locations
     /// - The piece with the specified ID does not exist
     pub fn update_quantity(&self, id: &str, quantity: i32) -> Result<()> {
         debug!("Updating quantity for piece {}: {}", id, quantity);
@@ -486,6 +1143,23 @@ impl Database {
                 operation: "update quantity".to_string(),
             })?;
 
+        let placed: i32 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(quantity), 0) FROM piece_locations WHERE piece_id = ?",
+                [id],
+                |row| row.get(0),
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query locations of piece '{}'", id),
+                source: Box::new(e),
+            })?;
+        if quantity < placed {
+            return Err(StudFinderError::InvalidPiece(format!(
+                "{} of piece '{}' are stored in locations; move them out before lowering the quantity to {}",
+                placed, id, quantity
+            )));
+        }
+
         let rows_affected = conn
             .execute(
                 "UPDATE pieces SET quantity = ?1 WHERE id = ?2",
@@ -503,8 +1177,160 @@ impl Database {
         Ok(())
     }
 
+    /// Rewrites the part number, color, category and condition of a piece
+    ///
+    /// The row is found by `piece.id`. If another row already holds the new
+    /// part number, color and condition, the piece is merged into it the
+    /// same way `add_piece` merges, and the edited row is removed. Its
+    /// storage locations are added to those of the row it merges into.
+    ///
+    /// # Arguments
+    ///
+    /// * `piece` - The piece with its new values
+    ///
+    /// # Returns
+    ///
+    /// The ID of the row that now holds the piece, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start or commit the transaction
+    /// - Failed to query, update, or delete the rows
+    /// - The piece with the specified ID does not exist
+    pub fn update_piece(&self, piece: &Piece) -> Result<String> {
+        debug!("Updating piece {}: {}", piece.id, piece);
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "update piece".to_string(),
+            })?;
+
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        let current: Option<(i32, f32)> = tx
+            .query_row(
+                "SELECT quantity, confidence FROM pieces WHERE id = ?",
+                [&piece.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query piece with id '{}'", piece.id),
+                source: Box::new(e),
+            })?;
+        let Some((quantity, confidence)) = current else {
+            return Err(StudFinderError::PieceNotFound(piece.id.clone()));
+        };
+
+        let condition = condition_key(piece.condition);
+        let existing: Option<(String, i32, f32)> = tx
+            .query_row(
+                "SELECT id, quantity, confidence FROM pieces
+                 WHERE part_number = ?1 AND color = ?2 AND condition = ?3 AND id != ?4",
+                params![
+                    piece.part_number,
+                    piece.color,
+                    condition,
+                    piece.id
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| StudFinderError::Database {
+                operation: "query existing piece".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let id = if let Some((id, other_quantity, other_confidence)) = existing {
+            debug!("Piece {} now matches {}, merging", piece.id, id);
+            let total = quantity + other_quantity;
+            let merged_confidence = if total > 0 {
+                (confidence * quantity as f32 + other_confidence * other_quantity as f32)
+                    / total as f32
+            } else {
+                confidence.max(other_confidence)
+            };
+            tx.execute(
+                "UPDATE pieces SET quantity = ?1, confidence = ?2 WHERE id = ?3",
+                params![
+                    total,
+                    merged_confidence,
+                    id
+                ],
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "update piece quantity".to_string(),
+                source: Box::new(e),
+            })?;
+            tx.execute(
+                "INSERT INTO piece_locations (piece_id, location, quantity)
+                 SELECT ?1, location, quantity FROM piece_locations WHERE piece_id = ?2
+                 ON CONFLICT (piece_id, location) DO UPDATE SET quantity = quantity + excluded.quantity",
+                params![id, piece.id],
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "merge piece locations".to_string(),
+                source: Box::new(e),
+            })?;
+            tx.execute("DELETE FROM pieces WHERE id = ?", [&piece.id])
+                .map_err(|e| StudFinderError::Database {
+                    operation: format!("delete piece '{}'", piece.id),
+                    source: Box::new(e),
+                })?;
+            tx.execute(
+                "UPDATE scan_pieces SET piece_id = ?1 WHERE piece_id = ?2",
+                params![id, piece.id],
+            )
+            .and_then(|_| {
+                tx.execute(
+                    "UPDATE scan_observations SET piece_id = ?1 WHERE piece_id = ?2",
+                    params![id, piece.id],
+                )
+            })
+            .map_err(|e| StudFinderError::Database {
+                operation: "update scan ledger".to_string(),
+                source: Box::new(e),
+            })?;
+            id
+        } else {
+            tx.execute(
+                "UPDATE pieces SET part_number = ?1, color = ?2, category = ?3, condition = ?4
+                 WHERE id = ?5",
+                params![
+                    piece.part_number,
+                    piece.color,
+                    piece.category,
+                    condition,
+                    piece.id
+                ],
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("update piece '{}'", piece.id),
+                source: Box::new(e),
+            })?;
+            piece.id.clone()
+        };
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        Ok(id)
+    }
+
     /// Deletes a piece from the database
     ///
+    /// The piece's scan observations and storage locations are deleted with
+    /// it.
+    ///
     /// # Arguments
     ///
     /// * `id` - The ID of the piece to delete
@@ -540,80 +1366,1544 @@ impl Database {
             return Err(StudFinderError::PieceNotFound(id.to_string()));
         }
 
+        conn.execute("DELETE FROM scan_observations WHERE piece_id = ?", [id])
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("delete observations of piece '{}'", id),
+                source: Box::new(e),
+            })?;
+
         Ok(())
     }
 
-    /// Gets the current schema version
+    /// Lists the storage locations holding some of a piece's quantity
+    ///
+    /// # Arguments
+    ///
+    /// * `piece_id` - The ID of the piece
     ///
     /// # Returns
     ///
-    /// The current schema version number, or an error
+    /// The piece's locations in location order, or an error
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - Failed to acquire the database lock
-    /// - Failed to query the schema version
-    fn get_schema_version(&self) -> Result<i32> {
+    /// - Failed to prepare or execute the query
+    pub fn list_piece_locations(&self, piece_id: &str) -> Result<Vec<PieceLocation>> {
+        debug!("Listing locations of piece {}", piece_id);
+
         let conn = self
             .conn
             .lock()
             .map_err(|_| StudFinderError::DatabaseLockFailed {
-                operation: "get schema version".to_string(),
+                operation: "list piece locations".to_string(),
             })?;
 
-        let version: i32 = conn
-            .query_row(
-                "SELECT COALESCE(MAX(version), 0) FROM schema_version",
-                [],
-                |row| row.get(0),
-            )
+        Self::piece_locations_in(&conn, piece_id)
+    }
+
+    /// Lists the storage locations of every piece
+    ///
+    /// # Returns
+    ///
+    /// The locations of each piece that has any, by piece ID, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the query
+    pub fn list_locations(&self) -> Result<HashMap<String, Vec<PieceLocation>>> {
+        debug!("Listing piece locations");
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "list locations".to_string(),
+            })?;
+
+        let rows: Vec<(String, PieceLocation)> = conn
+            .prepare("SELECT piece_id, location, quantity FROM piece_locations ORDER BY location")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, location_from_row(row, 1)?)))?
+                    .collect()
+            })
             .map_err(|e| StudFinderError::Database {
-                operation: "query schema version".to_string(),
+                operation: "query piece locations".to_string(),
                 source: Box::new(e),
             })?;
 
-        Ok(version)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn create_test_piece() -> Piece {
-        Piece {
-            id: String::from("test-piece"),
-            part_number: "3001".to_string(),
-            color: "Red".to_string(),
-            category: "Brick".to_string(),
-            quantity: 1,
-            confidence: 0.95,
+        let mut locations: HashMap<String, Vec<PieceLocation>> = HashMap::new();
+        for (piece_id, location) in rows {
+            locations.entry(piece_id).or_default().push(location);
         }
+        Ok(locations)
     }
 
-    #[test]
-    fn test_database_operations() {
-        let db = Database::new(":memory:").unwrap();
-        db.init().unwrap();
-
-        // Test schema version
-        assert_eq!(db.get_schema_version().unwrap(), 2);
-
-        // Test insert
-        let piece = create_test_piece();
-        db.add_piece(&piece).unwrap();
+    /// Moves some of a piece's quantity into a storage location
+    ///
+    /// The pieces are taken from `from`, or from the piece's unplaced
+    /// quantity (the part of its quantity in no location) when `from` is
+    /// `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `piece_id` - The ID of the piece
+    /// * `from` - The location to take the pieces from
+    /// * `to` - The location to put them in
+    /// * `quantity` - How many to move; everything at the source by default
+    ///
+    /// # Returns
+    ///
+    /// The number of pieces moved, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start or commit the transaction
+    /// - Failed to query or update the locations
+    /// - The piece with the specified ID does not exist
+    /// - The source holds none of the piece, or fewer than `quantity`
+    pub fn move_pieces(
+        &self,
+        piece_id: &str,
+        from: Option<&StorageLocation>,
+        to: &StorageLocation,
+        quantity: Option<i32>,
+    ) -> Result<i32> {
+        debug!("Moving piece {} to {}", piece_id, to);
 
-        // Test get
-        let fetched = db.get_piece(&piece.id).unwrap().unwrap();
-        assert_eq!(fetched.part_number, piece.part_number);
-        assert_eq!(fetched.confidence, piece.confidence);
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "move pieces".to_string(),
+            })?;
 
-        // Test list
-        let pieces = db.list_pieces().unwrap();
-        assert_eq!(pieces.len(), 1);
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
 
-        // Test update
+        let total: Option<i32> = tx
+            .query_row(
+                "SELECT quantity FROM pieces WHERE id = ?",
+                [piece_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query piece with id '{}'", piece_id),
+                source: Box::new(e),
+            })?;
+        let Some(total) = total else {
+            return Err(StudFinderError::PieceNotFound(piece_id.to_string()));
+        };
+        let locations = Self::piece_locations_in(&tx, piece_id)?;
+
+        let (available, source) = match from {
+            Some(from) => (
+                locations
+                    .iter()
+                    .find(|l| &l.location == from)
+                    .map_or(0, |l| l.quantity),
+                from.to_string(),
+            ),
+            None => (
+                total - locations.iter().map(|l| l.quantity).sum::<i32>(),
+                "unplaced stock".to_string(),
+            ),
+        };
+        let quantity = quantity.unwrap_or(available);
+        if available <= 0 {
+            return Err(StudFinderError::InvalidPiece(format!(
+                "no pieces of '{}' in {}",
+                piece_id, source
+            )));
+        }
+        if quantity <= 0 || quantity > available {
+            return Err(StudFinderError::InvalidPiece(format!(
+                "cannot move {} of '{}' from {}, which holds {}",
+                quantity, piece_id, source, available
+            )));
+        }
+
+        if let Some(from) = from {
+            Self::take_from_location_in(&tx, piece_id, from, quantity, available)?;
+        }
+        tx.execute(
+            "INSERT INTO piece_locations (piece_id, location, quantity) VALUES (?1, ?2, ?3)
+             ON CONFLICT (piece_id, location) DO UPDATE SET quantity = quantity + excluded.quantity",
+            params![piece_id, to.to_string(), quantity],
+        )
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("put piece '{}' in {}", piece_id, to),
+            source: Box::new(e),
+        })?;
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        info!(
+            "Moved {} of piece {} from {} to {}",
+            quantity, piece_id, source, to
+        );
+        Ok(quantity)
+    }
+
+    /// Read a piece's locations on an open connection or transaction
+    fn piece_locations_in(conn: &Connection, piece_id: &str) -> Result<Vec<PieceLocation>> {
+        conn.prepare(
+            "SELECT location, quantity FROM piece_locations WHERE piece_id = ? ORDER BY location",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([piece_id], |row| location_from_row(row, 0))?
+                .collect()
+        })
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("query locations of piece '{}'", piece_id),
+            source: Box::new(e),
+        })
+    }
+
+    /// Take pieces out of locations until they fit the piece's quantity
+    ///
+    /// Used when a rescan lowers a quantity below what is stored in
+    /// locations; the last locations in location order are emptied first.
+    /// A deleted piece loses all its locations.
+    fn fit_locations_in(tx: &Transaction<'_>, piece_id: &str) -> Result<()> {
+        let total: i32 = tx
+            .query_row(
+                "SELECT quantity FROM pieces WHERE id = ?",
+                [piece_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query piece with id '{}'", piece_id),
+                source: Box::new(e),
+            })?
+            .unwrap_or(0);
+        let locations = Self::piece_locations_in(tx, piece_id)?;
+        let mut excess = locations.iter().map(|l| l.quantity).sum::<i32>() - total;
+
+        for location in locations.iter().rev() {
+            if excess <= 0 {
+                break;
+            }
+            let taken = excess.min(location.quantity);
+            warn!(
+                "Taking {} of piece {} out of {} to match its quantity",
+                taken, piece_id, location.location
+            );
+            Self::take_from_location_in(
+                tx,
+                piece_id,
+                &location.location,
+                taken,
+                location.quantity,
+            )?;
+            excess -= taken;
+        }
+        Ok(())
+    }
+
+    /// Take `quantity` of the `held` pieces out of a location, dropping it when emptied
+    fn take_from_location_in(
+        tx: &Transaction<'_>,
+        piece_id: &str,
+        location: &StorageLocation,
+        quantity: i32,
+        held: i32,
+    ) -> Result<()> {
+        let location_key = location.to_string();
+        if quantity < held {
+            tx.execute(
+                "UPDATE piece_locations SET quantity = quantity - ?1
+                 WHERE piece_id = ?2 AND location = ?3",
+                params![
+                    quantity,
+                    piece_id,
+                    location_key
+                ],
+            )
+        } else {
+            tx.execute(
+                "DELETE FROM piece_locations WHERE piece_id = ?1 AND location = ?2",
+                params![
+                    piece_id,
+                    location_key
+                ],
+            )
+        }
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("take piece '{}' out of {}", piece_id, location),
+            source: Box::new(e),
+        })?;
+        Ok(())
+    }
+
+    /// Stores catalog parts, replacing any existing entries with the same number
+    ///
+    /// # Arguments
+    ///
+    /// * `parts` - The parts to store
+    ///
+    /// # Returns
+    ///
+    /// The number of parts stored, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start a transaction
+    /// - Failed to insert a part or its alternates
+    /// - Failed to commit the transaction
+    pub fn save_parts(&self, parts: &[PartInfo]) -> Result<usize> {
+        debug!("Saving {} catalog part(s)", parts.len());
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "save parts".to_string(),
+            })?;
+
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        {
+            let mut insert_part = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO parts (part_number, name, category, width, length, height)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare part insert".to_string(),
+                    source: Box::new(e),
+                })?;
+            let mut clear_alternates = tx
+                .prepare("DELETE FROM part_alternates WHERE part_number = ?")
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare alternate delete".to_string(),
+                    source: Box::new(e),
+                })?;
+            let mut insert_alternate = tx
+                .prepare(
+                    "INSERT OR IGNORE INTO part_alternates (alternate_number, part_number)
+                 VALUES (?1, ?2)",
+                )
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare alternate insert".to_string(),
+                    source: Box::new(e),
+                })?;
+
+            for part in parts {
+                let dimensions = part.dimensions;
+                insert_part
+                    .execute(params![
+                        part.part_number,
+                        part.name,
+                        part.category,
+                        dimensions.map(|d| d.width),
+                        dimensions.map(|d| d.length),
+                        dimensions.map(|d| d.height)
+                    ])
+                    .map_err(|e| StudFinderError::Database {
+                        operation: format!("insert part '{}'", part.part_number),
+                        source: Box::new(e),
+                    })?;
+
+                clear_alternates.execute([&part.part_number]).map_err(|e| {
+                    StudFinderError::Database {
+                        operation: format!("clear alternates of part '{}'", part.part_number),
+                        source: Box::new(e),
+                    }
+                })?;
+                for alternate in &part.alternates {
+                    insert_alternate
+                        .execute(params![
+                            alternate,
+                            part.part_number
+                        ])
+                        .map_err(|e| StudFinderError::Database {
+                            operation: format!("insert alternate '{}'", alternate),
+                            source: Box::new(e),
+                        })?;
+                }
+            }
+        }
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        info!("Saved {} catalog part(s)", parts.len());
+        Ok(parts.len())
+    }
+
+    /// Lists all catalog parts stored in the database
+    ///
+    /// # Returns
+    ///
+    /// A vector of all stored parts with their alternates, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the query
+    pub fn list_parts(&self) -> Result<Vec<PartInfo>> {
+        debug!("Listing catalog parts");
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "list parts".to_string(),
+            })?;
+
+        let mut alternates: std::collections::HashMap<String, Vec<String>> =
+            std::collections::HashMap::new();
+        {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT part_number, alternate_number FROM part_alternates
+                 ORDER BY part_number, alternate_number",
+                )
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare select statement".to_string(),
+                    source: Box::new(e),
+                })?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|e| StudFinderError::Database {
+                    operation: "query part alternates".to_string(),
+                    source: Box::new(e),
+                })?;
+            for row in rows {
+                let (part_number, alternate) = row.map_err(|e| StudFinderError::Database {
+                    operation: "process alternate row".to_string(),
+                    source: Box::new(e),
+                })?;
+                alternates.entry(part_number).or_default().push(alternate);
+            }
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT part_number, name, category, width, length, height
+             FROM parts ORDER BY part_number",
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "prepare select statement".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                let width: Option<u32> = row.get(3)?;
+                let length: Option<u32> = row.get(4)?;
+                let height: Option<u32> = row.get(5)?;
+                Ok(PartInfo {
+                    part_number: row.get(0)?,
+                    name: row.get(1)?,
+                    category: row.get(2)?,
+                    dimensions: match (width, length, height) {
+                        (Some(width), Some(length), Some(height)) => Some(PartDimensions {
+                            width,
+                            length,
+                            height,
+                        }),
+                        _ => None,
+                    },
+                    alternates: Vec::new(),
+                })
+            })
+            .map_err(|e| StudFinderError::Database {
+                operation: "query all parts".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let mut parts = Vec::new();
+        for row in rows {
+            let mut part = row.map_err(|e| StudFinderError::Database {
+                operation: "process part row".to_string(),
+                source: Box::new(e),
+            })?;
+            part.alternates = alternates.remove(&part.part_number).unwrap_or_default();
+            parts.push(part);
+        }
+
+        debug!("Found {} catalog part(s)", parts.len());
+        Ok(parts)
+    }
+
+    /// Stores imported sets, replacing the parts of any set already stored
+    ///
+    /// # Arguments
+    ///
+    /// * `sets` - The sets to store
+    ///
+    /// # Returns
+    ///
+    /// The number of sets stored, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start a transaction
+    /// - Failed to insert a set or its parts
+    /// - Failed to commit the transaction
+    pub fn save_sets(&self, sets: &[LegoSet]) -> Result<usize> {
+        debug!("Saving {} set(s)", sets.len());
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "save sets".to_string(),
+            })?;
+
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        {
+            let mut insert_set = tx
+                .prepare("INSERT OR REPLACE INTO sets (set_number, name, year) VALUES (?1, ?2, ?3)")
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare set insert".to_string(),
+                    source: Box::new(e),
+                })?;
+            let mut clear_parts = tx
+                .prepare("DELETE FROM set_parts WHERE set_number = ?")
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare set part delete".to_string(),
+                    source: Box::new(e),
+                })?;
+            let mut insert_part = tx
+                .prepare(
+                    "INSERT INTO set_parts (set_number, part_number, color, quantity)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (set_number, part_number, color)
+                 DO UPDATE SET quantity = quantity + excluded.quantity",
+                )
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare set part insert".to_string(),
+                    source: Box::new(e),
+                })?;
+
+            for set in sets {
+                insert_set
+                    .execute(params![
+                        set.set_number,
+                        set.name,
+                        set.year
+                    ])
+                    .map_err(|e| StudFinderError::Database {
+                        operation: format!("insert set '{}'", set.set_number),
+                        source: Box::new(e),
+                    })?;
+
+                clear_parts
+                    .execute([&set.set_number])
+                    .map_err(|e| StudFinderError::Database {
+                        operation: format!("clear parts of set '{}'", set.set_number),
+                        source: Box::new(e),
+                    })?;
+                for part in &set.parts {
+                    insert_part
+                        .execute(params![
+                            set.set_number,
+                            part.part_number,
+                            part.color,
+                            part.quantity
+                        ])
+                        .map_err(|e| StudFinderError::Database {
+                            operation: format!(
+                                "insert part '{}' of set '{}'",
+                                part.part_number, set.set_number
+                            ),
+                            source: Box::new(e),
+                        })?;
+                }
+            }
+        }
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        info!("Saved {} set(s)", sets.len());
+        Ok(sets.len())
+    }
+
+    /// Retrieves a set and its parts by set number
+    ///
+    /// # Arguments
+    ///
+    /// * `set_number` - The set number, including its version suffix
+    ///
+    /// # Returns
+    ///
+    /// The set if it has been imported, `None` otherwise, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the queries
+    pub fn get_set(&self, set_number: &str) -> Result<Option<LegoSet>> {
+        debug!("Getting set: {}", set_number);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "get set".to_string(),
+            })?;
+
+        let set = conn
+            .query_row(
+                "SELECT set_number, name, year FROM sets WHERE set_number = ?",
+                [set_number],
+                |row| {
+                    Ok(LegoSet {
+                        set_number: row.get(0)?,
+                        name: row.get(1)?,
+                        year: row.get(2)?,
+                        parts: Vec::new(),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query set '{}'", set_number),
+                source: Box::new(e),
+            })?;
+        let Some(mut set) = set else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT part_number, color, quantity FROM set_parts
+             WHERE set_number = ? ORDER BY part_number, color",
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "prepare select statement".to_string(),
+                source: Box::new(e),
+            })?;
+        set.parts = stmt
+            .query_map([set_number], |row| {
+                Ok(SetPart {
+                    part_number: row.get(0)?,
+                    color: row.get(1)?,
+                    quantity: row.get(2)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query parts of set '{}'", set_number),
+                source: Box::new(e),
+            })?;
+
+        debug!("Found {} part(s) in set {}", set.parts.len(), set_number);
+        Ok(Some(set))
+    }
+
+    /// Stores a calibration profile, replacing any profile with the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The profile to store
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database lock cannot be acquired or the
+    /// insert fails.
+    pub fn save_calibration(&self, profile: &CalibrationProfile) -> Result<()> {
+        debug!("Saving calibration profile: {}", profile.name);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "save calibration profile".to_string(),
+            })?;
+
+        let matrix = serde_json::to_string(&profile.white_balance.matrix).map_err(|e| {
+            StudFinderError::Database {
+                operation: format!("encode matrix of calibration profile '{}'", profile.name),
+                source: Box::new(e),
+            }
+        })?;
+        let [r, g, b] = profile.measured;
+        conn.execute(
+            "INSERT OR REPLACE INTO calibration_profiles
+                 (name, reference, measured_r, measured_g, measured_b, matrix)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                profile.name,
+                profile.reference,
+                r,
+                g,
+                b,
+                matrix
+            ],
+        )
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("insert calibration profile '{}'", profile.name),
+            source: Box::new(e),
+        })?;
+        Ok(())
+    }
+
+    /// Retrieves a calibration profile by name
+    ///
+    /// # Returns
+    ///
+    /// The profile, or `None` if there is none by that name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database lock cannot be acquired or the
+    /// query fails.
+    pub fn get_calibration(&self, name: &str) -> Result<Option<CalibrationProfile>> {
+        debug!("Getting calibration profile: {}", name);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "get calibration profile".to_string(),
+            })?;
+
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM calibration_profiles WHERE name = ?",
+                CALIBRATION_COLUMNS
+            ),
+            [name],
+            calibration_from_row,
+        )
+        .optional()
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("query calibration profile '{}'", name),
+            source: Box::new(e),
+        })
+    }
+
+    /// Lists every calibration profile, by name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database lock cannot be acquired or the
+    /// query fails.
+    pub fn list_calibrations(&self) -> Result<Vec<CalibrationProfile>> {
+        debug!("Listing calibration profiles");
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "list calibration profiles".to_string(),
+            })?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM calibration_profiles ORDER BY name",
+                CALIBRATION_COLUMNS
+            ))
+            .map_err(|e| StudFinderError::Database {
+                operation: "prepare select statement".to_string(),
+                source: Box::new(e),
+            })?;
+        stmt.query_map([], calibration_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| StudFinderError::Database {
+                operation: "query calibration profiles".to_string(),
+                source: Box::new(e),
+            })
+    }
+
+    /// Deletes a calibration profile
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationNotFound` if there is no profile by that name, or
+    /// an error if the delete fails.
+    pub fn delete_calibration(&self, name: &str) -> Result<()> {
+        debug!("Deleting calibration profile: {}", name);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "delete calibration profile".to_string(),
+            })?;
+
+        let rows_affected = conn
+            .execute("DELETE FROM calibration_profiles WHERE name = ?", [name])
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("delete calibration profile '{}'", name),
+                source: Box::new(e),
+            })?;
+        if rows_affected == 0 {
+            return Err(StudFinderError::CalibrationNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the content hashes of every image in the scan ledger
+    ///
+    /// # Returns
+    ///
+    /// The set of recorded hashes, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the query
+    pub fn scanned_hashes(&self) -> Result<HashSet<String>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "list scanned hashes".to_string(),
+            })?;
+
+        let mut stmt =
+            conn.prepare("SELECT hash FROM scans")
+                .map_err(|e| StudFinderError::Database {
+                    operation: "prepare scans statement".to_string(),
+                    source: Box::new(e),
+                })?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<HashSet<String>>>())
+            .map_err(|e| StudFinderError::Database {
+                operation: "query scanned hashes".to_string(),
+                source: Box::new(e),
+            })?;
+
+        debug!("Scan ledger holds {} image(s)", hashes.len());
+        Ok(hashes)
+    }
+
+    /// Adds the pieces found in an image and records the image in the scan ledger
+    ///
+    /// Both happen in one transaction, so an interrupted batch never leaves
+    /// pieces without their ledger entry. If an image with the same hash is
+    /// already recorded, nothing is added unless `replace` is set; then the
+    /// pieces of every earlier scan with the same hash or path are taken
+    /// back out of the inventory first, together with their observations.
+    /// Each detection is also stored as an observation of the row its piece
+    /// was stored in, and the pieces' IDs are set to those rows. `pending`
+    /// detections are put in the review queue instead; a rescan replaces
+    /// those that are still queued too.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The scanned image and how it was processed
+    /// * `detections` - The detected pieces to add to the inventory
+    /// * `pending` - The detected pieces to queue for review
+    /// * `replace` - Whether to replace earlier scans of the image
+    ///
+    /// # Returns
+    ///
+    /// Whether the scan was recorded, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start or commit the transaction
+    /// - Failed to query or update the ledger or the pieces
+    pub fn record_scan(
+        &self,
+        source: &ScanSource<'_>,
+        detections: &mut [Detection],
+        pending: &[Detection],
+        replace: bool,
+    ) -> Result<RecordOutcome> {
+        let hash = source.hash;
+        debug!("Recording scan of {} ({})", source.path.display(), hash);
+        let path = source.path.display().to_string();
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "record scan".to_string(),
+            })?;
+
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        let mut replaced = 0;
+        let mut emptied = Vec::new();
+        if replace {
+            let previous: Vec<String> = tx
+                .prepare("SELECT id FROM scans WHERE hash = ?1 OR path = ?2")
+                .and_then(|mut stmt| {
+                    stmt.query_map(params![hash, path], |row| row.get(0))?
+                        .collect()
+                })
+                .map_err(|e| StudFinderError::Database {
+                    operation: "query previous scans".to_string(),
+                    source: Box::new(e),
+                })?;
+            for scan_id in &previous {
+                emptied.extend(Self::remove_scan_in(&tx, scan_id)?);
+            }
+            replaced = previous.len();
+        } else {
+            let existing: Option<String> = tx
+                .query_row("SELECT path FROM scans WHERE hash = ?", [hash], |row| {
+                    row.get(0)
+                })
+                .optional()
+                .map_err(|e| StudFinderError::Database {
+                    operation: "query scan ledger".to_string(),
+                    source: Box::new(e),
+                })?;
+            if let Some(path) = existing {
+                return Ok(RecordOutcome::AlreadyScanned {
+                    path,
+                });
+            }
+        }
+
+        let scan_id = Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO scans (id, path, hash, processor) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                scan_id,
+                path,
+                hash,
+                source.processor.as_str()
+            ],
+        )
+        .map_err(|e| StudFinderError::Database {
+            operation: "insert scan".to_string(),
+            source: Box::new(e),
+        })?;
+
+        for detection in detections.iter_mut() {
+            let piece = &mut detection.piece;
+            piece.id = Self::add_piece_in(&tx, piece)?.id().to_string();
+            tx.execute(
+                "INSERT INTO scan_pieces (scan_id, piece_id, quantity) VALUES (?1, ?2, ?3)",
+                params![
+                    scan_id,
+                    piece.id,
+                    piece.quantity
+                ],
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "insert scan piece".to_string(),
+                source: Box::new(e),
+            })?;
+            Self::add_observation_in(&tx, Some(&scan_id), source, detection)?;
+        }
+        for detection in pending {
+            Self::queue_review_in(&tx, Some(&scan_id), source, detection)?;
+        }
+
+        // Deleted only now, so pieces found again keep their IDs
+        for piece_id in emptied {
+            tx.execute(
+                "DELETE FROM pieces WHERE id = ? AND quantity = 0",
+                [&piece_id],
+            )
+            .and_then(|_| {
+                tx.execute(
+                    "DELETE FROM scan_observations
+                     WHERE piece_id = ?1 AND NOT EXISTS (SELECT 1 FROM pieces WHERE id = ?1)",
+                    [&piece_id],
+                )
+            })
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("delete emptied piece '{}'", piece_id),
+                source: Box::new(e),
+            })?;
+            Self::fit_locations_in(&tx, &piece_id)?;
+        }
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        Ok(RecordOutcome::Recorded {
+            replaced,
+        })
+    }
+
+    /// Adds the pieces found in an image, with their observations, without a ledger entry
+    ///
+    /// Used for single-image scans, which add to the inventory every time
+    /// they are run. The detections' piece IDs are set to the rows they were
+    /// stored in; `pending` detections are put in the review queue instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The scanned image and how it was processed
+    /// * `detections` - The detected pieces to add to the inventory
+    /// * `pending` - The detected pieces to queue for review
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start or commit the transaction
+    /// - Failed to store a piece or observation
+    pub fn add_detections(
+        &self,
+        source: &ScanSource<'_>,
+        detections: &mut [Detection],
+        pending: &[Detection],
+    ) -> Result<()> {
+        debug!(
+            "Adding {} detection(s) from {}",
+            detections.len(),
+            source.path.display()
+        );
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "add detections".to_string(),
+            })?;
+
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        for detection in detections.iter_mut() {
+            detection.piece.id = Self::add_piece_in(&tx, &detection.piece)?.id().to_string();
+            Self::add_observation_in(&tx, None, source, detection)?;
+        }
+        for detection in pending {
+            Self::queue_review_in(&tx, None, source, detection)?;
+        }
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })
+    }
+
+    /// Lists the observations of an inventory entry, oldest first
+    ///
+    /// # Arguments
+    ///
+    /// * `piece_id` - The ID of the inventory entry
+    ///
+    /// # Returns
+    ///
+    /// The entry's observations (empty for pieces added by hand or
+    /// imported), or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the query
+    pub fn list_observations(&self, piece_id: &str) -> Result<Vec<Observation>> {
+        debug!("Listing observations of piece {}", piece_id);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "list observations".to_string(),
+            })?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM scan_observations WHERE piece_id = ?
+                 ORDER BY observed_at, rowid",
+                OBSERVATION_COLUMNS
+            ))
+            .map_err(|e| StudFinderError::Database {
+                operation: "prepare observations statement".to_string(),
+                source: Box::new(e),
+            })?;
+        let observations = stmt
+            .query_map([piece_id], observation_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query observations of piece '{}'", piece_id),
+                source: Box::new(e),
+            })?;
+
+        Ok(observations)
+    }
+
+    /// Store a detection as an observation of the row its piece is in
+    fn add_observation_in(
+        tx: &Transaction<'_>,
+        scan_id: Option<&str>,
+        source: &ScanSource<'_>,
+        detection: &Detection,
+    ) -> Result<()> {
+        let piece = &detection.piece;
+        let bounds = detection.bounds;
+        let [r, g, b] = detection
+            .measured_rgb
+            .map_or([None; 3], |rgb| rgb.map(Some));
+        tx.execute(
+            &format!(
+                "INSERT INTO scan_observations ({}, scan_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                         CURRENT_TIMESTAMP, ?17)",
+                OBSERVATION_COLUMNS
+            ),
+            params![
+                Uuid::new_v4().to_string(),
+                piece.id,
+                source.path.display().to_string(),
+                source.hash,
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height,
+                source.processor.as_str(),
+                source.quality.as_str(),
+                r,
+                g,
+                b,
+                piece.part_number,
+                piece.color,
+                piece.confidence,
+                scan_id
+            ],
+        )
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("insert observation of piece '{}'", piece.id),
+            source: Box::new(e),
+        })?;
+        Ok(())
+    }
+
+    /// Put a detection in the review queue
+    fn queue_review_in(
+        tx: &Transaction<'_>,
+        scan_id: Option<&str>,
+        source: &ScanSource<'_>,
+        detection: &Detection,
+    ) -> Result<()> {
+        let piece = &detection.piece;
+        let bounds = detection.bounds;
+        let [r, g, b] = detection
+            .measured_rgb
+            .map_or([None; 3], |rgb| rgb.map(Some));
+        debug!("Queueing {} for review ({:.2})", piece, piece.confidence);
+        tx.execute(
+            &format!(
+                "INSERT INTO pending_review ({}, scan_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                         CURRENT_TIMESTAMP, ?17)",
+                PENDING_COLUMNS
+            ),
+            params![
+                Uuid::new_v4().to_string(),
+                piece.part_number,
+                piece.color,
+                piece.category,
+                piece.confidence,
+                source.path.display().to_string(),
+                source.hash,
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height,
+                source.processor.as_str(),
+                source.quality.as_str(),
+                r,
+                g,
+                b,
+                scan_id
+            ],
+        )
+        .map_err(|e| StudFinderError::Database {
+            operation: "queue detection for review".to_string(),
+            source: Box::new(e),
+        })?;
+        Ok(())
+    }
+
+    /// Lists the detections waiting for review, oldest first
+    ///
+    /// # Returns
+    ///
+    /// The queued detections, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to prepare or execute the query
+    pub fn list_pending_reviews(&self) -> Result<Vec<PendingReview>> {
+        debug!("Listing pending reviews");
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "list pending reviews".to_string(),
+            })?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM pending_review ORDER BY queued_at, rowid",
+                PENDING_COLUMNS
+            ))
+            .map_err(|e| StudFinderError::Database {
+                operation: "prepare pending review statement".to_string(),
+                source: Box::new(e),
+            })?;
+        let pending = stmt
+            .query_map([], pending_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| StudFinderError::Database {
+                operation: "query pending reviews".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(pending)
+    }
+
+    /// Retrieves a queued detection by its ID
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the queued detection
+    ///
+    /// # Returns
+    ///
+    /// The queued detection if found, None if not found, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to execute the query
+    pub fn get_pending_review(&self, id: &str) -> Result<Option<PendingReview>> {
+        debug!("Getting pending review {}", id);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "get pending review".to_string(),
+            })?;
+
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM pending_review WHERE id = ?",
+                PENDING_COLUMNS
+            ),
+            [id],
+            pending_from_row,
+        )
+        .optional()
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("query review '{}'", id),
+            source: Box::new(e),
+        })
+    }
+
+    /// Moves a queued detection into the inventory
+    ///
+    /// `piece` is what the reviewer decided the detection is; it is added
+    /// like a scanned piece, and the detection becomes an observation of
+    /// the row it is stored in. If the detection came from a batch scan, a
+    /// rescan of the image takes the piece back out.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the queued detection
+    /// * `piece` - The piece to add
+    ///
+    /// # Returns
+    ///
+    /// The ID of the row the piece was stored in, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to start or commit the transaction
+    /// - Failed to store the piece or its observation
+    /// - The queued detection with the specified ID does not exist
+    pub fn accept_review(&self, id: &str, piece: &Piece) -> Result<String> {
+        debug!("Accepting review {} as {}", id, piece);
+
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "accept review".to_string(),
+            })?;
+
+        let tx = conn.transaction().map_err(|e| StudFinderError::Database {
+            operation: "begin transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        let scan_id: Option<Option<String>> = tx
+            .query_row(
+                "SELECT scan_id FROM pending_review WHERE id = ?",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("query review '{}'", id),
+                source: Box::new(e),
+            })?;
+        let Some(scan_id) = scan_id else {
+            return Err(StudFinderError::ReviewNotFound(id.to_string()));
+        };
+
+        let piece_id = Self::add_piece_in(&tx, piece)?.id().to_string();
+        if let Some(scan_id) = &scan_id {
+            tx.execute(
+                "INSERT INTO scan_pieces (scan_id, piece_id, quantity) VALUES (?1, ?2, ?3)",
+                params![
+                    scan_id,
+                    piece_id,
+                    piece.quantity
+                ],
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "insert scan piece".to_string(),
+                source: Box::new(e),
+            })?;
+        }
+
+        // The observation keeps what was detected, not the correction
+        tx.execute(
+            &format!(
+                "INSERT INTO scan_observations ({}, scan_id)
+                 SELECT ?1, ?2, image_path, image_hash, bbox_x, bbox_y, bbox_width, bbox_height,
+                        processor, quality, measured_r, measured_g, measured_b,
+                        part_number, color, confidence, queued_at, scan_id
+                 FROM pending_review WHERE id = ?3",
+                OBSERVATION_COLUMNS
+            ),
+            params![
+                Uuid::new_v4().to_string(),
+                piece_id,
+                id
+            ],
+        )
+        .and_then(|_| tx.execute("DELETE FROM pending_review WHERE id = ?", [id]))
+        .map_err(|e| StudFinderError::Database {
+            operation: format!("move review '{}' to observations", id),
+            source: Box::new(e),
+        })?;
+
+        tx.commit().map_err(|e| StudFinderError::Database {
+            operation: "commit transaction".to_string(),
+            source: Box::new(e),
+        })?;
+
+        Ok(piece_id)
+    }
+
+    /// Removes a detection from the review queue without adding it
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the queued detection
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to execute the delete
+    /// - The queued detection with the specified ID does not exist
+    pub fn discard_review(&self, id: &str) -> Result<()> {
+        debug!("Discarding review {}", id);
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "discard review".to_string(),
+            })?;
+
+        let rows_affected = conn
+            .execute("DELETE FROM pending_review WHERE id = ?", [id])
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("delete review '{}'", id),
+                source: Box::new(e),
+            })?;
+
+        if rows_affected == 0 {
+            return Err(StudFinderError::ReviewNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Take a scan's pieces back out of the inventory and forget the scan
+    ///
+    /// Quantities moved or deleted by hand since the scan are left alone.
+    /// Returns the IDs of the rows the scan contributed to; the caller
+    /// deletes those left at zero.
+    fn remove_scan_in(tx: &Transaction<'_>, scan_id: &str) -> Result<Vec<String>> {
+        debug!("Removing the pieces of scan {}", scan_id);
+        let contributions: Vec<(String, i32)> = tx
+            .prepare("SELECT piece_id, quantity FROM scan_pieces WHERE scan_id = ?")
+            .and_then(|mut stmt| {
+                stmt.query_map([scan_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .map_err(|e| StudFinderError::Database {
+                operation: "query scan pieces".to_string(),
+                source: Box::new(e),
+            })?;
+
+        for (piece_id, quantity) in &contributions {
+            tx.execute(
+                "UPDATE pieces SET quantity = MAX(quantity - ?1, 0) WHERE id = ?2",
+                params![quantity, piece_id],
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("remove scanned quantity from piece '{}'", piece_id),
+                source: Box::new(e),
+            })?;
+        }
+
+        tx.execute("DELETE FROM scan_observations WHERE scan_id = ?", [scan_id])
+            .and_then(|_| tx.execute("DELETE FROM pending_review WHERE scan_id = ?", [scan_id]))
+            .and_then(|_| tx.execute("DELETE FROM scan_pieces WHERE scan_id = ?", [scan_id]))
+            .and_then(|_| tx.execute("DELETE FROM scans WHERE id = ?", [scan_id]))
+            .map_err(|e| StudFinderError::Database {
+                operation: format!("delete scan '{}'", scan_id),
+                source: Box::new(e),
+            })?;
+        Ok(contributions
+            .into_iter()
+            .map(|(piece_id, _)| piece_id)
+            .collect())
+    }
+
+    /// Gets the current schema version
+    ///
+    /// # Returns
+    ///
+    /// The current schema version number, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to acquire the database lock
+    /// - Failed to query the schema version
+    fn get_schema_version(&self) -> Result<i32> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| StudFinderError::DatabaseLockFailed {
+                operation: "get schema version".to_string(),
+            })?;
+
+        let version: i32 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| StudFinderError::Database {
+                operation: "query schema version".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use crate::core::piece::{ProcessorType, ScanQuality};
+
+    fn create_test_piece() -> Piece {
+        Piece {
+            id: String::from("test-piece"),
+            part_number: "3001".to_string(),
+            color: "Red".to_string(),
+            category: "Brick".to_string(),
+            quantity: 1,
+            confidence: 0.95,
+            condition: None,
+        }
+    }
+
+    fn create_test_detection(piece: Piece) -> Detection {
+        Detection {
+            piece,
+            bounds: BoundingBox {
+                x: 10,
+                y: 20,
+                width: 30,
+                height: 40,
+            },
+            measured_rgb: Some([
+                201, 26, 9,
+            ]),
+            color_candidates: Vec::new(),
+            part_candidates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_database_operations() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        // Test schema version
+        assert_eq!(db.get_schema_version().unwrap(), 10);
+
+        // Test insert
+        let piece = create_test_piece();
+        db.add_piece(&piece).unwrap();
+
+        // Test get
+        let fetched = db.get_piece(&piece.id).unwrap().unwrap();
+        assert_eq!(fetched.part_number, piece.part_number);
+        assert_eq!(fetched.confidence, piece.confidence);
+
+        // Test list
+        let pieces = db.list_pieces().unwrap();
+        assert_eq!(pieces.len(), 1);
+
+        // Test update
         db.update_quantity(&piece.id, 2).unwrap();
         let updated = db.get_piece(&piece.id).unwrap().unwrap();
         assert_eq!(updated.quantity, 2);
@@ -637,7 +2927,7 @@ mod tests {
         // Reset database
         db.reset().unwrap();
         assert_eq!(db.list_pieces().unwrap().len(), 0);
-        assert_eq!(db.get_schema_version().unwrap(), 2);
+        assert_eq!(db.get_schema_version().unwrap(), 10);
     }
 
     #[test]
@@ -658,6 +2948,21 @@ mod tests {
             e => panic!("Expected PieceNotFound error, got: {:?}", e),
         }
 
+        // Test edit with non-existent ID
+        let piece = Piece {
+            id: "non-existent".to_string(),
+            part_number: "3001".to_string(),
+            color: "Red".to_string(),
+            category: "Bricks".to_string(),
+            quantity: 1,
+            confidence: 1.0,
+            condition: None,
+        };
+        match db.update_piece(&piece).unwrap_err() {
+            StudFinderError::PieceNotFound(id) => assert_eq!(id, "non-existent"),
+            e => panic!("Expected PieceNotFound error, got: {:?}", e),
+        }
+
         // Test delete with non-existent ID
         let result = db.delete_piece("non-existent");
         assert!(result.is_err());
@@ -682,4 +2987,607 @@ mod tests {
             e => panic!("Expected Database error, got: {:?}", e),
         }
     }
+
+    #[test]
+    fn test_parts_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+        assert!(db.list_parts().unwrap().is_empty());
+
+        let mut tile = PartInfo::new("3068b", "Tile 2 x 2 with Groove", "Tiles");
+        tile.alternates = vec!["3068".to_string()];
+        let parts = vec![
+            PartInfo::new("3001", "Brick 2 x 4", "Bricks"),
+            tile,
+        ];
+        assert_eq!(db.save_parts(&parts).unwrap(), 2);
+
+        // Saving again replaces rather than duplicates
+        db.save_parts(&parts).unwrap();
+        let stored = db.list_parts().unwrap();
+        assert_eq!(stored, parts);
+
+        db.reset().unwrap();
+        assert!(db.list_parts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sets_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+        assert_eq!(db.get_set("10696-1").unwrap(), None);
+
+        let part = |part_number: &str, color: &str, quantity| SetPart {
+            part_number: part_number.to_string(),
+            color: color.to_string(),
+            quantity,
+        };
+        let mut set = LegoSet {
+            set_number: "10696-1".to_string(),
+            name: "Medium Creative Brick Box".to_string(),
+            year: Some(2015),
+            parts: vec![
+                part("3001", "Red", 8),
+                part("3020", "Blue", 4),
+            ],
+        };
+        assert_eq!(db.save_sets(std::slice::from_ref(&set)).unwrap(), 1);
+        assert_eq!(db.get_set("10696-1").unwrap().as_ref(), Some(&set));
+
+        // Importing again replaces the part list
+        set.parts = vec![part("3003", "Black", 2)];
+        db.save_sets(std::slice::from_ref(&set)).unwrap();
+        assert_eq!(db.get_set("10696-1").unwrap(), Some(set));
+
+        db.reset().unwrap();
+        assert_eq!(db.get_set("10696-1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_calibration_profiles() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+        assert_eq!(db.get_calibration("rig").unwrap(), None);
+
+        let mut profile = CalibrationProfile {
+            name: "rig".to_string(),
+            reference: "White".to_string(),
+            measured: [
+                240, 215, 170,
+            ],
+            white_balance: WhiteBalance::from_reference(
+                [
+                    240, 215, 170,
+                ],
+                [
+                    255, 255, 255,
+                ],
+            )
+            .unwrap(),
+            created_at: None,
+        };
+        db.save_calibration(&profile).unwrap();
+        let stored = db.get_calibration("rig").unwrap().unwrap();
+        assert!(stored.created_at.is_some());
+        assert_eq!(stored.white_balance, profile.white_balance);
+
+        // Calibrating again under the same name replaces the profile
+        profile.measured = [
+            200, 200, 200,
+        ];
+        db.save_calibration(&profile).unwrap();
+        let profiles = db.list_calibrations().unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].measured, profile.measured);
+
+        db.delete_calibration("rig").unwrap();
+        assert!(matches!(
+            db.delete_calibration("rig"),
+            Err(StudFinderError::CalibrationNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_piece_aggregates_by_key() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        let first = create_test_piece();
+        let outcome = db.add_piece(&first).unwrap();
+        assert_eq!(
+            outcome,
+            AddOutcome::Inserted {
+                id: first.id.clone(),
+            }
+        );
+
+        // A freshly scanned piece with a new ID merges into the same row
+        let second = Piece {
+            id: "another-scan".to_string(),
+            quantity: 3,
+            confidence: 0.75,
+            ..create_test_piece()
+        };
+        let outcome = db.add_piece(&second).unwrap();
+        assert_eq!(
+            outcome,
+            AddOutcome::Merged {
+                id: first.id.clone(),
+                quantity: 4,
+            }
+        );
+        let merged = db.get_piece(&first.id).unwrap().unwrap();
+        assert!((merged.confidence - 0.8).abs() < 1e-6);
+
+        // Other colors and conditions get their own rows
+        let blue = Piece {
+            id: "blue".to_string(),
+            color: "Blue".to_string(),
+            ..create_test_piece()
+        };
+        let used = Piece {
+            id: first.id.clone(),
+            condition: Some(PieceCondition::Used),
+            ..create_test_piece()
+        };
+        assert!(matches!(
+            db.add_piece(&blue).unwrap(),
+            AddOutcome::Inserted { .. }
+        ));
+        let outcome = db.add_piece(&used).unwrap();
+        // The caller's ID is already taken, so a new one is assigned
+        assert_ne!(outcome.id(), first.id);
+        assert_eq!(
+            db.get_piece(outcome.id()).unwrap().unwrap().condition,
+            Some(PieceCondition::Used)
+        );
+        assert_eq!(db.list_pieces().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_migration_consolidates_duplicates() {
+        let db = Database::new(":memory:").unwrap();
+        {
+            // A version 2 database holding one row per scanned piece
+            let conn = db.conn.lock().unwrap();
+            conn.execute_batch(
+                "CREATE TABLE schema_version (
+                    version INTEGER PRIMARY KEY,
+                    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                 );
+                 INSERT INTO schema_version (version) VALUES (1), (2);
+                 CREATE TABLE pieces (
+                    id TEXT PRIMARY KEY,
+                    part_number TEXT NOT NULL,
+                    color TEXT NOT NULL,
+                    category TEXT NOT NULL,
+                    quantity INTEGER NOT NULL DEFAULT 1,
+                    confidence REAL NOT NULL DEFAULT 1.0
+                 );
+                 INSERT INTO pieces VALUES ('a', '3001', 'Red', 'Brick', 1, 0.9);
+                 INSERT INTO pieces VALUES ('b', '3001', 'Red', 'Brick', 1, 0.7);
+                 INSERT INTO pieces VALUES ('c', '3001', 'Red', 'Brick', 2, 0.8);
+                 INSERT INTO pieces VALUES ('d', '3001', 'Blue', 'Brick', 1, 0.9);",
+            )
+            .unwrap();
+        }
+
+        db.init().unwrap();
+        assert_eq!(db.get_schema_version().unwrap(), 10);
+
+        let pieces = db.list_pieces().unwrap();
+        assert_eq!(pieces.len(), 2);
+        let red = db.get_piece("a").unwrap().unwrap();
+        assert_eq!(red.quantity, 4);
+        assert!((red.confidence - 0.8).abs() < 1e-6);
+        assert!(db.get_piece("b").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_piece_merges_on_key_collision() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        let piece = |id: &str, color: &str, quantity: i32, confidence: f32| Piece {
+            id: id.to_string(),
+            part_number: "3001".to_string(),
+            color: color.to_string(),
+            category: "Bricks".to_string(),
+            quantity,
+            confidence,
+            condition: None,
+        };
+        db.add_piece(&piece("red", "Red", 3, 1.0)).unwrap();
+        db.add_piece(&piece("blue", "Blue", 1, 0.6)).unwrap();
+
+        // A free key is written in place
+        let id = db.update_piece(&piece("blue", "Green", 0, 0.0)).unwrap();
+        assert_eq!(id, "blue");
+        let green = db.get_piece("blue").unwrap().unwrap();
+        assert_eq!(green.color, "Green");
+        assert_eq!(green.quantity, 1);
+
+        // A taken key merges into the existing row
+        let id = db.update_piece(&piece("blue", "Red", 0, 0.0)).unwrap();
+        assert_eq!(id, "red");
+        assert!(db.get_piece("blue").unwrap().is_none());
+        let red = db.get_piece("red").unwrap().unwrap();
+        assert_eq!(red.quantity, 4);
+        assert!((red.confidence - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_piece_locations() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        let piece = |id: &str, color: &str, quantity: i32| Piece {
+            id: id.to_string(),
+            part_number: "3023".to_string(),
+            color: color.to_string(),
+            category: "Plates".to_string(),
+            quantity,
+            confidence: 1.0,
+            condition: None,
+        };
+        let location = |s: &str| s.parse::<StorageLocation>().unwrap();
+        let at = |s: &str, quantity| PieceLocation {
+            location: location(s),
+            quantity,
+        };
+        db.add_piece(&piece("tan", "Dark Tan", 10)).unwrap();
+
+        // Unplaced pieces move first; a location can be emptied into another
+        assert_eq!(
+            db.move_pieces("tan", None, &location("A/3/12"), Some(6))
+                .unwrap(),
+            6
+        );
+        assert_eq!(
+            db.move_pieces("tan", Some(&location("A/3/12")), &location("B/1"), Some(2))
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            db.list_piece_locations("tan").unwrap(),
+            vec![
+                at("A/3/12", 4),
+                at("B/1", 2),
+            ]
+        );
+        assert!(matches!(
+            db.move_pieces("tan", None, &location("C"), Some(5)),
+            Err(StudFinderError::InvalidPiece(_))
+        ));
+        assert!(matches!(
+            db.move_pieces("tan", Some(&location("C")), &location("B/1"), None),
+            Err(StudFinderError::InvalidPiece(_))
+        ));
+        assert!(matches!(
+            db.move_pieces("missing", None, &location("C"), None),
+            Err(StudFinderError::PieceNotFound(_))
+        ));
+        assert_eq!(
+            db.move_pieces("tan", Some(&location("B/1")), &location("A/3/12"), None)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            db.list_piece_locations("tan").unwrap(),
+            vec![at("A/3/12", 6)]
+        );
+
+        // Quantities cannot drop below what is stored
+        assert!(matches!(
+            db.update_quantity("tan", 5),
+            Err(StudFinderError::InvalidPiece(_))
+        ));
+        db.update_quantity("tan", 8).unwrap();
+
+        // Merged entries keep their locations
+        db.add_piece(&piece("brown", "Dark Brown", 3)).unwrap();
+        db.move_pieces("brown", None, &location("A/3/12"), None)
+            .unwrap();
+        db.update_piece(&piece("brown", "Dark Tan", 0)).unwrap();
+        assert_eq!(
+            db.list_piece_locations("tan").unwrap(),
+            vec![at("A/3/12", 9)]
+        );
+        assert!(db.list_piece_locations("brown").unwrap().is_empty());
+        assert_eq!(db.list_locations().unwrap()["tan"], vec![at("A/3/12", 9)]);
+
+        db.delete_piece("tan").unwrap();
+        assert!(db.list_locations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_scan_skips_and_replaces() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        let detected = || {
+            vec![
+                create_test_detection(Piece {
+                    id: String::new(),
+                    quantity: 2,
+                    ..create_test_piece()
+                }),
+            ]
+        };
+        let path = Path::new("photos/tray.jpg");
+        let source = |path, hash, processor| ScanSource {
+            path,
+            hash,
+            processor,
+            quality: ScanQuality::Balanced,
+        };
+
+        let mut detections = detected();
+        let outcome = db
+            .record_scan(
+                &source(path, "abc", ProcessorType::Scanner),
+                &mut detections,
+                &[create_test_detection(create_test_piece())],
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            outcome,
+            RecordOutcome::Recorded {
+                replaced: 0
+            }
+        );
+        assert!(db.scanned_hashes().unwrap().contains("abc"));
+        let id = detections[0].piece.id.clone();
+        assert_eq!(db.get_piece(&id).unwrap().unwrap().quantity, 2);
+        assert_eq!(db.list_observations(&id).unwrap().len(), 1);
+        assert_eq!(db.list_pending_reviews().unwrap().len(), 1);
+
+        // The same content is not added twice, even under another name
+        let outcome = db
+            .record_scan(
+                &source(Path::new("copy.jpg"), "abc", ProcessorType::Scanner),
+                &mut detected(),
+                &[],
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            outcome,
+            RecordOutcome::AlreadyScanned {
+                path: "photos/tray.jpg".to_string()
+            }
+        );
+        assert_eq!(db.get_piece(&id).unwrap().unwrap().quantity, 2);
+
+        // A rescan replaces the earlier result and its observations
+        let outcome = db
+            .record_scan(
+                &source(path, "abc", ProcessorType::Detector),
+                &mut detected(),
+                &[],
+                true,
+            )
+            .unwrap();
+        assert_eq!(
+            outcome,
+            RecordOutcome::Recorded {
+                replaced: 1
+            }
+        );
+        assert_eq!(db.get_piece(&id).unwrap().unwrap().quantity, 2);
+        let observations = db.list_observations(&id).unwrap();
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].processor, "detector");
+        // Detections still queued from the earlier scan go with it
+        assert!(db.list_pending_reviews().unwrap().is_empty());
+
+        // A rescan that finds nothing of the old piece removes its row
+        let mut other = vec![
+            create_test_detection(Piece {
+                id: String::new(),
+                color: "Blue".to_string(),
+                ..create_test_piece()
+            }),
+        ];
+        db.record_scan(
+            &source(path, "def", ProcessorType::Scanner),
+            &mut other,
+            &[],
+            true,
+        )
+        .unwrap();
+        assert!(db.get_piece(&id).unwrap().is_none());
+        assert!(db.list_observations(&id).unwrap().is_empty());
+        assert_eq!(db.scanned_hashes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_observations_follow_their_piece() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        let source = ScanSource {
+            path: Path::new("tray.jpg"),
+            hash: "abc",
+            processor: ProcessorType::Scanner,
+            quality: ScanQuality::Accurate,
+        };
+        let mut detections = vec![
+            create_test_detection(create_test_piece()),
+            create_test_detection(Piece {
+                color: "Blue".to_string(),
+                ..create_test_piece()
+            }),
+        ];
+        db.add_detections(&source, &mut detections, &[]).unwrap();
+        let red = detections[0].piece.id.clone();
+        let blue = detections[1].piece.id.clone();
+
+        let observations = db.list_observations(&red).unwrap();
+        assert_eq!(observations.len(), 1);
+        let observation = &observations[0];
+        assert_eq!(observation.image_path, "tray.jpg");
+        assert_eq!(observation.image_hash, "abc");
+        assert_eq!(observation.bounds, detections[0].bounds);
+        assert_eq!(observation.quality, "accurate");
+        assert_eq!(observation.measured_rgb, Some([201, 26, 9]));
+        assert_eq!(observation.color, "Red");
+
+        // Correcting the blue entry to red merges it, observations included
+        let mut piece = db.get_piece(&blue).unwrap().unwrap();
+        piece.color = "Red".to_string();
+        assert_eq!(db.update_piece(&piece).unwrap(), red);
+        let observations = db.list_observations(&red).unwrap();
+        assert_eq!(observations.len(), 2);
+        // The observation still shows what was detected
+        assert_eq!(observations[1].color, "Blue");
+
+        db.delete_piece(&red).unwrap();
+        assert!(db.list_observations(&red).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_review_queue() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        let source = ScanSource {
+            path: Path::new("tray.jpg"),
+            hash: "abc",
+            processor: ProcessorType::Scanner,
+            quality: ScanQuality::Fast,
+        };
+        let pending = [
+            create_test_detection(Piece {
+                confidence: 0.6,
+                ..create_test_piece()
+            }),
+            create_test_detection(Piece {
+                color: "Blue".to_string(),
+                confidence: 0.55,
+                ..create_test_piece()
+            }),
+        ];
+        db.add_detections(&source, &mut [], &pending).unwrap();
+        assert!(db.list_pieces().unwrap().is_empty());
+
+        let queue = db.list_pending_reviews().unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue[0].color, "Red");
+        assert_eq!(queue[0].image_path, "tray.jpg");
+        assert_eq!(queue[0].bounds, pending[0].bounds);
+        assert_eq!(queue[0].measured_rgb, Some([201, 26, 9]));
+        assert_eq!(
+            db.get_pending_review(&queue[1].id).unwrap(),
+            Some(queue[1].clone())
+        );
+
+        // Accepting a correction stores the piece; the observation keeps the detection
+        let id = db
+            .accept_review(
+                &queue[0].id,
+                &Piece {
+                    color: "Dark Red".to_string(),
+                    confidence: 1.0,
+                    ..create_test_piece()
+                },
+            )
+            .unwrap();
+        assert_eq!(db.get_piece(&id).unwrap().unwrap().color, "Dark Red");
+        let observations = db.list_observations(&id).unwrap();
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].color, "Red");
+        assert!((observations[0].confidence - 0.6).abs() < 1e-6);
+        assert_eq!(observations[0].observed_at, queue[0].queued_at);
+
+        db.discard_review(&queue[1].id).unwrap();
+        assert!(db.list_pending_reviews().unwrap().is_empty());
+        assert_eq!(db.list_pieces().unwrap().len(), 1);
+
+        assert!(matches!(
+            db.discard_review(&queue[1].id),
+            Err(StudFinderError::ReviewNotFound(_))
+        ));
+        assert!(matches!(
+            db.accept_review(&queue[0].id, &create_test_piece()),
+            Err(StudFinderError::ReviewNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_query_pieces() {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+
+        for (part_number, color, category, quantity, confidence) in [
+            ("3001", "Red", "Bricks", 4, 0.95),
+            ("3001", "Blue", "Bricks", 1, 0.70),
+            ("3020", "Red", "Plates", 7, 0.85),
+            ("3022", "Black", "Plates", 2, 0.99),
+        ] {
+            db.add_piece(&Piece {
+                id: String::new(),
+                part_number: part_number.to_string(),
+                color: color.to_string(),
+                category: category.to_string(),
+                quantity,
+                confidence,
+                condition: None,
+            })
+            .unwrap();
+        }
+
+        let query = |filter: PieceFilter| -> Vec<(String, String)> {
+            db.query_pieces(&filter)
+                .unwrap()
+                .into_iter()
+                .map(|p| (p.part_number, p.color))
+                .collect()
+        };
+        let key = |part: &str, color: &str| (part.to_string(), color.to_string());
+
+        // Default order is by part number, then color
+        assert_eq!(
+            query(PieceFilter::default()),
+            vec![
+                key("3001", "Blue"),
+                key("3001", "Red"),
+                key("3020", "Red"),
+                key("3022", "Black"),
+            ]
+        );
+
+        assert_eq!(
+            query(PieceFilter {
+                part_number: Some("3001".to_string()),
+                color: Some("Red".to_string()),
+                ..PieceFilter::default()
+            }),
+            vec![key("3001", "Red")]
+        );
+
+        // Category matches by prefix, case-insensitively
+        assert_eq!(
+            query(PieceFilter {
+                category: Some("plate".to_string()),
+                min_confidence: Some(0.9),
+                ..PieceFilter::default()
+            }),
+            vec![key("3022", "Black")]
+        );
+
+        assert_eq!(
+            query(PieceFilter {
+                sort: Some(crate::storage::PieceSort::Quantity),
+                descending: true,
+                limit: Some(2),
+                ..PieceFilter::default()
+            }),
+            vec![
+                key("3020", "Red"),
+                key("3001", "Red")
+            ]
+        );
+    }
 }
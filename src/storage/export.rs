@@ -1,6 +1,50 @@
-use crate::core::piece::{ExportFormat, Piece};
+use crate::core::catalog::UNKNOWN_CATEGORY;
+use crate::core::location::LocatedPiece;
+use crate::core::piece::{BrickLinkList, ExportFormat, Piece, PieceCondition};
 use crate::error::{Result, StudFinderError};
+use crate::storage::bricklink::BrickLinkXml;
+use crate::storage::ldraw::LDrawModel;
+use crate::storage::rebrickable::RebrickablePartsList;
+use serde::Deserialize;
 use std::path::Path;
+use tracing::debug;
+
+/// Header line of the CSV export, in column order
+const CSV_HEADER: [&str; 8] = [
+    "id",
+    "part_number",
+    "color",
+    "category",
+    "quantity",
+    "confidence",
+    "condition",
+    "location",
+];
+
+/// Row of an inventory CSV, matched to columns by header name
+#[derive(Debug, Deserialize)]
+struct CsvRecord {
+    #[serde(default)]
+    id: String,
+    part_number: String,
+    color: String,
+    #[serde(default)]
+    category: String,
+    quantity: i32,
+    #[serde(default)]
+    confidence: Option<f32>,
+    #[serde(default)]
+    condition: Option<String>,
+}
+
+/// Options for export formats that record more than the pieces themselves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Condition to report for every piece, overriding the stored condition
+    pub condition: Option<PieceCondition>,
+    /// Kind of BrickLink XML document to write
+    pub bricklink_list: BrickLinkList,
+}
 
 /// Functions for exporting and importing inventory data
 pub struct ExportManager;
@@ -26,33 +70,90 @@ impl ExportManager {
         path: P,
         format: &ExportFormat,
     ) -> Result<()> {
+        Self::export_inventory_with_options(pieces, path, format, &ExportOptions::default())
+    }
+
+    /// Export inventory data to a file with format-specific options
+    ///
+    /// # Arguments
+    /// * `pieces` - The pieces to export
+    /// * `path` - The path to export to
+    /// * `format` - The format to export in
+    /// * `options` - Condition override and BrickLink list type
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the export was successful, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to serialize the data
+    /// - A color or condition cannot be expressed in the target format
+    /// - Failed to write to the file
+    pub fn export_inventory_with_options<P: AsRef<Path>>(
+        pieces: &[Piece],
+        path: P,
+        format: &ExportFormat,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        let pieces: Vec<LocatedPiece> = pieces
+            .iter()
+            .map(|piece| LocatedPiece::new(piece.clone(), Vec::new()))
+            .collect();
+        Self::export_located_inventory(&pieces, path, format, options)
+    }
+
+    /// Export inventory data with the storage locations of each piece
+    ///
+    /// JSON gives every piece a `locations` list and an `unplaced` count.
+    /// CSV writes one row per location holding the piece, plus one for its
+    /// unplaced pieces, with the row's share in `quantity`. The other
+    /// formats have no place for locations and export the pieces alone.
+    ///
+    /// # Arguments
+    /// * `pieces` - The pieces to export, with their locations
+    /// * `path` - The path to export to
+    /// * `format` - The format to export in
+    /// * `options` - Condition override and BrickLink list type
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the export was successful, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Failed to serialize the data
+    /// - A color or condition cannot be expressed in the target format
+    /// - Failed to write to the file
+    pub fn export_located_inventory<P: AsRef<Path>>(
+        pieces: &[LocatedPiece],
+        path: P,
+        format: &ExportFormat,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        let plain = || -> Vec<Piece> { pieces.iter().map(|p| p.piece.clone()).collect() };
         match format {
             ExportFormat::Json => {
                 let json = serde_json::to_string_pretty(pieces)
                     .map_err(|e| StudFinderError::Config(e.to_string()))?;
                 std::fs::write(&path, json).map_err(StudFinderError::Io)?;
             }
-            ExportFormat::Csv => {
-                let mut output = String::new();
-                output.push_str("id,part_number,color,category,quantity,confidence\n");
-                for piece in pieces {
-                    output.push_str(&format!(
-                        "{},{},{},{},{},{}\n",
-                        piece.id,
-                        piece.part_number,
-                        piece.color,
-                        piece.category,
-                        piece.quantity,
-                        piece.confidence
-                    ));
-                }
-                std::fs::write(&path, output).map_err(StudFinderError::Io)?;
+            ExportFormat::Csv => Self::write_csv(pieces, path.as_ref())?,
+            ExportFormat::Rebrickable => RebrickablePartsList::write(&plain(), &path)?,
+            ExportFormat::BrickLinkXml => BrickLinkXml::write(&plain(), &path, options)?,
+            ExportFormat::LDraw => {
+                return Err(StudFinderError::ExportFailed(
+                    "LDraw models can be imported but not exported".to_string(),
+                ))
             }
         }
         Ok(())
     }
 
-    /// Import inventory data from a file
+    /// Import inventory data from a file, detecting its format
+    ///
+    /// The format is recognized from the content (see `detect_format`), and
+    /// from the file extension if the content is not conclusive.
     ///
     /// # Arguments
     /// * `path` - The path to import from
@@ -64,49 +165,205 @@ impl ExportManager {
     ///
     /// Returns an error if:
     /// - Failed to read the file
-    /// - Failed to parse the data
+    /// - The format cannot be recognized
+    /// - Failed to parse the data (`ImportFailed` names the offending line
+    ///   where the format has lines)
     pub fn import_inventory<P: AsRef<Path>>(path: P) -> Result<Vec<Piece>> {
         let path = path.as_ref();
-
-        if path.extension().and_then(|s| s.to_str()) == Some("json") {
-            let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
-            let pieces: Vec<Piece> = serde_json::from_str(&data).map_err(|e| {
-                StudFinderError::Config(format!("Failed to parse JSON data: {}", e))
+        let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
+        let format = Self::detect_format(&data)
+            .or_else(|| ExportFormat::from_path(path))
+            .ok_or_else(|| {
+                StudFinderError::ImportFailed(format!(
+                    "{}: unrecognized format; pass the format explicitly",
+                    path.display()
+                ))
             })?;
-            Ok(pieces)
+        debug!("Importing {} as {}", path.display(), format);
+        Self::import_inventory_as(path, &format)
+    }
+
+    /// Import inventory data from a file in the given format
+    ///
+    /// # Arguments
+    /// * `path` - The path to import from
+    /// * `format` - The format of the file
+    ///
+    /// # Returns
+    /// * `Result<Vec<Piece>>` - The imported pieces, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not valid in the
+    /// given format.
+    pub fn import_inventory_as<P: AsRef<Path>>(
+        path: P,
+        format: &ExportFormat,
+    ) -> Result<Vec<Piece>> {
+        let path = path.as_ref();
+        match format {
+            ExportFormat::Json => {
+                let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
+                serde_json::from_str(&data).map_err(|e| {
+                    StudFinderError::ImportFailed(format!(
+                        "{} (line {}): {}",
+                        path.display(),
+                        e.line(),
+                        e
+                    ))
+                })
+            }
+            ExportFormat::Csv => Self::read_csv(path),
+            ExportFormat::Rebrickable => RebrickablePartsList::read(path),
+            ExportFormat::BrickLinkXml => BrickLinkXml::read(path),
+            ExportFormat::LDraw => LDrawModel::read(path),
+        }
+    }
+
+    /// Recognize an inventory format from file content
+    ///
+    /// Checks, in order, for a JSON array, a BrickLink XML document, an
+    /// LDraw model, a Rebrickable parts-list header and a StudFinder CSV
+    /// header (any column order, as long as there is a `part_number`
+    /// column).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use studfinder::storage::ExportManager;
+    /// use studfinder::ExportFormat;
+    ///
+    /// let format = ExportManager::detect_format("Part,Color,Quantity,Is Spare\n3001,4,1,False\n");
+    /// assert_eq!(format, Some(ExportFormat::Rebrickable));
+    /// assert_eq!(ExportManager::detect_format("hello"), None);
+    /// ```
+    #[must_use]
+    pub fn detect_format(data: &str) -> Option<ExportFormat> {
+        let data = data.trim_start_matches('\u{feff}').trim_start();
+        let header = data.lines().next().unwrap_or_default();
+
+        if data.starts_with('[') {
+            Some(ExportFormat::Json)
+        } else if BrickLinkXml::is_document(data) {
+            Some(ExportFormat::BrickLinkXml)
+        } else if LDrawModel::is_document(data) {
+            Some(ExportFormat::LDraw)
+        } else if RebrickablePartsList::is_header(header) {
+            Some(ExportFormat::Rebrickable)
+        } else if header.split(',').any(|column| {
+            column
+                .trim()
+                .trim_matches('"')
+                .eq_ignore_ascii_case("part_number")
+        }) {
+            Some(ExportFormat::Csv)
         } else {
-            // Assume CSV
-            let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
-            let mut pieces = Vec::new();
-
-            for line in data.lines().skip(1) {
-                // Skip header
-                let fields: Vec<&str> = line.split(',').collect();
-                if fields.len() == 6 {
-                    let piece = Piece {
-                        id: fields[0].to_string(),
-                        part_number: fields[1].to_string(),
-                        color: fields[2].to_string(),
-                        category: fields[3].to_string(),
-                        quantity: fields[4].parse().map_err(|_| {
-                            StudFinderError::Config("Failed to parse quantity".to_string())
-                        })?,
-                        confidence: fields[5].parse().map_err(|_| {
-                            StudFinderError::Config("Failed to parse confidence".to_string())
-                        })?,
-                    };
-                    pieces.push(piece);
-                }
+            None
+        }
+    }
+
+    /// Write pieces as RFC 4180 CSV with a header line
+    ///
+    /// A piece kept in several locations gets one row per location, and one
+    /// with an empty location for its unplaced pieces.
+    fn write_csv(pieces: &[LocatedPiece], path: &Path) -> Result<()> {
+        let to_error = |e: csv::Error| StudFinderError::ExportFailed(e.to_string());
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(path)
+            .map_err(to_error)?;
+
+        // Written explicitly so that an empty inventory still has a header
+        writer.write_record(CSV_HEADER).map_err(to_error)?;
+        for located in pieces {
+            let piece = &located.piece;
+            let mut shares: Vec<(String, i32)> = located
+                .locations
+                .iter()
+                .map(|l| (l.location.to_string(), l.quantity))
+                .collect();
+            if located.unplaced > 0 || shares.is_empty() {
+                shares.push((String::new(), located.unplaced));
             }
-            Ok(pieces)
+            for (location, quantity) in shares {
+                writer
+                    .serialize((
+                        &piece.id,
+                        &piece.part_number,
+                        &piece.color,
+                        &piece.category,
+                        quantity,
+                        piece.confidence,
+                        piece.condition,
+                        location,
+                    ))
+                    .map_err(to_error)?;
+            }
+        }
+        writer.flush().map_err(StudFinderError::Io)
+    }
+
+    /// Read pieces from CSV, mapping columns by their header names
+    ///
+    /// Columns may appear in any order and unknown columns are ignored.
+    /// `part_number`, `color` and `quantity` are required; a missing `id` is
+    /// left for the database to assign, a missing `category` is "Unknown" and
+    /// a missing `confidence` is 1.0.
+    fn read_csv(path: &Path) -> Result<Vec<Piece>> {
+        let to_error = |e: csv::Error| {
+            let location = e
+                .position()
+                .map(|pos| format!(" (line {})", pos.line()))
+                .unwrap_or_default();
+            StudFinderError::ImportFailed(format!("{}{}: {}", path.display(), location, e))
+        };
+
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .map_err(to_error)?;
+        let headers = reader.headers().map_err(to_error)?.clone();
+
+        let mut pieces = Vec::new();
+        for result in reader.records() {
+            let row = result.map_err(to_error)?;
+            let record: CsvRecord = row.deserialize(Some(&headers)).map_err(to_error)?;
+            let condition = match record.condition.as_deref() {
+                None | Some("") => None,
+                Some(value) => Some(value.parse().map_err(|e| {
+                    StudFinderError::ImportFailed(format!(
+                        "{} (line {}): {}",
+                        path.display(),
+                        row.position().map_or(0, csv::Position::line),
+                        e
+                    ))
+                })?),
+            };
+
+            pieces.push(Piece {
+                id: record.id,
+                part_number: record.part_number,
+                color: record.color,
+                category: if record.category.is_empty() {
+                    UNKNOWN_CATEGORY.to_string()
+                } else {
+                    record.category
+                },
+                quantity: record.quantity,
+                confidence: record.confidence.unwrap_or(1.0),
+                condition,
+            });
         }
+        Ok(pieces)
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::disallowed_methods)]
 mod tests {
     use super::*;
-    use tempfile;
+    use crate::core::location::PieceLocation;
+    use crate::core::piece::PieceCondition;
     use uuid::Uuid;
 
     fn create_test_pieces() -> Vec<Piece> {
@@ -118,6 +375,7 @@ mod tests {
                 category: "Brick".to_string(),
                 quantity: 1,
                 confidence: 0.95,
+                condition: None,
             },
             Piece {
                 id: Uuid::new_v4().to_string(),
@@ -126,6 +384,7 @@ mod tests {
                 category: "Plate".to_string(),
                 quantity: 2,
                 confidence: 0.85,
+                condition: Some(PieceCondition::Used),
             },
         ]
     }
@@ -166,5 +425,234 @@ mod tests {
         assert_eq!(imported.len(), pieces.len());
         assert_eq!(imported[0].part_number, pieces[0].part_number);
         assert_eq!(imported[1].color, pieces[1].color);
+        assert_eq!(imported[0].condition, None);
+        assert_eq!(imported[1].condition, Some(PieceCondition::Used));
+    }
+
+    #[test]
+    fn test_export_with_locations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pieces = create_test_pieces();
+        let located = vec![
+            LocatedPiece::new(
+                Piece {
+                    quantity: 6,
+                    ..pieces[0].clone()
+                },
+                vec![
+                    PieceLocation {
+                        location: "A/3/12".parse().unwrap(),
+                        quantity: 4,
+                    },
+                ],
+            ),
+            LocatedPiece::new(pieces[1].clone(), Vec::new()),
+        ];
+
+        let path = temp_dir.path().join("test.csv");
+        ExportManager::export_located_inventory(
+            &located,
+            &path,
+            &ExportFormat::Csv,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+        let data = std::fs::read_to_string(&path).unwrap();
+        let rows: Vec<&str> = data.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].ends_with(",condition,location"), "{}", data);
+        assert!(rows[1].ends_with(",A/3/12"), "{}", data);
+        assert!(rows[2].ends_with(','), "{}", data);
+
+        // Importing the rows adds the shares back up
+        let imported = ExportManager::import_inventory(&path).unwrap();
+        assert_eq!(imported[0].quantity + imported[1].quantity, 6);
+
+        let path = temp_dir.path().join("test.json");
+        ExportManager::export_located_inventory(
+            &located,
+            &path,
+            &ExportFormat::Json,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json[0]["locations"][0]["location"], "A/3/12");
+        assert_eq!(json[0]["unplaced"], 2);
+        assert_eq!(ExportManager::import_inventory(&path).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_rebrickable_export_import() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("rebrickable.csv");
+
+        let pieces = create_test_pieces();
+
+        // Export
+        ExportManager::export_inventory(&pieces, &path, &ExportFormat::Rebrickable).unwrap();
+
+        // Import (recognized by its header)
+        let imported = ExportManager::import_inventory(&path).unwrap();
+
+        // Verify
+        assert_eq!(imported.len(), pieces.len());
+        assert_eq!(imported[0].part_number, pieces[0].part_number);
+        assert_eq!(imported[0].color, pieces[0].color);
+        assert_eq!(imported[1].color, pieces[1].color);
+        assert_eq!(imported[1].quantity, pieces[1].quantity);
+    }
+
+    #[test]
+    fn test_bricklink_export_import() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("bricklink.xml");
+
+        let pieces = create_test_pieces();
+        let options = ExportOptions {
+            condition: Some(PieceCondition::New),
+            bricklink_list: BrickLinkList::Store,
+        };
+
+        // Export
+        ExportManager::export_inventory_with_options(
+            &pieces,
+            &path,
+            &ExportFormat::BrickLinkXml,
+            &options,
+        )
+        .unwrap();
+
+        // Import (recognized by its root element)
+        let imported = ExportManager::import_inventory(&path).unwrap();
+
+        // Verify
+        assert_eq!(imported.len(), pieces.len());
+        assert_eq!(imported[0].part_number, pieces[0].part_number);
+        assert_eq!(imported[1].color, pieces[1].color);
+        assert_eq!(imported[1].quantity, pieces[1].quantity);
+        // The condition option overrides the stored condition
+        assert_eq!(imported[1].condition, Some(PieceCondition::New));
+    }
+
+    #[test]
+    fn test_csv_quoting_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.csv");
+
+        let mut pieces = create_test_pieces();
+        pieces[0].category = "Brick 2 x 4, with Holes".to_string();
+        pieces[1].color = "Light \"Bluish\" Gray".to_string();
+
+        ExportManager::export_inventory(&pieces, &path, &ExportFormat::Csv).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            content.contains("\"Brick 2 x 4, with Holes\""),
+            "{}",
+            content
+        );
+
+        let imported = ExportManager::import_inventory(&path).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].category, pieces[0].category);
+        assert_eq!(imported[1].color, pieces[1].color);
+    }
+
+    #[test]
+    fn test_csv_columns_by_header() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.csv");
+        std::fs::write(
+            &path,
+            "color,notes,quantity,part_number\nRed,from the attic,4,3001\n",
+        )
+        .unwrap();
+
+        let imported = ExportManager::import_inventory(&path).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].part_number, "3001");
+        assert_eq!(imported[0].color, "Red");
+        assert_eq!(imported[0].quantity, 4);
+        assert_eq!(imported[0].category, UNKNOWN_CATEGORY);
+        assert_eq!(imported[0].confidence, 1.0);
+        assert!(imported[0].id.is_empty());
+    }
+
+    #[test]
+    fn test_csv_reports_bad_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.csv");
+
+        for (content, expected) in [
+            (
+                "part_number,color,quantity\n3001,Red,1\n3020,Blue,two\n",
+                "line 3",
+            ),
+            ("part_number,color,quantity\n3001,Red\n", "line 2"),
+            (
+                "part_number,color,quantity,condition\n3001,Red,1,mint\n",
+                "line 2",
+            ),
+            ("part_number,quantity\n3001,1\n", "color"),
+        ] {
+            std::fs::write(&path, content).unwrap();
+            match ExportManager::import_inventory(&path) {
+                Err(StudFinderError::ImportFailed(message)) => {
+                    assert!(message.contains(expected), "{}", message);
+                }
+                other => panic!("Expected ImportFailed, got: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_format() {
+        for (data, expected) in [
+            ("  [{\"id\": \"1\"}]", Some(ExportFormat::Json)),
+            (
+                "id,part_number,color,category,quantity,confidence\n",
+                Some(ExportFormat::Csv),
+            ),
+            (
+                "\u{feff}quantity,color,part_number\n",
+                Some(ExportFormat::Csv),
+            ),
+            (
+                "Part,Color,Quantity,Is Spare\n",
+                Some(ExportFormat::Rebrickable),
+            ),
+            (
+                "part_num,color_id,quantity\n",
+                Some(ExportFormat::Rebrickable),
+            ),
+            (
+                "<?xml version=\"1.0\"?>\n<INVENTORY></INVENTORY>",
+                Some(ExportFormat::BrickLinkXml),
+            ),
+            (
+                "0 Model\n1 4 0 0 0 1 0 0 0 1 0 0 0 1 3001.dat\n",
+                Some(ExportFormat::LDraw),
+            ),
+            ("name,count\nfoo,1\n", None),
+        ] {
+            assert_eq!(ExportManager::detect_format(data), expected, "{}", data);
+        }
+    }
+
+    #[test]
+    fn test_import_detects_content_over_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pieces.txt");
+
+        let pieces = create_test_pieces();
+        ExportManager::export_inventory(&pieces, &path, &ExportFormat::Json).unwrap();
+        assert_eq!(ExportManager::import_inventory(&path).unwrap().len(), 2);
+
+        std::fs::write(&path, "name,count\nfoo,1\n").unwrap();
+        assert!(matches!(
+            ExportManager::import_inventory(&path),
+            Err(StudFinderError::ImportFailed(_))
+        ));
     }
 }
@@ -0,0 +1,192 @@
+use rusqlite::types::Value;
+
+/// Column to order inventory queries by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceSort {
+    /// Part number
+    Part,
+    /// Color name
+    Color,
+    /// Category
+    Category,
+    /// Quantity
+    Quantity,
+    /// Detection confidence
+    Confidence,
+}
+
+impl PieceSort {
+    /// Column of the `pieces` table this sort orders by
+    fn column(self) -> &'static str {
+        match self {
+            PieceSort::Part => "part_number",
+            PieceSort::Color => "color",
+            PieceSort::Category => "category",
+            PieceSort::Quantity => "quantity",
+            PieceSort::Confidence => "confidence",
+        }
+    }
+}
+
+impl std::str::FromStr for PieceSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "part" | "part_number" => Ok(PieceSort::Part),
+            "color" => Ok(PieceSort::Color),
+            "category" => Ok(PieceSort::Category),
+            "quantity" | "qty" => Ok(PieceSort::Quantity),
+            "confidence" => Ok(PieceSort::Confidence),
+            other => Err(format!(
+                "unknown sort column '{}' (expected part, color, category, quantity or confidence)",
+                other
+            )),
+        }
+    }
+}
+
+/// Criteria for `Database::query_pieces`
+///
+/// Every field is optional; the default filter matches every piece, ordered
+/// by part number and color.
+///
+/// # Examples
+///
+/// ```
+/// use studfinder::storage::{PieceFilter, PieceSort};
+///
+/// let filter = PieceFilter {
+///     color: Some("Red".to_string()),
+///     sort: Some(PieceSort::Quantity),
+///     descending: true,
+///     limit: Some(10),
+///     ..PieceFilter::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PieceFilter {
+    /// Exact part number
+    pub part_number: Option<String>,
+    /// Exact color name
+    pub color: Option<String>,
+    /// Category prefix, case-insensitive ("Plate" matches "Plates")
+    pub category: Option<String>,
+    /// Lowest confidence to include
+    pub min_confidence: Option<f32>,
+    /// Column to order by
+    pub sort: Option<PieceSort>,
+    /// Reverse the order of `sort`
+    pub descending: bool,
+    /// Maximum number of pieces to return
+    pub limit: Option<usize>,
+}
+
+impl PieceFilter {
+    /// Build the `WHERE`/`ORDER BY`/`LIMIT` tail of a query and its parameters
+    ///
+    /// Values are always bound as parameters; only column names chosen by
+    /// `PieceSort` are written into the SQL. Part number and color are
+    /// compared with `=` so that `idx_part_number` and `idx_color` apply.
+    pub(crate) fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+
+        if let Some(part_number) = &self.part_number {
+            conditions.push("part_number = ?");
+            params.push(Value::Text(part_number.clone()));
+        }
+        if let Some(color) = &self.color {
+            conditions.push("color = ?");
+            params.push(Value::Text(color.clone()));
+        }
+        if let Some(category) = &self.category {
+            conditions.push("category LIKE ? ESCAPE '\\'");
+            params.push(Value::Text(format!("{}%", escape_like(category))));
+        }
+        if let Some(min_confidence) = self.min_confidence {
+            conditions.push("confidence >= ?");
+            params.push(Value::Real(f64::from(min_confidence)));
+        }
+
+        let mut sql = String::new();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        match self.sort {
+            Some(sort) => sql.push_str(&format!(
+                " ORDER BY {} {}, part_number, color",
+                sort.column(),
+                direction
+            )),
+            None => sql.push_str(&format!(" ORDER BY part_number {0}, color {0}", direction)),
+        }
+
+        if let Some(limit) = self.limit {
+            sql.push_str(" LIMIT ?");
+            params.push(Value::Integer(i64::try_from(limit).unwrap_or(i64::MAX)));
+        }
+
+        (sql, params)
+    }
+}
+
+/// Escape `LIKE` wildcards so user input matches literally
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_filter_sql() {
+        let (sql, params) = PieceFilter::default().to_sql();
+        assert_eq!(sql, " ORDER BY part_number ASC, color ASC");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_filter_sql_binds_values() {
+        let filter = PieceFilter {
+            part_number: Some("3001".to_string()),
+            category: Some("100%_off".to_string()),
+            min_confidence: Some(0.5),
+            sort: Some(PieceSort::Quantity),
+            descending: true,
+            limit: Some(5),
+            ..PieceFilter::default()
+        };
+        let (sql, params) = filter.to_sql();
+
+        assert_eq!(
+            sql,
+            " WHERE part_number = ? AND category LIKE ? ESCAPE '\\' AND confidence >= ? \
+             ORDER BY quantity DESC, part_number, color LIMIT ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                Value::Text("3001".to_string()),
+                Value::Text("100\\%\\_off%".to_string()),
+                Value::Real(0.5),
+                Value::Integer(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_from_str() {
+        assert_eq!("qty".parse::<PieceSort>(), Ok(PieceSort::Quantity));
+        assert_eq!("Part".parse::<PieceSort>(), Ok(PieceSort::Part));
+        assert!("size".parse::<PieceSort>().is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::core::catalog::UNKNOWN_CATEGORY;
+use crate::core::piece::Piece;
+use crate::error::{Result, StudFinderError};
+use crate::processing::palette;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// LDraw color code meaning "inherit the color of the referencing line"
+const MAIN_COLOR: u32 = 16;
+
+/// Deepest submodel nesting followed before giving up (guards against cycles)
+const MAX_DEPTH: usize = 32;
+
+/// A type 1 (sub-file reference) line
+struct Reference {
+    line: usize,
+    color: u32,
+    file: String,
+}
+
+/// A parsed model: the main model and the submodels it may reference
+struct Document {
+    main: Vec<Reference>,
+    submodels: HashMap<String, Vec<Reference>>,
+}
+
+/// Functions for reading LDraw models as inventories
+///
+/// Every part referenced by a model (`.ldr`) or multi-part document (`.mpd`)
+/// becomes a piece. Submodels defined in the same document are expanded, so
+/// a submodel used twice contributes its parts twice. LDraw color codes are
+/// mapped through the palette's Rebrickable ids, which match LDraw codes for
+/// the standard colors.
+pub struct LDrawModel;
+
+impl LDrawModel {
+    /// Read the parts of an LDraw model
+    ///
+    /// # Arguments
+    /// * `path` - The `.ldr` or `.mpd` file to read
+    ///
+    /// # Returns
+    /// * `Result<Vec<Piece>>` - One piece per part and color, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImportFailed`, naming the line, if a reference line is
+    /// malformed or uses a color that is not in the palette, or if
+    /// submodels are nested too deeply.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<Piece>> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).map_err(StudFinderError::Io)?;
+        let document = Self::parse(path, &data)?;
+
+        let mut counts: BTreeMap<(String, u32), i32> = BTreeMap::new();
+        Self::count(
+            path,
+            &document.main,
+            &document.submodels,
+            None,
+            0,
+            &mut counts,
+        )?;
+
+        let pieces: Vec<Piece> = counts
+            .into_iter()
+            .map(|((part_number, code), quantity)| {
+                // Every code in `counts` was checked against the palette
+                let color =
+                    palette::find_by_rebrickable_id(code).map_or("Unknown", |c| c.bricklink_name);
+                Piece {
+                    id: Uuid::new_v4().to_string(),
+                    part_number,
+                    color: color.to_string(),
+                    category: UNKNOWN_CATEGORY.to_string(),
+                    quantity,
+                    confidence: 1.0,
+                    condition: None,
+                }
+            })
+            .collect();
+
+        debug!(
+            "Read {} part/color combination(s) from LDraw model",
+            pieces.len()
+        );
+        Ok(pieces)
+    }
+
+    /// Whether a document looks like an LDraw model
+    ///
+    /// Every non-blank line must start with an LDraw line type (0-5) and at
+    /// least one line must reference a sub-file.
+    #[must_use]
+    pub fn is_document(data: &str) -> bool {
+        let mut lines = data
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .peekable();
+        if lines.peek().is_none() {
+            return false;
+        }
+
+        let mut has_reference = false;
+        for line in lines {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("1") => has_reference = true,
+                Some("0" | "2" | "3" | "4" | "5") => {}
+                _ => return false,
+            }
+        }
+        has_reference
+    }
+
+    /// Split a document into its main model and named submodels
+    fn parse(path: &Path, data: &str) -> Result<Document> {
+        let mut sections: Vec<(Option<String>, Vec<Reference>)> = vec![(None, Vec::new())];
+
+        for (index, line) in data.lines().enumerate() {
+            let line_number = index + 1;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["0", "FILE", name @ ..] if !name.is_empty() => {
+                    sections.push((Some(name.join(" ").to_ascii_lowercase()), Vec::new()));
+                }
+                ["1", color, rest @ ..] => {
+                    // 12 position/rotation values precede the file name
+                    if rest.len() < 13 {
+                        return Err(Self::error(path, line_number, "malformed reference line"));
+                    }
+                    let color = color.parse().map_err(|_| {
+                        Self::error(path, line_number, &format!("unsupported color '{}'", color))
+                    })?;
+                    let reference = Reference {
+                        line: line_number,
+                        color,
+                        file: rest[12..].join(" ").to_ascii_lowercase().replace('\\', "/"),
+                    };
+                    if let Some((_, references)) = sections.last_mut() {
+                        references.push(reference);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Lines before the first `0 FILE` belong to a plain .ldr model; in an
+        // MPD the first FILE section is the main model.
+        let mut sections = sections.into_iter();
+        let (_, unnamed) = sections.next().unwrap_or((None, Vec::new()));
+        let mut named: Vec<(String, Vec<Reference>)> = sections
+            .filter_map(|(name, references)| name.map(|name| (name, references)))
+            .collect();
+
+        let main = if !unnamed.is_empty() || named.is_empty() {
+            unnamed
+        } else {
+            named.remove(0).1
+        };
+        Ok(Document {
+            main,
+            submodels: named.into_iter().collect(),
+        })
+    }
+
+    /// Count the parts of a (sub)model, expanding submodel references
+    fn count(
+        path: &Path,
+        references: &[Reference],
+        sections: &HashMap<String, Vec<Reference>>,
+        inherited: Option<u32>,
+        depth: usize,
+        counts: &mut BTreeMap<(String, u32), i32>,
+    ) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(StudFinderError::ImportFailed(format!(
+                "{}: submodels nested more than {} deep",
+                path.display(),
+                MAX_DEPTH
+            )));
+        }
+
+        for reference in references {
+            let color = if reference.color == MAIN_COLOR {
+                inherited
+            } else {
+                Some(reference.color)
+            };
+
+            if let Some(submodel) = sections.get(&reference.file) {
+                Self::count(path, submodel, sections, color, depth + 1, counts)?;
+                continue;
+            }
+
+            let Some(part_number) = reference.file.strip_suffix(".dat") else {
+                warn!(
+                    "Skipping reference to {} on line {}: not a part or known submodel",
+                    reference.file, reference.line
+                );
+                continue;
+            };
+            let part_number = part_number.strip_prefix("parts/").unwrap_or(part_number);
+
+            let color = color.ok_or_else(|| {
+                Self::error(
+                    path,
+                    reference.line,
+                    "part uses the main color outside a submodel",
+                )
+            })?;
+            if palette::find_by_rebrickable_id(color).is_none() {
+                return Err(Self::error(
+                    path,
+                    reference.line,
+                    &format!("unknown LDraw color {}", color),
+                ));
+            }
+
+            *counts.entry((part_number.to_string(), color)).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn error(path: &Path, line: usize, message: &str) -> StudFinderError {
+        StudFinderError::ImportFailed(format!("{} (line {}): {}", path.display(), line, message))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: &str = "0 0 0 1 0 0 0 1 0 0 0 1";
+
+    #[test]
+    fn test_read_model() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("model.ldr");
+        std::fs::write(
+            &path,
+            format!(
+                "0 Little wall\n0 Name: model.ldr\n\
+                 1 4 {IDENTITY} 3001.dat\n\
+                 1 4 {IDENTITY} 3001.dat\n\
+                 1 0 {IDENTITY} parts\\3020.dat\n"
+            ),
+        )
+        .unwrap();
+
+        let pieces = LDrawModel::read(&path).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].part_number, "3001");
+        assert_eq!(pieces[0].color, "Red");
+        assert_eq!(pieces[0].quantity, 2);
+        assert_eq!(pieces[1].part_number, "3020");
+        assert_eq!(pieces[1].color, "Black");
+    }
+
+    #[test]
+    fn test_read_mpd_expands_submodels() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("model.mpd");
+        std::fs::write(
+            &path,
+            format!(
+                "0 FILE main.ldr\n\
+                 1 1 {IDENTITY} wheel.ldr\n\
+                 1 4 {IDENTITY} wheel.ldr\n\
+                 0 NOFILE\n\
+                 0 FILE wheel.ldr\n\
+                 1 16 {IDENTITY} 3003.dat\n\
+                 1 0 {IDENTITY} 3024.dat\n"
+            ),
+        )
+        .unwrap();
+
+        let pieces = LDrawModel::read(&path).unwrap();
+        let quantity = |part: &str, color: &str| {
+            pieces
+                .iter()
+                .find(|p| p.part_number == part && p.color == color)
+                .map(|p| p.quantity)
+        };
+        // The main color of the submodel follows the referencing line
+        assert_eq!(quantity("3003", "Blue"), Some(1));
+        assert_eq!(quantity("3003", "Red"), Some(1));
+        assert_eq!(quantity("3024", "Black"), Some(2));
+    }
+
+    #[test]
+    fn test_read_reports_unknown_color() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("model.ldr");
+        std::fs::write(&path, format!("0 Model\n1 9999 {IDENTITY} 3001.dat\n")).unwrap();
+
+        match LDrawModel::read(&path) {
+            Err(StudFinderError::ImportFailed(message)) => {
+                assert!(message.contains("line 2"), "{}", message);
+            }
+            other => panic!("Expected ImportFailed, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_document() {
+        assert!(LDrawModel::is_document(&format!(
+            "0 Model\n\n1 4 {IDENTITY} 3001.dat\n"
+        )));
+        assert!(!LDrawModel::is_document("0 Model\n"));
+        assert!(!LDrawModel::is_document(
+            "part_number,color,quantity\n3001,Red,1\n"
+        ));
+    }
+}
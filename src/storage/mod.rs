@@ -1,5 +1,21 @@
+pub mod bricklink;
+pub mod catalog;
 pub mod database;
 pub mod export;
+pub mod filter;
+pub mod ldraw;
+pub mod observation;
+pub mod rebrickable;
+pub mod review;
+pub mod sets;
 
+pub use bricklink::*;
+pub use catalog::*;
 pub use database::*;
 pub use export::*;
+pub use filter::*;
+pub use ldraw::*;
+pub use observation::*;
+pub use rebrickable::*;
+pub use review::*;
+pub use sets::*;
@@ -0,0 +1,49 @@
+use crate::core::piece::{ProcessorType, ScanQuality};
+use crate::processing::segment::BoundingBox;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The image a set of detections came from and how it was processed
+#[derive(Debug, Clone, Copy)]
+pub struct ScanSource<'a> {
+    /// The scanned image
+    pub path: &'a Path,
+    /// Hex-encoded SHA-256 of the image content
+    pub hash: &'a str,
+    /// Processor that produced the detections
+    pub processor: ProcessorType,
+    /// Scan quality the processor was configured with
+    pub quality: ScanQuality,
+}
+
+/// One sighting of an inventory entry in a scanned image
+///
+/// Part number, color and confidence are recorded as detected, so they
+/// still show what the processor saw after the entry is corrected by hand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    /// Unique identifier of the observation
+    pub id: String,
+    /// The inventory entry the detection was stored in
+    pub piece_id: String,
+    /// The scanned image, as given to the scan
+    pub image_path: String,
+    /// Hex-encoded SHA-256 of the image content
+    pub image_hash: String,
+    /// Where the piece was found in the image
+    pub bounds: BoundingBox,
+    /// Processor that made the detection (scanner or detector)
+    pub processor: String,
+    /// Scan quality the processor was configured with
+    pub quality: String,
+    /// Median RGB of the piece's pixels, before palette matching
+    pub measured_rgb: Option<[u8; 3]>,
+    /// Part number as detected
+    pub part_number: String,
+    /// Color as detected
+    pub color: String,
+    /// Confidence of the detection (0.0-1.0)
+    pub confidence: f32,
+    /// When the image was scanned (UTC, `YYYY-MM-DD HH:MM:SS`)
+    pub observed_at: String,
+}
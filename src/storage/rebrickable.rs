@@ -0,0 +1,274 @@
+use crate::core::catalog::UNKNOWN_CATEGORY;
+use crate::core::piece::Piece;
+use crate::error::{Result, StudFinderError};
+use crate::processing::palette;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Row of a Rebrickable parts-list CSV
+///
+/// The aliases accept the column names of Rebrickable's
+/// `inventory_parts.csv` download as well.
+#[derive(Debug, Serialize, Deserialize)]
+struct PartsListRecord {
+    #[serde(rename = "Part", alias = "part", alias = "part_num")]
+    part: String,
+    #[serde(rename = "Color", alias = "color", alias = "color_id")]
+    color: u32,
+    #[serde(rename = "Quantity", alias = "quantity")]
+    quantity: i32,
+    #[serde(rename = "Is Spare", alias = "is_spare", default)]
+    is_spare: String,
+}
+
+/// Functions for reading and writing Rebrickable parts lists
+///
+/// A parts list is a CSV file with the columns `Part`, `Color`, `Quantity`
+/// and `Is Spare`, where `Color` is a Rebrickable color id. Color names are
+/// translated through the reference palette in both directions.
+pub struct RebrickablePartsList;
+
+impl RebrickablePartsList {
+    /// Write pieces as a Rebrickable parts list
+    ///
+    /// Pieces that differ only in condition share a row, since parts lists
+    /// do not record condition. Every row is written as a non-spare part.
+    ///
+    /// # Arguments
+    /// * `pieces` - The pieces to export
+    /// * `path` - The path to write to
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExportFailed` if a piece's color has no Rebrickable id, or an
+    /// I/O error if the file cannot be written.
+    pub fn write<P: AsRef<Path>>(pieces: &[Piece], path: P) -> Result<()> {
+        let mut rows: BTreeMap<(String, u32), i32> = BTreeMap::new();
+        for piece in pieces {
+            let color = palette::find_by_name(&piece.color).ok_or_else(|| {
+                StudFinderError::ExportFailed(format!(
+                    "color '{}' of part {} has no Rebrickable color id",
+                    piece.color, piece.part_number
+                ))
+            })?;
+            *rows
+                .entry((piece.part_number.clone(), color.rebrickable_id))
+                .or_default() += piece.quantity;
+        }
+
+        let to_error = |e: csv::Error| StudFinderError::ExportFailed(e.to_string());
+        let mut writer = csv::Writer::from_path(path.as_ref()).map_err(to_error)?;
+        for ((part, color), quantity) in rows {
+            writer
+                .serialize(PartsListRecord {
+                    part,
+                    color,
+                    quantity,
+                    is_spare: "False".to_string(),
+                })
+                .map_err(to_error)?;
+        }
+        writer.flush().map_err(StudFinderError::Io)?;
+
+        debug!(
+            "Wrote Rebrickable parts list to {}",
+            path.as_ref().display()
+        );
+        Ok(())
+    }
+
+    /// Read pieces from a Rebrickable parts list
+    ///
+    /// Colors are mapped back to their BrickLink names. Parts lists carry no
+    /// category, so imported pieces are marked "Unknown" for the caller to
+    /// resolve against the part catalog. Spare parts are imported like any
+    /// other part.
+    ///
+    /// # Arguments
+    /// * `path` - The path to read from
+    ///
+    /// # Returns
+    /// * `Result<Vec<Piece>>` - One piece per row, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImportFailed`, naming the line, if a row cannot be parsed or
+    /// uses a color id that is not in the palette.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<Piece>> {
+        let path = path.as_ref();
+        let to_error = |e: csv::Error| {
+            let location = e
+                .position()
+                .map(|pos| format!(" (line {})", pos.line()))
+                .unwrap_or_default();
+            StudFinderError::ImportFailed(format!("{}{}: {}", path.display(), location, e))
+        };
+
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .map_err(to_error)?;
+
+        let headers = reader.headers().map_err(to_error)?.clone();
+        let mut pieces = Vec::new();
+        for result in reader.records() {
+            let row = result.map_err(to_error)?;
+            let record: PartsListRecord = row.deserialize(Some(&headers)).map_err(to_error)?;
+            let color = palette::find_by_rebrickable_id(record.color).ok_or_else(|| {
+                StudFinderError::ImportFailed(format!(
+                    "{} (line {}): unknown Rebrickable color id {}",
+                    path.display(),
+                    row.position().map_or(0, csv::Position::line),
+                    record.color
+                ))
+            })?;
+            pieces.push(Piece {
+                id: Uuid::new_v4().to_string(),
+                part_number: record.part,
+                color: color.bricklink_name.to_string(),
+                category: UNKNOWN_CATEGORY.to_string(),
+                quantity: record.quantity,
+                confidence: 1.0,
+                condition: None,
+            });
+        }
+
+        debug!("Read {} row(s) from Rebrickable parts list", pieces.len());
+        Ok(pieces)
+    }
+
+    /// Whether a CSV header line is a Rebrickable parts-list header
+    ///
+    /// Both the parts-list header (`Part,Color,Quantity,Is Spare`) and the
+    /// `inventory_parts.csv` header (`part_num,color_id,quantity,…`) are
+    /// recognized; other columns are allowed.
+    #[must_use]
+    pub fn is_header(line: &str) -> bool {
+        let columns: Vec<String> = line
+            .split(',')
+            .map(|column| column.trim().trim_matches('"').to_ascii_lowercase())
+            .collect();
+        let has = |names: &[&str]| columns.iter().any(|c| names.contains(&c.as_str()));
+        has(&[
+            "part", "part_num",
+        ]) && has(&[
+            "color", "color_id",
+        ]) && has(&["quantity"])
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+    use crate::core::piece::PieceCondition;
+
+    fn piece(part_number: &str, color: &str, quantity: i32) -> Piece {
+        Piece {
+            id: Uuid::new_v4().to_string(),
+            part_number: part_number.to_string(),
+            color: color.to_string(),
+            category: "Bricks".to_string(),
+            quantity,
+            confidence: 0.9,
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn test_write_maps_colors_and_merges_conditions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("parts.csv");
+
+        let mut used = piece("3001", "Red", 2);
+        used.condition = Some(PieceCondition::Used);
+        let pieces = vec![
+            piece("3001", "Red", 1),
+            used,
+            piece("3020", "Black", 4),
+        ];
+        RebrickablePartsList::write(&pieces, &path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "Part,Color,Quantity,Is Spare\n3001,4,3,False\n3020,0,4,False\n"
+        );
+    }
+
+    #[test]
+    fn test_write_rejects_unmapped_color() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("parts.csv");
+
+        let result = RebrickablePartsList::write(&[piece("3001", "Unknown", 1)], &path);
+        assert!(matches!(result, Err(StudFinderError::ExportFailed(_))));
+    }
+
+    #[test]
+    fn test_read_parts_list() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("parts.csv");
+        std::fs::write(
+            &path,
+            "Part,Color,Quantity,Is Spare\n3001,4,3,False\n3020,0,1,True\n",
+        )
+        .unwrap();
+
+        let pieces = RebrickablePartsList::read(&path).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].part_number, "3001");
+        assert_eq!(pieces[0].color, "Red");
+        assert_eq!(pieces[0].quantity, 3);
+        assert_eq!(pieces[0].category, UNKNOWN_CATEGORY);
+        assert_eq!(pieces[1].color, "Black");
+    }
+
+    #[test]
+    fn test_read_inventory_parts_columns() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("inventory_parts.csv");
+        std::fs::write(
+            &path,
+            "inventory_id,part_num,color_id,quantity,is_spare,img_url\n1,3001,4,2,f,\n",
+        )
+        .unwrap();
+
+        let pieces = RebrickablePartsList::read(&path).unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].color, "Red");
+        assert_eq!(pieces[0].quantity, 2);
+    }
+
+    #[test]
+    fn test_read_reports_unknown_color() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("parts.csv");
+        std::fs::write(
+            &path,
+            "Part,Color,Quantity,Is Spare\n3001,4,3,False\n3001,99999,1,False\n",
+        )
+        .unwrap();
+
+        match RebrickablePartsList::read(&path) {
+            Err(StudFinderError::ImportFailed(message)) => {
+                assert!(message.contains("line 3"), "{}", message);
+                assert!(message.contains("99999"), "{}", message);
+            }
+            other => panic!("Expected ImportFailed, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_header() {
+        assert!(RebrickablePartsList::is_header(
+            "Part,Color,Quantity,Is Spare"
+        ));
+        assert!(!RebrickablePartsList::is_header(
+            "id,part_number,color,category,quantity,confidence"
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::processing::segment::BoundingBox;
+use serde::{Deserialize, Serialize};
+
+/// A detection held in the review queue
+///
+/// Detections whose confidence falls between the review and accept
+/// thresholds wait here until they are accepted (possibly corrected) into
+/// the inventory or discarded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingReview {
+    /// Unique identifier of the queued detection
+    pub id: String,
+    /// Part number as detected
+    pub part_number: String,
+    /// Color as detected
+    pub color: String,
+    /// Category of the detected part
+    pub category: String,
+    /// Confidence of the detection (0.0-1.0)
+    pub confidence: f32,
+    /// The scanned image, as given to the scan
+    pub image_path: String,
+    /// Hex-encoded SHA-256 of the image content
+    pub image_hash: String,
+    /// Where the piece was found in the image
+    pub bounds: BoundingBox,
+    /// Processor that made the detection (scanner or detector)
+    pub processor: String,
+    /// Scan quality the processor was configured with
+    pub quality: String,
+    /// Median RGB of the piece's pixels, before palette matching
+    pub measured_rgb: Option<[u8; 3]>,
+    /// When the image was scanned (UTC, `YYYY-MM-DD HH:MM:SS`)
+    pub queued_at: String,
+}
@@ -0,0 +1,276 @@
+use crate::core::set::{LegoSet, SetPart};
+use crate::error::{Result, StudFinderError};
+use crate::processing::palette;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// Rebrickable sets table
+const SETS_FILE: &str = "sets.csv";
+
+/// Rebrickable inventory table, linking sets to their part lists
+const INVENTORIES_FILE: &str = "inventories.csv";
+
+/// Rebrickable inventory part table
+const INVENTORY_PARTS_FILE: &str = "inventory_parts.csv";
+
+/// Row of Rebrickable's `sets.csv`
+#[derive(Debug, Deserialize)]
+struct SetRecord {
+    set_num: String,
+    name: String,
+    year: Option<i32>,
+}
+
+/// Row of Rebrickable's `inventories.csv`
+#[derive(Debug, Deserialize)]
+struct InventoryRecord {
+    id: u32,
+    version: u32,
+    set_num: String,
+}
+
+/// Row of Rebrickable's `inventory_parts.csv`
+#[derive(Debug, Deserialize)]
+struct InventoryPartRecord {
+    inventory_id: u32,
+    part_num: String,
+    color_id: u32,
+    quantity: i32,
+    is_spare: String,
+}
+
+impl InventoryPartRecord {
+    /// Whether the row lists spare pieces, which the set does not need
+    fn is_spare(&self) -> bool {
+        matches!(self.is_spare.to_ascii_lowercase().as_str(), "t" | "true")
+    }
+}
+
+/// Functions for loading set inventory dumps
+pub struct SetImporter;
+
+impl SetImporter {
+    /// Load sets and their parts from a Rebrickable CSV dump
+    ///
+    /// `path` may be a directory containing Rebrickable's `sets.csv`,
+    /// `inventories.csv` and `inventory_parts.csv`, or the `sets.csv` file
+    /// itself (the other tables are looked up next to it). Only the first
+    /// inventory version of each set is used, and spare pieces are skipped.
+    /// Minifigures and sub-sets listed in a set's inventory are not
+    /// expanded into their parts.
+    ///
+    /// # Arguments
+    /// * `path` - The directory or file to import from
+    ///
+    /// # Returns
+    /// * `Result<Vec<LegoSet>>` - The sets found, or an error
+    ///
+    /// # Errors
+    ///
+    /// Returns `SetImportFailed` if a file is missing or a row cannot be
+    /// parsed.
+    pub fn import<P: AsRef<Path>>(path: P) -> Result<Vec<LegoSet>> {
+        let path = path.as_ref();
+        debug!("Importing sets from: {}", path.display());
+
+        let (sets_path, dir) = if path.is_dir() {
+            (path.join(SETS_FILE), path)
+        } else {
+            (
+                path.to_path_buf(),
+                path.parent().unwrap_or_else(|| Path::new(".")),
+            )
+        };
+
+        // The lowest inventory version of each set is its original release
+        let mut inventories: HashMap<String, (u32, u32)> = HashMap::new();
+        for record in Self::read_csv::<InventoryRecord>(&dir.join(INVENTORIES_FILE))? {
+            let entry = inventories
+                .entry(record.set_num)
+                .or_insert((record.version, record.id));
+            if record.version < entry.0 {
+                *entry = (record.version, record.id);
+            }
+        }
+        let set_numbers: HashMap<u32, &str> = inventories
+            .iter()
+            .map(|(set_num, &(_, id))| (id, set_num.as_str()))
+            .collect();
+        debug!("Read {} set inventories", set_numbers.len());
+
+        let mut parts: HashMap<&str, BTreeMap<(String, String), i32>> = HashMap::new();
+        let mut unknown_colors = BTreeMap::new();
+        for record in Self::read_csv::<InventoryPartRecord>(&dir.join(INVENTORY_PARTS_FILE))? {
+            if record.is_spare() {
+                continue;
+            }
+            let Some(&set_num) = set_numbers.get(&record.inventory_id) else {
+                continue;
+            };
+            let color = match palette::find_by_rebrickable_id(record.color_id) {
+                Some(color) => color.bricklink_name.to_string(),
+                None => {
+                    *unknown_colors.entry(record.color_id).or_insert(0) += 1;
+                    format!("Rebrickable color {}", record.color_id)
+                }
+            };
+            *parts
+                .entry(set_num)
+                .or_default()
+                .entry((record.part_num, color))
+                .or_default() += record.quantity;
+        }
+        for (color_id, rows) in &unknown_colors {
+            warn!(
+                "Rebrickable color {} is not in the palette ({} row(s))",
+                color_id, rows
+            );
+        }
+
+        let sets: Vec<LegoSet> = Self::read_csv::<SetRecord>(&sets_path)?
+            .into_iter()
+            .map(|record| {
+                let parts = parts
+                    .remove(record.set_num.as_str())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|((part_number, color), quantity)| SetPart {
+                        part_number,
+                        color,
+                        quantity,
+                    })
+                    .collect();
+                LegoSet {
+                    set_number: record.set_num,
+                    name: record.name,
+                    year: record.year,
+                    parts,
+                }
+            })
+            .collect();
+
+        info!("Read {} set(s) from {}", sets.len(), path.display());
+        Ok(sets)
+    }
+
+    /// Read every row of a CSV file with a header line
+    fn read_csv<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+        let to_error = |e: csv::Error| {
+            let location = e
+                .position()
+                .map(|pos| format!(" (line {})", pos.line()))
+                .unwrap_or_default();
+            StudFinderError::SetImportFailed(format!("{}{}: {}", path.display(), location, e))
+        };
+
+        let mut reader = csv::Reader::from_path(path).map_err(to_error)?;
+        reader
+            .deserialize()
+            .collect::<std::result::Result<Vec<T>, _>>()
+            .map_err(to_error)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::disallowed_methods)]
+mod tests {
+    use super::*;
+
+    fn write_dump(dir: &Path) {
+        std::fs::write(
+            dir.join(SETS_FILE),
+            "set_num,name,year,theme_id,num_parts,img_url\n\
+             10696-1,Medium Creative Brick Box,2015,621,484,\n\
+             60000-1,\"Fire Motorcycle, Small\",2013,58,40,\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(INVENTORIES_FILE),
+            "id,version,set_num\n1,1,10696-1\n2,2,10696-1\n3,1,60000-1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(INVENTORY_PARTS_FILE),
+            "inventory_id,part_num,color_id,quantity,is_spare,img_url\n\
+             1,3001,4,6,f,\n\
+             1,3001,4,2,f,\n\
+             1,3001,4,1,t,\n\
+             1,3020,1,4,False,\n\
+             1,3062b,9999,2,f,\n\
+             2,3003,0,10,f,\n\
+             3,3010,0,1,f,\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_import_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        write_dump(dir.path());
+
+        let sets = SetImporter::import(dir.path()).unwrap();
+        assert_eq!(sets.len(), 2);
+
+        let set = &sets[0];
+        assert_eq!(set.set_number, "10696-1");
+        assert_eq!(set.year, Some(2015));
+        // Version 2 of the inventory and the spare row are ignored
+        assert_eq!(
+            set.parts,
+            vec![
+                SetPart {
+                    part_number: "3001".to_string(),
+                    color: "Red".to_string(),
+                    quantity: 8,
+                },
+                SetPart {
+                    part_number: "3020".to_string(),
+                    color: "Blue".to_string(),
+                    quantity: 4,
+                },
+                SetPart {
+                    part_number: "3062b".to_string(),
+                    color: "Rebrickable color 9999".to_string(),
+                    quantity: 2,
+                },
+            ]
+        );
+        assert_eq!(sets[1].name, "Fire Motorcycle, Small");
+        assert_eq!(sets[1].parts[0].color, "Black");
+    }
+
+    #[test]
+    fn test_import_sets_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_dump(dir.path());
+
+        let sets = SetImporter::import(dir.path().join(SETS_FILE)).unwrap();
+        assert_eq!(sets.len(), 2);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        write_dump(dir.path());
+        std::fs::remove_file(dir.path().join(INVENTORIES_FILE)).unwrap();
+        assert!(matches!(
+            SetImporter::import(dir.path()),
+            Err(StudFinderError::SetImportFailed(_))
+        ));
+
+        write_dump(dir.path());
+        std::fs::write(
+            dir.path().join(INVENTORY_PARTS_FILE),
+            "inventory_id,part_num,color_id,quantity,is_spare\n1,3001,red,6,f\n",
+        )
+        .unwrap();
+        match SetImporter::import(dir.path()) {
+            Err(StudFinderError::SetImportFailed(message)) => {
+                assert!(message.contains("line 2"), "{}", message);
+            }
+            other => panic!("expected SetImportFailed, got {:?}", other),
+        }
+    }
+}
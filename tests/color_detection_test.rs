@@ -1,5 +1,8 @@
+#![allow(clippy::disallowed_methods)]
+
 use assert_fs::prelude::*;
 use image::{Rgb, RgbImage};
+use std::path::PathBuf;
 use studfinder::{Config, ProcessorType, ScanQuality, StudFinder};
 use test_case::test_case;
 
@@ -21,11 +24,17 @@ fn create_test_image(r: u8, g: u8, b: u8) -> (assert_fs::TempDir, assert_fs::fix
 
 // Test cases for different colors
 #[test_case(255, 0, 0 => "Red"; "pure red")]
-#[test_case(0, 255, 0 => "Green"; "pure green")]
-#[test_case(0, 0, 255 => "Blue"; "pure blue")]
-#[test_case(255, 255, 0 => "Yellow"; "pure yellow")]
-#[test_case(255, 255, 255 => "White"; "pure white")]
-#[test_case(0, 0, 0 => "Black"; "pure black")]
+#[test_case(215, 40, 25 => "Red"; "off-reference red")]
+#[test_case(150, 150, 150 => "Light Gray"; "mid gray")]
+#[test_case(201, 26, 9 => "Red"; "red")]
+#[test_case(35, 120, 65 => "Green"; "green")]
+#[test_case(0, 85, 191 => "Blue"; "blue")]
+#[test_case(242, 205, 55 => "Yellow"; "yellow")]
+#[test_case(255, 255, 255 => "White"; "white")]
+#[test_case(5, 19, 29 => "Black"; "black")]
+#[test_case(108, 110, 104 => "Dark Bluish Gray"; "dark bluish gray")]
+#[test_case(228, 205, 158 => "Tan"; "tan")]
+#[test_case(88, 42, 18 => "Reddish Brown"; "reddish brown")]
 #[tokio::test]
 async fn test_color_detection(r: u8, g: u8, b: u8) -> String {
     // Create test image with the specified color
@@ -38,27 +47,33 @@ async fn test_color_detection(r: u8, g: u8, b: u8) -> String {
         scan_quality: ScanQuality::Fast,
         processor_type: ProcessorType::Scanner,
         confidence_threshold: 0.7,
+        review_threshold: 0.0,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
     };
 
     let finder = StudFinder::new(config).unwrap();
     finder.init().unwrap();
 
-    // Scan the image and return the detected color
-    let piece = finder
-        .scan_image(image_path.path().to_path_buf())
+    // Scan the image and return the detected color; a plain swatch has no
+    // studs, so its piece is queued for review rather than stored, and at
+    // half its color's confidence it is kept only with no review threshold
+    let detections = finder
+        .scan_image_detections(image_path.path().to_path_buf(), None)
         .await
         .unwrap();
-    piece.color
+    assert_eq!(detections.len(), 1);
+    detections[0].piece.color.clone()
 }
 
 // Test that confidence decreases as colors become less pure
 #[tokio::test]
 async fn test_color_confidence_decreases_with_impurity() {
-    // Create a pure red image
-    let (temp1, pure_path) = create_test_image(255, 0, 0);
+    // Create a red image close to the reference value
+    let (temp1, pure_path) = create_test_image(215, 40, 25);
 
-    // Create an impure red image (with some green and blue)
-    let (_temp2, impure_path) = create_test_image(255, 50, 50);
+    // Create a red image that drifts further from it
+    let (_temp2, impure_path) = create_test_image(255, 0, 0);
 
     // Initialize StudFinder with Scanner processor
     let config = Config {
@@ -67,6 +82,9 @@ async fn test_color_confidence_decreases_with_impurity() {
         scan_quality: ScanQuality::Fast,
         processor_type: ProcessorType::Scanner,
         confidence_threshold: 0.7,
+        review_threshold: 0.0,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
     };
 
     let finder = StudFinder::new(config).unwrap();
@@ -74,13 +92,17 @@ async fn test_color_confidence_decreases_with_impurity() {
 
     // Scan both images
     let pure_piece = finder
-        .scan_image(pure_path.path().to_path_buf())
+        .scan_image_detections(pure_path.path().to_path_buf(), None)
         .await
-        .unwrap();
+        .unwrap()
+        .remove(0)
+        .piece;
     let impure_piece = finder
-        .scan_image(impure_path.path().to_path_buf())
+        .scan_image_detections(impure_path.path().to_path_buf(), None)
         .await
-        .unwrap();
+        .unwrap()
+        .remove(0)
+        .piece;
 
     // Both should be detected as red
     assert_eq!(pure_piece.color, "Red");
@@ -1,3 +1,7 @@
+#![allow(clippy::disallowed_methods)]
+
+use std::path::PathBuf;
+use studfinder::storage::AddOutcome;
 use studfinder::{Config, Piece, ProcessorType, ScanQuality, StudFinder};
 use uuid::Uuid;
 
@@ -10,6 +14,7 @@ fn create_test_piece() -> Piece {
         category: "Brick".to_string(),
         quantity: 1,
         confidence: 0.95,
+        condition: None,
     }
 }
 
@@ -26,6 +31,9 @@ async fn test_studfinder_inventory_operations() {
         scan_quality: ScanQuality::Fast,
         processor_type: ProcessorType::Scanner,
         confidence_threshold: 0.8,
+        review_threshold: 0.5,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
     };
 
     // Create the finder
@@ -36,7 +44,13 @@ async fn test_studfinder_inventory_operations() {
     let piece = create_test_piece();
 
     // Add the piece to the inventory
-    finder.add_piece(piece.clone()).unwrap();
+    let outcome = finder.add_piece(piece.clone()).unwrap();
+    assert_eq!(
+        outcome,
+        AddOutcome::Inserted {
+            id: piece.id.clone(),
+        }
+    );
 
     // Test inventory
     let pieces = finder.list_inventory().unwrap();
@@ -52,9 +66,17 @@ async fn test_studfinder_inventory_operations() {
         category: piece.category.clone(),
         quantity: 2,
         confidence: piece.confidence,
+        condition: None,
     };
 
-    finder.add_piece(updated_piece).unwrap();
+    let outcome = finder.add_piece(updated_piece).unwrap();
+    assert_eq!(
+        outcome,
+        AddOutcome::Merged {
+            id: piece.id.clone(),
+            quantity: 3,
+        }
+    );
 
     // Verify the update
     let pieces = finder.list_inventory().unwrap();
@@ -1,6 +1,47 @@
+#![allow(clippy::disallowed_methods)]
+
 use assert_fs::prelude::*;
 use predicates::prelude::*;
-use studfinder::{Config, ExportFormat, ProcessorType, ScanQuality, StudFinder};
+use std::path::PathBuf;
+use std::time::Duration;
+use studfinder::{
+    BatchOptions, BatchReport, Config, ExportFormat, FileOutcome, ProcessorType, ScanQuality,
+    StudFinder, WatchOptions, DONE_DIR, FAILED_DIR,
+};
+
+/// Draw a 2x4 brick seen from above at (x, y), its studs catching the light
+fn draw_brick(img: &mut image::RgbImage, x: i32, y: i32, color: [u8; 3]) {
+    let pitch = 40;
+    imageproc::drawing::draw_filled_rect_mut(
+        img,
+        imageproc::rect::Rect::at(x, y).of_size(4 * pitch as u32, 2 * pitch as u32),
+        image::Rgb(color),
+    );
+    let highlight = color.map(|c| c + (255 - c) / 4);
+    for column in 0..4 {
+        for row in 0..2 {
+            imageproc::drawing::draw_filled_circle_mut(
+                img,
+                (x + column * pitch + pitch / 2, y + row * pitch + pitch / 2),
+                12,
+                image::Rgb(highlight),
+            );
+        }
+    }
+}
+
+/// A photo of a single 2x4 brick on a white background
+fn brick_image(color: [u8; 3]) -> image::RgbImage {
+    let mut img = image::RgbImage::from_pixel(
+        240,
+        160,
+        image::Rgb([
+            255, 255, 255,
+        ]),
+    );
+    draw_brick(&mut img, 40, 40, color);
+    img
+}
 
 #[tokio::test]
 async fn test_full_workflow() {
@@ -11,13 +52,11 @@ async fn test_full_workflow() {
     let export_path = temp.child("export.json");
 
     // Create test image (a red brick)
-    let mut img = image::RgbImage::new(200, 200);
-    for pixel in img.pixels_mut() {
-        *pixel = image::Rgb([
-            255, 0, 0,
-        ]); // Pure red
-    }
-    img.save(image_path.path()).unwrap();
+    brick_image([
+        201, 26, 9,
+    ])
+    .save(image_path.path())
+    .unwrap();
 
     // Initialize StudFinder with test config
     let config = Config {
@@ -26,23 +65,25 @@ async fn test_full_workflow() {
         scan_quality: ScanQuality::Fast,
         processor_type: ProcessorType::Scanner,
         confidence_threshold: 0.8,
+        review_threshold: 0.5,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
     };
 
     let finder = StudFinder::new(config).unwrap();
     finder.init().unwrap();
 
     // Test scanning
-    let piece = finder
+    let pieces = finder
         .scan_image(image_path.path().to_path_buf())
         .await
         .unwrap();
+    assert_eq!(pieces.len(), 1);
+    let piece = &pieces[0];
     assert_eq!(piece.color, "Red");
     assert!(piece.confidence > 0.7);
 
-    // Add the piece to the inventory
-    finder.add_piece(piece.clone()).unwrap();
-
-    // Test inventory
+    // Test inventory (scanned pieces are stored automatically)
     let pieces = finder.list_inventory().unwrap();
     assert_eq!(pieces.len(), 1);
     assert_eq!(pieces[0].color, "Red");
@@ -60,3 +101,379 @@ async fn test_full_workflow() {
     assert!(export_content.contains(&piece.color));
     assert!(export_content.contains(&piece.part_number));
 }
+
+#[tokio::test]
+async fn test_multi_piece_scan() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let image_path = temp.child("tray.png");
+
+    // A tray of three loose pieces on a white background
+    let mut img = image::RgbImage::from_pixel(
+        480,
+        300,
+        image::Rgb([
+            255, 255, 255,
+        ]),
+    );
+    for (x, y, color) in [
+        (
+            20,
+            20,
+            [
+                201, 26, 9,
+            ],
+        ),
+        (
+            280,
+            40,
+            [
+                0, 85, 191,
+            ],
+        ),
+        (
+            100,
+            180,
+            [
+                201, 26, 9,
+            ],
+        ),
+    ] {
+        draw_brick(&mut img, x, y, color);
+    }
+    img.save(image_path.path()).unwrap();
+
+    let config = Config {
+        database_path: temp.child("test.db").path().to_path_buf(),
+        export_format: ExportFormat::Json,
+        scan_quality: ScanQuality::Fast,
+        processor_type: ProcessorType::Scanner,
+        confidence_threshold: 0.8,
+        review_threshold: 0.5,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
+    };
+
+    let finder = StudFinder::new(config).unwrap();
+    finder.init().unwrap();
+
+    let pieces = finder
+        .scan_image(image_path.path().to_path_buf())
+        .await
+        .unwrap();
+    assert_eq!(pieces.len(), 3);
+    assert_eq!(pieces[0].color, "Red");
+    assert_eq!(pieces[1].color, "Blue");
+    assert_eq!(pieces[2].color, "Red");
+
+    // Every detected piece is stored, identical pieces sharing a row
+    let inventory = finder.list_inventory().unwrap();
+    assert_eq!(inventory.len(), 2);
+    let stored: i32 = inventory.iter().map(|p| p.quantity).sum();
+    assert_eq!(stored, 3);
+    assert_eq!(pieces[0].id, pieces[2].id);
+}
+
+#[tokio::test]
+async fn test_side_view_scan() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let top_path = temp.child("top.png");
+    let side_path = temp.child("side.png");
+
+    // A red 2x2 part seen from above, studs catching the light
+    let mut top = image::RgbImage::from_pixel(
+        160,
+        160,
+        image::Rgb([
+            255, 255, 255,
+        ]),
+    );
+    imageproc::drawing::draw_filled_rect_mut(
+        &mut top,
+        imageproc::rect::Rect::at(40, 40).of_size(80, 80),
+        image::Rgb([
+            201, 26, 9,
+        ]),
+    );
+    for (x, y) in [
+        (60, 60),
+        (100, 60),
+        (60, 100),
+        (100, 100),
+    ] {
+        imageproc::drawing::draw_filled_circle_mut(
+            &mut top,
+            (x, y),
+            12,
+            image::Rgb([
+                240, 90, 70,
+            ]),
+        );
+    }
+    top.save(top_path.path()).unwrap();
+
+    // The same part from the side: 80px wide, 25px tall, so a plate
+    let mut side = image::RgbImage::from_pixel(
+        160,
+        100,
+        image::Rgb([
+            255, 255, 255,
+        ]),
+    );
+    imageproc::drawing::draw_filled_rect_mut(
+        &mut side,
+        imageproc::rect::Rect::at(40, 40).of_size(80, 25),
+        image::Rgb([
+            201, 26, 9,
+        ]),
+    );
+    side.save(side_path.path()).unwrap();
+
+    let config = Config {
+        database_path: temp.child("test.db").path().to_path_buf(),
+        export_format: ExportFormat::Json,
+        scan_quality: ScanQuality::Fast,
+        processor_type: ProcessorType::Scanner,
+        confidence_threshold: 0.8,
+        review_threshold: 0.5,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
+    };
+
+    let finder = StudFinder::new(config).unwrap();
+    finder.init().unwrap();
+
+    // Without a side view the part is assumed to be a brick
+    let pieces = finder
+        .scan_image(top_path.path().to_path_buf())
+        .await
+        .unwrap();
+    assert_eq!(pieces[0].part_number, "3003");
+
+    let pieces = finder
+        .scan_image_with_side_view(
+            top_path.path().to_path_buf(),
+            side_path.path().to_path_buf(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(pieces.len(), 1);
+    assert_eq!(pieces[0].part_number, "3022");
+    assert_eq!(pieces[0].category, "Plates");
+}
+
+#[tokio::test]
+async fn test_batch_scan() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let config = Config {
+        database_path: temp.child("test.db").path().to_path_buf(),
+        export_format: ExportFormat::Json,
+        scan_quality: ScanQuality::Fast,
+        processor_type: ProcessorType::Scanner,
+        confidence_threshold: 0.8,
+        review_threshold: 0.5,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
+    };
+    let finder = StudFinder::new(config).unwrap();
+    finder.init().unwrap();
+
+    let pieces = temp.child("pieces");
+    for (name, color) in [
+        (
+            "red.png",
+            [
+                201, 26, 9,
+            ],
+        ),
+        (
+            "drawer/blue.png",
+            [
+                0, 85, 191,
+            ],
+        ),
+        (
+            "drawer/bin/red.jpg",
+            [
+                201, 26, 9,
+            ],
+        ),
+    ] {
+        let child = pieces.child(name);
+        std::fs::create_dir_all(child.path().parent().unwrap()).unwrap();
+        brick_image(color).save(child.path()).unwrap();
+    }
+    pieces.child("notes.txt").write_str("not a photo").unwrap();
+    pieces.child("broken.png").write_str("not a png").unwrap();
+
+    let mut updates = Vec::new();
+    let options = BatchOptions {
+        workers: 2,
+        rescan: false,
+    };
+    let report = finder
+        .scan_batch(pieces.path(), &options, |progress| {
+            updates.push((progress.completed, progress.total));
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        (report.scanned, report.skipped, report.failed, report.pieces),
+        (3, 1, 1, 3)
+    );
+    assert_eq!(updates.len(), 5);
+    assert_eq!(updates.last(), Some(&(5, 5)));
+
+    let outcome = |name: &str| {
+        &report
+            .files
+            .iter()
+            .find(|f| f.path == pieces.path().join(name))
+            .unwrap()
+            .outcome
+    };
+    assert!(matches!(outcome("notes.txt"), FileOutcome::Skipped { .. }));
+    assert!(matches!(
+        outcome("broken.png"),
+        FileOutcome::Failed { kind, .. } if kind == "image"
+    ));
+
+    // Pieces from every subdirectory are stored
+    let inventory = finder.list_inventory().unwrap();
+    let red: i32 = inventory
+        .iter()
+        .filter(|p| p.color == "Red")
+        .map(|p| p.quantity)
+        .sum();
+    assert_eq!(red, 2);
+
+    // The report can be saved and read back
+    let report_path = temp.child("report.json");
+    report.save(report_path.path()).unwrap();
+    let saved: BatchReport =
+        serde_json::from_str(&std::fs::read_to_string(report_path.path()).unwrap()).unwrap();
+    assert_eq!(saved, report);
+
+    // A second run skips the images already in the scan ledger
+    let rerun = finder
+        .scan_batch(pieces.path(), &options, |_| {})
+        .await
+        .unwrap();
+    assert_eq!((rerun.scanned, rerun.skipped, rerun.failed), (0, 4, 1));
+    assert_eq!(finder.list_inventory().unwrap(), inventory);
+
+    // A rescan replaces the earlier results instead of adding to them
+    let rescan = BatchOptions {
+        rescan: true,
+        ..options
+    };
+    let rerun = finder
+        .scan_batch(pieces.path(), &rescan, |_| {})
+        .await
+        .unwrap();
+    assert_eq!((rerun.scanned, rerun.pieces), (3, 3));
+    let total: i32 = finder
+        .list_inventory()
+        .unwrap()
+        .iter()
+        .map(|p| p.quantity)
+        .sum();
+    assert_eq!(total, 3);
+}
+
+#[tokio::test]
+async fn test_watch_folder() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let inbox = temp.child("inbox");
+    inbox.create_dir_all().unwrap();
+    inbox.child("old.png").write_str("already here").unwrap();
+
+    let finder = StudFinder::new(Config {
+        database_path: temp.child("test.db").path().to_path_buf(),
+        export_format: ExportFormat::Json,
+        scan_quality: ScanQuality::Fast,
+        processor_type: ProcessorType::Scanner,
+        confidence_threshold: 0.8,
+        review_threshold: 0.5,
+        template_dir: PathBuf::from("templates"),
+        calibration_profile: None,
+    })
+    .unwrap();
+    finder.init().unwrap();
+
+    // Drop files in once the watch has started, the way a camera rig would:
+    // one written in place, one renamed in after writing, one broken
+    let dir = inbox.path().to_path_buf();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        brick_image([
+            201, 26, 9,
+        ])
+        .save(dir.join("red.png"))
+        .unwrap();
+        brick_image([
+            0, 85, 191,
+        ])
+        .save_with_format(dir.join("blue.part"), image::ImageFormat::Png)
+        .unwrap();
+        std::fs::rename(dir.join("blue.part"), dir.join("blue.png")).unwrap();
+        std::fs::write(dir.join("broken.jpg"), "not a jpeg").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a photo").unwrap();
+    });
+
+    let (done, finished) = tokio::sync::oneshot::channel();
+    let mut done = Some(done);
+    let mut handled = 0;
+    let options = WatchOptions {
+        settle: Duration::from_millis(200),
+        move_processed: true,
+    };
+    let shutdown = async {
+        tokio::select! {
+            _ = finished => {}
+            () = tokio::time::sleep(Duration::from_secs(20)) => {}
+        }
+    };
+    let report = finder
+        .watch(inbox.path(), &options, shutdown, |_| {
+            handled += 1;
+            if handled == 3 {
+                if let Some(done) = done.take() {
+                    let _ = done.send(());
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        (report.scanned, report.failed, report.pieces),
+        (2, 1, 2),
+        "{:?}",
+        report.files
+    );
+    let colors: Vec<String> = finder
+        .list_inventory()
+        .unwrap()
+        .into_iter()
+        .map(|p| p.color)
+        .collect();
+    assert!(colors.contains(&"Red".to_string()) && colors.contains(&"Blue".to_string()));
+
+    // Processed files are sorted into subfolders; others are left alone
+    inbox
+        .child(DONE_DIR)
+        .child("red.png")
+        .assert(predicate::path::is_file());
+    inbox
+        .child(DONE_DIR)
+        .child("blue.png")
+        .assert(predicate::path::is_file());
+    inbox
+        .child(FAILED_DIR)
+        .child("broken.jpg")
+        .assert(predicate::path::is_file());
+    inbox.child("red.png").assert(predicate::path::missing());
+    inbox.child("old.png").assert(predicate::path::is_file());
+    inbox.child("notes.txt").assert(predicate::path::is_file());
+}